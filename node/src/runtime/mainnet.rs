@@ -59,6 +59,19 @@ fn default_pair_interlay(currency_id: CurrencyId) -> VaultCurrencyPair<CurrencyI
 	}
 }
 
+/// Every pair of the given DEX assets, with the lower asset id first.
+fn dex_trading_pairs(asset_ids: &[u32]) -> Vec<(u32, u32)> {
+	let mut pairs = Vec::new();
+	for &base_asset_id in asset_ids {
+		for &quote_asset_id in asset_ids {
+			if base_asset_id < quote_asset_id {
+				pairs.push((base_asset_id, quote_asset_id));
+			}
+		}
+	}
+	pairs
+}
+
 fn expected_transaction_size() -> u32 {
 	virtual_transaction_size(
 		TransactionInputMetadata {
@@ -335,6 +348,18 @@ pub fn testnet_genesis(
 		dex: DexConfig {
 			asset_ids: vec![8888, 999, 888, 777, 666, 667],
 			native_asset_id: 8888,
+			trading_pairs: dex_trading_pairs(&[8888, 999, 888, 777, 666, 667]),
+			// the wrapped and the relay chain currency of the bridge, held by orml_tokens
+			tokens: vec![
+				(
+					1_000,
+					ggxchain_runtime_sydney::btcbridge::GetWrappedCurrencyId::get(),
+				),
+				(
+					1_001,
+					ggxchain_runtime_sydney::btcbridge::GetRelayChainCurrencyId::get(),
+				),
+			],
 		},
 	}
 }
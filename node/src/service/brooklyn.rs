@@ -960,6 +960,7 @@ where
 		H256,
 		ReplaceRequest<AccountId, BlockNumber, Balance, CurrencyId>,
 	>,
+	C::Api: pallet_dex_rpc::DexRuntimeApi<Block, AccountId, Balance, BlockNumber>,
 {
 	use pallet_transaction_payment_rpc::{TransactionPayment, TransactionPaymentApiServer};
 	use sc_consensus_beefy_rpc::{Beefy, BeefyApiServer};
@@ -972,6 +973,8 @@ where
 	use replace_rpc::{Replace, ReplaceApiServer};
 	use vault_registry_rpc::{VaultRegistry, VaultRegistryApiServer};
 
+	use pallet_dex_rpc::{Dex, DexApiServer};
+
 	let mut io = RpcModule::new(());
 	let FullDeps {
 		client,
@@ -1094,6 +1097,7 @@ where
 	io.merge(Issue::new(client.clone()).into_rpc())?;
 	io.merge(Redeem::new(client.clone()).into_rpc())?;
 	io.merge(Replace::new(client.clone()).into_rpc())?;
+	io.merge(Dex::new(client.clone()).into_rpc())?;
 
 	Ok(io)
 }
@@ -0,0 +1,64 @@
+//! Runtime API definition for the DEX pallet.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use scale_codec::Codec;
+use sp_std::vec::Vec;
+
+pub use pallet_dex::{
+	BestBidAsk, Candle, LiquidityPool, Order, OrderBookDepth, PriceAccumulator, TokenInfo, Twap,
+};
+
+sp_api::decl_runtime_apis! {
+	pub trait DexApi<AccountId, Balance, BlockNumber> where
+		AccountId: Codec,
+		Balance: Codec,
+		BlockNumber: Codec,
+	{
+		/// Order book of a pair aggregated by price, with at most `levels` prices per side.
+		/// The pair is given with the lower asset id first, as it is stored on chain.
+		fn order_book_depth(pair: (u32, u32), levels: u32) -> OrderBookDepth<Balance>;
+
+		/// Highest buy price and lowest sell price of a pair.
+		fn best_bid_ask(pair: (u32, u32)) -> BestBidAsk;
+
+		/// Open orders of an account.
+		fn user_open_orders(account_id: AccountId) -> Vec<Order<AccountId, Balance, BlockNumber>>;
+
+		/// Deposited and reserved amounts of an account, by asset id.
+		fn user_balances(account_id: AccountId) -> Vec<(u32, TokenInfo<Balance>)>;
+
+		/// Latest candles of a pair for an interval length in seconds, at most `limit` of
+		/// them, oldest first. Intervals without trades are left out.
+		fn candles(pair: (u32, u32), interval: u64, limit: u32) -> Vec<Candle<Balance>>;
+
+		/// Reserves and total shares of the liquidity pool of a pair, if it has liquidity.
+		fn liquidity_pool(pair: (u32, u32)) -> Option<LiquidityPool<Balance>>;
+
+		/// Shares an account holds in the liquidity pool of a pair.
+		fn pool_shares(pair: (u32, u32), account_id: AccountId) -> Balance;
+
+		/// Amount of the last asset of `path` that `amount_in` of its first asset swaps
+		/// for with `swap_exact_in`, if the path can be swapped.
+		fn quote_swap_exact_in(path: Vec<u32>, amount_in: Balance) -> Option<Balance>;
+
+		/// Amount of the first asset of `path` that `swap_exact_out` spends for
+		/// `amount_out` of its last asset, if the path can be swapped.
+		fn quote_swap_exact_out(path: Vec<u32>, amount_out: Balance) -> Option<Balance>;
+
+		/// Cumulative price of the liquidity pool of a pair up to the last change of its
+		/// reserves, to average its price between two queries.
+		fn price_accumulator(pair: (u32, u32)) -> Option<PriceAccumulator>;
+
+		/// Time-weighted average price of the liquidity pool of a pair over at least the
+		/// last `period` seconds, if the pool has been priced for that long.
+		fn twap(pair: (u32, u32), period: u64) -> Option<Twap>;
+
+		/// Trading volume of an account in a pair over the last 30 days, in the quote asset
+		/// of the pair. It sets the fee tier of the account.
+		fn trading_volume(account_id: AccountId, pair: (u32, u32)) -> Balance;
+
+		/// Liquidity mining rewards an account can claim, by asset id.
+		fn pending_rewards(account_id: AccountId) -> Vec<(u32, Balance)>;
+	}
+}
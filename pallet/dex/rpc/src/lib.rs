@@ -0,0 +1,343 @@
+//! RPC interface for the DEX pallet.
+
+use jsonrpsee::{
+	core::{async_trait, Error as JsonRpseeError, RpcResult},
+	proc_macros::rpc,
+	types::error::{CallError, ErrorCode, ErrorObject},
+};
+use scale_codec::Codec;
+use sp_api::ProvideRuntimeApi;
+use sp_blockchain::HeaderBackend;
+use sp_runtime::traits::Block as BlockT;
+use std::sync::Arc;
+
+pub use pallet_dex_rpc_runtime_api::DexApi as DexRuntimeApi;
+use pallet_dex_rpc_runtime_api::{
+	BestBidAsk, Candle, LiquidityPool, Order, OrderBookDepth, PriceAccumulator, TokenInfo, Twap,
+};
+
+#[rpc(client, server)]
+pub trait DexApi<BlockHash, AccountId, Balance, BlockNumber>
+where
+	AccountId: Codec,
+	Balance: Codec,
+	BlockNumber: Codec,
+{
+	#[method(name = "dex_orderBookDepth")]
+	fn order_book_depth(
+		&self,
+		pair: (u32, u32),
+		levels: u32,
+		at: Option<BlockHash>,
+	) -> RpcResult<OrderBookDepth<Balance>>;
+
+	#[method(name = "dex_bestBidAsk")]
+	fn best_bid_ask(&self, pair: (u32, u32), at: Option<BlockHash>) -> RpcResult<BestBidAsk>;
+
+	#[method(name = "dex_userOpenOrders")]
+	fn user_open_orders(
+		&self,
+		account_id: AccountId,
+		at: Option<BlockHash>,
+	) -> RpcResult<Vec<Order<AccountId, Balance, BlockNumber>>>;
+
+	#[method(name = "dex_userBalances")]
+	fn user_balances(
+		&self,
+		account_id: AccountId,
+		at: Option<BlockHash>,
+	) -> RpcResult<Vec<(u32, TokenInfo<Balance>)>>;
+
+	#[method(name = "dex_candles")]
+	fn candles(
+		&self,
+		pair: (u32, u32),
+		interval: u64,
+		limit: u32,
+		at: Option<BlockHash>,
+	) -> RpcResult<Vec<Candle<Balance>>>;
+
+	#[method(name = "dex_liquidityPool")]
+	fn liquidity_pool(
+		&self,
+		pair: (u32, u32),
+		at: Option<BlockHash>,
+	) -> RpcResult<Option<LiquidityPool<Balance>>>;
+
+	#[method(name = "dex_poolShares")]
+	fn pool_shares(
+		&self,
+		pair: (u32, u32),
+		account_id: AccountId,
+		at: Option<BlockHash>,
+	) -> RpcResult<Balance>;
+
+	#[method(name = "dex_quoteSwapExactIn")]
+	fn quote_swap_exact_in(
+		&self,
+		path: Vec<u32>,
+		amount_in: Balance,
+		at: Option<BlockHash>,
+	) -> RpcResult<Option<Balance>>;
+
+	#[method(name = "dex_quoteSwapExactOut")]
+	fn quote_swap_exact_out(
+		&self,
+		path: Vec<u32>,
+		amount_out: Balance,
+		at: Option<BlockHash>,
+	) -> RpcResult<Option<Balance>>;
+
+	#[method(name = "dex_priceAccumulator")]
+	fn price_accumulator(
+		&self,
+		pair: (u32, u32),
+		at: Option<BlockHash>,
+	) -> RpcResult<Option<PriceAccumulator>>;
+
+	#[method(name = "dex_twap")]
+	fn twap(&self, pair: (u32, u32), period: u64, at: Option<BlockHash>)
+		-> RpcResult<Option<Twap>>;
+
+	#[method(name = "dex_tradingVolume")]
+	fn trading_volume(
+		&self,
+		account_id: AccountId,
+		pair: (u32, u32),
+		at: Option<BlockHash>,
+	) -> RpcResult<Balance>;
+
+	#[method(name = "dex_pendingRewards")]
+	fn pending_rewards(
+		&self,
+		account_id: AccountId,
+		at: Option<BlockHash>,
+	) -> RpcResult<Vec<(u32, Balance)>>;
+}
+
+fn internal_err<T: ToString>(message: T) -> JsonRpseeError {
+	JsonRpseeError::Call(CallError::Custom(ErrorObject::owned(
+		ErrorCode::InternalError.code(),
+		message.to_string(),
+		None::<()>,
+	)))
+}
+
+fn handle_response<T, E: std::fmt::Debug>(result: Result<T, E>, msg: &str) -> RpcResult<T> {
+	result.map_err(|err| internal_err(format!("Runtime error: {}: {:?}", msg, err)))
+}
+
+/// A struct that implements the [`DexApiServer`].
+pub struct Dex<C, B> {
+	client: Arc<C>,
+	_marker: std::marker::PhantomData<B>,
+}
+
+impl<C, B> Dex<C, B> {
+	/// Create new `Dex` with the given reference to the client.
+	pub fn new(client: Arc<C>) -> Self {
+		Dex {
+			client,
+			_marker: Default::default(),
+		}
+	}
+}
+
+#[async_trait]
+impl<C, Block, AccountId, Balance, BlockNumber>
+	DexApiServer<<Block as BlockT>::Hash, AccountId, Balance, BlockNumber> for Dex<C, Block>
+where
+	Block: BlockT,
+	C: Send + Sync + 'static + ProvideRuntimeApi<Block> + HeaderBackend<Block>,
+	C::Api: DexRuntimeApi<Block, AccountId, Balance, BlockNumber>,
+	AccountId: Codec,
+	Balance: Codec,
+	BlockNumber: Codec,
+{
+	fn order_book_depth(
+		&self,
+		pair: (u32, u32),
+		levels: u32,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> RpcResult<OrderBookDepth<Balance>> {
+		let api = self.client.runtime_api();
+		let at = at.unwrap_or_else(|| self.client.info().best_hash);
+
+		handle_response(
+			api.order_book_depth(at, pair, levels),
+			"Unable to query the order book depth.",
+		)
+	}
+
+	fn best_bid_ask(
+		&self,
+		pair: (u32, u32),
+		at: Option<<Block as BlockT>::Hash>,
+	) -> RpcResult<BestBidAsk> {
+		let api = self.client.runtime_api();
+		let at = at.unwrap_or_else(|| self.client.info().best_hash);
+
+		handle_response(
+			api.best_bid_ask(at, pair),
+			"Unable to query the best bid and ask.",
+		)
+	}
+
+	fn user_open_orders(
+		&self,
+		account_id: AccountId,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> RpcResult<Vec<Order<AccountId, Balance, BlockNumber>>> {
+		let api = self.client.runtime_api();
+		let at = at.unwrap_or_else(|| self.client.info().best_hash);
+
+		handle_response(
+			api.user_open_orders(at, account_id),
+			"Unable to query the open orders.",
+		)
+	}
+
+	fn user_balances(
+		&self,
+		account_id: AccountId,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> RpcResult<Vec<(u32, TokenInfo<Balance>)>> {
+		let api = self.client.runtime_api();
+		let at = at.unwrap_or_else(|| self.client.info().best_hash);
+
+		handle_response(
+			api.user_balances(at, account_id),
+			"Unable to query the balances.",
+		)
+	}
+
+	fn candles(
+		&self,
+		pair: (u32, u32),
+		interval: u64,
+		limit: u32,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> RpcResult<Vec<Candle<Balance>>> {
+		let api = self.client.runtime_api();
+		let at = at.unwrap_or_else(|| self.client.info().best_hash);
+
+		handle_response(
+			api.candles(at, pair, interval, limit),
+			"Unable to query the candles.",
+		)
+	}
+
+	fn liquidity_pool(
+		&self,
+		pair: (u32, u32),
+		at: Option<<Block as BlockT>::Hash>,
+	) -> RpcResult<Option<LiquidityPool<Balance>>> {
+		let api = self.client.runtime_api();
+		let at = at.unwrap_or_else(|| self.client.info().best_hash);
+
+		handle_response(
+			api.liquidity_pool(at, pair),
+			"Unable to query the liquidity pool.",
+		)
+	}
+
+	fn pool_shares(
+		&self,
+		pair: (u32, u32),
+		account_id: AccountId,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> RpcResult<Balance> {
+		let api = self.client.runtime_api();
+		let at = at.unwrap_or_else(|| self.client.info().best_hash);
+
+		handle_response(
+			api.pool_shares(at, pair, account_id),
+			"Unable to query the pool shares.",
+		)
+	}
+
+	fn quote_swap_exact_in(
+		&self,
+		path: Vec<u32>,
+		amount_in: Balance,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> RpcResult<Option<Balance>> {
+		let api = self.client.runtime_api();
+		let at = at.unwrap_or_else(|| self.client.info().best_hash);
+
+		handle_response(
+			api.quote_swap_exact_in(at, path, amount_in),
+			"Unable to quote the swap.",
+		)
+	}
+
+	fn quote_swap_exact_out(
+		&self,
+		path: Vec<u32>,
+		amount_out: Balance,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> RpcResult<Option<Balance>> {
+		let api = self.client.runtime_api();
+		let at = at.unwrap_or_else(|| self.client.info().best_hash);
+
+		handle_response(
+			api.quote_swap_exact_out(at, path, amount_out),
+			"Unable to quote the swap.",
+		)
+	}
+
+	fn price_accumulator(
+		&self,
+		pair: (u32, u32),
+		at: Option<<Block as BlockT>::Hash>,
+	) -> RpcResult<Option<PriceAccumulator>> {
+		let api = self.client.runtime_api();
+		let at = at.unwrap_or_else(|| self.client.info().best_hash);
+
+		handle_response(
+			api.price_accumulator(at, pair),
+			"Unable to query the price accumulator.",
+		)
+	}
+
+	fn twap(
+		&self,
+		pair: (u32, u32),
+		period: u64,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> RpcResult<Option<Twap>> {
+		let api = self.client.runtime_api();
+		let at = at.unwrap_or_else(|| self.client.info().best_hash);
+
+		handle_response(api.twap(at, pair, period), "Unable to query the TWAP.")
+	}
+
+	fn trading_volume(
+		&self,
+		account_id: AccountId,
+		pair: (u32, u32),
+		at: Option<<Block as BlockT>::Hash>,
+	) -> RpcResult<Balance> {
+		let api = self.client.runtime_api();
+		let at = at.unwrap_or_else(|| self.client.info().best_hash);
+
+		handle_response(
+			api.trading_volume(at, account_id, pair),
+			"Unable to query the trading volume.",
+		)
+	}
+
+	fn pending_rewards(
+		&self,
+		account_id: AccountId,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> RpcResult<Vec<(u32, Balance)>> {
+		let api = self.client.runtime_api();
+		let at = at.unwrap_or_else(|| self.client.info().best_hash);
+
+		handle_response(
+			api.pending_rewards(at, account_id),
+			"Unable to query the pending rewards.",
+		)
+	}
+}
@@ -0,0 +1,200 @@
+//! Lets the DEX trade the assets of two `fungibles` implementations under one set of
+//! `u32` asset ids, e.g. `pallet_assets` next to the `CurrencyId` tokens of `orml_tokens`.
+
+use core::marker::PhantomData;
+use frame_support::traits::tokens::{
+	fungibles::{Create, Dust, Inspect, Mutate, Unbalanced},
+	DepositConsequence, Fortitude, Precision, Preservation, Provenance, TokenError,
+	WithdrawConsequence,
+};
+use sp_runtime::{traits::Convert, DispatchError, DispatchResult};
+
+/// `fungibles` implementation for `Config::Fungibles` that holds the DEX asset ids mapped
+/// to a token by `TokenIds` in `Tokens`, and all other ids in `Assets` with the same id.
+///
+/// Token ids should be mapped to DEX asset ids that are never used by `Assets`, the
+/// asset of `Assets` with such an id can't be traded on the DEX.
+pub struct FungiblesAdapter<Assets, Tokens, TokenIds>(PhantomData<(Assets, Tokens, TokenIds)>);
+
+macro_rules! route {
+	($asset_id:expr, $method:ident($($arg:expr),*)) => {
+		match TokenIds::convert($asset_id) {
+			Some(token_id) => Tokens::$method(token_id, $($arg),*),
+			None => Assets::$method($asset_id, $($arg),*),
+		}
+	};
+}
+
+impl<AccountId, Assets, Tokens, TokenIds> Inspect<AccountId>
+	for FungiblesAdapter<Assets, Tokens, TokenIds>
+where
+	Assets: Inspect<AccountId, AssetId = u32>,
+	Tokens: Inspect<AccountId, Balance = Assets::Balance>,
+	TokenIds: Convert<u32, Option<Tokens::AssetId>>,
+{
+	type AssetId = u32;
+	type Balance = Assets::Balance;
+
+	fn total_issuance(asset_id: u32) -> Self::Balance {
+		route!(asset_id, total_issuance())
+	}
+
+	fn minimum_balance(asset_id: u32) -> Self::Balance {
+		route!(asset_id, minimum_balance())
+	}
+
+	fn total_balance(asset_id: u32, who: &AccountId) -> Self::Balance {
+		route!(asset_id, total_balance(who))
+	}
+
+	fn balance(asset_id: u32, who: &AccountId) -> Self::Balance {
+		route!(asset_id, balance(who))
+	}
+
+	fn reducible_balance(
+		asset_id: u32,
+		who: &AccountId,
+		preservation: Preservation,
+		force: Fortitude,
+	) -> Self::Balance {
+		route!(asset_id, reducible_balance(who, preservation, force))
+	}
+
+	fn can_deposit(
+		asset_id: u32,
+		who: &AccountId,
+		amount: Self::Balance,
+		provenance: Provenance,
+	) -> DepositConsequence {
+		route!(asset_id, can_deposit(who, amount, provenance))
+	}
+
+	fn can_withdraw(
+		asset_id: u32,
+		who: &AccountId,
+		amount: Self::Balance,
+	) -> WithdrawConsequence<Self::Balance> {
+		route!(asset_id, can_withdraw(who, amount))
+	}
+
+	fn asset_exists(asset_id: u32) -> bool {
+		route!(asset_id, asset_exists())
+	}
+}
+
+impl<AccountId, Assets, Tokens, TokenIds> Unbalanced<AccountId>
+	for FungiblesAdapter<Assets, Tokens, TokenIds>
+where
+	Assets: Unbalanced<AccountId, AssetId = u32>,
+	Tokens: Unbalanced<AccountId, Balance = Assets::Balance>,
+	TokenIds: Convert<u32, Option<Tokens::AssetId>>,
+{
+	fn handle_dust(Dust(asset_id, amount): Dust<AccountId, Self>) {
+		match TokenIds::convert(asset_id) {
+			Some(token_id) => Tokens::handle_dust(Dust(token_id, amount)),
+			None => Assets::handle_dust(Dust(asset_id, amount)),
+		}
+	}
+
+	fn write_balance(
+		asset_id: u32,
+		who: &AccountId,
+		amount: Self::Balance,
+	) -> Result<Option<Self::Balance>, DispatchError> {
+		route!(asset_id, write_balance(who, amount))
+	}
+
+	fn set_total_issuance(asset_id: u32, amount: Self::Balance) {
+		route!(asset_id, set_total_issuance(amount))
+	}
+
+	fn decrease_balance(
+		asset_id: u32,
+		who: &AccountId,
+		amount: Self::Balance,
+		precision: Precision,
+		preservation: Preservation,
+		force: Fortitude,
+	) -> Result<Self::Balance, DispatchError> {
+		route!(
+			asset_id,
+			decrease_balance(who, amount, precision, preservation, force)
+		)
+	}
+
+	fn increase_balance(
+		asset_id: u32,
+		who: &AccountId,
+		amount: Self::Balance,
+		precision: Precision,
+	) -> Result<Self::Balance, DispatchError> {
+		route!(asset_id, increase_balance(who, amount, precision))
+	}
+}
+
+impl<AccountId, Assets, Tokens, TokenIds> Mutate<AccountId>
+	for FungiblesAdapter<Assets, Tokens, TokenIds>
+where
+	Assets: Mutate<AccountId, AssetId = u32>,
+	Tokens: Mutate<AccountId, Balance = Assets::Balance>,
+	TokenIds: Convert<u32, Option<Tokens::AssetId>>,
+{
+	fn mint_into(
+		asset_id: u32,
+		who: &AccountId,
+		amount: Self::Balance,
+	) -> Result<Self::Balance, DispatchError> {
+		route!(asset_id, mint_into(who, amount))
+	}
+
+	fn burn_from(
+		asset_id: u32,
+		who: &AccountId,
+		amount: Self::Balance,
+		precision: Precision,
+		force: Fortitude,
+	) -> Result<Self::Balance, DispatchError> {
+		route!(asset_id, burn_from(who, amount, precision, force))
+	}
+
+	fn transfer(
+		asset_id: u32,
+		source: &AccountId,
+		dest: &AccountId,
+		amount: Self::Balance,
+		preservation: Preservation,
+	) -> Result<Self::Balance, DispatchError> {
+		route!(asset_id, transfer(source, dest, amount, preservation))
+	}
+}
+
+/// New assets are created in `Assets`, tokens are created by the pallet of `Tokens`.
+impl<AccountId, Assets, Tokens, TokenIds> Create<AccountId>
+	for FungiblesAdapter<Assets, Tokens, TokenIds>
+where
+	Assets: Create<AccountId, AssetId = u32>,
+	Tokens: Inspect<AccountId, Balance = Assets::Balance>,
+	TokenIds: Convert<u32, Option<Tokens::AssetId>>,
+{
+	fn create(
+		asset_id: u32,
+		admin: AccountId,
+		is_sufficient: bool,
+		min_balance: Self::Balance,
+	) -> DispatchResult {
+		match TokenIds::convert(asset_id) {
+			Some(_) => Err(TokenError::Unsupported.into()),
+			None => Assets::create(asset_id, admin, is_sufficient, min_balance),
+		}
+	}
+}
+
+/// `TokenIds` of [`FungiblesAdapter`] that maps the DEX asset ids registered with
+/// `register_token` to their tokens.
+pub struct RegisteredTokenIds<T>(PhantomData<T>);
+
+impl<T: crate::Config> Convert<u32, Option<T::TokenId>> for RegisteredTokenIds<T> {
+	fn convert(asset_id: u32) -> Option<T::TokenId> {
+		crate::TokenIds::<T>::get(asset_id)
+	}
+}
@@ -0,0 +1,1111 @@
+//! Benchmarks for the DEX pallet.
+
+use super::*;
+use frame_benchmarking::v2::*;
+use frame_support::{
+	traits::{fungibles::Create, Hooks},
+	BoundedVec,
+};
+use frame_system::RawOrigin;
+use sp_runtime::traits::TrailingZeroInput;
+
+use crate::Pallet as Dex;
+
+const BASE_ASSET_ID: u32 = 1_000_001;
+const QUOTE_ASSET_ID: u32 = 1_000_002;
+const NATIVE_ASSET_ID: u32 = 1_000_000;
+const SEED: u32 = 0;
+
+fn setup_assets<T: Config>()
+where
+	T::Fungibles: Create<T::AccountId>,
+{
+	for asset_id in [BASE_ASSET_ID, QUOTE_ASSET_ID] {
+		// the asset might already exist in the genesis of the benchmarked chain
+		let _ = T::Fungibles::create(asset_id, Dex::<T>::account_id(), true, One::one());
+		TokenIndex::<T>::insert(asset_id, asset_id as u64);
+	}
+	TradingPairs::<T>::insert((BASE_ASSET_ID, QUOTE_ASSET_ID), TradingPair::default());
+}
+
+/// Deposits `amount` of `asset_id` into the DEX for `who`.
+fn fund<T: Config>(who: &T::AccountId, asset_id: u32, amount: u32) {
+	T::Fungibles::mint_into(asset_id, who, amount.into()).expect("asset exists");
+	Dex::<T>::deposit(
+		RawOrigin::Signed(who.clone()).into(),
+		asset_id,
+		amount.into(),
+	)
+	.expect("asset is allowlisted");
+}
+
+/// Places a good-till-expiration order of `base_amount` base for as much quote, returns its
+/// index.
+fn make_order<T: Config>(who: &T::AccountId, order_type: OrderType, base_amount: u32) -> u64 {
+	let order_index = NextOrderIndex::<T>::get();
+	let expiration_block = frame_system::Pallet::<T>::block_number() + 100u32.into();
+	Dex::<T>::make_order(
+		RawOrigin::Signed(who.clone()).into(),
+		BASE_ASSET_ID,
+		QUOTE_ASSET_ID,
+		base_amount.into(),
+		base_amount.into(),
+		order_type,
+		expiration_block,
+	)
+	.expect("order is valid");
+	order_index
+}
+
+/// A stop-loss that sells `base_amount` base for as much quote once the last trade price
+/// drops to 1.
+fn stop_loss<T: Config>(
+	base_amount: u32,
+) -> (OrderRequest<BalanceOf<T>, BlockNumberFor<T>>, Trigger) {
+	let order = OrderRequest {
+		asset_id_1: BASE_ASSET_ID,
+		asset_id_2: QUOTE_ASSET_ID,
+		offered_amount: base_amount.into(),
+		requested_amount: base_amount.into(),
+		order_type: OrderType::SELL,
+		order_kind: OrderKind::Limit,
+		time_in_force: TimeInForce::GoodTillExpiration,
+		expiration_block: frame_system::Pallet::<T>::block_number() + 100u32.into(),
+	};
+	let trigger = Trigger {
+		kind: ConditionalOrderKind::StopLoss,
+		source: TriggerSource::LastTradePrice,
+		price: FixedU128::one(),
+	};
+	(order, trigger)
+}
+
+/// Adds `amount` of both assets to the pool of a pair for `who`.
+fn add_liquidity<T: Config>(who: &T::AccountId, pair: (u32, u32), amount: u32) {
+	fund::<T>(who, pair.0, amount);
+	fund::<T>(who, pair.1, amount);
+	Dex::<T>::add_liquidity(
+		RawOrigin::Signed(who.clone()).into(),
+		pair.0,
+		pair.1,
+		amount.into(),
+		amount.into(),
+		Zero::zero(),
+	)
+	.expect("pair is registered");
+}
+
+/// A swap path of `n` assets that starts with quote for base, with a pool of 1_000_000 of
+/// both assets in each of its pairs.
+fn setup_path<T: Config>(n: u32) -> Vec<u32>
+where
+	T::Fungibles: Create<T::AccountId>,
+{
+	setup_assets::<T>();
+	let path: Vec<u32> = [QUOTE_ASSET_ID, BASE_ASSET_ID]
+		.into_iter()
+		.chain((1..n - 1).map(|i| QUOTE_ASSET_ID + i))
+		.collect();
+	for asset_id in &path[2..] {
+		let _ = T::Fungibles::create(*asset_id, Dex::<T>::account_id(), true, One::one());
+		TokenIndex::<T>::insert(asset_id, *asset_id as u64);
+	}
+
+	let provider: T::AccountId = account("provider", 0, SEED);
+	for assets in path.windows(2) {
+		let pair = (assets[0].min(assets[1]), assets[0].max(assets[1]));
+		TradingPairs::<T>::insert(pair, TradingPair::default());
+		add_liquidity::<T>(&provider, pair, 1_000_000);
+	}
+	path
+}
+
+fn set_fees<T: Config>() {
+	PairFees::<T>::insert(
+		(BASE_ASSET_ID, QUOTE_ASSET_ID),
+		PairFee {
+			maker_fee: Permill::from_percent(1),
+			taker_fee: Permill::from_percent(2),
+		},
+	);
+}
+
+/// Terms of a liquidity mining epoch of 100 blocks that rewards orders within 10% of the
+/// last trade price, with the reward minted to the `RewardSource`.
+fn reward_terms<T: Config>() -> RewardTerms<BalanceOf<T>, BlockNumberFor<T>> {
+	T::Fungibles::mint_into(BASE_ASSET_ID, &T::RewardSource::get(), 100_000u32.into())
+		.expect("asset exists");
+	RewardTerms {
+		asset_id: BASE_ASSET_ID,
+		reward_per_block: 1_000u32.into(),
+		duration: 100u32.into(),
+		reference: ReferencePrice::LastTrade,
+		max_spread: Permill::from_percent(10),
+	}
+}
+
+#[benchmarks(where T::Fungibles: Create<T::AccountId>)]
+mod benchmarks {
+	use super::*;
+
+	#[benchmark]
+	fn deposit() {
+		setup_assets::<T>();
+		let caller: T::AccountId = whitelisted_caller();
+		T::Fungibles::mint_into(BASE_ASSET_ID, &caller, 1_000u32.into()).unwrap();
+
+		#[extrinsic_call]
+		_(
+			RawOrigin::Signed(caller.clone()),
+			BASE_ASSET_ID,
+			1_000u32.into(),
+		);
+
+		assert_eq!(
+			UserTokenInfoes::<T>::get(&caller, BASE_ASSET_ID).amount,
+			1_000u32.into()
+		);
+	}
+
+	#[benchmark]
+	fn withdraw() {
+		setup_assets::<T>();
+		let caller: T::AccountId = whitelisted_caller();
+		fund::<T>(&caller, BASE_ASSET_ID, 1_000);
+
+		#[extrinsic_call]
+		_(
+			RawOrigin::Signed(caller.clone()),
+			BASE_ASSET_ID,
+			1_000u32.into(),
+		);
+
+		assert!(UserTokenInfoes::<T>::get(&caller, BASE_ASSET_ID)
+			.amount
+			.is_zero());
+	}
+
+	#[benchmark]
+	fn make_order() {
+		setup_assets::<T>();
+		let caller: T::AccountId = whitelisted_caller();
+		fund::<T>(&caller, QUOTE_ASSET_ID, 1_000);
+		let expiration_block = frame_system::Pallet::<T>::block_number() + 100u32.into();
+		let order_index = NextOrderIndex::<T>::get();
+
+		#[extrinsic_call]
+		_(
+			RawOrigin::Signed(caller),
+			BASE_ASSET_ID,
+			QUOTE_ASSET_ID,
+			1_000u32.into(),
+			100u32.into(),
+			OrderType::BUY,
+			expiration_block,
+		);
+
+		assert!(Orders::<T>::contains_key(order_index));
+	}
+
+	#[benchmark]
+	fn cancel_order() {
+		setup_assets::<T>();
+		let caller: T::AccountId = whitelisted_caller();
+		fund::<T>(&caller, BASE_ASSET_ID, 1_000);
+		let order_index = super::make_order::<T>(&caller, OrderType::SELL, 1_000);
+
+		#[extrinsic_call]
+		_(RawOrigin::Signed(caller), order_index);
+
+		assert!(!Orders::<T>::contains_key(order_index));
+	}
+
+	#[benchmark]
+	fn take_order() {
+		setup_assets::<T>();
+		set_fees::<T>();
+		let maker: T::AccountId = account("maker", 0, SEED);
+		fund::<T>(&maker, BASE_ASSET_ID, 1_000);
+		let order_index = super::make_order::<T>(&maker, OrderType::SELL, 1_000);
+		let caller: T::AccountId = whitelisted_caller();
+		fund::<T>(&caller, QUOTE_ASSET_ID, 1_000);
+
+		#[extrinsic_call]
+		_(RawOrigin::Signed(caller), order_index);
+
+		assert!(!Orders::<T>::contains_key(order_index));
+	}
+
+	#[benchmark]
+	fn deposit_native() {
+		NativeAssetId::<T>::put(NATIVE_ASSET_ID);
+		TokenIndex::<T>::insert(NATIVE_ASSET_ID, NATIVE_ASSET_ID as u64);
+		let caller: T::AccountId = whitelisted_caller();
+		let amount = T::Currency::minimum_balance() * 10u32.into();
+		T::Currency::make_free_balance_be(&caller, amount * 10u32.into());
+
+		#[extrinsic_call]
+		_(RawOrigin::Signed(caller.clone()), amount);
+
+		assert_eq!(
+			UserTokenInfoes::<T>::get(&caller, NATIVE_ASSET_ID).amount,
+			amount
+		);
+	}
+
+	#[benchmark]
+	fn withdraw_native() {
+		NativeAssetId::<T>::put(NATIVE_ASSET_ID);
+		TokenIndex::<T>::insert(NATIVE_ASSET_ID, NATIVE_ASSET_ID as u64);
+		let caller: T::AccountId = whitelisted_caller();
+		let amount = T::Currency::minimum_balance() * 10u32.into();
+		T::Currency::make_free_balance_be(&caller, amount * 10u32.into());
+		Dex::<T>::deposit_native(RawOrigin::Signed(caller.clone()).into(), amount).unwrap();
+
+		#[extrinsic_call]
+		_(RawOrigin::Signed(caller.clone()), amount);
+
+		assert!(UserTokenInfoes::<T>::get(&caller, NATIVE_ASSET_ID)
+			.amount
+			.is_zero());
+	}
+
+	#[benchmark]
+	fn allowlist_asset() -> Result<(), BenchmarkError> {
+		let origin =
+			T::PrivilegedOrigin::try_successful_origin().map_err(|_| BenchmarkError::Weightless)?;
+
+		#[extrinsic_call]
+		_(origin as T::RuntimeOrigin, BASE_ASSET_ID);
+
+		assert!(TokenIndex::<T>::contains_key(BASE_ASSET_ID));
+		Ok(())
+	}
+
+	#[benchmark]
+	fn update_match_order_unsigned(t: Linear<1, 100>) {
+		setup_assets::<T>();
+		set_fees::<T>();
+
+		let maker_orders: Vec<u64> = (0..t)
+			.map(|i| {
+				let maker: T::AccountId = account("maker", i, SEED);
+				fund::<T>(&maker, BASE_ASSET_ID, 1);
+				super::make_order::<T>(&maker, OrderType::SELL, 1)
+			})
+			.collect();
+		let caller: T::AccountId = whitelisted_caller();
+		fund::<T>(&caller, QUOTE_ASSET_ID, t);
+		let taker_index = super::make_order::<T>(&caller, OrderType::BUY, t);
+		let taker_order = Orders::<T>::get(taker_index).unwrap();
+
+		// the orders of a trade are the snapshots the matching engine takes after it
+		let one: BalanceOf<T> = One::one();
+		let mut taker_snapshot = taker_order.clone();
+		let match_details = maker_orders
+			.into_iter()
+			.map(|maker_index| {
+				let mut maker_snapshot = Orders::<T>::get(maker_index).unwrap();
+				maker_snapshot.unfilled_offered -= one;
+				maker_snapshot.unfilled_requested -= one;
+				taker_snapshot.unfilled_offered -= one;
+				taker_snapshot.unfilled_requested -= one;
+				Trade {
+					price: FixedU128::one(),
+					quantity_base: one,
+					quantity_quote: one,
+					taker_order: taker_snapshot.clone(),
+					maker_order: maker_snapshot,
+				}
+			})
+			.collect();
+		let match_result = MatchResult {
+			taker_order,
+			match_details,
+			self_trades: Vec::new(),
+		};
+		let signature = Decode::decode(&mut TrailingZeroInput::zeroes()).unwrap();
+
+		#[extrinsic_call]
+		_(RawOrigin::None, match_result, 0, signature);
+
+		assert!(!Orders::<T>::contains_key(taker_index));
+	}
+
+	#[benchmark]
+	fn place_order() {
+		setup_assets::<T>();
+		let caller: T::AccountId = whitelisted_caller();
+		fund::<T>(&caller, QUOTE_ASSET_ID, 1_000);
+		let expiration_block = frame_system::Pallet::<T>::block_number() + 100u32.into();
+		let order_index = NextOrderIndex::<T>::get();
+
+		#[extrinsic_call]
+		_(
+			RawOrigin::Signed(caller),
+			BASE_ASSET_ID,
+			QUOTE_ASSET_ID,
+			1_000u32.into(),
+			100u32.into(),
+			OrderType::BUY,
+			OrderKind::Limit,
+			TimeInForce::GoodTillExpiration,
+			expiration_block,
+		);
+
+		assert!(Orders::<T>::contains_key(order_index));
+	}
+
+	#[benchmark]
+	fn set_pair_config() -> Result<(), BenchmarkError> {
+		let origin =
+			T::PrivilegedOrigin::try_successful_origin().map_err(|_| BenchmarkError::Weightless)?;
+		let config = PairConfig {
+			tick_size: FixedU128::from_rational(1, 100),
+			lot_size: 10u32.into(),
+			min_notional: 100u32.into(),
+		};
+
+		#[extrinsic_call]
+		_(
+			origin as T::RuntimeOrigin,
+			BASE_ASSET_ID,
+			QUOTE_ASSET_ID,
+			config.clone(),
+		);
+
+		assert_eq!(
+			PairConfigs::<T>::get((BASE_ASSET_ID, QUOTE_ASSET_ID)),
+			config
+		);
+		Ok(())
+	}
+
+	#[benchmark]
+	fn set_pair_fee() -> Result<(), BenchmarkError> {
+		let origin =
+			T::PrivilegedOrigin::try_successful_origin().map_err(|_| BenchmarkError::Weightless)?;
+		let fee = PairFee {
+			maker_fee: Permill::from_percent(1),
+			taker_fee: Permill::from_percent(2),
+		};
+
+		#[extrinsic_call]
+		_(
+			origin as T::RuntimeOrigin,
+			BASE_ASSET_ID,
+			QUOTE_ASSET_ID,
+			fee.clone(),
+		);
+
+		assert_eq!(PairFees::<T>::get((BASE_ASSET_ID, QUOTE_ASSET_ID)), fee);
+		Ok(())
+	}
+
+	#[benchmark]
+	fn on_initialize(e: Linear<0, 100>) {
+		setup_assets::<T>();
+		let expiration_block = frame_system::Pallet::<T>::block_number() + 100u32.into();
+		for i in 0..e {
+			let maker: T::AccountId = account("maker", i, SEED);
+			fund::<T>(&maker, BASE_ASSET_ID, 1);
+			super::make_order::<T>(&maker, OrderType::SELL, 1);
+		}
+
+		#[block]
+		{
+			Dex::<T>::on_initialize(expiration_block);
+		}
+
+		assert!(OrderExpiration::<T>::get(expiration_block).is_empty());
+	}
+
+	#[benchmark]
+	fn match_order(t: Linear<0, 100>) {
+		setup_assets::<T>();
+		set_fees::<T>();
+		for i in 0..t {
+			let maker: T::AccountId = account("maker", i, SEED);
+			fund::<T>(&maker, BASE_ASSET_ID, 1);
+			let maker_index = super::make_order::<T>(&maker, OrderType::SELL, 1);
+			Dex::<T>::match_order_on_chain(maker_index).unwrap();
+		}
+		let caller: T::AccountId = whitelisted_caller();
+		fund::<T>(&caller, QUOTE_ASSET_ID, t.max(1));
+		let taker_index = super::make_order::<T>(&caller, OrderType::BUY, t.max(1));
+
+		#[block]
+		{
+			Dex::<T>::match_order_on_chain(taker_index).unwrap();
+		}
+
+		if t > 0 {
+			assert!(!Orders::<T>::contains_key(taker_index));
+		}
+	}
+
+	#[benchmark]
+	fn amend_order() {
+		setup_assets::<T>();
+		let caller: T::AccountId = whitelisted_caller();
+		fund::<T>(&caller, BASE_ASSET_ID, 1_000);
+		let order_index = super::make_order::<T>(&caller, OrderType::SELL, 1_000);
+		let new_order_index = NextOrderIndex::<T>::get();
+
+		// a new price replaces the order, which costs more than amending it in place
+		#[extrinsic_call]
+		_(
+			RawOrigin::Signed(caller),
+			order_index,
+			1_000u32.into(),
+			2_000u32.into(),
+		);
+
+		assert!(!Orders::<T>::contains_key(order_index));
+		assert!(Orders::<T>::contains_key(new_order_index));
+	}
+
+	#[benchmark]
+	fn batch_place(n: Linear<1, 20>) {
+		setup_assets::<T>();
+		let caller: T::AccountId = whitelisted_caller();
+		fund::<T>(&caller, QUOTE_ASSET_ID, 1_000 * n);
+		let order = OrderRequest {
+			asset_id_1: BASE_ASSET_ID,
+			asset_id_2: QUOTE_ASSET_ID,
+			offered_amount: 1_000u32.into(),
+			requested_amount: 100u32.into(),
+			order_type: OrderType::BUY,
+			order_kind: OrderKind::Limit,
+			time_in_force: TimeInForce::GoodTillExpiration,
+			expiration_block: frame_system::Pallet::<T>::block_number() + 100u32.into(),
+		};
+		let orders: BoundedVec<_, T::MaxOrdersPerBatch> = (0..n)
+			.map(|_| order.clone())
+			.collect::<Vec<_>>()
+			.try_into()
+			.unwrap();
+		let last_order_index = NextOrderIndex::<T>::get() + n as u64 - 1;
+
+		#[extrinsic_call]
+		_(RawOrigin::Signed(caller), orders);
+
+		assert!(Orders::<T>::contains_key(last_order_index));
+	}
+
+	#[benchmark]
+	fn batch_cancel(n: Linear<1, 20>) {
+		setup_assets::<T>();
+		let caller: T::AccountId = whitelisted_caller();
+		fund::<T>(&caller, BASE_ASSET_ID, n);
+		let order_indexes: Vec<u64> = (0..n)
+			.map(|_| super::make_order::<T>(&caller, OrderType::SELL, 1))
+			.collect();
+		let order_indexes: BoundedVec<_, T::MaxOrdersPerBatch> = order_indexes.try_into().unwrap();
+
+		#[extrinsic_call]
+		_(RawOrigin::Signed(caller.clone()), order_indexes);
+
+		assert!(UserOrders::<T>::iter_key_prefix(&caller).next().is_none());
+	}
+
+	#[benchmark]
+	fn cancel_all(n: Linear<0, 100>) {
+		setup_assets::<T>();
+		let caller: T::AccountId = whitelisted_caller();
+		fund::<T>(&caller, BASE_ASSET_ID, n.max(1));
+		for _ in 0..n {
+			super::make_order::<T>(&caller, OrderType::SELL, 1);
+		}
+
+		#[extrinsic_call]
+		_(
+			RawOrigin::Signed(caller.clone()),
+			BASE_ASSET_ID,
+			QUOTE_ASSET_ID,
+		);
+
+		assert!(UserOrders::<T>::iter_key_prefix(&caller).next().is_none());
+	}
+
+	#[benchmark]
+	fn place_conditional_order() {
+		setup_assets::<T>();
+		let caller: T::AccountId = whitelisted_caller();
+		fund::<T>(&caller, BASE_ASSET_ID, 1_000);
+		let (order, trigger) = stop_loss::<T>(1_000);
+		let conditional_order_index = NextConditionalOrderIndex::<T>::get();
+
+		#[extrinsic_call]
+		_(RawOrigin::Signed(caller), order, trigger);
+
+		assert!(ConditionalOrders::<T>::contains_key(
+			conditional_order_index
+		));
+	}
+
+	#[benchmark]
+	fn cancel_conditional_order() {
+		setup_assets::<T>();
+		let caller: T::AccountId = whitelisted_caller();
+		fund::<T>(&caller, BASE_ASSET_ID, 1_000);
+		let (order, trigger) = stop_loss::<T>(1_000);
+		let conditional_order_index =
+			Dex::<T>::do_place_conditional_order(caller.clone(), order, trigger).unwrap();
+
+		#[extrinsic_call]
+		_(RawOrigin::Signed(caller), conditional_order_index);
+
+		assert!(!ConditionalOrders::<T>::contains_key(
+			conditional_order_index
+		));
+	}
+
+	#[benchmark]
+	fn trigger_conditional_order() {
+		setup_assets::<T>();
+		let caller: T::AccountId = whitelisted_caller();
+		fund::<T>(&caller, BASE_ASSET_ID, 1_000);
+		let (order, trigger) = stop_loss::<T>(1_000);
+		let conditional_order_index =
+			Dex::<T>::do_place_conditional_order(caller, order, trigger).unwrap();
+		let conditional_order = ConditionalOrders::<T>::get(conditional_order_index).unwrap();
+		let order_index = NextOrderIndex::<T>::get();
+
+		#[block]
+		{
+			Dex::<T>::trigger_conditional_order(conditional_order_index, conditional_order);
+		}
+
+		assert!(!ConditionalOrders::<T>::contains_key(
+			conditional_order_index
+		));
+		assert!(Orders::<T>::contains_key(order_index));
+	}
+
+	#[benchmark]
+	fn register_trading_pair() {
+		setup_assets::<T>();
+		TradingPairs::<T>::remove((BASE_ASSET_ID, QUOTE_ASSET_ID));
+
+		#[extrinsic_call]
+		_(RawOrigin::Root, BASE_ASSET_ID, QUOTE_ASSET_ID, One::one());
+
+		assert!(TradingPairs::<T>::contains_key((
+			BASE_ASSET_ID,
+			QUOTE_ASSET_ID
+		)));
+	}
+
+	#[benchmark]
+	fn set_trading_pair_status(o: Linear<0, 100>, c: Linear<0, 100>) {
+		setup_assets::<T>();
+		for i in 0..o {
+			let maker: T::AccountId = account("maker", i, SEED);
+			fund::<T>(&maker, BASE_ASSET_ID, 1);
+			super::make_order::<T>(&maker, OrderType::SELL, 1);
+		}
+		// a runtime may allow fewer conditional orders per pair than the component range
+		let c = c.min(T::MaxConditionalOrdersPerPair::get());
+		let caller: T::AccountId = whitelisted_caller();
+		fund::<T>(&caller, BASE_ASSET_ID, c.max(1));
+		for _ in 0..c {
+			let (order, trigger) = stop_loss::<T>(1);
+			Dex::<T>::do_place_conditional_order(caller.clone(), order, trigger).unwrap();
+		}
+
+		// delisting cancels every order of the pair
+		#[extrinsic_call]
+		_(
+			RawOrigin::Root,
+			BASE_ASSET_ID,
+			QUOTE_ASSET_ID,
+			PairStatus::Delisted,
+		);
+
+		assert_eq!(PairOrderCount::<T>::get((BASE_ASSET_ID, QUOTE_ASSET_ID)), 0);
+		assert!(PairConditionalOrders::<T>::get((BASE_ASSET_ID, QUOTE_ASSET_ID)).is_empty());
+	}
+
+	#[benchmark]
+	fn set_min_order_size() {
+		setup_assets::<T>();
+
+		#[extrinsic_call]
+		_(RawOrigin::Root, BASE_ASSET_ID, QUOTE_ASSET_ID, 10u32.into());
+
+		assert_eq!(
+			TradingPairs::<T>::get((BASE_ASSET_ID, QUOTE_ASSET_ID))
+				.unwrap()
+				.min_order_size,
+			10u32.into()
+		);
+	}
+
+	#[benchmark]
+	fn set_price_band() {
+		setup_assets::<T>();
+		let price_band = PriceBand {
+			reference: ReferencePrice::LastTrade,
+			max_deviation: Permill::from_percent(10),
+			max_move: Permill::from_percent(20),
+			window: 10u32.into(),
+			halt_duration: 5u32.into(),
+		};
+
+		#[extrinsic_call]
+		_(
+			RawOrigin::Root,
+			BASE_ASSET_ID,
+			QUOTE_ASSET_ID,
+			Some(price_band.clone()),
+		);
+
+		assert_eq!(
+			PriceBands::<T>::get((BASE_ASSET_ID, QUOTE_ASSET_ID)),
+			Some(price_band)
+		);
+	}
+
+	#[benchmark]
+	fn add_liquidity() {
+		setup_assets::<T>();
+		let provider: T::AccountId = account("provider", 0, SEED);
+		super::add_liquidity::<T>(&provider, (BASE_ASSET_ID, QUOTE_ASSET_ID), 10_000);
+		let caller: T::AccountId = whitelisted_caller();
+		fund::<T>(&caller, BASE_ASSET_ID, 1_000);
+		fund::<T>(&caller, QUOTE_ASSET_ID, 1_000);
+
+		// liquidity added to an existing pool is priced at the ratio of its reserves
+		#[extrinsic_call]
+		_(
+			RawOrigin::Signed(caller.clone()),
+			BASE_ASSET_ID,
+			QUOTE_ASSET_ID,
+			1_000u32.into(),
+			1_000u32.into(),
+			Zero::zero(),
+		);
+
+		assert_eq!(
+			PoolShares::<T>::get((BASE_ASSET_ID, QUOTE_ASSET_ID), &caller),
+			1_000u32.into()
+		);
+	}
+
+	#[benchmark]
+	fn remove_liquidity() {
+		setup_assets::<T>();
+		let provider: T::AccountId = account("provider", 0, SEED);
+		super::add_liquidity::<T>(&provider, (BASE_ASSET_ID, QUOTE_ASSET_ID), 10_000);
+		let caller: T::AccountId = whitelisted_caller();
+		super::add_liquidity::<T>(&caller, (BASE_ASSET_ID, QUOTE_ASSET_ID), 1_000);
+
+		#[extrinsic_call]
+		_(
+			RawOrigin::Signed(caller.clone()),
+			BASE_ASSET_ID,
+			QUOTE_ASSET_ID,
+			1_000u32.into(),
+			Zero::zero(),
+			Zero::zero(),
+		);
+
+		assert!(PoolShares::<T>::get((BASE_ASSET_ID, QUOTE_ASSET_ID), &caller).is_zero());
+	}
+
+	#[benchmark]
+	fn swap() {
+		setup_assets::<T>();
+		set_fees::<T>();
+		let provider: T::AccountId = account("provider", 0, SEED);
+		super::add_liquidity::<T>(&provider, (BASE_ASSET_ID, QUOTE_ASSET_ID), 1_000_000);
+		let maker: T::AccountId = account("maker", 0, SEED);
+		fund::<T>(&maker, BASE_ASSET_ID, 1_000);
+		let maker_index = super::make_order::<T>(&maker, OrderType::SELL, 1_000);
+		let _ = Dex::<T>::match_order_on_chain(maker_index);
+		let caller: T::AccountId = whitelisted_caller();
+		fund::<T>(&caller, QUOTE_ASSET_ID, 1_000);
+
+		#[extrinsic_call]
+		_(
+			RawOrigin::Signed(caller.clone()),
+			QUOTE_ASSET_ID,
+			BASE_ASSET_ID,
+			1_000u32.into(),
+			Zero::zero(),
+		);
+
+		assert!(UserTokenInfoes::<T>::get(&caller, QUOTE_ASSET_ID)
+			.amount
+			.is_zero());
+	}
+
+	#[benchmark]
+	fn swap_exact_in(n: Linear<2, 4>) {
+		let path = setup_path::<T>(n);
+		set_fees::<T>();
+		let maker: T::AccountId = account("maker", 0, SEED);
+		fund::<T>(&maker, BASE_ASSET_ID, 1_000);
+		let maker_index = super::make_order::<T>(&maker, OrderType::SELL, 1_000);
+		let _ = Dex::<T>::match_order_on_chain(maker_index);
+		let caller: T::AccountId = whitelisted_caller();
+		fund::<T>(&caller, QUOTE_ASSET_ID, 1_000);
+		let last_asset_id = path[path.len() - 1];
+		let path: BoundedVec<_, T::MaxSwapPathLength> = path.try_into().unwrap();
+
+		#[extrinsic_call]
+		_(
+			RawOrigin::Signed(caller.clone()),
+			path,
+			1_000u32.into(),
+			Zero::zero(),
+		);
+
+		assert!(!UserTokenInfoes::<T>::get(&caller, last_asset_id)
+			.amount
+			.is_zero());
+	}
+
+	#[benchmark]
+	fn swap_exact_out(n: Linear<2, 4>) {
+		let path = setup_path::<T>(n);
+		let caller: T::AccountId = whitelisted_caller();
+		fund::<T>(&caller, QUOTE_ASSET_ID, 1_000);
+		let last_asset_id = path[path.len() - 1];
+		let path: BoundedVec<_, T::MaxSwapPathLength> = path.try_into().unwrap();
+
+		#[extrinsic_call]
+		_(
+			RawOrigin::Signed(caller.clone()),
+			path,
+			100u32.into(),
+			1_000u32.into(),
+		);
+
+		assert_eq!(
+			UserTokenInfoes::<T>::get(&caller, last_asset_id).amount,
+			100u32.into()
+		);
+	}
+
+	#[benchmark]
+	fn set_fee_tiers() {
+		setup_assets::<T>();
+		let tiers: FeeTiersOf<T> = (0..T::MaxFeeTiers::get())
+			.map(|i| FeeTier {
+				min_volume: (1_000 * (i + 1)).into(),
+				fee: PairFee {
+					maker_fee: Permill::zero(),
+					taker_fee: Permill::from_percent(1),
+				},
+				maker_rebate: Permill::from_percent(10),
+			})
+			.collect::<Vec<_>>()
+			.try_into()
+			.unwrap();
+
+		#[extrinsic_call]
+		_(
+			RawOrigin::Root,
+			BASE_ASSET_ID,
+			QUOTE_ASSET_ID,
+			tiers.clone(),
+		);
+
+		assert_eq!(FeeTiers::<T>::get((BASE_ASSET_ID, QUOTE_ASSET_ID)), tiers);
+	}
+
+	#[benchmark]
+	fn fund_reward_epoch() {
+		setup_assets::<T>();
+		let terms = reward_terms::<T>();
+
+		#[extrinsic_call]
+		_(RawOrigin::Root, BASE_ASSET_ID, QUOTE_ASSET_ID, terms);
+
+		assert!(PairRewardEpochs::<T>::contains_key((
+			BASE_ASSET_ID,
+			QUOTE_ASSET_ID
+		)));
+	}
+
+	#[benchmark]
+	fn claim_rewards(o: Linear<0, 100>) {
+		setup_assets::<T>();
+		Dex::<T>::fund_reward_epoch(
+			RawOrigin::Root.into(),
+			BASE_ASSET_ID,
+			QUOTE_ASSET_ID,
+			reward_terms::<T>(),
+		)
+		.expect("terms are valid");
+		LastTradePrice::<T>::insert((BASE_ASSET_ID, QUOTE_ASSET_ID), FixedU128::one());
+		let caller: T::AccountId = whitelisted_caller();
+		fund::<T>(&caller, BASE_ASSET_ID, o.max(1));
+		for _ in 0..o {
+			super::make_order::<T>(&caller, OrderType::SELL, 1);
+		}
+		// rewards of orders that were closed before
+		UnclaimedRewards::<T>::insert(&caller, BASE_ASSET_ID, BalanceOf::<T>::from(1_000u32));
+		frame_system::Pallet::<T>::set_block_number(
+			frame_system::Pallet::<T>::block_number() + 10u32.into(),
+		);
+
+		#[extrinsic_call]
+		_(RawOrigin::Signed(caller.clone()));
+
+		assert!(UnclaimedRewards::<T>::iter_prefix(&caller).next().is_none());
+	}
+
+	#[benchmark]
+	fn update_match_engine(b: Linear<0, MAX_BOOK_ORDERS>) {
+		setup_assets::<T>();
+		let caller: T::AccountId = whitelisted_caller();
+		fund::<T>(&caller, BASE_ASSET_ID, 1);
+		let order = Orders::<T>::get(super::make_order::<T>(&caller, OrderType::SELL, 1)).unwrap();
+
+		// the on-chain order book of the pair with `b` orders on one side
+		let mut sell_book = BoundedBTreeMap::new();
+		for order_id in 0..b as u64 {
+			let mut order = order.clone();
+			order.counter = order_id;
+			let key = OrderBookKey {
+				order_id,
+				price: order.price,
+			};
+			assert!(sell_book.try_insert(key, order).is_ok());
+		}
+		let pair = (BASE_ASSET_ID, QUOTE_ASSET_ID);
+		MatchEngines::<T>::insert(
+			pair,
+			MatchEngine {
+				buy_book: OrderBook {
+					order_type: OrderType::BUY,
+					book: BoundedBTreeMap::new(),
+				},
+				sell_book: OrderBook {
+					order_type: OrderType::SELL,
+					book: sell_book,
+				},
+				market_price: FixedU128::one(),
+				last_process_order_id: 0,
+			},
+		);
+
+		#[block]
+		{
+			MatchEngines::<T>::mutate(pair, |engine| {
+				if let Some(engine) = engine {
+					engine.last_process_order_id += 1;
+				}
+			});
+		}
+
+		assert_eq!(
+			MatchEngines::<T>::get(pair).unwrap().last_process_order_id,
+			1
+		);
+	}
+
+	#[benchmark]
+	fn register_token() {
+		let asset_id = u32::MAX;
+		let token_id = T::TokenId::decode(&mut TrailingZeroInput::zeroes()).unwrap();
+
+		#[extrinsic_call]
+		_(RawOrigin::Root, asset_id, token_id);
+
+		assert!(TokenIds::<T>::contains_key(asset_id));
+	}
+
+	#[benchmark]
+	fn deregister_token() {
+		let asset_id = u32::MAX;
+		let token_id = T::TokenId::decode(&mut TrailingZeroInput::zeroes()).unwrap();
+		TokenIds::<T>::insert(asset_id, &token_id);
+		TokenAssetIds::<T>::insert(&token_id, asset_id);
+
+		#[extrinsic_call]
+		_(RawOrigin::Root, asset_id);
+
+		assert!(!TokenIds::<T>::contains_key(asset_id));
+	}
+
+	#[benchmark]
+	fn reclaim_rewards() {
+		setup_assets::<T>();
+		Dex::<T>::fund_reward_epoch(
+			RawOrigin::Root.into(),
+			BASE_ASSET_ID,
+			QUOTE_ASSET_ID,
+			reward_terms::<T>(),
+		)
+		.expect("terms are valid");
+		// no order earned the rewards of the epoch
+		frame_system::Pallet::<T>::set_block_number(
+			frame_system::Pallet::<T>::block_number() + 100u32.into(),
+		);
+
+		#[extrinsic_call]
+		_(RawOrigin::Root, 0);
+
+		assert!(RewardEpochs::<T>::get(0).unwrap().unearned_reward.is_zero());
+	}
+
+	#[benchmark]
+	fn query_tokens() {
+		// the allowlist is full
+		let token_infoes: BoundedVec<u32, T::MaxAssets> = (0..T::MaxAssets::get())
+			.collect::<Vec<_>>()
+			.try_into()
+			.expect("at most MaxAssets tokens");
+		TokenInfoes::<T>::put(token_infoes);
+		let tokens;
+
+		#[block]
+		{
+			tokens = TokenInfoes::<T>::get();
+		}
+
+		assert_eq!(tokens.len() as u32, T::MaxAssets::get());
+	}
+
+	#[benchmark]
+	fn query_balance() {
+		setup_assets::<T>();
+		let owner: T::AccountId = account("owner", 0, SEED);
+		fund::<T>(&owner, BASE_ASSET_ID, 1_000);
+		let token_info;
+
+		#[block]
+		{
+			token_info = UserTokenInfoes::<T>::get(&owner, BASE_ASSET_ID);
+		}
+
+		assert_eq!(token_info.amount, 1_000u32.into());
+	}
+
+	#[benchmark]
+	fn query_owner_tokens(t: Linear<0, 256>) {
+		let owner: T::AccountId = account("owner", 0, SEED);
+		for asset_id in 0..t {
+			UserTokenInfoes::<T>::insert(&owner, asset_id, TokenInfo::default());
+		}
+		let owner_tokens: Vec<_>;
+
+		#[block]
+		{
+			owner_tokens = UserTokenInfoes::<T>::iter_key_prefix(&owner).collect();
+		}
+
+		assert_eq!(owner_tokens.len() as u32, t);
+	}
+
+	#[benchmark]
+	fn query_order() {
+		setup_assets::<T>();
+		let caller: T::AccountId = whitelisted_caller();
+		fund::<T>(&caller, BASE_ASSET_ID, 1);
+		let order_index = super::make_order::<T>(&caller, OrderType::SELL, 1);
+		let order;
+
+		#[block]
+		{
+			order = Orders::<T>::get(order_index);
+		}
+
+		assert!(order.is_some());
+	}
+
+	#[benchmark]
+	fn query_pair_orders(
+		n: Linear<MAX_ORDERS_PER_PAGE, MAX_BOOK_ORDERS>,
+		l: Linear<0, MAX_ORDERS_PER_PAGE>,
+	) {
+		setup_assets::<T>();
+		let caller: T::AccountId = whitelisted_caller();
+		fund::<T>(&caller, BASE_ASSET_ID, 1);
+		let first_index = super::make_order::<T>(&caller, OrderType::SELL, 1);
+		let order = Orders::<T>::get(first_index).unwrap();
+		let pair = (BASE_ASSET_ID, QUOTE_ASSET_ID);
+		// the pair has `n` orders
+		for order_index in first_index + 1..first_index + n as u64 {
+			let mut order = order.clone();
+			order.counter = order_index;
+			Orders::<T>::insert(order_index, order);
+			PairOrders::<T>::insert(pair, order_index, ());
+		}
+		let page;
+
+		#[block]
+		{
+			page = Dex::<T>::pair_orders_page(pair, 0, l);
+		}
+
+		assert_eq!(page.0, n);
+		assert_eq!(page.1.len() as u32, l);
+	}
+
+	#[benchmark]
+	fn query_user_orders(
+		n: Linear<MAX_ORDERS_PER_PAGE, MAX_BOOK_ORDERS>,
+		l: Linear<0, MAX_ORDERS_PER_PAGE>,
+	) {
+		setup_assets::<T>();
+		let caller: T::AccountId = whitelisted_caller();
+		fund::<T>(&caller, BASE_ASSET_ID, 1);
+		let first_index = super::make_order::<T>(&caller, OrderType::SELL, 1);
+		let order = Orders::<T>::get(first_index).unwrap();
+		// the account has `n` orders
+		for order_index in first_index + 1..first_index + n as u64 {
+			let mut order = order.clone();
+			order.counter = order_index;
+			Orders::<T>::insert(order_index, order);
+			UserOrders::<T>::insert(&caller, order_index, ());
+		}
+		let page;
+
+		#[block]
+		{
+			page = Dex::<T>::user_orders_page(&caller, 0, l);
+		}
+
+		assert_eq!(page.0, n);
+		assert_eq!(page.1.len() as u32, l);
+	}
+
+	#[benchmark]
+	fn query_liquidity_pool() {
+		setup_assets::<T>();
+		let provider: T::AccountId = account("provider", 0, SEED);
+		let pair = (BASE_ASSET_ID, QUOTE_ASSET_ID);
+		super::add_liquidity::<T>(&provider, pair, 1_000_000);
+		let pool;
+
+		#[block]
+		{
+			pool = LiquidityPools::<T>::get(pair);
+		}
+
+		assert!(pool.is_some());
+	}
+
+	#[benchmark]
+	fn query_pool_shares() {
+		setup_assets::<T>();
+		let provider: T::AccountId = account("provider", 0, SEED);
+		let pair = (BASE_ASSET_ID, QUOTE_ASSET_ID);
+		super::add_liquidity::<T>(&provider, pair, 1_000_000);
+		let shares;
+
+		#[block]
+		{
+			shares = PoolShares::<T>::get(pair, &provider);
+		}
+
+		assert!(!shares.is_zero());
+	}
+
+	impl_benchmark_test_suite!(Dex, crate::mock::new_test_ext(), crate::mock::Test);
+}
@@ -4,8 +4,8 @@
 use frame_support::{
 	ensure,
 	pallet_prelude::{ConstU32, DispatchResult},
-	sp_std::{convert::TryInto, prelude::*},
-	traits::{Currency, ExistenceRequirement::AllowDeath, Get, ReservableCurrency},
+	sp_std::{collections::btree_map::BTreeMap, convert::TryInto, prelude::*},
+	traits::{Currency, ExistenceRequirement::AllowDeath, Get, ReservableCurrency, UnixTime},
 	BoundedBTreeMap, PalletId, RuntimeDebug,
 };
 
@@ -16,7 +16,11 @@ use sp_runtime::{
 		storage_lock::{BlockAndTime, StorageLock},
 		Duration,
 	},
-	traits::{BlockNumberProvider, CheckedAdd, CheckedDiv, CheckedMul, CheckedSub},
+	traits::{
+		AtLeast32BitUnsigned, BlockNumberProvider, Bounded, CheckedAdd, CheckedDiv, CheckedMul,
+		CheckedSub, IntegerSquareRoot, Saturating, UniqueSaturatedInto, Zero,
+	},
+	FixedPointNumber, FixedU128, PerThing, Permill, RuntimeAppPublic,
 };
 
 use core::cmp::Ordering;
@@ -25,31 +29,63 @@ use frame_system::pallet_prelude::*;
 pub use pallet::*;
 use scale_codec::{Decode, Encode, MaxEncodedLen};
 use scale_info::{prelude::cmp, TypeInfo};
-use sp_runtime::{traits::One, DispatchError};
+#[cfg(feature = "std")]
+use serde::{Deserialize, Serialize};
+use sp_runtime::{
+	helpers_128bit::multiply_by_rational_with_rounding, traits::One, DispatchError, Rounding,
+};
 
 use frame_support::{
 	sp_runtime::traits::AccountIdConversion,
 	traits::{
-		fungibles::{Balanced, Mutate},
+		fungibles::{Inspect, Mutate},
 		tokens::Preservation,
 	},
 };
 
+pub mod adapter;
+#[cfg(feature = "runtime-benchmarks")]
+mod benchmarking;
+pub mod memo;
+pub mod migration;
 #[cfg(test)]
 mod mock;
 #[cfg(test)]
+mod proptests;
+#[cfg(test)]
 mod tests;
+pub mod weights;
+pub use adapter::{FungiblesAdapter, RegisteredTokenIds};
+use memo::DepositMemo;
+pub use weights::WeightInfo;
 
 const LOCK_TIMEOUT_EXPIRATION: u64 = 4000; // in milli-seconds
 const LOCK_BLOCK_EXPIRATION: u32 = 3; // in block number
 
-#[derive(Encode, Decode, Default, Eq, PartialEq, Clone, RuntimeDebug, TypeInfo)]
+/// Offchain storage key of the [`MatchingHealth`] reported by the offchain worker.
+pub const OCW_HEALTH_KEY: &[u8] = b"dex_ocw::health";
+
+/// Most orders one side of the order book of a pair holds. `MaxOrdersPerPair` must not be
+/// larger.
+pub const MAX_BOOK_ORDERS: u32 = 1_000;
+
+/// Most orders [`Pallet::pair_orders_page`] and [`Pallet::user_orders_page`] are asked for at
+/// once by the chain extension of the runtime.
+pub const MAX_ORDERS_PER_PAGE: u32 = 100;
+
+/// Shares the first liquidity of a pool locks in the pallet account for good. The pool never
+/// empties, so a share can't be made worth so much that later liquidity is lost to rounding.
+pub const MINIMUM_LIQUIDITY: u32 = 1_000;
+
+#[derive(Encode, Decode, Default, Eq, PartialEq, Clone, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
 pub struct TokenInfo<Balance> {
 	pub amount: Balance,
 	pub reserved: Balance,
 }
 
 #[derive(Encode, Default, Decode, Eq, PartialEq, Clone, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
 pub enum OrderType {
 	#[default]
 	BUY,
@@ -67,6 +103,7 @@ impl OrderType {
 }
 
 #[derive(Encode, Decode, Default, Eq, PartialEq, Clone, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
 pub enum OrderStatus {
 	#[default]
 	Pending, //unfilledQuantity == quantity
@@ -74,9 +111,40 @@ pub enum OrderStatus {
 	PartialFilled,    // (quantity > unfilledQuantity > 0)
 	PartialCancelled, // (quantity > unfilledQuantity > 0)
 	FullyCancelled,   // (unfilledQuantity == quantity)
+	Rejected,         // post-only order that would have taken liquidity
 }
 
-#[derive(Encode, Decode, Default, Eq, PartialEq, Clone, RuntimeDebug, TypeInfo)]
+#[derive(
+	Encode, Decode, Default, Eq, PartialEq, Clone, Copy, RuntimeDebug, TypeInfo, MaxEncodedLen,
+)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub enum OrderKind {
+	/// Trades only at the order price or better.
+	#[default]
+	Limit,
+	/// Trades at any price, spending at most the offered amount.
+	Market,
+}
+
+#[derive(
+	Encode, Decode, Default, Eq, PartialEq, Clone, Copy, RuntimeDebug, TypeInfo, MaxEncodedLen,
+)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub enum TimeInForce {
+	/// Rests on the order book until it is filled, canceled or expires.
+	#[default]
+	GoodTillExpiration,
+	/// Takes whatever it can from the order book, the rest is canceled.
+	ImmediateOrCancel,
+	/// Is filled completely from the order book or canceled without any trade.
+	FillOrKill,
+	/// Only rests on the order book, it is rejected if it would take liquidity.
+	PostOnly,
+}
+
+#[derive(Encode, Decode, Default, Eq, PartialEq, Clone, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
 pub struct Order<AccountId, Balance, BlockNumber> {
 	counter: u64,       //order index
 	address: AccountId, //
@@ -85,13 +153,58 @@ pub struct Order<AccountId, Balance, BlockNumber> {
 	order_type: OrderType,
 	amount_offered: Balance,
 	amout_requested: Balance,
-	price: Balance,
+	price: FixedU128, // quote per unit of base
+
 	unfilled_offered: Balance,
 	unfilled_requested: Balance,
 	order_status: OrderStatus,
+	order_kind: OrderKind,
+	time_in_force: TimeInForce,
 }
 
 impl<AccountId, Balance, BlockNumber> Order<AccountId, Balance, BlockNumber> {
+	pub fn address(&self) -> &AccountId {
+		&self.address
+	}
+
+	/// Base and quote asset of the order.
+	pub fn pair(&self) -> (u32, u32) {
+		self.pair
+	}
+
+	pub fn order_type(&self) -> &OrderType {
+		&self.order_type
+	}
+
+	pub fn price(&self) -> FixedU128 {
+		self.price
+	}
+
+	pub fn order_status(&self) -> &OrderStatus {
+		&self.order_status
+	}
+
+	pub fn expiration_block(&self) -> &BlockNumber {
+		&self.expiration_block
+	}
+
+	/// Whether the unfilled part of the order is added to the order book after matching.
+	pub fn rests_on_book(&self) -> bool {
+		self.order_kind == OrderKind::Limit
+			&& matches!(
+				self.time_in_force,
+				TimeInForce::GoodTillExpiration | TimeInForce::PostOnly
+			)
+	}
+
+	/// The asset reserved for the order.
+	pub fn get_offered_asset_id(&self) -> u32 {
+		match self.order_type {
+			OrderType::SELL => self.pair.0,
+			OrderType::BUY => self.pair.1,
+		}
+	}
+
 	pub fn get_base_amount(&self) -> &Balance {
 		match self.order_type {
 			OrderType::SELL => &self.amount_offered,
@@ -120,7 +233,764 @@ impl<AccountId, Balance, BlockNumber> Order<AccountId, Balance, BlockNumber> {
 	}
 }
 
+/// An order placed by `batch_place`, with the arguments of `place_order`.
+#[derive(Encode, Decode, Eq, PartialEq, Clone, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+pub struct OrderRequest<Balance, BlockNumber> {
+	pub asset_id_1: u32,
+	pub asset_id_2: u32,
+	pub offered_amount: Balance,
+	pub requested_amount: Balance,
+	pub order_type: OrderType,
+	pub order_kind: OrderKind,
+	pub time_in_force: TimeInForce,
+	pub expiration_block: BlockNumber,
+}
+
+/// Which price a conditional order is triggered by.
+#[derive(
+	Encode, Decode, Default, Eq, PartialEq, Clone, Copy, RuntimeDebug, TypeInfo, MaxEncodedLen,
+)]
+pub enum TriggerSource {
+	/// Price of the last trade settled in the pair.
+	#[default]
+	LastTradePrice,
+	/// Price reported by the `PriceOracle` of the pallet.
+	Oracle,
+}
+
+/// Whether a conditional order limits a loss or takes a profit.
+#[derive(Encode, Decode, Eq, PartialEq, Clone, Copy, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+pub enum ConditionalOrderKind {
+	/// A sell order is triggered when the price falls to the trigger price, a buy order
+	/// when it rises to it.
+	StopLoss,
+	/// A sell order is triggered when the price rises to the trigger price, a buy order
+	/// when it falls to it.
+	TakeProfit,
+}
+
+/// Condition under which a conditional order becomes a live order.
+#[derive(Encode, Decode, Eq, PartialEq, Clone, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+pub struct Trigger {
+	pub kind: ConditionalOrderKind,
+	pub source: TriggerSource,
+	/// Price of `asset_id_1` in `asset_id_2` of the order request.
+	pub price: FixedU128,
+}
+
+impl Trigger {
+	/// Whether an order of `order_type` is triggered when the pair trades at `price`.
+	pub fn is_triggered_at(&self, order_type: &OrderType, price: FixedU128) -> bool {
+		match (order_type, self.kind) {
+			(OrderType::SELL, ConditionalOrderKind::StopLoss)
+			| (OrderType::BUY, ConditionalOrderKind::TakeProfit) => price <= self.price,
+			(OrderType::SELL, ConditionalOrderKind::TakeProfit)
+			| (OrderType::BUY, ConditionalOrderKind::StopLoss) => price >= self.price,
+		}
+	}
+}
+
+/// An order that stays dormant until its trigger fires. The offered amount is reserved
+/// while it waits.
+#[derive(Encode, Decode, Eq, PartialEq, Clone, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+pub struct ConditionalOrder<AccountId, Balance, BlockNumber> {
+	pub address: AccountId,
+	pub trigger: Trigger,
+	/// The order placed once the trigger fires, the assets of its pair are in order.
+	pub order: OrderRequest<Balance, BlockNumber>,
+}
+
+impl<AccountId, Balance, BlockNumber> ConditionalOrder<AccountId, Balance, BlockNumber> {
+	pub fn pair(&self) -> (u32, u32) {
+		(self.order.asset_id_1, self.order.asset_id_2)
+	}
+
+	/// The asset reserved for the order.
+	pub fn offered_asset_id(&self) -> u32 {
+		match self.order.order_type {
+			OrderType::SELL => self.order.asset_id_1,
+			OrderType::BUY => self.order.asset_id_2,
+		}
+	}
+}
+
+/// Source of reference prices for conditional orders.
+pub trait PriceOracle {
+	/// Price of the base asset of a pair in its quote asset, if it is known.
+	fn price(pair: (u32, u32)) -> Option<FixedU128>;
+}
+
+impl PriceOracle for () {
+	fn price(_pair: (u32, u32)) -> Option<FixedU128> {
+		None
+	}
+}
+
+/// Whether orders of a trading pair can be placed and matched.
+#[derive(
+	Encode, Decode, Default, Eq, PartialEq, Clone, Copy, RuntimeDebug, TypeInfo, MaxEncodedLen,
+)]
+pub enum PairStatus {
+	/// Orders are placed and matched as usual.
+	#[default]
+	Active,
+	/// Only post-only orders can be placed and orders can't be taken, so the order book
+	/// fills up without trading, e.g. before a market opens.
+	PostOnly,
+	/// No orders can be placed and nothing trades, resting orders can still be canceled.
+	Halted,
+	/// The pair is no longer traded, its resting orders were canceled and refunded.
+	Delisted,
+}
+
+impl PairStatus {
+	/// Whether orders of the pair trade against each other.
+	pub fn allows_trading(&self) -> bool {
+		matches!(self, PairStatus::Active | PairStatus::PostOnly)
+	}
+}
+
+/// A pair of assets that can be traded on the DEX. The base asset is the one with the
+/// lower id.
+#[derive(Encode, Decode, Default, Eq, PartialEq, Clone, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+pub struct TradingPair<Balance> {
+	pub status: PairStatus,
+	/// Smallest amount of the base asset an order can be placed for.
+	pub min_order_size: Balance,
+}
+
+/// Price a price band is centered on.
+#[derive(
+	Encode, Decode, Default, Eq, PartialEq, Clone, Copy, RuntimeDebug, TypeInfo, MaxEncodedLen,
+)]
+pub enum ReferencePrice {
+	/// Price of the last trade settled in the pair.
+	#[default]
+	LastTrade,
+	/// Time-weighted average price of the pair since the start of the window of its
+	/// circuit breaker.
+	Twap,
+	/// Price reported by the `PriceOracle` of the pallet.
+	Oracle,
+}
+
+/// Protects a pair against orders priced far off the market and against sudden moves.
+#[derive(Encode, Decode, Eq, PartialEq, Clone, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+pub struct PriceBand<BlockNumber> {
+	pub reference: ReferencePrice,
+	/// Limit orders priced further than this from the reference price are rejected and
+	/// market orders don't trade beyond it.
+	pub max_deviation: Permill,
+	/// The circuit breaker trips when the price moves more than this within `window`
+	/// blocks.
+	pub max_move: Permill,
+	pub window: BlockNumber,
+	/// Number of blocks a pair is halted for once its circuit breaker trips.
+	pub halt_duration: BlockNumber,
+}
+
+/// Prices of a pair within the current window of its circuit breaker.
+#[derive(Encode, Decode, Eq, PartialEq, Clone, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+pub struct PriceWindow<BlockNumber> {
+	/// Block the window started in.
+	pub start: BlockNumber,
+	/// Price of the pair when the window started, moves are measured from it.
+	pub open_price: FixedU128,
+	/// Sum of the prices of the pair in each block from `start` to `last_update`.
+	pub cumulative_price: FixedU128,
+	pub last_price: FixedU128,
+	pub last_update: BlockNumber,
+}
+
+impl<BlockNumber: AtLeast32BitUnsigned + Copy> PriceWindow<BlockNumber> {
+	pub fn new(start: BlockNumber, open_price: FixedU128) -> Self {
+		Self {
+			start,
+			open_price,
+			cumulative_price: Zero::zero(),
+			last_price: open_price,
+			last_update: start,
+		}
+	}
+
+	/// Accounts for the blocks since the last update and sets the current price.
+	pub fn update(&mut self, now: BlockNumber, price: FixedU128) {
+		self.cumulative_price = self.cumulative_price_at(now);
+		self.last_price = price;
+		self.last_update = now;
+	}
+
+	/// Time-weighted average price from the start of the window to `now`.
+	pub fn twap(&self, now: BlockNumber) -> FixedU128 {
+		let elapsed: u128 = now.saturating_sub(self.start).unique_saturated_into();
+		if elapsed == 0 {
+			return self.last_price;
+		}
+		self.cumulative_price_at(now)
+			.checked_div(&FixedU128::saturating_from_integer(elapsed))
+			.unwrap_or(self.last_price)
+	}
+
+	fn cumulative_price_at(&self, now: BlockNumber) -> FixedU128 {
+		let elapsed: u128 = now.saturating_sub(self.last_update).unique_saturated_into();
+		self.cumulative_price.saturating_add(
+			self.last_price
+				.saturating_mul(FixedU128::saturating_from_integer(elapsed)),
+		)
+	}
+}
+
+/// Running sum of the price of the liquidity pool of a pair over time, the way Uniswap v2
+/// accumulates prices. The average price between two points in time is the difference of
+/// the cumulative prices at them divided by the seconds between them.
+///
+/// Only the reserves of the pool price the pair, read before each change to them. Trades
+/// on the order book don't, a trade of a single lot could set the price of the pair
+/// until the next trade otherwise.
+#[derive(Encode, Decode, Default, Eq, PartialEq, Clone, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+pub struct PriceAccumulator {
+	/// Sum of the price of the pool in each second up to `last_update`. It wraps around
+	/// on overflow, only differences of it are meaningful.
+	pub cumulative_price: FixedU128,
+	/// Unix time in seconds the reserves of the pool last changed at.
+	pub last_update: u64,
+}
+
+impl PriceAccumulator {
+	pub fn new(now: u64) -> Self {
+		Self {
+			cumulative_price: Zero::zero(),
+			last_update: now,
+		}
+	}
+
+	/// Accounts for the seconds since the last update at `price`, the price the pool had
+	/// since then.
+	pub fn update(&mut self, now: u64, price: FixedU128) {
+		self.cumulative_price = self.cumulative_price_at(now, price);
+		self.last_update = now.max(self.last_update);
+	}
+
+	/// Cumulative price at `now` of a pool that has been at `price` since the last update.
+	pub fn cumulative_price_at(&self, now: u64, price: FixedU128) -> FixedU128 {
+		let elapsed = now.saturating_sub(self.last_update) as u128;
+		FixedU128::from_inner(
+			self.cumulative_price
+				.into_inner()
+				.wrapping_add(price.into_inner().wrapping_mul(elapsed)),
+		)
+	}
+}
+
+/// Cumulative price of a pair at a point in time, kept to average the price since then.
+#[derive(Encode, Decode, Default, Eq, PartialEq, Clone, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+pub struct PriceObservation {
+	/// Unix time in seconds.
+	pub timestamp: u64,
+	pub cumulative_price: FixedU128,
+}
+
+impl PriceObservation {
+	/// Time-weighted average price from an earlier observation to this one.
+	pub fn twap_since(&self, earlier: &PriceObservation) -> Option<Twap> {
+		let elapsed = self
+			.timestamp
+			.checked_sub(earlier.timestamp)
+			.filter(|elapsed| *elapsed > 0)?;
+		let price = self
+			.cumulative_price
+			.into_inner()
+			.wrapping_sub(earlier.cumulative_price.into_inner())
+			/ elapsed as u128;
+		Some(Twap {
+			price: FixedU128::from_inner(price),
+			start: earlier.timestamp,
+			end: self.timestamp,
+		})
+	}
+}
+
+/// Time-weighted average price of a pair over a span of time.
+#[derive(Encode, Decode, Default, Eq, PartialEq, Clone, RuntimeDebug, TypeInfo)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+pub struct Twap {
+	pub price: FixedU128,
+	/// Unix time in seconds the span starts at.
+	pub start: u64,
+	/// Unix time in seconds the span ends at.
+	pub end: u64,
+}
+
+/// Time-weighted average prices of the DEX pairs, for other pallets to price assets with.
+pub trait TwapProvider {
+	/// Average price of the base asset of a pair in its quote asset over at least the last
+	/// `period` seconds, if the liquidity pool of the pair has been priced for that long.
+	/// The pair is given with the lower asset id first.
+	fn twap(pair: (u32, u32), period: u64) -> Option<FixedU128>;
+}
+
+/// Trading rules of a pair.
+#[derive(Encode, Decode, Eq, PartialEq, Clone, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+pub struct PairConfig<Balance> {
+	/// Limit order prices are multiples of the tick size.
+	pub tick_size: FixedU128,
+	/// Amounts of the base asset are multiples of the lot size.
+	pub lot_size: Balance,
+	/// Smallest amount of the quote asset an order can be placed for.
+	pub min_notional: Balance,
+}
+
+impl<Balance: Zero + One> Default for PairConfig<Balance> {
+	fn default() -> Self {
+		Self {
+			tick_size: FixedU128::from_inner(1),
+			lot_size: One::one(),
+			min_notional: Zero::zero(),
+		}
+	}
+}
+
+/// Trading fees of a pair, charged in the asset each side receives.
+#[derive(Encode, Decode, Default, Eq, PartialEq, Clone, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+pub struct PairFee {
+	/// Fee of the order that was on the order book.
+	pub maker_fee: Permill,
+	/// Fee of the order that took liquidity from the order book.
+	pub taker_fee: Permill,
+}
+
+/// Number of days of trading volume the fee tier of an account is based on.
+pub const VOLUME_WINDOW_DAYS: u32 = 30;
+
+/// Fees of the accounts that traded at least `min_volume` in a pair over the last
+/// `VOLUME_WINDOW_DAYS` days. Accounts below the lowest tier pay the `PairFee` of the pair.
+#[derive(Encode, Decode, Default, Eq, PartialEq, Clone, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+pub struct FeeTier<Balance> {
+	/// Trading volume in the quote asset of the pair.
+	pub min_volume: Balance,
+	pub fee: PairFee,
+	/// Share of the taker fee of a trade that is passed on to the maker, paid in the asset
+	/// the maker gave.
+	pub maker_rebate: Permill,
+}
+
+/// Trading volume of an account in a pair on each of the last `VOLUME_WINDOW_DAYS` days.
+#[derive(Encode, Decode, Default, Eq, PartialEq, Clone, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+pub struct TradingVolume<Balance> {
+	/// Day since the Unix epoch of the latest trade.
+	pub day: u32,
+	/// Volume in the quote asset of each day, indexed by the day modulo the window.
+	pub daily: [Balance; VOLUME_WINDOW_DAYS as usize],
+}
+
+impl<Balance: Copy + Zero + Saturating> TradingVolume<Balance> {
+	/// Adds the volume of a trade on `day`. The days since the latest trade are reset, their
+	/// slots still hold the volume of a previous window.
+	pub fn add(&mut self, day: u32, volume: Balance) {
+		if day > self.day {
+			for day in (self.day + 1..=day).take(VOLUME_WINDOW_DAYS as usize) {
+				self.daily[(day % VOLUME_WINDOW_DAYS) as usize] = Zero::zero();
+			}
+			self.day = day;
+		}
+		let slot = (self.day % VOLUME_WINDOW_DAYS) as usize;
+		self.daily[slot] = self.daily[slot].saturating_add(volume);
+	}
+
+	/// Volume of the window that ends on `day`.
+	pub fn volume(&self, day: u32) -> Balance {
+		let first_day = day.max(self.day).saturating_sub(VOLUME_WINDOW_DAYS - 1);
+		(first_day..=self.day).fold(Zero::zero(), |volume: Balance, day| {
+			volume.saturating_add(self.daily[(day % VOLUME_WINDOW_DAYS) as usize])
+		})
+	}
+}
+
+/// Terms governance funds a liquidity mining epoch of a pair with.
+#[derive(Encode, Decode, Eq, PartialEq, Clone, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+pub struct RewardTerms<Balance, BlockNumber> {
+	/// Asset the rewards are paid in.
+	pub asset_id: u32,
+	/// Reward shared by the orders earning rewards in each block of the epoch.
+	pub reward_per_block: Balance,
+	/// Number of blocks the epoch lasts, starting with the block it is funded in.
+	pub duration: BlockNumber,
+	/// Price the spread of an order is measured from. It stands in for the mid price of
+	/// the order book, which isn't known on chain without reading every order.
+	pub reference: ReferencePrice,
+	/// Orders priced further than this from the reference price don't earn rewards.
+	pub max_spread: Permill,
+}
+
+/// Liquidity mining epoch of a pair. Orders that rest on the order book within the spread
+/// of the epoch earn a share of the reward of each block in proportion to their unfilled
+/// base amount, their stake. The spread is checked when an order is placed and whenever
+/// its reward accrues, an order that left it forfeits the reward since its last accrual
+/// and stops earning.
+#[derive(Encode, Decode, Eq, PartialEq, Clone, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+pub struct RewardEpoch<Balance, BlockNumber> {
+	pub pair: (u32, u32),
+	pub terms: RewardTerms<Balance, BlockNumber>,
+	pub start: BlockNumber,
+	pub end: BlockNumber,
+	/// Stakes of the orders earning rewards.
+	pub total_stake: Balance,
+	/// Reward earned by a unit of stake since the start of the epoch, like the reward per
+	/// share of a staking pool.
+	pub reward_per_stake: FixedU128,
+	pub last_update: BlockNumber,
+	/// Reward of the blocks without stakes and reward forfeited by orders that left the
+	/// spread. It goes back to the `RewardSource` with `reclaim_rewards`.
+	pub unearned_reward: Balance,
+}
+
+impl<Balance, BlockNumber> RewardEpoch<Balance, BlockNumber>
+where
+	Balance: AtLeast32BitUnsigned + Copy,
+	BlockNumber: AtLeast32BitUnsigned + Copy,
+{
+	pub fn is_running(&self, now: BlockNumber) -> bool {
+		self.start <= now && now < self.end
+	}
+
+	/// Shares the rewards of the blocks since the last update among the current stakes.
+	/// The rewards of blocks without stakes are unearned.
+	pub fn update(&mut self, now: BlockNumber) {
+		let now = now.min(self.end);
+		if now <= self.last_update {
+			return;
+		}
+
+		let blocks: u128 = (now - self.last_update).unique_saturated_into();
+		let reward_per_block: u128 = self.terms.reward_per_block.unique_saturated_into();
+		if self.total_stake.is_zero() {
+			self.unearned_reward = self.unearned_reward.saturating_add(
+				reward_per_block
+					.saturating_mul(blocks)
+					.unique_saturated_into(),
+			);
+		} else {
+			let total_stake: u128 = self.total_stake.unique_saturated_into();
+			self.reward_per_stake =
+				self.reward_per_stake
+					.saturating_add(FixedU128::saturating_from_rational(
+						reward_per_block.saturating_mul(blocks),
+						total_stake,
+					));
+		}
+		self.last_update = now;
+	}
+
+	/// Reward a stake has earned since it was last paid.
+	pub fn reward(&self, stake: &RewardStake<Balance>) -> Balance {
+		let amount: u128 = stake.amount.unique_saturated_into();
+		self.reward_per_stake
+			.saturating_sub(stake.reward_per_stake)
+			.saturating_mul_int(amount)
+			.unique_saturated_into()
+	}
+}
+
+/// Stake of an open order in a liquidity mining epoch.
+#[derive(Encode, Decode, Eq, PartialEq, Clone, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+pub struct RewardStake<Balance> {
+	pub epoch_id: u32,
+	/// Unfilled base amount of the order.
+	pub amount: Balance,
+	/// Reward per stake of the epoch the order has been paid until.
+	pub reward_per_stake: FixedU128,
+}
+
+/// Unfilled base amount of the orders resting on the order book at one price.
+#[derive(Encode, Decode, Default, Eq, PartialEq, Clone, RuntimeDebug, TypeInfo)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+pub struct PriceLevel<Balance> {
+	pub price: FixedU128,
+	pub base_amount: Balance,
+	pub order_count: u32,
+}
+
+/// Order book of a pair aggregated by price, best prices first.
+#[derive(Encode, Decode, Default, Eq, PartialEq, Clone, RuntimeDebug, TypeInfo)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub struct OrderBookDepth<Balance> {
+	pub bids: Vec<PriceLevel<Balance>>,
+	pub asks: Vec<PriceLevel<Balance>>,
+}
+
+/// Highest buy price and lowest sell price on the order book of a pair.
+#[derive(Encode, Decode, Default, Eq, PartialEq, Clone, RuntimeDebug, TypeInfo)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+pub struct BestBidAsk {
+	pub best_bid: Option<FixedU128>,
+	pub best_ask: Option<FixedU128>,
+}
+
+/// Trades of a pair during one interval. Prices are in quote per base.
+#[derive(Encode, Decode, Default, Eq, PartialEq, Clone, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+pub struct Candle<Balance> {
+	/// Unix time in seconds the interval starts at.
+	pub start: u64,
+	pub open: FixedU128,
+	pub high: FixedU128,
+	pub low: FixedU128,
+	pub close: FixedU128,
+	pub volume_base: Balance,
+	pub volume_quote: Balance,
+	pub trade_count: u32,
+}
+
+impl<Balance: Saturating + Copy> Candle<Balance> {
+	fn new(start: u64, price: FixedU128, quantity_base: Balance, quantity_quote: Balance) -> Self {
+		Candle {
+			start,
+			open: price,
+			high: price,
+			low: price,
+			close: price,
+			volume_base: quantity_base,
+			volume_quote: quantity_quote,
+			trade_count: 1,
+		}
+	}
+
+	fn add_trade(&mut self, price: FixedU128, quantity_base: Balance, quantity_quote: Balance) {
+		self.high = self.high.max(price);
+		self.low = self.low.min(price);
+		self.close = price;
+		self.volume_base = self.volume_base.saturating_add(quantity_base);
+		self.volume_quote = self.volume_quote.saturating_add(quantity_quote);
+		self.trade_count = self.trade_count.saturating_add(1);
+	}
+}
+
+/// Constant-product liquidity pool of a pair, next to its order book. The reserves are
+/// held by the pallet account like the DEX balances.
+#[derive(Encode, Decode, Default, Eq, PartialEq, Clone, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+pub struct LiquidityPool<Balance> {
+	pub base_reserve: Balance,
+	pub quote_reserve: Balance,
+	/// Shares of the pool held by its liquidity providers.
+	pub total_shares: Balance,
+}
+
+impl<Balance: AtLeast32BitUnsigned + Copy> LiquidityPool<Balance> {
+	/// Reserves of the asset a swap on `side` pays into the pool and of the asset it
+	/// takes out. Buying pays the quote asset for the base asset.
+	pub fn reserves(&self, side: &OrderType) -> (Balance, Balance) {
+		match side {
+			OrderType::BUY => (self.quote_reserve, self.base_reserve),
+			OrderType::SELL => (self.base_reserve, self.quote_reserve),
+		}
+	}
+
+	/// Amount a swap on `side` takes out of the pool for `amount_in`, the fee stays in
+	/// the pool.
+	pub fn amount_out(
+		&self,
+		side: &OrderType,
+		amount_in: Balance,
+		fee: Permill,
+	) -> Option<Balance> {
+		let (reserve_in, reserve_out) = self.reserves(side);
+		let amount_in = amount_in.checked_sub(&fee.mul_ceil(amount_in))?;
+		mul_div(
+			reserve_out,
+			amount_in,
+			reserve_in.checked_add(&amount_in)?,
+			Rounding::Down,
+		)
+	}
+
+	/// Amount a swap on `side` pays into the pool to take `amount_out` out of it,
+	/// rounded up so that the pool pays out at least `amount_out` for it.
+	pub fn amount_in(
+		&self,
+		side: &OrderType,
+		amount_out: Balance,
+		fee: Permill,
+	) -> Option<Balance> {
+		let (reserve_in, reserve_out) = self.reserves(side);
+		let amount_in = mul_div(
+			reserve_in,
+			amount_out,
+			reserve_out
+				.checked_sub(&amount_out)
+				.filter(|left| !left.is_zero())?,
+			Rounding::Up,
+		)?;
+		mul_div(
+			amount_in,
+			Balance::from(Permill::ACCURACY),
+			Balance::from(fee.left_from_one().deconstruct()),
+			Rounding::Up,
+		)
+	}
+
+	/// Amounts of the base and quote assets taken for adding at most `base_amount` and
+	/// `quote_amount` to the pool at its current ratio, and the shares they are worth.
+	pub fn shares_for(
+		&self,
+		base_amount: Balance,
+		quote_amount: Balance,
+	) -> Option<(Balance, Balance, Balance)> {
+		if self.total_shares.is_zero() {
+			let shares = match base_amount.checked_mul(&quote_amount) {
+				Some(product) => product.integer_sqrt(),
+				None => base_amount
+					.integer_sqrt()
+					.saturating_mul(quote_amount.integer_sqrt()),
+			};
+			return Some((base_amount, quote_amount, shares));
+		}
+
+		let quote_optimal = mul_div(
+			base_amount,
+			self.quote_reserve,
+			self.base_reserve,
+			Rounding::Up,
+		)?;
+		let (base_amount, quote_amount) = if quote_optimal <= quote_amount {
+			(base_amount, quote_optimal)
+		} else {
+			let base_optimal = mul_div(
+				quote_amount,
+				self.base_reserve,
+				self.quote_reserve,
+				Rounding::Down,
+			)?;
+			(base_optimal, quote_amount)
+		};
+		let shares = cmp::min(
+			mul_div(
+				base_amount,
+				self.total_shares,
+				self.base_reserve,
+				Rounding::Down,
+			)?,
+			mul_div(
+				quote_amount,
+				self.total_shares,
+				self.quote_reserve,
+				Rounding::Down,
+			)?,
+		);
+
+		Some((base_amount, quote_amount, shares))
+	}
+
+	/// Amounts of the base and quote assets `shares` of the pool are worth.
+	pub fn amounts_for(&self, shares: Balance) -> Option<(Balance, Balance)> {
+		Some((
+			mul_div(self.base_reserve, shares, self.total_shares, Rounding::Down)?,
+			mul_div(
+				self.quote_reserve,
+				shares,
+				self.total_shares,
+				Rounding::Down,
+			)?,
+		))
+	}
+
+	/// Marginal price of the base asset in the quote asset, the ratio of the reserves.
+	pub fn price(&self) -> Option<FixedU128> {
+		multiply_by_rational_with_rounding(
+			self.quote_reserve.unique_saturated_into(),
+			FixedU128::DIV,
+			self.base_reserve.unique_saturated_into(),
+			Rounding::Down,
+		)
+		.filter(|price| *price != 0)
+		.map(FixedU128::from_inner)
+	}
+}
+
+/// `a * b / c` without intermediate overflow, `None` when `c` is zero or the result
+/// doesn't fit.
+fn mul_div<Balance: AtLeast32BitUnsigned>(
+	a: Balance,
+	b: Balance,
+	c: Balance,
+	rounding: Rounding,
+) -> Option<Balance> {
+	let result = multiply_by_rational_with_rounding(
+		a.unique_saturated_into(),
+		b.unique_saturated_into(),
+		c.unique_saturated_into(),
+		rounding,
+	)?;
+	Balance::try_from(result).ok()
+}
+
+/// Where a swap was filled.
+#[derive(Encode, Decode, Eq, PartialEq, Clone, Copy, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub enum SwapVenue {
+	Pool,
+	OrderBook,
+}
+
+/// Where new orders are matched against the order book.
+#[derive(
+	Encode, Decode, Default, Eq, PartialEq, Clone, Copy, RuntimeDebug, TypeInfo, MaxEncodedLen,
+)]
+pub enum MatchingMode {
+	/// Authorities match orders in their offchain workers and submit the trades as
+	/// signed unsigned transactions.
+	#[default]
+	Offchain,
+	/// Orders are matched against the on-chain order book when they are created.
+	OnOrderCreation,
+	/// Orders are matched against the on-chain order book in `on_idle`.
+	OnIdle,
+}
+
+impl MatchingMode {
+	pub fn is_on_chain(&self) -> bool {
+		*self != MatchingMode::Offchain
+	}
+}
+
+/// What happens when an order would trade against another order of the same account.
+#[derive(
+	Encode, Decode, Default, Eq, PartialEq, Clone, Copy, RuntimeDebug, TypeInfo, MaxEncodedLen,
+)]
+pub enum SelfTradePolicy {
+	/// The taker order is canceled.
+	#[default]
+	CancelNewest,
+	/// The maker order is canceled and the taker order keeps matching.
+	CancelOldest,
+	/// Both orders are reduced by the quantity they would have traded, an order that
+	/// has nothing left is canceled.
+	DecrementAndCancel,
+}
+
+/// Progress of the offchain matching engine. The offchain worker keeps it in the
+/// persistent offchain storage under [`OCW_HEALTH_KEY`], node operators can read it
+/// with the `offchain_localStorageGet` RPC.
 #[derive(Encode, Decode, Default, Eq, PartialEq, Clone, RuntimeDebug, TypeInfo)]
+pub struct MatchingHealth<BlockNumber> {
+	/// Block the offchain worker last ran on.
+	pub block_number: BlockNumber,
+	/// Index of the next order the offchain worker will match.
+	pub next_order_index: u64,
+	/// Number of orders placed on chain that were not matched yet.
+	pub pending_orders: u64,
+	/// Block at which the order books were last rebuilt from chain state.
+	pub last_rebuild_block: BlockNumber,
+}
+
+#[derive(Encode, Decode, Default, Eq, PartialEq, Clone, RuntimeDebug, TypeInfo, MaxEncodedLen)]
 pub struct MatchEngine<Order, Balance: cmp::Ord> {
 	buy_book: OrderBook<Order, Balance>,
 	sell_book: OrderBook<Order, Balance>,
@@ -128,7 +998,7 @@ pub struct MatchEngine<Order, Balance: cmp::Ord> {
 	last_process_order_id: u64,
 }
 
-#[derive(Encode, Decode, Default, Eq, PartialEq, Clone, RuntimeDebug, TypeInfo)]
+#[derive(Encode, Decode, Default, Eq, PartialEq, Clone, RuntimeDebug, TypeInfo, MaxEncodedLen)]
 pub struct OrderBookKey<Balance> {
 	order_id: u64,
 	price: Balance,
@@ -162,27 +1032,42 @@ impl<Balance: cmp::PartialEq + cmp::PartialOrd> PartialOrd for OrderBookKey<Bala
 	}
 }
 
-#[derive(Encode, Decode, Default, Eq, PartialEq, Clone, RuntimeDebug, TypeInfo)]
+/// Orders of one side of an order book by price and order index.
+pub type BookOrders<Order, Balance> =
+	BoundedBTreeMap<OrderBookKey<Balance>, Order, ConstU32<MAX_BOOK_ORDERS>>;
+
+#[derive(Encode, Decode, Default, Eq, PartialEq, Clone, RuntimeDebug, TypeInfo, MaxEncodedLen)]
 pub struct OrderBook<Order, Balance: cmp::Ord> {
 	order_type: OrderType,
-	book: BoundedBTreeMap<OrderBookKey<Balance>, Order, ConstU32<{ u32::MAX }>>,
+	book: BookOrders<Order, Balance>,
 }
 
 #[derive(Encode, Decode, Default, Eq, PartialEq, Clone, RuntimeDebug, TypeInfo)]
 pub struct MatchResult<Balance, Order> {
 	taker_order: Order,
 	match_details: Vec<Trade<Balance, Order>>,
+	self_trades: Vec<SelfTrade>,
 }
 
 #[derive(Encode, Decode, Default, Eq, PartialEq, Clone, RuntimeDebug, TypeInfo)]
 pub struct Trade<Balance, Order> {
-	price: Balance,
+	price: FixedU128,
 	quantity_base: Balance,
 	quantity_quote: Balance,
 	taker_order: Order,
 	maker_order: Order,
 }
 
+/// A taker order that met an order of the same account on the order book. The
+/// self-trade policy is applied to both orders instead of trading them.
+#[derive(Encode, Decode, Default, Eq, PartialEq, Clone, RuntimeDebug, TypeInfo)]
+pub struct SelfTrade {
+	taker_order: u64,
+	maker_order: u64,
+	/// Number of trades the taker order made before it met the maker order.
+	trades_before: u32,
+}
+
 #[allow(clippy::unused_unit)]
 #[frame_support::pallet]
 pub mod pallet {
@@ -190,6 +1075,7 @@ pub mod pallet {
 	use frame_support::{
 		dispatch::DispatchResultWithPostInfo,
 		pallet_prelude::{ValueQuery, *},
+		storage::with_storage_layer,
 		Blake2_128Concat,
 	};
 	use frame_system::offchain::SubmitTransaction;
@@ -200,24 +1086,50 @@ pub mod pallet {
 	type OrderOf<T> =
 		Order<<T as frame_system::Config>::AccountId, BalanceOf<T>, BlockNumberFor<T>>;
 
+	type ConditionalOrderOf<T> =
+		ConditionalOrder<<T as frame_system::Config>::AccountId, BalanceOf<T>, BlockNumberFor<T>>;
+
+	type PriceBandOf<T> = PriceBand<BlockNumberFor<T>>;
+
+	pub type FeeTiersOf<T> = BoundedVec<FeeTier<BalanceOf<T>>, <T as Config>::MaxFeeTiers>;
+
+	type RewardTermsOf<T> = RewardTerms<BalanceOf<T>, BlockNumberFor<T>>;
+
+	type RewardEpochOf<T> = RewardEpoch<BalanceOf<T>, BlockNumberFor<T>>;
+
 	type MapMatchEnginesOf<T> =
-		BoundedBTreeMap<(u32, u32), MatchEngine<OrderOf<T>, BalanceOf<T>>, ConstU32<{ u32::MAX }>>;
+		BoundedBTreeMap<(u32, u32), MatchEngine<OrderOf<T>, FixedU128>, ConstU32<{ u32::MAX }>>;
 
 	#[pallet::genesis_config]
-	#[derive(Default)]
-	pub struct GenesisConfig {
+	pub struct GenesisConfig<T: Config> {
 		pub asset_ids: Vec<u32>,
 		pub native_asset_id: u32,
+		/// Pairs that are active from genesis, with the base asset first.
+		pub trading_pairs: Vec<(u32, u32)>,
+		/// Tokens held outside of `pallet_assets` that are registered from genesis, by DEX
+		/// asset id, as `register_token` does.
+		pub tokens: Vec<(u32, T::TokenId)>,
+	}
+
+	impl<T: Config> Default for GenesisConfig<T> {
+		fn default() -> Self {
+			Self {
+				asset_ids: Default::default(),
+				native_asset_id: Default::default(),
+				trading_pairs: Default::default(),
+				tokens: Default::default(),
+			}
+		}
 	}
 
 	#[pallet::genesis_build]
-	impl<T: Config> GenesisBuild<T> for GenesisConfig {
+	impl<T: Config> GenesisBuild<T> for GenesisConfig<T> {
 		fn build(&self) {
-			let bounded_token_infoes: BoundedVec<u32, ConstU32<{ u32::MAX }>> = self
+			let bounded_token_infoes: BoundedVec<u32, T::MaxAssets> = self
 				.asset_ids
 				.clone()
 				.try_into()
-				.expect("genesis asset_ids are more than u32::MAX");
+				.expect("genesis asset_ids are more than MaxAssets");
 
 			let mut index = 0;
 			self.asset_ids.iter().for_each(|asset_id| {
@@ -228,11 +1140,36 @@ pub mod pallet {
 			TokenInfoes::<T>::put(bounded_token_infoes);
 
 			NativeAssetId::<T>::put(self.native_asset_id);
+
+			for &(base_asset_id, quote_asset_id) in self.trading_pairs.iter() {
+				assert!(
+					base_asset_id < quote_asset_id
+						&& self.asset_ids.contains(&base_asset_id)
+						&& self.asset_ids.contains(&quote_asset_id),
+					"genesis trading pair {:?} is invalid",
+					(base_asset_id, quote_asset_id)
+				);
+				TradingPairs::<T>::insert((base_asset_id, quote_asset_id), TradingPair::default());
+			}
+
+			for (asset_id, token_id) in self.tokens.iter() {
+				assert!(
+					!TokenIds::<T>::contains_key(asset_id)
+						&& !TokenAssetIds::<T>::contains_key(token_id),
+					"genesis token {:?} is registered twice",
+					(asset_id, token_id)
+				);
+				TokenIds::<T>::insert(asset_id, token_id);
+				TokenAssetIds::<T>::insert(token_id, asset_id);
+			}
 		}
 	}
 
+	/// The current storage version.
+	const STORAGE_VERSION: StorageVersion = StorageVersion::new(2);
+
 	#[pallet::pallet]
-	#[pallet::without_storage_info]
+	#[pallet::storage_version(STORAGE_VERSION)]
 	pub struct Pallet<T>(_);
 
 	#[pallet::config]
@@ -244,9 +1181,13 @@ pub mod pallet {
 		#[pallet::constant]
 		type PalletId: Get<PalletId>;
 
-		/// Expose customizable associated type of asset transfer, lock and unlock
-		type Fungibles: Balanced<Self::AccountId>
-			+ Mutate<Self::AccountId, AssetId = u32, Balance = BalanceOf<Self>>;
+		/// Assets deposited to the DEX besides the native currency. [`FungiblesAdapter`]
+		/// combines the assets of two pallets, e.g. `pallet_assets` and `orml_tokens`.
+		type Fungibles: Mutate<Self::AccountId, AssetId = u32, Balance = BalanceOf<Self>>;
+
+		/// Id of the tokens that `register_token` maps to DEX asset ids, e.g. the `CurrencyId`
+		/// of `orml_tokens`. [`RegisteredTokenIds`] reads the mapping for [`FungiblesAdapter`].
+		type TokenId: Parameter + MaxEncodedLen + MaybeSerializeDeserialize;
 
 		type PrivilegedOrigin: EnsureOrigin<<Self as frame_system::Config>::RuntimeOrigin>;
 
@@ -255,29 +1196,122 @@ pub mod pallet {
 
 		#[pallet::constant]
 		type UnsignedPriority: Get<TransactionPriority>;
-	}
 
-	/************* STORAGE ************ */
-	#[pallet::storage]
-	#[pallet::getter(fn user_token_infoes)]
-	pub type UserTokenInfoes<T: Config> = StorageDoubleMap<
-		_,
-		Blake2_128Concat,
-		T::AccountId, //address
-		Blake2_128Concat,
-		u32, //asset id
-		TokenInfo<BalanceOf<T>>,
-		ValueQuery,
-	>;
+		/// The identifier type for an authority allowed to submit offchain match results.
+		type AuthorityId: Member
+			+ Parameter
+			+ RuntimeAppPublic
+			+ Ord
+			+ MaybeSerializeDeserialize
+			+ MaxEncodedLen;
 
-	#[pallet::storage]
-	#[pallet::getter(fn token_infoes)]
-	pub type TokenInfoes<T: Config> =
-		StorageValue<_, BoundedVec<u32, ConstU32<{ u32::MAX }>>, ValueQuery>;
+		/// The current set of authorities whose keys may sign offchain match results.
+		type MatchAuthorities: Get<Vec<Self::AuthorityId>>;
 
-	#[pallet::storage]
-	#[pallet::getter(fn token_index)]
-	pub type TokenIndex<T: Config> = StorageMap<
+		/// Whether orders are matched by the offchain worker or on chain.
+		#[pallet::constant]
+		type MatchingMode: Get<MatchingMode>;
+
+		/// Account that receives the trading fees.
+		type FeeDestination: Get<Self::AccountId>;
+
+		/// How orders of the same account that would trade against each other are handled.
+		#[pallet::constant]
+		type SelfTradePolicy: Get<SelfTradePolicy>;
+
+		/// Maximum number of orders that can expire in the same block.
+		#[pallet::constant]
+		type MaxExpiringOrdersPerBlock: Get<u32>;
+
+		/// Maximum number of assets that can be allowlisted.
+		#[pallet::constant]
+		type MaxAssets: Get<u32>;
+
+		/// Maximum number of open orders of a pair.
+		#[pallet::constant]
+		type MaxOrdersPerPair: Get<u32>;
+
+		/// Maximum number of open orders of an account.
+		#[pallet::constant]
+		type MaxOpenOrdersPerAccount: Get<u32>;
+
+		/// Maximum number of orders placed or canceled by a batch call.
+		#[pallet::constant]
+		type MaxOrdersPerBatch: Get<u32>;
+
+		/// Prices conditional orders with an oracle trigger are checked against.
+		type PriceOracle: PriceOracle;
+
+		/// Maximum number of conditional orders waiting for their trigger in a pair.
+		#[pallet::constant]
+		type MaxConditionalOrdersPerPair: Get<u32>;
+
+		/// Maximum number of conditional orders triggered in a block.
+		#[pallet::constant]
+		type MaxTriggeredOrdersPerBlock: Get<u32>;
+
+		/// Time that candles are aggregated by.
+		type UnixTime: UnixTime;
+
+		/// Lengths in seconds of the intervals candles are kept for, e.g. a minute, an hour
+		/// and a day.
+		#[pallet::constant]
+		type CandleIntervals: Get<Vec<u64>>;
+
+		/// Number of candles kept for each pair and interval, older candles are overwritten.
+		#[pallet::constant]
+		type MaxCandles: Get<u32>;
+
+		/// Fee of swaps with the liquidity pools, charged in the asset paid in. It stays
+		/// in the pool and accrues to its liquidity providers.
+		#[pallet::constant]
+		type PoolFee: Get<Permill>;
+
+		/// Maximum number of assets in the path of a multi-hop swap.
+		#[pallet::constant]
+		type MaxSwapPathLength: Get<u32>;
+
+		/// Length in seconds of the periods that cumulative prices are observed in, the
+		/// first price update of a pair in each period records an observation.
+		#[pallet::constant]
+		type PriceObservationPeriod: Get<u64>;
+
+		/// Number of observations kept for each pair, older observations are overwritten.
+		/// Average prices are available over up to `MaxPriceObservations - 1` periods.
+		#[pallet::constant]
+		type MaxPriceObservations: Get<u32>;
+
+		/// Maximum number of fee tiers of a pair.
+		#[pallet::constant]
+		type MaxFeeTiers: Get<u32>;
+
+		/// Account that liquidity mining epochs are funded from, e.g. the treasury.
+		type RewardSource: Get<Self::AccountId>;
+
+		/// Weight information for extrinsics and hooks in this pallet.
+		type WeightInfo: WeightInfo;
+	}
+
+	/************* STORAGE ************ */
+	#[pallet::storage]
+	#[pallet::getter(fn user_token_infoes)]
+	pub type UserTokenInfoes<T: Config> = StorageDoubleMap<
+		_,
+		Blake2_128Concat,
+		T::AccountId, //address
+		Blake2_128Concat,
+		u32, //asset id
+		TokenInfo<BalanceOf<T>>,
+		ValueQuery,
+	>;
+
+	#[pallet::storage]
+	#[pallet::getter(fn token_infoes)]
+	pub type TokenInfoes<T: Config> = StorageValue<_, BoundedVec<u32, T::MaxAssets>, ValueQuery>;
+
+	#[pallet::storage]
+	#[pallet::getter(fn token_index)]
+	pub type TokenIndex<T: Config> = StorageMap<
 		_,
 		Blake2_128Concat,
 		u32,
@@ -303,23 +1337,32 @@ pub mod pallet {
 	#[pallet::getter(fn next_pair_order_index)]
 	pub(super) type NextPairOrderIndex<T: Config> = StorageValue<_, u64, ValueQuery>;
 
+	/// Open orders of each pair, iterate with `iter_key_prefix` to page through a pair.
 	#[pallet::storage]
 	#[pallet::getter(fn pair_orders)]
-	pub type PairOrders<T: Config> = StorageMap<
+	pub type PairOrders<T: Config> = StorageDoubleMap<
 		_,
 		Blake2_128Concat,
 		(u32, u32),
-		BoundedVec<u64, ConstU32<{ u32::MAX }>>,
+		Blake2_128Concat,
+		u64, //order index
+		(),
 		ValueQuery,
 	>;
 
+	/// Number of open orders of each pair, at most `MaxOrdersPerPair`.
+	#[pallet::storage]
+	#[pallet::getter(fn pair_order_count)]
+	pub type PairOrderCount<T: Config> =
+		StorageMap<_, Blake2_128Concat, (u32, u32), u32, ValueQuery>;
+
 	#[pallet::storage]
 	#[pallet::getter(fn order_expirations)]
 	pub type OrderExpiration<T: Config> = StorageMap<
 		_,
 		Blake2_128Concat,
 		BlockNumberFor<T>,
-		BoundedVec<u64, ConstU32<{ u32::MAX }>>,
+		BoundedVec<u64, T::MaxExpiringOrdersPerBlock>,
 		ValueQuery,
 	>;
 
@@ -335,10 +1378,233 @@ pub mod pallet {
 		ValueQuery,
 	>;
 
+	/// Number of open orders of each account, at most `MaxOpenOrdersPerAccount`.
+	#[pallet::storage]
+	#[pallet::getter(fn user_order_count)]
+	pub type UserOrderCount<T: Config> =
+		StorageMap<_, Blake2_128Concat, T::AccountId, u32, ValueQuery>;
+
 	#[pallet::storage]
 	#[pallet::getter(fn native_asset_id)]
 	pub type NativeAssetId<T: Config> = StorageValue<_, u32, ValueQuery>;
 
+	/// Order books used when orders are matched on chain. A book never holds more than
+	/// `MaxOrdersPerPair` orders, the whole book of a pair is read and written back on
+	/// every change, see `match_engine_weight`.
+	#[pallet::storage]
+	#[pallet::getter(fn match_engines)]
+	pub type MatchEngines<T: Config> = StorageMap<
+		_,
+		Blake2_128Concat,
+		(u32, u32),
+		MatchEngine<OrderOf<T>, FixedU128>,
+		OptionQuery,
+	>;
+
+	/// Tick size, lot size and minimum notional of each pair.
+	#[pallet::storage]
+	#[pallet::getter(fn pair_configs)]
+	pub type PairConfigs<T: Config> =
+		StorageMap<_, Blake2_128Concat, (u32, u32), PairConfig<BalanceOf<T>>, ValueQuery>;
+
+	/// Maker and taker fees of each pair.
+	#[pallet::storage]
+	#[pallet::getter(fn pair_fees)]
+	pub type PairFees<T: Config> = StorageMap<_, Blake2_128Concat, (u32, u32), PairFee, ValueQuery>;
+
+	/// Pairs orders can be placed in, with the base asset first.
+	#[pallet::storage]
+	#[pallet::getter(fn trading_pairs)]
+	pub type TradingPairs<T: Config> =
+		StorageMap<_, Blake2_128Concat, (u32, u32), TradingPair<BalanceOf<T>>, OptionQuery>;
+
+	/// Index of the next order to be matched on chain.
+	#[pallet::storage]
+	#[pallet::getter(fn next_match_order_index)]
+	pub type NextMatchOrderIndex<T: Config> = StorageValue<_, u64, ValueQuery>;
+
+	/// Id of the next trade settled on the DEX, trade ids are shared by all pairs.
+	#[pallet::storage]
+	#[pallet::getter(fn next_trade_id)]
+	pub type NextTradeId<T: Config> = StorageValue<_, u64, ValueQuery>;
+
+	/// Price of the last trade settled in each pair.
+	#[pallet::storage]
+	#[pallet::getter(fn last_trade_price)]
+	pub type LastTradePrice<T: Config> =
+		StorageMap<_, Blake2_128Concat, (u32, u32), FixedU128, OptionQuery>;
+
+	#[pallet::storage]
+	#[pallet::getter(fn next_conditional_order_index)]
+	pub(super) type NextConditionalOrderIndex<T: Config> = StorageValue<_, u64, ValueQuery>;
+
+	/// Conditional orders waiting for their trigger.
+	#[pallet::storage]
+	#[pallet::getter(fn conditional_orders)]
+	pub type ConditionalOrders<T: Config> = StorageMap<
+		_,
+		Blake2_128Concat,
+		u64, //conditional order index
+		ConditionalOrderOf<T>,
+		OptionQuery,
+	>;
+
+	/// Conditional orders waiting for their trigger in each pair, at most
+	/// `MaxConditionalOrdersPerPair`.
+	#[pallet::storage]
+	#[pallet::getter(fn pair_conditional_orders)]
+	pub type PairConditionalOrders<T: Config> = StorageMap<
+		_,
+		Blake2_128Concat,
+		(u32, u32),
+		BoundedVec<u64, T::MaxConditionalOrdersPerPair>,
+		ValueQuery,
+	>;
+
+	/// Pair whose conditional orders were checked last, the next block continues after it.
+	#[pallet::storage]
+	pub(super) type ConditionalOrderCursor<T: Config> = StorageValue<_, (u32, u32), OptionQuery>;
+
+	/// Candles of each pair and interval length. They are kept in `MaxCandles` slots
+	/// indexed by the number of the interval, so a candle replaces the one `MaxCandles`
+	/// intervals before it.
+	#[pallet::storage]
+	pub type Candles<T: Config> = StorageDoubleMap<
+		_,
+		Blake2_128Concat,
+		((u32, u32), u64), // pair, interval length
+		Twox64Concat,
+		u32, // slot
+		Candle<BalanceOf<T>>,
+		OptionQuery,
+	>;
+
+	/// Price band and circuit breaker of each pair that has one.
+	#[pallet::storage]
+	#[pallet::getter(fn price_bands)]
+	pub type PriceBands<T: Config> =
+		StorageMap<_, Blake2_128Concat, (u32, u32), PriceBandOf<T>, OptionQuery>;
+
+	/// Current window of the circuit breaker of each pair with a price band.
+	#[pallet::storage]
+	#[pallet::getter(fn price_windows)]
+	pub type PriceWindows<T: Config> =
+		StorageMap<_, Blake2_128Concat, (u32, u32), PriceWindow<BlockNumberFor<T>>, OptionQuery>;
+
+	/// Block until which a pair is halted by its circuit breaker. Entries of halts that
+	/// are over are left in place and ignored.
+	#[pallet::storage]
+	#[pallet::getter(fn circuit_breaker_halts)]
+	pub type CircuitBreakerHalts<T: Config> =
+		StorageMap<_, Blake2_128Concat, (u32, u32), BlockNumberFor<T>, OptionQuery>;
+
+	/// Constant-product liquidity pool of each pair that has liquidity.
+	#[pallet::storage]
+	#[pallet::getter(fn liquidity_pools)]
+	pub type LiquidityPools<T: Config> =
+		StorageMap<_, Blake2_128Concat, (u32, u32), LiquidityPool<BalanceOf<T>>, OptionQuery>;
+
+	/// Shares each liquidity provider holds in the pool of a pair.
+	#[pallet::storage]
+	#[pallet::getter(fn pool_shares)]
+	pub type PoolShares<T: Config> = StorageDoubleMap<
+		_,
+		Blake2_128Concat,
+		(u32, u32),
+		Blake2_128Concat,
+		T::AccountId,
+		BalanceOf<T>,
+		ValueQuery,
+	>;
+
+	/// Cumulative price of the liquidity pool of each pair that has one.
+	#[pallet::storage]
+	#[pallet::getter(fn price_accumulators)]
+	pub type PriceAccumulators<T: Config> =
+		StorageMap<_, Blake2_128Concat, (u32, u32), PriceAccumulator, OptionQuery>;
+
+	/// Observations of the cumulative price of each pair. They are kept in
+	/// `MaxPriceObservations` slots indexed by the number of the observation period, like
+	/// candles.
+	#[pallet::storage]
+	pub type PriceObservations<T: Config> = StorageDoubleMap<
+		_,
+		Blake2_128Concat,
+		(u32, u32),
+		Twox64Concat,
+		u32, // slot
+		PriceObservation,
+		OptionQuery,
+	>;
+
+	/// Fee tiers of each pair by trading volume, lowest volume first.
+	#[pallet::storage]
+	#[pallet::getter(fn fee_tiers)]
+	pub type FeeTiers<T: Config> =
+		StorageMap<_, Blake2_128Concat, (u32, u32), FeeTiersOf<T>, ValueQuery>;
+
+	/// Daily trading volume of each account in each pair, in the quote asset of the pair.
+	#[pallet::storage]
+	pub type TradingVolumes<T: Config> = StorageDoubleMap<
+		_,
+		Blake2_128Concat,
+		T::AccountId,
+		Blake2_128Concat,
+		(u32, u32),
+		TradingVolume<BalanceOf<T>>,
+		ValueQuery,
+	>;
+
+	#[pallet::storage]
+	pub(super) type NextRewardEpochId<T: Config> = StorageValue<_, u32, ValueQuery>;
+
+	/// Liquidity mining epochs. Epochs that ended are kept for the stakes of the orders
+	/// placed during them.
+	#[pallet::storage]
+	#[pallet::getter(fn reward_epochs)]
+	pub type RewardEpochs<T: Config> =
+		StorageMap<_, Blake2_128Concat, u32, RewardEpochOf<T>, OptionQuery>;
+
+	/// Latest liquidity mining epoch of each pair.
+	#[pallet::storage]
+	#[pallet::getter(fn pair_reward_epochs)]
+	pub type PairRewardEpochs<T: Config> =
+		StorageMap<_, Blake2_128Concat, (u32, u32), u32, OptionQuery>;
+
+	/// Stakes of the open orders that earn liquidity mining rewards.
+	#[pallet::storage]
+	pub type RewardStakes<T: Config> = StorageMap<
+		_,
+		Blake2_128Concat,
+		u64, //order index
+		RewardStake<BalanceOf<T>>,
+		OptionQuery,
+	>;
+
+	/// Liquidity mining rewards of each account that were not claimed yet, by asset id.
+	/// Rewards of open orders are added when the orders change or are claimed.
+	#[pallet::storage]
+	#[pallet::getter(fn unclaimed_rewards)]
+	pub type UnclaimedRewards<T: Config> = StorageDoubleMap<
+		_,
+		Blake2_128Concat,
+		T::AccountId,
+		Blake2_128Concat,
+		u32,
+		BalanceOf<T>,
+		ValueQuery,
+	>;
+
+	/// Tokens held outside of `pallet_assets` by DEX asset id, see [`RegisteredTokenIds`].
+	#[pallet::storage]
+	#[pallet::getter(fn token_ids)]
+	pub type TokenIds<T: Config> = StorageMap<_, Blake2_128Concat, u32, T::TokenId, OptionQuery>;
+
+	/// DEX asset id of each registered token, a token has a single asset id.
+	#[pallet::storage]
+	pub type TokenAssetIds<T: Config> =
+		StorageMap<_, Blake2_128Concat, T::TokenId, u32, OptionQuery>;
+
 	#[pallet::event]
 	#[pallet::generate_deposit(pub(super) fn deposit_event)]
 	pub enum Event<T: Config> {
@@ -349,34 +1615,215 @@ pub mod pallet {
 			order_index: u64,
 			order: OrderOf<T>,
 		},
+		/// Order `order_index` was taken as a whole by `taker`. `side` is the side of the
+		/// taker, fees are charged in the asset each side received.
 		OrderTaken {
-			account: T::AccountId,
+			trade_id: u64,
 			order_index: u64,
-			order: OrderOf<T>,
+			pair: (u32, u32),
+			side: OrderType,
+			price: FixedU128,
+			quantity_base: BalanceOf<T>,
+			quantity_quote: BalanceOf<T>,
+			maker: T::AccountId,
+			taker: T::AccountId,
+			maker_fee: BalanceOf<T>,
+			taker_fee: BalanceOf<T>,
 		},
 		OrderCanceled {
 			order_index: u64,
+			who: T::AccountId,
+		},
+		/// An order was amended. `new_order_index` is the order that replaced it, or
+		/// `order_index` when the order kept its place in the queue.
+		OrderAmended {
+			order_index: u64,
+			new_order_index: u64,
+		},
+		/// The unfilled part of an order that must not rest on the order book was
+		/// canceled after matching.
+		OrderClosed {
+			order_index: u64,
+			order_status: OrderStatus,
+		},
+		/// An order was filled and removed from storage, `order` is its final state.
+		OrderFilled {
+			order_index: u64,
+			order: OrderOf<T>,
+		},
+		/// The self-trade policy was applied to a taker order that met an order of the
+		/// same account. `quantity_base` is the amount of the base asset that was taken
+		/// off the orders instead of being traded.
+		SelfTradePrevented {
+			taker_order_index: u64,
+			maker_order_index: u64,
+			policy: SelfTradePolicy,
+			quantity_base: BalanceOf<T>,
+		},
+		/// The self-trade policy was applied to an account that took its own order.
+		/// `quantity_base` is the unfilled amount of the base asset that was not traded.
+		SelfTakePrevented {
+			order_index: u64,
+			policy: SelfTradePolicy,
+			quantity_base: BalanceOf<T>,
 		},
+		/// A trade between two orders was settled at `price`. `side` is the side of the
+		/// taker order.
 		OrderMatched {
+			trade_id: u64,
+			pair: (u32, u32),
+			side: OrderType,
+			price: FixedU128,
 			quantity_base: BalanceOf<T>,
 			quantity_quote: BalanceOf<T>,
-			taker_order: OrderOf<T>,
-			maker_order: OrderOf<T>,
+			maker: T::AccountId,
+			taker: T::AccountId,
+			maker_order_index: u64,
+			taker_order_index: u64,
+			/// Fee charged to the maker in the asset it received.
+			maker_fee: BalanceOf<T>,
+			/// Fee charged to the taker in the asset it received.
+			taker_fee: BalanceOf<T>,
 		},
 		Deposited {
+			who: T::AccountId,
 			asset_id: u32,
 			amount: BalanceOf<T>,
 		},
 		Withdrawed {
+			who: T::AccountId,
 			asset_id: u32,
 			amount: BalanceOf<T>,
 		},
 		NativeDeposited {
+			who: T::AccountId,
 			amount: BalanceOf<T>,
 		},
 		NativeWithdrawed {
+			who: T::AccountId,
+			amount: BalanceOf<T>,
+		},
+		PairConfigSet {
+			pair: (u32, u32),
+			config: PairConfig<BalanceOf<T>>,
+		},
+		PairFeeSet {
+			pair: (u32, u32),
+			fee: PairFee,
+		},
+		TradingPairRegistered {
+			pair: (u32, u32),
+			trading_pair: TradingPair<BalanceOf<T>>,
+		},
+		TradingPairStatusChanged {
+			pair: (u32, u32),
+			status: PairStatus,
+		},
+		TradingPairMinOrderSizeSet {
+			pair: (u32, u32),
+			min_order_size: BalanceOf<T>,
+		},
+		/// The price band of a pair was set, or removed when it is `None`.
+		PriceBandSet {
+			pair: (u32, u32),
+			price_band: Option<PriceBandOf<T>>,
+		},
+		/// The price of a pair moved more than its price band allows within a window, the
+		/// pair doesn't trade until `halted_until`.
+		CircuitBreakerTripped {
+			pair: (u32, u32),
+			open_price: FixedU128,
+			price: FixedU128,
+			halted_until: BlockNumberFor<T>,
+		},
+		ConditionalOrderCreated {
+			conditional_order_index: u64,
+			conditional_order: ConditionalOrderOf<T>,
+		},
+		ConditionalOrderCanceled {
+			conditional_order_index: u64,
+		},
+		/// A conditional order was triggered and placed as order `order_index`.
+		ConditionalOrderTriggered {
+			conditional_order_index: u64,
+			order_index: u64,
+		},
+		/// A triggered conditional order could not be placed, its funds were released.
+		ConditionalOrderFailed {
+			conditional_order_index: u64,
+			error: DispatchError,
+		},
+		LiquidityAdded {
+			who: T::AccountId,
+			pair: (u32, u32),
+			base_amount: BalanceOf<T>,
+			quote_amount: BalanceOf<T>,
+			shares: BalanceOf<T>,
+		},
+		LiquidityRemoved {
+			who: T::AccountId,
+			pair: (u32, u32),
+			base_amount: BalanceOf<T>,
+			quote_amount: BalanceOf<T>,
+			shares: BalanceOf<T>,
+		},
+		/// `who` swapped `amount_in` for `amount_out` on `side` of a pair. Trades of swaps
+		/// filled on the order book are reported by `OrderMatched` as well.
+		Swapped {
+			who: T::AccountId,
+			pair: (u32, u32),
+			side: OrderType,
+			amount_in: BalanceOf<T>,
+			amount_out: BalanceOf<T>,
+			venue: SwapVenue,
+		},
+		/// `who` swapped `amount_in` of the first asset of `path` for `amount_out` of the
+		/// last one, each hop is reported by `Swapped`.
+		PathSwapped {
+			who: T::AccountId,
+			path: BoundedVec<u32, T::MaxSwapPathLength>,
+			amount_in: BalanceOf<T>,
+			amount_out: BalanceOf<T>,
+		},
+		FeeTiersSet {
+			pair: (u32, u32),
+			tiers: FeeTiersOf<T>,
+		},
+		/// The maker of a trade received `amount` of `asset_id` out of the taker fee.
+		MakerRebatePaid {
+			trade_id: u64,
+			maker: T::AccountId,
+			asset_id: u32,
+			amount: BalanceOf<T>,
+		},
+		/// A liquidity mining epoch of a pair was funded with `reward` from the
+		/// `RewardSource`.
+		RewardEpochFunded {
+			epoch_id: u32,
+			pair: (u32, u32),
+			terms: RewardTermsOf<T>,
+			reward: BalanceOf<T>,
+		},
+		RewardsClaimed {
+			who: T::AccountId,
+			asset_id: u32,
+			amount: BalanceOf<T>,
+		},
+		/// The unearned reward of an ended liquidity mining epoch went back to the
+		/// `RewardSource`.
+		RewardsReclaimed {
+			epoch_id: u32,
+			asset_id: u32,
 			amount: BalanceOf<T>,
 		},
+		TokenRegistered {
+			asset_id: u32,
+			token_id: T::TokenId,
+		},
+		TokenDeregistered {
+			asset_id: u32,
+			token_id: T::TokenId,
+		},
 	}
 
 	#[pallet::error]
@@ -395,127 +1842,251 @@ pub mod pallet {
 		NotEnoughBalance,
 		ExpirationMustBeInFuture,
 		OffchainUnsignedTxError,
-		PriceDoNotMatchOfferedRequestedAmount,
+		InvalidPrice,
 		DivOverflow,
 		MulOverflow,
+		OffchainSigningError,
+		InvalidTradeOrder,
+		InvalidTradePrice,
+		InvalidTradeAmount,
+		PricesDoNotCross,
+		TradeExceedsUnfilledAmount,
+		MarketOrderMustNotRest,
+		FillOrKillNotFilled,
+		InvalidPairConfig,
+		InvalidLotSize,
+		BelowMinNotional,
+		TooManyAssets,
+		TooManyPairOrders,
+		TooManyOpenOrders,
+		TooManyExpiringOrders,
+		OrderNotAmendable,
+		SelfTrade,
+		TooManyConditionalOrders,
+		InvalidConditionalOrderIndex,
+		InvalidTriggerPrice,
+		PairNotRegistered,
+		PairAlreadyRegistered,
+		PairNotTradable,
+		PairIsPostOnly,
+		BelowMinOrderSize,
+		InvalidPriceBand,
+		PriceOutsideBand,
+		PoolNotFound,
+		InsufficientLiquidity,
+		InsufficientShares,
+		SlippageExceeded,
+		InvalidSwapPath,
+		MalformedDepositMemo,
+		InvalidFeeTiers,
+		InvalidRewardTerms,
+		RewardEpochRunning,
+		NoRewardsToClaim,
+		StaleMatchResult,
+		AssetIdInUse,
+		TokenAlreadyRegistered,
+		TokenNotRegistered,
+		TokenHasDeposits,
+		RewardEpochNotFound,
+		NoRewardsToReclaim,
+		OrderNotOpen,
 	}
 
 	#[pallet::hooks]
 	impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
+		fn integrity_test() {
+			assert!(
+				T::MaxOrdersPerPair::get() <= MAX_BOOK_ORDERS,
+				"an order book holds at most MAX_BOOK_ORDERS orders"
+			);
+		}
+
 		fn on_initialize(n: BlockNumberFor<T>) -> Weight {
 			// Check if there are any orders that have expired
 			// 1. If the order has expired, the order will be canceled
+			// 2. At most MaxExpiringOrdersPerBlock orders expire in a block
 
 			let expired_orders = OrderExpiration::<T>::take(n);
+			let expired_count = expired_orders.len() as u32;
 			for order_id in expired_orders {
 				let _ = Self::cancel_order_impl(order_id);
 			}
-			// We would need to return the weight consumed by this function later.
-			// But for now, we can keep it as it is cause we don't estimate gas usage anyways...
-			Weight::zero()
+
+			T::WeightInfo::on_initialize(expired_count)
+				.saturating_add(Self::match_engine_weight(expired_count))
 		}
 
-		fn offchain_worker(_block_number: T::BlockNumber) {
-			let timestamp_now = sp_io::offchain::timestamp();
-			log::info!("###### Current time: {:?} ", timestamp_now.unix_millis());
+		fn on_idle(n: BlockNumberFor<T>, remaining_weight: Weight) -> Weight {
+			let mut consumed_weight = Self::trigger_conditional_orders(n, remaining_weight);
 
-			let store_hashmap_match_engines =
-				StorageValueRef::persistent(b"dex_ocw::match_engines");
+			if !T::MatchingMode::get().is_on_chain() {
+				return consumed_weight;
+			}
 
-			let store_last_process_order_id =
-				StorageValueRef::persistent(b"dex_ocw::last_process_order_id");
+			// Orders that were not matched on creation are matched here, oldest first.
+			let db_weight = T::DbWeight::get();
+			consumed_weight.saturating_accrue(db_weight.reads(2));
+			let next_order_index = NextOrderIndex::<T>::get();
+			let mut match_order_index = NextMatchOrderIndex::<T>::get();
 
-			let mut map_match_engines: MapMatchEnginesOf<T>;
+			while match_order_index < next_order_index {
+				let order_weight =
+					T::WeightInfo::match_order(0).saturating_add(Self::match_engine_weight(1));
+				if !remaining_weight.all_gte(consumed_weight.saturating_add(order_weight)) {
+					break;
+				}
 
-			if let Ok(Some(engines)) = store_hashmap_match_engines.get::<BoundedBTreeMap<
-				(u32, u32),
-				MatchEngine<OrderOf<T>, BalanceOf<T>>,
-				ConstU32<{ u32::MAX }>,
-			>>() {
-				map_match_engines = engines;
-			} else {
-				map_match_engines = BoundedBTreeMap::new();
+				match with_storage_layer(|| Self::match_order_on_chain(match_order_index)) {
+					Ok(trades) => {
+						consumed_weight.saturating_accrue(
+							T::WeightInfo::match_order(trades)
+								.saturating_add(Self::match_engine_weight(1)),
+						);
+					}
+					Err(e) => {
+						log::error!(
+							"Failed to match order {:?} on chain: {:?}",
+							match_order_index,
+							e
+						);
+						consumed_weight.saturating_accrue(order_weight);
+					}
+				}
+
+				match_order_index += 1;
+				NextMatchOrderIndex::<T>::put(match_order_index);
+				consumed_weight.saturating_accrue(db_weight.writes(1));
 			}
 
-			let mut last_process_order_id: u64;
-			if let Ok(Some(order_id)) = store_last_process_order_id.get::<u64>() {
-				last_process_order_id = order_id;
-			} else {
-				last_process_order_id = u64::default();
+			consumed_weight
+		}
+
+		fn offchain_worker(block_number: T::BlockNumber) {
+			if T::MatchingMode::get().is_on_chain() {
+				return;
 			}
 
+			let timestamp_now = sp_io::offchain::timestamp();
+			log::info!("###### Current time: {:?} ", timestamp_now.unix_millis());
+
+			// Only authorities can sign match results, so there is no point in matching
+			// orders on a node that doesn't hold an authority key.
+			let (authority_index, authority_key) = match Self::local_authority_keys().next() {
+				Some(local_key) => local_key,
+				None => {
+					log::debug!("No local DEX match authority key, skipping order matching");
+					return;
+				}
+			};
+
 			let mut lock = StorageLock::<BlockAndTime<Self>>::with_block_and_time_deadline(
 				b"offchain-dex::lock",
 				LOCK_BLOCK_EXPIRATION,
 				Duration::from_millis(LOCK_TIMEOUT_EXPIRATION),
 			);
+			let _guard = match lock.try_lock() {
+				Ok(guard) => guard,
+				Err(_) => return,
+			};
 
-			let on_chain_order_index = NextOrderIndex::<T>::get();
-
-			if last_process_order_id > on_chain_order_index {
-				return;
-			}
+			let store_hashmap_match_engines =
+				StorageValueRef::persistent(b"dex_ocw::match_engines");
 
-			if let Ok(_guard) = lock.try_lock() {
-				let mut max_time = 50;
-				while last_process_order_id <= on_chain_order_index && max_time > 0 {
-					if !Orders::<T>::contains_key(last_process_order_id) {
-						return;
-					}
-					let order = Orders::<T>::get(last_process_order_id).unwrap();
+			let store_last_process_order_id =
+				StorageValueRef::persistent(b"dex_ocw::last_process_order_id");
 
-					let mut engine: MatchEngine<OrderOf<T>, BalanceOf<T>>;
-					if let Some(en) = map_match_engines.get_mut(&order.pair) {
-						engine = en.clone();
-					} else {
-						engine = MatchEngine {
-							buy_book: OrderBook {
-								order_type: OrderType::BUY,
-								book: BoundedBTreeMap::new(),
-							},
-							sell_book: OrderBook {
-								order_type: OrderType::SELL,
-								book: BoundedBTreeMap::new(),
-							},
-							market_price: Default::default(),
-							last_process_order_id,
-						};
-					}
+			let store_last_block = StorageValueRef::persistent(b"dex_ocw::last_block");
 
-					match Self::process_order(last_process_order_id, order.clone(), &mut engine) {
-						Ok(match_result) => {
-							last_process_order_id += 1;
-							store_last_process_order_id.set(&last_process_order_id);
+			let store_health = StorageValueRef::persistent(OCW_HEALTH_KEY);
 
-							let rt = map_match_engines.try_insert(order.pair, engine.clone());
+			let block_hash = frame_system::Pallet::<T>::block_hash(block_number);
+			let mut health = match store_health.get::<MatchingHealth<T::BlockNumber>>() {
+				Ok(Some(health)) => health,
+				_ => MatchingHealth::default(),
+			};
 
-							if let Err(e) = rt {
-								log::error!("Failed in  map_match_engines.try_insert {:?}", e);
-							}
+			let mut map_match_engines: MapMatchEnginesOf<T>;
+			let mut last_process_order_id: u64;
 
-							store_hashmap_match_engines.set(&map_match_engines);
-
-							if !match_result.match_details.is_empty() {
-								match Self::offchain_unsigned_tx(match_result.clone()) {
-									Ok(_) => {}
-									Err(e) => {
-										log::error!(
-											"Failed in  Self::offchain_unsigned_tx {:?} {:?}",
-											e,
-											match_result
-										);
-									}
-								}
-							}
-						}
-						Err(e) => {
-							log::error!("Failed in  Self::process_order {:?}", e);
-						}
-					}
+			let state_is_current = match store_last_block.get::<(T::BlockNumber, T::Hash)>() {
+				Ok(Some((number, hash))) => {
+					Self::is_on_current_chain(number, hash, block_number, block_hash)
+				}
+				_ => false,
+			};
+
+			if state_is_current {
+				if let Ok(Some(engines)) = store_hashmap_match_engines.get::<MapMatchEnginesOf<T>>()
+				{
+					map_match_engines = engines;
+				} else {
+					map_match_engines = BoundedBTreeMap::new();
+				}
+
+				if let Ok(Some(order_id)) = store_last_process_order_id.get::<u64>() {
+					last_process_order_id = order_id;
+				} else {
+					last_process_order_id = u64::default();
+				}
+
+				Self::refresh_match_engines(&mut map_match_engines);
+			} else {
+				// After a restart or a reorg the offchain state can't be trusted, the order
+				// books are rebuilt by matching the open orders again, oldest first.
+				log::info!(
+					"Rebuilding DEX order books from chain state at block {:?}",
+					block_number
+				);
+
+				map_match_engines = BoundedBTreeMap::new();
+				let mut open_orders: Vec<u64> = PairOrders::<T>::iter_keys()
+					.map(|(_, order_index)| order_index)
+					.collect();
+				open_orders.sort();
+				for order_index in open_orders {
+					Self::offchain_match_order(
+						order_index,
+						&mut map_match_engines,
+						authority_index,
+						&authority_key,
+					);
+				}
+
+				last_process_order_id = NextOrderIndex::<T>::get();
+				health.last_rebuild_block = block_number;
+			}
 
+			let next_order_index = NextOrderIndex::<T>::get();
+			let mut max_time = 50;
+			while last_process_order_id < next_order_index && max_time > 0 {
+				// Indexes of orders that were cancelled or taken before they were matched
+				// are skipped.
+				if Self::offchain_match_order(
+					last_process_order_id,
+					&mut map_match_engines,
+					authority_index,
+					&authority_key,
+				) {
 					max_time -= 1;
 				}
-			};
+				last_process_order_id += 1;
+			}
+
+			store_hashmap_match_engines.set(&map_match_engines);
+			store_last_process_order_id.set(&last_process_order_id);
+			store_last_block.set(&(block_number, block_hash));
+
+			health.block_number = block_number;
+			health.next_order_index = last_process_order_id;
+			health.pending_orders = next_order_index.saturating_sub(last_process_order_id);
+			if health.pending_orders > 0 {
+				log::warn!(
+					"DEX order matching lags behind by {} orders at block {:?}",
+					health.pending_orders,
+					block_number
+				);
+			}
+			store_health.set(&health);
 		}
 	}
 
@@ -540,8 +2111,34 @@ pub mod pallet {
 			};
 
 			match call {
-				Call::update_match_order_unsigned { match_result: _ } => {
-					valid_tx(b"update_match_order_unsigned".to_vec())
+				Call::update_match_order_unsigned {
+					match_result,
+					authority_index,
+					signature,
+				} if !T::MatchingMode::get().is_on_chain() => {
+					// The match result must be signed by one of the current authorities.
+					let authorities = T::MatchAuthorities::get();
+					let authority_id = match authorities.get(*authority_index as usize) {
+						Some(id) => id,
+						None => return InvalidTransaction::BadProof.into(),
+					};
+
+					let signature_valid = (match_result, authority_index)
+						.using_encoded(|payload| authority_id.verify(&payload, signature));
+
+					if !signature_valid {
+						return InvalidTransaction::BadProof.into();
+					}
+
+					if !Self::taker_unchanged(match_result) {
+						return InvalidTransaction::Stale.into();
+					}
+
+					valid_tx(
+						match_result
+							.using_encoded(sp_io::hashing::blake2_256)
+							.to_vec(),
+					)
 				}
 				_ => InvalidTransaction::Call.into(),
 			}
@@ -550,7 +2147,7 @@ pub mod pallet {
 
 	#[pallet::call]
 	impl<T: Config> Pallet<T> {
-		#[pallet::weight({0})]
+		#[pallet::weight(T::WeightInfo::deposit())]
 		#[pallet::call_index(0)]
 		pub fn deposit(
 			origin: OriginFor<T>,
@@ -559,38 +2156,12 @@ pub mod pallet {
 		) -> DispatchResultWithPostInfo {
 			let who = ensure_signed(origin)?;
 
-			ensure!(
-				TokenIndex::<T>::contains_key(asset_id),
-				Error::<T>::AssetIdNotInTokenIndex
-			);
-
-			<T::Fungibles as Mutate<T::AccountId>>::transfer(
-				asset_id,
-				&who,
-				&Self::account_id(),
-				amount,
-				Preservation::Expendable,
-			)?;
-
-			let mut info = TokenInfo::default();
-			if UserTokenInfoes::<T>::contains_key(who.clone(), asset_id) {
-				info = UserTokenInfoes::<T>::get(who.clone(), asset_id);
-				info.amount = info
-					.amount
-					.checked_add(&amount)
-					.ok_or(Error::<T>::TokenBalanceOverflow)?;
-			} else {
-				info.amount = amount;
-			}
-
-			UserTokenInfoes::<T>::insert(who, asset_id, info);
-
-			Self::deposit_event(Event::Deposited { asset_id, amount });
+			Self::do_deposit(&who, asset_id, amount)?;
 
 			Ok(().into())
 		}
 
-		#[pallet::weight({1})]
+		#[pallet::weight(T::WeightInfo::withdraw())]
 		#[pallet::call_index(1)]
 		pub fn withdraw(
 			origin: OriginFor<T>,
@@ -623,13 +2194,19 @@ pub mod pallet {
 				Preservation::Expendable,
 			)?;
 
-			UserTokenInfoes::<T>::insert(who, asset_id, info);
+			UserTokenInfoes::<T>::insert(&who, asset_id, info);
 
-			Self::deposit_event(Event::Withdrawed { asset_id, amount });
+			Self::deposit_event(Event::Withdrawed {
+				who,
+				asset_id,
+				amount,
+			});
 			Ok(().into())
 		}
 
-		#[pallet::weight({2})]
+		#[pallet::weight(
+			T::WeightInfo::make_order().saturating_add(Pallet::<T>::match_engine_weight(1))
+		)]
 		#[pallet::call_index(2)]
 		#[allow(clippy::too_many_arguments)]
 		pub fn make_order(
@@ -643,101 +2220,24 @@ pub mod pallet {
 		) -> DispatchResultWithPostInfo {
 			let who = ensure_signed(origin)?;
 
-			let (asset_id_1, asset_id_2, order_type) = if asset_id_1 > asset_id_2 {
-				(asset_id_2, asset_id_1, order_type.get_opposite())
-			} else {
-				(asset_id_1, asset_id_2, order_type)
-			};
-
-			ensure!(
-				asset_id_1 != asset_id_2,
-				Error::<T>::PairAssetIdMustNotEqual
-			);
-
-			ensure!(
-				expiration_block > frame_system::Pallet::<T>::block_number(),
-				Error::<T>::ExpirationMustBeInFuture
-			);
-
-			let (a, b) = match order_type {
-				OrderType::SELL => (requested_amount, offered_amount),
-				OrderType::BUY => (offered_amount, requested_amount),
-			};
-
-			// because price is an integer, we need to check if the division is exact
-			// (does not have a remainder)
-			let price = a
-				.checked_div(&b)
-				.ok_or(Error::<T>::PriceDoNotMatchOfferedRequestedAmount)?;
-
-			// do the check
-			if price
-				.checked_mul(&b)
-				.ok_or(Error::<T>::PriceDoNotMatchOfferedRequestedAmount)?
-				!= a
-			{
-				return Err(Error::<T>::PriceDoNotMatchOfferedRequestedAmount.into());
-			}
-
-			NextOrderIndex::<T>::try_mutate(|index| -> DispatchResult {
-				let order_index = *index;
-
-				let order = Order {
-					counter: order_index,
-					pair: (asset_id_1, asset_id_2),
-					expiration_block,
-					order_type,
-					address: who.clone(),
-					amount_offered: offered_amount,
-					amout_requested: requested_amount,
-					price,
-					unfilled_offered: offered_amount,
-					unfilled_requested: requested_amount,
-					order_status: OrderStatus::Pending,
-				};
-
-				let update_asset_id = match order.order_type {
-					OrderType::SELL => asset_id_1,
-					OrderType::BUY => asset_id_2,
-				};
-				let mut info = UserTokenInfoes::<T>::get(who.clone(), update_asset_id);
-				info.amount = info
-					.amount
-					.checked_sub(&order.amount_offered)
-					.ok_or(Error::<T>::NotEnoughBalance)?;
-				info.reserved = info
-					.reserved
-					.checked_add(&order.amount_offered)
-					.ok_or(Error::<T>::TokenBalanceOverflow)?;
-				UserTokenInfoes::<T>::insert(who.clone(), update_asset_id, info);
-
-				*index = index
-					.checked_add(One::one())
-					.ok_or(Error::<T>::OrderIndexOverflow)?;
-
-				Orders::<T>::insert(order_index, &order);
-				UserOrders::<T>::insert(who.clone(), order_index, ());
-
-				let mut expiration_orders = OrderExpiration::<T>::get(expiration_block);
-				expiration_orders
-					.try_push(order_index)
-					.expect("Max expiration_orders");
-				OrderExpiration::<T>::insert(expiration_block, expiration_orders);
-
-				let mut bounded_pair_orders = PairOrders::<T>::get((asset_id_1, asset_id_2));
-				bounded_pair_orders
-					.try_push(order_index)
-					.expect("Max bounded_pair_orders");
-				PairOrders::<T>::insert((asset_id_1, asset_id_2), bounded_pair_orders);
-
-				Self::deposit_event(Event::OrderCreated { order_index, order });
-				Ok(())
-			})?;
+			Self::do_place_order(
+				who,
+				asset_id_1,
+				asset_id_2,
+				offered_amount,
+				requested_amount,
+				order_type,
+				OrderKind::Limit,
+				TimeInForce::GoodTillExpiration,
+				expiration_block,
+			)?;
 
 			Ok(().into())
 		}
 
-		#[pallet::weight({3})]
+		#[pallet::weight(
+			T::WeightInfo::cancel_order().saturating_add(Pallet::<T>::match_engine_weight(1))
+		)]
 		#[pallet::call_index(3)]
 		pub fn cancel_order(origin: OriginFor<T>, order_index: u64) -> DispatchResultWithPostInfo {
 			let who = ensure_signed(origin)?;
@@ -750,63 +2250,95 @@ pub mod pallet {
 			Ok(().into())
 		}
 
-		#[pallet::weight({4})]
+		#[pallet::weight(
+			T::WeightInfo::take_order().saturating_add(Pallet::<T>::match_engine_weight(1))
+		)]
 		#[pallet::call_index(4)]
 		pub fn take_order(origin: OriginFor<T>, order_index: u64) -> DispatchResultWithPostInfo {
 			let who = ensure_signed(origin)?;
 
+			let order = Orders::<T>::get(order_index).ok_or(Error::<T>::InvalidOrderIndex)?;
+			ensure!(Self::is_order_open(&order), Error::<T>::OrderNotOpen);
+			if order.address == who {
+				// the taker has no order of its own, so whatever the policy cancels on the
+				// taker side is the take itself, and the maker order is taken off whole
+				let policy = T::SelfTradePolicy::get();
+				match policy {
+					SelfTradePolicy::CancelNewest => {}
+					SelfTradePolicy::CancelOldest | SelfTradePolicy::DecrementAndCancel => {
+						Self::cancel_order_impl(order_index)?
+					}
+				}
+				Self::deposit_event(Event::SelfTakePrevented {
+					order_index,
+					policy,
+					quantity_base: *order.get_unfilled_base_amout(),
+				});
+				return Ok(().into());
+			}
+
 			Orders::<T>::try_mutate_exists(order_index, |order| -> DispatchResult {
 				let order = order.take().ok_or(Error::<T>::InvalidOrderIndex)?;
 
-				UserOrders::<T>::remove(&order.address, order_index);
-
-				PairOrders::<T>::try_mutate_exists(
-					order.pair,
-					|bounded_pair_orders| -> DispatchResult {
-						let pair_orders = bounded_pair_orders
-							.as_mut()
-							.ok_or(Error::<T>::PairOrderNotFound)?;
-						let rt = pair_orders.binary_search(&order_index);
-						if let Ok(rt) = rt {
-							pair_orders.remove(rt);
-						}
-
-						PairOrders::<T>::insert(order.pair, pair_orders);
-						Ok(())
-					},
-				)?;
-
-				match order.order_type {
-					OrderType::SELL => {
-						// for maker
-						Self::add_assert(&order.address, order.pair.1, order.amout_requested)?;
-						Self::sub_reserved_assert(
-							&order.address,
-							order.pair.0,
-							order.amount_offered,
-						)?;
-						// for taker
-						Self::add_assert(&who, order.pair.0, order.amount_offered)?;
-						Self::sub_assert(&who, order.pair.1, order.amout_requested)?;
-					}
-					OrderType::BUY => {
-						// for maker
-						Self::add_assert(&order.address, order.pair.0, order.amout_requested)?;
-						Self::sub_reserved_assert(
-							&order.address,
-							order.pair.1,
-							order.amount_offered,
-						)?;
-						// for taker
-						Self::add_assert(&who, order.pair.1, order.amount_offered)?;
-						Self::sub_assert(&who, order.pair.0, order.amout_requested)?;
+				// taking an order takes liquidity from the book
+				match Self::trading_status(order.pair) {
+					PairStatus::Active => {}
+					PairStatus::PostOnly => return Err(Error::<T>::PairIsPostOnly.into()),
+					PairStatus::Halted | PairStatus::Delisted => {
+						return Err(Error::<T>::PairNotTradable.into())
 					}
 				}
 
+				Self::remove_order_indexes(&order);
+				Self::remove_from_match_engine(&order);
+
+				let (offered_asset_id, requested_asset_id) = match order.order_type {
+					OrderType::SELL => (order.pair.0, order.pair.1),
+					OrderType::BUY => (order.pair.1, order.pair.0),
+				};
+				// only the unfilled part of a partially filled order is left to take
+				let (offered, requested) = (order.unfilled_offered, order.unfilled_requested);
+				let (quantity_base, quantity_quote) = match order.order_type {
+					OrderType::SELL => (offered, requested),
+					OrderType::BUY => (requested, offered),
+				};
+				let trade_id =
+					Self::record_trade(order.pair, order.price, quantity_base, quantity_quote);
+
+				let (fee, maker_rebate) = Self::trade_fees(order.pair, &order.address, &who);
+				let maker_fee = fee.maker_fee.mul_floor(requested);
+				let taker_fee = fee.taker_fee.mul_floor(offered);
+				Self::record_volume(order.pair, &order.address, &who, quantity_quote);
+
+				// for maker
+				Self::add_assert(&order.address, requested_asset_id, requested - maker_fee)?;
+				Self::sub_reserved_assert(&order.address, offered_asset_id, offered)?;
+				// for taker
+				Self::add_assert(&who, offered_asset_id, offered - taker_fee)?;
+				Self::sub_assert(&who, requested_asset_id, requested)?;
+
+				Self::pay_fee(requested_asset_id, maker_fee)?;
+				let taker_fee_paid = Self::pay_maker_rebate(
+					trade_id,
+					&order.address,
+					offered_asset_id,
+					taker_fee,
+					maker_rebate,
+				)?;
+				Self::pay_fee(offered_asset_id, taker_fee_paid)?;
+
 				Self::deposit_event(Event::OrderTaken {
-					account: who,
+					trade_id,
 					order_index,
-					order,
+					pair: order.pair,
+					side: order.order_type.get_opposite(),
+					price: order.price,
+					quantity_base,
+					quantity_quote,
+					maker: order.address,
+					taker: who,
+					maker_fee,
+					taker_fee,
 				});
 
 				Ok(())
@@ -815,7 +2347,7 @@ pub mod pallet {
 			Ok(().into())
 		}
 
-		#[pallet::weight({5})]
+		#[pallet::weight(T::WeightInfo::deposit_native())]
 		#[pallet::call_index(5)]
 		pub fn deposit_native(
 			origin: OriginFor<T>,
@@ -843,14 +2375,14 @@ pub mod pallet {
 				info.amount = amount;
 			}
 
-			UserTokenInfoes::<T>::insert(who, asset_id, info);
+			UserTokenInfoes::<T>::insert(&who, asset_id, info);
 
-			Self::deposit_event(Event::NativeDeposited { amount });
+			Self::deposit_event(Event::NativeDeposited { who, amount });
 
 			Ok(().into())
 		}
 
-		#[pallet::weight({6})]
+		#[pallet::weight(T::WeightInfo::withdraw_native())]
 		#[pallet::call_index(6)]
 		pub fn withdraw_native(
 			origin: OriginFor<T>,
@@ -878,294 +2410,3366 @@ pub mod pallet {
 
 			T::Currency::transfer(&Self::account_id(), &who, amount, AllowDeath)?;
 
-			UserTokenInfoes::<T>::insert(who, asset_id, info);
+			UserTokenInfoes::<T>::insert(&who, asset_id, info);
 
-			Self::deposit_event(Event::NativeWithdrawed { amount });
+			Self::deposit_event(Event::NativeWithdrawed { who, amount });
 			Ok(().into())
 		}
 
-		#[pallet::weight({7})]
+		#[pallet::weight(T::WeightInfo::allowlist_asset())]
 		#[pallet::call_index(7)]
 		pub fn allowlist_asset(origin: OriginFor<T>, asset_id: u32) -> DispatchResultWithPostInfo {
 			T::PrivilegedOrigin::ensure_origin(origin)?;
 
-			TokenInfoes::<T>::mutate(|token_infoes| {
+			TokenInfoes::<T>::try_mutate(|token_infoes| -> DispatchResult {
 				TokenIndex::<T>::insert(asset_id, token_infoes.len() as u64);
 
-				token_infoes.try_push(asset_id).expect("Max token_infoes");
-			});
+				token_infoes
+					.try_push(asset_id)
+					.map_err(|_| Error::<T>::TooManyAssets)?;
+				Ok(())
+			})?;
 			Ok(().into())
 		}
 
-		#[pallet::weight({8})]
+		#[pallet::weight(T::WeightInfo::update_match_order_unsigned(
+			(match_result.match_details.len() + match_result.self_trades.len()) as u32
+		))]
 		#[pallet::call_index(8)]
 		pub fn update_match_order_unsigned(
 			origin: OriginFor<T>,
 			match_result: MatchResult<BalanceOf<T>, OrderOf<T>>,
+			_authority_index: u32,
+			_signature: <T::AuthorityId as RuntimeAppPublic>::Signature,
 		) -> DispatchResult {
 			// This ensures that the function can only be called via unsigned transaction.
+			// The authority signature is checked in `validate_unsigned`.
 			ensure_none(origin)?;
+			ensure!(
+				Self::taker_unchanged(&match_result),
+				Error::<T>::StaleMatchResult
+			);
+
+			Self::settle_match_result(&match_result)?;
+
+			Self::close_taker_order(match_result.taker_order.counter)?;
+
+			Ok(())
+		}
+
+		/// Places an order with an explicit order kind and time in force.
+		///
+		/// Market orders trade at any price, a market buy order spends at most the
+		/// offered amount. They never rest on the order book, so they must be
+		/// `ImmediateOrCancel` or `FillOrKill`.
+		#[pallet::weight(
+			T::WeightInfo::place_order().saturating_add(Pallet::<T>::match_engine_weight(1))
+		)]
+		#[pallet::call_index(9)]
+		#[allow(clippy::too_many_arguments)]
+		pub fn place_order(
+			origin: OriginFor<T>,
+			asset_id_1: u32,
+			asset_id_2: u32,
+			offered_amount: BalanceOf<T>,
+			requested_amount: BalanceOf<T>,
+			order_type: OrderType,
+			order_kind: OrderKind,
+			time_in_force: TimeInForce,
+			expiration_block: BlockNumberFor<T>,
+		) -> DispatchResultWithPostInfo {
+			let who = ensure_signed(origin)?;
+
+			Self::do_place_order(
+				who,
+				asset_id_1,
+				asset_id_2,
+				offered_amount,
+				requested_amount,
+				order_type,
+				order_kind,
+				time_in_force,
+				expiration_block,
+			)?;
+
+			Ok(().into())
+		}
+
+		/// Sets the tick size, lot size and minimum notional of a pair. The base asset
+		/// is the one with the lower id.
+		#[pallet::weight(T::WeightInfo::set_pair_config())]
+		#[pallet::call_index(10)]
+		pub fn set_pair_config(
+			origin: OriginFor<T>,
+			base_asset_id: u32,
+			quote_asset_id: u32,
+			config: PairConfig<BalanceOf<T>>,
+		) -> DispatchResultWithPostInfo {
+			T::PrivilegedOrigin::ensure_origin(origin)?;
+
+			ensure!(
+				base_asset_id < quote_asset_id
+					&& !config.tick_size.is_zero()
+					&& !config.lot_size.is_zero(),
+				Error::<T>::InvalidPairConfig
+			);
+
+			let pair = (base_asset_id, quote_asset_id);
+			PairConfigs::<T>::insert(pair, &config);
+
+			Self::deposit_event(Event::PairConfigSet { pair, config });
+			Ok(().into())
+		}
+
+		/// Sets the maker and taker fees of a pair. The base asset is the one with the
+		/// lower id.
+		#[pallet::weight(T::WeightInfo::set_pair_fee())]
+		#[pallet::call_index(11)]
+		pub fn set_pair_fee(
+			origin: OriginFor<T>,
+			base_asset_id: u32,
+			quote_asset_id: u32,
+			fee: PairFee,
+		) -> DispatchResultWithPostInfo {
+			T::PrivilegedOrigin::ensure_origin(origin)?;
+
+			ensure!(
+				base_asset_id < quote_asset_id,
+				Error::<T>::InvalidPairConfig
+			);
+
+			let pair = (base_asset_id, quote_asset_id);
+			PairFees::<T>::insert(pair, &fee);
+
+			Self::deposit_event(Event::PairFeeSet { pair, fee });
+			Ok(().into())
+		}
+
+		/// Changes the price or the size of an open order. The amounts are the new unfilled
+		/// amounts, offered and requested the same way as when the order was placed.
+		///
+		/// The order keeps its place in the queue when its size only shrinks, otherwise it
+		/// is canceled and replaced by a new order with the same options.
+		#[pallet::weight(
+			T::WeightInfo::amend_order().saturating_add(Pallet::<T>::match_engine_weight(1))
+		)]
+		#[pallet::call_index(12)]
+		pub fn amend_order(
+			origin: OriginFor<T>,
+			order_index: u64,
+			offered_amount: BalanceOf<T>,
+			requested_amount: BalanceOf<T>,
+		) -> DispatchResultWithPostInfo {
+			let who = ensure_signed(origin)?;
+
+			Self::do_amend_order(&who, order_index, offered_amount, requested_amount)?;
+
+			Ok(().into())
+		}
+
+		/// Places several orders at once, either all of them are placed or none.
+		#[pallet::weight(
+			T::WeightInfo::batch_place(orders.len() as u32)
+				.saturating_add(Pallet::<T>::match_engine_weight(orders.len() as u32))
+		)]
+		#[pallet::call_index(13)]
+		pub fn batch_place(
+			origin: OriginFor<T>,
+			orders: BoundedVec<OrderRequest<BalanceOf<T>, BlockNumberFor<T>>, T::MaxOrdersPerBatch>,
+		) -> DispatchResultWithPostInfo {
+			let who = ensure_signed(origin)?;
+
+			for order in orders {
+				Self::do_place_order(
+					who.clone(),
+					order.asset_id_1,
+					order.asset_id_2,
+					order.offered_amount,
+					order.requested_amount,
+					order.order_type,
+					order.order_kind,
+					order.time_in_force,
+					order.expiration_block,
+				)?;
+			}
+
+			Ok(().into())
+		}
+
+		/// Cancels several orders of the caller at once, either all of them are canceled or
+		/// none.
+		#[pallet::weight(
+			T::WeightInfo::batch_cancel(order_indexes.len() as u32)
+				.saturating_add(Pallet::<T>::match_engine_weight(order_indexes.len() as u32))
+		)]
+		#[pallet::call_index(14)]
+		pub fn batch_cancel(
+			origin: OriginFor<T>,
+			order_indexes: BoundedVec<u64, T::MaxOrdersPerBatch>,
+		) -> DispatchResultWithPostInfo {
+			let who = ensure_signed(origin)?;
+
+			for order_index in order_indexes {
+				let order = Orders::<T>::get(order_index).ok_or(Error::<T>::InvalidOrderIndex)?;
+				ensure!(order.address == who, Error::<T>::NotOwner);
+
+				Self::cancel_order_impl(order_index)?;
+			}
+
+			Ok(().into())
+		}
+
+		/// Cancels all open orders of the caller in a pair.
+		#[pallet::weight(
+			T::WeightInfo::cancel_all(T::MaxOpenOrdersPerAccount::get())
+				.saturating_add(Pallet::<T>::match_engine_weight(T::MaxOpenOrdersPerAccount::get()))
+		)]
+		#[pallet::call_index(15)]
+		pub fn cancel_all(
+			origin: OriginFor<T>,
+			asset_id_1: u32,
+			asset_id_2: u32,
+		) -> DispatchResultWithPostInfo {
+			let who = ensure_signed(origin)?;
+
+			let pair = if asset_id_1 > asset_id_2 {
+				(asset_id_2, asset_id_1)
+			} else {
+				(asset_id_1, asset_id_2)
+			};
+
+			let order_indexes: Vec<u64> = UserOrders::<T>::iter_key_prefix(&who)
+				.filter(|order_index| PairOrders::<T>::contains_key(pair, order_index))
+				.collect();
+			for order_index in order_indexes.iter() {
+				Self::cancel_order_impl(*order_index)?;
+			}
+
+			let cancelled = order_indexes.len() as u32;
+			Ok(Some(
+				T::WeightInfo::cancel_all(cancelled)
+					.saturating_add(Self::match_engine_weight(cancelled)),
+			)
+			.into())
+		}
+
+		/// Places an order that stays dormant until the price of its pair crosses the
+		/// trigger price. The offered amount is reserved right away, like for `make_order`.
+		#[pallet::weight(T::WeightInfo::place_conditional_order())]
+		#[pallet::call_index(16)]
+		pub fn place_conditional_order(
+			origin: OriginFor<T>,
+			order: OrderRequest<BalanceOf<T>, BlockNumberFor<T>>,
+			trigger: Trigger,
+		) -> DispatchResultWithPostInfo {
+			let who = ensure_signed(origin)?;
 
-			for trade in match_result.match_details {
-				let taker_order = trade.taker_order;
-				let maker_order = trade.maker_order;
+			Self::do_place_conditional_order(who, order, trigger)?;
+			Ok(().into())
+		}
+
+		#[pallet::weight(T::WeightInfo::cancel_conditional_order())]
+		#[pallet::call_index(17)]
+		pub fn cancel_conditional_order(
+			origin: OriginFor<T>,
+			conditional_order_index: u64,
+		) -> DispatchResultWithPostInfo {
+			let who = ensure_signed(origin)?;
+
+			let conditional_order = ConditionalOrders::<T>::get(conditional_order_index)
+				.ok_or(Error::<T>::InvalidConditionalOrderIndex)?;
+			ensure!(conditional_order.address == who, Error::<T>::NotOwner);
+
+			Self::remove_conditional_order(conditional_order_index, &conditional_order)?;
+
+			Self::deposit_event(Event::ConditionalOrderCanceled {
+				conditional_order_index,
+			});
+			Ok(().into())
+		}
+
+		/// Registers an active trading pair. Both assets must be allowlisted and the base
+		/// asset is the one with the lower id.
+		#[pallet::weight(T::WeightInfo::register_trading_pair())]
+		#[pallet::call_index(18)]
+		pub fn register_trading_pair(
+			origin: OriginFor<T>,
+			base_asset_id: u32,
+			quote_asset_id: u32,
+			min_order_size: BalanceOf<T>,
+		) -> DispatchResultWithPostInfo {
+			T::PrivilegedOrigin::ensure_origin(origin)?;
 
-				// update order status
-				Self::update_order_from_trade_order(&taker_order)?;
-				Self::update_order_from_trade_order(&maker_order)?;
+			ensure!(
+				base_asset_id < quote_asset_id,
+				Error::<T>::InvalidPairConfig
+			);
+			ensure!(
+				TokenIndex::<T>::contains_key(base_asset_id)
+					&& TokenIndex::<T>::contains_key(quote_asset_id),
+				Error::<T>::AssetIdNotInTokenIndex
+			);
+
+			let pair = (base_asset_id, quote_asset_id);
+			ensure!(
+				!TradingPairs::<T>::contains_key(pair),
+				Error::<T>::PairAlreadyRegistered
+			);
+
+			let trading_pair = TradingPair {
+				status: PairStatus::Active,
+				min_order_size,
+			};
+			TradingPairs::<T>::insert(pair, &trading_pair);
+
+			Self::deposit_event(Event::TradingPairRegistered { pair, trading_pair });
+			Ok(().into())
+		}
+
+		/// Changes the status of a trading pair. Delisting a pair cancels all its orders,
+		/// including the conditional ones, and releases their reserved funds.
+		#[pallet::weight(T::WeightInfo::set_trading_pair_status(
+			T::MaxOrdersPerPair::get(),
+			T::MaxConditionalOrdersPerPair::get(),
+		))]
+		#[pallet::call_index(19)]
+		pub fn set_trading_pair_status(
+			origin: OriginFor<T>,
+			base_asset_id: u32,
+			quote_asset_id: u32,
+			status: PairStatus,
+		) -> DispatchResultWithPostInfo {
+			T::PrivilegedOrigin::ensure_origin(origin)?;
+
+			let pair = (base_asset_id, quote_asset_id);
+			TradingPairs::<T>::try_mutate(pair, |trading_pair| -> DispatchResult {
+				let trading_pair = trading_pair.as_mut().ok_or(Error::<T>::PairNotRegistered)?;
+				trading_pair.status = status;
+				Ok(())
+			})?;
+
+			let (order_count, conditional_order_count) = if status == PairStatus::Delisted {
+				Self::cancel_pair_orders(pair)?
+			} else {
+				(0, 0)
+			};
+
+			Self::deposit_event(Event::TradingPairStatusChanged { pair, status });
+			Ok(Some(T::WeightInfo::set_trading_pair_status(
+				order_count,
+				conditional_order_count,
+			))
+			.into())
+		}
+
+		/// Sets the smallest amount of the base asset an order of a trading pair can be
+		/// placed for.
+		#[pallet::weight(T::WeightInfo::set_min_order_size())]
+		#[pallet::call_index(20)]
+		pub fn set_min_order_size(
+			origin: OriginFor<T>,
+			base_asset_id: u32,
+			quote_asset_id: u32,
+			min_order_size: BalanceOf<T>,
+		) -> DispatchResultWithPostInfo {
+			T::PrivilegedOrigin::ensure_origin(origin)?;
+
+			let pair = (base_asset_id, quote_asset_id);
+			TradingPairs::<T>::try_mutate(pair, |trading_pair| -> DispatchResult {
+				let trading_pair = trading_pair.as_mut().ok_or(Error::<T>::PairNotRegistered)?;
+				trading_pair.min_order_size = min_order_size;
+				Ok(())
+			})?;
+
+			Self::deposit_event(Event::TradingPairMinOrderSizeSet {
+				pair,
+				min_order_size,
+			});
+			Ok(().into())
+		}
+
+		/// Sets the price band and circuit breaker of a trading pair, `None` removes them.
+		/// The window of the circuit breaker starts over, removing the band also lifts a
+		/// halt by the circuit breaker.
+		#[pallet::weight(T::WeightInfo::set_price_band())]
+		#[pallet::call_index(21)]
+		pub fn set_price_band(
+			origin: OriginFor<T>,
+			base_asset_id: u32,
+			quote_asset_id: u32,
+			price_band: Option<PriceBandOf<T>>,
+		) -> DispatchResultWithPostInfo {
+			T::PrivilegedOrigin::ensure_origin(origin)?;
+
+			let pair = (base_asset_id, quote_asset_id);
+			ensure!(
+				TradingPairs::<T>::contains_key(pair),
+				Error::<T>::PairNotRegistered
+			);
+
+			PriceWindows::<T>::remove(pair);
+			match &price_band {
+				Some(band) => {
+					ensure!(
+						!band.window.is_zero() && !band.halt_duration.is_zero(),
+						Error::<T>::InvalidPriceBand
+					);
+					PriceBands::<T>::insert(pair, band);
+				}
+				None => {
+					PriceBands::<T>::remove(pair);
+					CircuitBreakerHalts::<T>::remove(pair);
+				}
+			}
+
+			Self::deposit_event(Event::PriceBandSet { pair, price_band });
+			Ok(().into())
+		}
+
+		/// Adds at most `amount_1` of `asset_id_1` and `amount_2` of `asset_id_2` from the
+		/// DEX balances of the caller to the liquidity pool of a trading pair. The first
+		/// liquidity sets the price of the pool and locks [`MINIMUM_LIQUIDITY`] of its
+		/// shares, later liquidity is added at the ratio of its reserves.
+		#[pallet::weight(T::WeightInfo::add_liquidity())]
+		#[pallet::call_index(22)]
+		pub fn add_liquidity(
+			origin: OriginFor<T>,
+			asset_id_1: u32,
+			asset_id_2: u32,
+			amount_1: BalanceOf<T>,
+			amount_2: BalanceOf<T>,
+			min_shares: BalanceOf<T>,
+		) -> DispatchResultWithPostInfo {
+			let who = ensure_signed(origin)?;
+
+			let (pair, base_amount, quote_amount) =
+				Self::pool_amounts(asset_id_1, asset_id_2, amount_1, amount_2)?;
+			let trading_pair = TradingPairs::<T>::get(pair).ok_or(Error::<T>::PairNotRegistered)?;
+			ensure!(
+				trading_pair.status != PairStatus::Delisted,
+				Error::<T>::PairNotTradable
+			);
+
+			let mut pool = LiquidityPools::<T>::get(pair).unwrap_or_default();
+			let (base_amount, quote_amount, shares) = pool
+				.shares_for(base_amount, quote_amount)
+				.ok_or(Error::<T>::MulOverflow)?;
+			let locked_shares = if pool.total_shares.is_zero() {
+				BalanceOf::<T>::from(MINIMUM_LIQUIDITY)
+			} else {
+				Zero::zero()
+			};
+			let provider_shares = shares
+				.checked_sub(&locked_shares)
+				.filter(|shares| !shares.is_zero())
+				.ok_or(Error::<T>::InsufficientLiquidity)?;
+			ensure!(provider_shares >= min_shares, Error::<T>::SlippageExceeded);
+
+			Self::sub_assert(&who, pair.0, base_amount)?;
+			Self::sub_assert(&who, pair.1, quote_amount)?;
+
+			pool.base_reserve = pool
+				.base_reserve
+				.checked_add(&base_amount)
+				.ok_or(Error::<T>::TokenBalanceOverflow)?;
+			pool.quote_reserve = pool
+				.quote_reserve
+				.checked_add(&quote_amount)
+				.ok_or(Error::<T>::TokenBalanceOverflow)?;
+			pool.total_shares = pool
+				.total_shares
+				.checked_add(&shares)
+				.ok_or(Error::<T>::TokenBalanceOverflow)?;
+			Self::accumulate_pool_price(pair);
+			LiquidityPools::<T>::insert(pair, pool);
+			if !locked_shares.is_zero() {
+				PoolShares::<T>::insert(pair, Self::account_id(), locked_shares);
+			}
+			// the shares of a provider never exceed the total shares of the pool
+			PoolShares::<T>::mutate(pair, &who, |held| {
+				*held = held.saturating_add(provider_shares)
+			});
+
+			Self::deposit_event(Event::LiquidityAdded {
+				who,
+				pair,
+				base_amount,
+				quote_amount,
+				shares: provider_shares,
+			});
+			Ok(().into())
+		}
+
+		/// Removes `shares` of the liquidity pool of a pair, the assets they are worth are
+		/// added to the DEX balances of the caller. Liquidity can be removed whatever the
+		/// status of the pair.
+		#[pallet::weight(T::WeightInfo::remove_liquidity())]
+		#[pallet::call_index(23)]
+		pub fn remove_liquidity(
+			origin: OriginFor<T>,
+			asset_id_1: u32,
+			asset_id_2: u32,
+			shares: BalanceOf<T>,
+			min_amount_1: BalanceOf<T>,
+			min_amount_2: BalanceOf<T>,
+		) -> DispatchResultWithPostInfo {
+			let who = ensure_signed(origin)?;
+
+			let (pair, min_base_amount, min_quote_amount) =
+				Self::pool_amounts(asset_id_1, asset_id_2, min_amount_1, min_amount_2)?;
+			let mut pool = LiquidityPools::<T>::get(pair).ok_or(Error::<T>::PoolNotFound)?;
+			let held = PoolShares::<T>::get(pair, &who);
+			ensure!(
+				!shares.is_zero() && shares <= held,
+				Error::<T>::InsufficientShares
+			);
+
+			let (base_amount, quote_amount) =
+				pool.amounts_for(shares).ok_or(Error::<T>::MulOverflow)?;
+			ensure!(
+				base_amount >= min_base_amount && quote_amount >= min_quote_amount,
+				Error::<T>::SlippageExceeded
+			);
+
+			pool.base_reserve = pool.base_reserve.saturating_sub(base_amount);
+			pool.quote_reserve = pool.quote_reserve.saturating_sub(quote_amount);
+			pool.total_shares = pool.total_shares.saturating_sub(shares);
+			Self::accumulate_pool_price(pair);
+			if pool.total_shares.is_zero() {
+				LiquidityPools::<T>::remove(pair);
+			} else {
+				LiquidityPools::<T>::insert(pair, pool);
+			}
+			if shares == held {
+				PoolShares::<T>::remove(pair, &who);
+			} else {
+				PoolShares::<T>::insert(pair, &who, held - shares);
+			}
+
+			Self::add_assert(&who, pair.0, base_amount)?;
+			Self::add_assert(&who, pair.1, quote_amount)?;
+
+			Self::deposit_event(Event::LiquidityRemoved {
+				who,
+				pair,
+				base_amount,
+				quote_amount,
+				shares,
+			});
+			Ok(().into())
+		}
+
+		/// Swaps `amount_in` of `asset_in` for at least `min_amount_out` of `asset_out`
+		/// within the DEX balances of the caller.
+		///
+		/// The swap is filled by the liquidity pool or the order book of the pair,
+		/// whichever pays out more. The order book holds the open orders of the pair in
+		/// every matching mode, it isn't used while orders matched on chain wait to be
+		/// matched. A swap filled by the order book trades as a market order and may leave
+		/// part of `amount_in` unspent. Both venues follow the status, the circuit breaker
+		/// and the price band of the pair.
+		#[pallet::weight(
+			T::WeightInfo::swap().saturating_add(Pallet::<T>::pair_book_weight(2))
+		)]
+		#[pallet::call_index(24)]
+		pub fn swap(
+			origin: OriginFor<T>,
+			asset_in: u32,
+			asset_out: u32,
+			amount_in: BalanceOf<T>,
+			min_amount_out: BalanceOf<T>,
+		) -> DispatchResultWithPostInfo {
+			let who = ensure_signed(origin)?;
+
+			Self::do_swap(&who, asset_in, asset_out, amount_in, min_amount_out)?;
+
+			Ok(().into())
+		}
+
+		/// Swaps `amount_in` of the first asset of `path` for at least `min_amount_out` of
+		/// its last asset, through the pairs of consecutive assets of the path. Either all
+		/// hops are swapped or none.
+		///
+		/// Each hop is filled like a `swap` and may leave part of its amount unspent when
+		/// filled by the order book, so at most `amount_in` is spent.
+		#[pallet::weight(
+			T::WeightInfo::swap_exact_in(path.len() as u32)
+				.saturating_add(Pallet::<T>::pair_book_weight(2 * path.len() as u32))
+		)]
+		#[pallet::call_index(25)]
+		pub fn swap_exact_in(
+			origin: OriginFor<T>,
+			path: BoundedVec<u32, T::MaxSwapPathLength>,
+			amount_in: BalanceOf<T>,
+			min_amount_out: BalanceOf<T>,
+		) -> DispatchResultWithPostInfo {
+			let who = ensure_signed(origin)?;
+
+			let (amount_in, amount_out) =
+				Self::do_swap_exact_in(&who, &path, amount_in, min_amount_out)?;
+
+			Self::deposit_event(Event::PathSwapped {
+				who,
+				path,
+				amount_in,
+				amount_out,
+			});
+			Ok(().into())
+		}
+
+		/// Swaps at most `max_amount_in` of the first asset of `path` for exactly
+		/// `amount_out` of its last asset, through the liquidity pools of the pairs of
+		/// consecutive assets of the path. Either all hops are swapped or none.
+		#[pallet::weight(T::WeightInfo::swap_exact_out(path.len() as u32))]
+		#[pallet::call_index(26)]
+		pub fn swap_exact_out(
+			origin: OriginFor<T>,
+			path: BoundedVec<u32, T::MaxSwapPathLength>,
+			amount_out: BalanceOf<T>,
+			max_amount_in: BalanceOf<T>,
+		) -> DispatchResultWithPostInfo {
+			let who = ensure_signed(origin)?;
+
+			let amount_in = Self::do_swap_exact_out(&who, &path, amount_out, max_amount_in)?;
+
+			Self::deposit_event(Event::PathSwapped {
+				who,
+				path,
+				amount_in,
+				amount_out,
+			});
+			Ok(().into())
+		}
+
+		/// Sets the fee tiers of a trading pair by the trading volume of the last
+		/// `VOLUME_WINDOW_DAYS` days, no tiers remove them. The minimum volumes of the tiers
+		/// must increase.
+		#[pallet::weight(T::WeightInfo::set_fee_tiers())]
+		#[pallet::call_index(27)]
+		pub fn set_fee_tiers(
+			origin: OriginFor<T>,
+			base_asset_id: u32,
+			quote_asset_id: u32,
+			tiers: FeeTiersOf<T>,
+		) -> DispatchResultWithPostInfo {
+			T::PrivilegedOrigin::ensure_origin(origin)?;
+
+			let pair = (base_asset_id, quote_asset_id);
+			ensure!(
+				TradingPairs::<T>::contains_key(pair),
+				Error::<T>::PairNotRegistered
+			);
+			ensure!(
+				tiers
+					.windows(2)
+					.all(|tiers| tiers[0].min_volume < tiers[1].min_volume),
+				Error::<T>::InvalidFeeTiers
+			);
+
+			if tiers.is_empty() {
+				FeeTiers::<T>::remove(pair);
+			} else {
+				FeeTiers::<T>::insert(pair, &tiers);
+			}
+
+			Self::deposit_event(Event::FeeTiersSet { pair, tiers });
+			Ok(().into())
+		}
+
+		/// Funds a liquidity mining epoch of a trading pair from the `RewardSource`, it
+		/// starts in this block. The reward of all blocks of the epoch is transferred to the
+		/// rewards account up front, the rewards no order earns stay there until they are
+		/// reclaimed. A pair runs one epoch at a time.
+		#[pallet::weight(T::WeightInfo::fund_reward_epoch())]
+		#[pallet::call_index(28)]
+		pub fn fund_reward_epoch(
+			origin: OriginFor<T>,
+			base_asset_id: u32,
+			quote_asset_id: u32,
+			terms: RewardTermsOf<T>,
+		) -> DispatchResultWithPostInfo {
+			T::PrivilegedOrigin::ensure_origin(origin)?;
+
+			let pair = (base_asset_id, quote_asset_id);
+			ensure!(
+				TradingPairs::<T>::contains_key(pair),
+				Error::<T>::PairNotRegistered
+			);
+			ensure!(
+				TokenIndex::<T>::contains_key(terms.asset_id),
+				Error::<T>::AssetIdNotInTokenIndex
+			);
+			ensure!(
+				!terms.reward_per_block.is_zero() && !terms.duration.is_zero(),
+				Error::<T>::InvalidRewardTerms
+			);
+
+			let now = frame_system::Pallet::<T>::block_number();
+			if let Some(epoch) = PairRewardEpochs::<T>::get(pair).and_then(RewardEpochs::<T>::get) {
+				ensure!(!epoch.is_running(now), Error::<T>::RewardEpochRunning);
+			}
+
+			let duration: u128 = terms.duration.unique_saturated_into();
+			let reward = terms
+				.reward_per_block
+				.checked_mul(&duration.unique_saturated_into())
+				.ok_or(Error::<T>::MulOverflow)?;
+			Self::transfer_asset(
+				terms.asset_id,
+				&T::RewardSource::get(),
+				&Self::rewards_account_id(),
+				reward,
+			)?;
+
+			let epoch_id = NextRewardEpochId::<T>::mutate(|epoch_id| {
+				let id = *epoch_id;
+				*epoch_id = epoch_id.saturating_add(1);
+				id
+			});
+			RewardEpochs::<T>::insert(
+				epoch_id,
+				RewardEpoch {
+					pair,
+					terms: terms.clone(),
+					start: now,
+					end: now.saturating_add(terms.duration),
+					total_stake: Zero::zero(),
+					reward_per_stake: Zero::zero(),
+					last_update: now,
+					unearned_reward: Zero::zero(),
+				},
+			);
+			PairRewardEpochs::<T>::insert(pair, epoch_id);
+
+			Self::deposit_event(Event::RewardEpochFunded {
+				epoch_id,
+				pair,
+				terms,
+				reward,
+			});
+			Ok(().into())
+		}
+
+		/// Credits the liquidity mining rewards of the caller to its DEX balances, with the
+		/// rewards its open orders earned so far.
+		#[pallet::weight(T::WeightInfo::claim_rewards(T::MaxOpenOrdersPerAccount::get()))]
+		#[pallet::call_index(29)]
+		pub fn claim_rewards(origin: OriginFor<T>) -> DispatchResultWithPostInfo {
+			let who = ensure_signed(origin)?;
+
+			let order_indexes: Vec<u64> = UserOrders::<T>::iter_key_prefix(&who).collect();
+			for order in order_indexes.iter().filter_map(Orders::<T>::get) {
+				Self::restake_order(&order);
+			}
+
+			let rewards: Vec<(u32, BalanceOf<T>)> =
+				UnclaimedRewards::<T>::drain_prefix(&who).collect();
+			ensure!(!rewards.is_empty(), Error::<T>::NoRewardsToClaim);
+			for (asset_id, amount) in rewards {
+				Self::transfer_asset(
+					asset_id,
+					&Self::rewards_account_id(),
+					&Self::account_id(),
+					amount,
+				)?;
+				Self::add_assert(&who, asset_id, amount)?;
+
+				Self::deposit_event(Event::RewardsClaimed {
+					who: who.clone(),
+					asset_id,
+					amount,
+				});
+			}
+
+			Ok(Some(T::WeightInfo::claim_rewards(order_indexes.len() as u32)).into())
+		}
+
+		/// Maps the DEX asset id `asset_id` to a token held outside of `pallet_assets`. The id
+		/// must not be used by an asset, and the token must not have an asset id yet.
+		#[pallet::weight(T::WeightInfo::register_token())]
+		#[pallet::call_index(30)]
+		pub fn register_token(
+			origin: OriginFor<T>,
+			asset_id: u32,
+			token_id: T::TokenId,
+		) -> DispatchResultWithPostInfo {
+			T::PrivilegedOrigin::ensure_origin(origin)?;
+
+			ensure!(
+				!TokenIds::<T>::contains_key(asset_id) && !T::Fungibles::asset_exists(asset_id),
+				Error::<T>::AssetIdInUse
+			);
+			ensure!(
+				!TokenAssetIds::<T>::contains_key(&token_id),
+				Error::<T>::TokenAlreadyRegistered
+			);
+
+			TokenIds::<T>::insert(asset_id, &token_id);
+			TokenAssetIds::<T>::insert(&token_id, asset_id);
+
+			Self::deposit_event(Event::TokenRegistered { asset_id, token_id });
+			Ok(().into())
+		}
+
+		/// Removes the token mapped to `asset_id`, the DEX must not hold any of it, neither in
+		/// deposits nor in the rewards of liquidity mining epochs.
+		#[pallet::weight(T::WeightInfo::deregister_token())]
+		#[pallet::call_index(31)]
+		pub fn deregister_token(origin: OriginFor<T>, asset_id: u32) -> DispatchResultWithPostInfo {
+			T::PrivilegedOrigin::ensure_origin(origin)?;
+
+			let token_id = TokenIds::<T>::get(asset_id).ok_or(Error::<T>::TokenNotRegistered)?;
+			ensure!(
+				T::Fungibles::balance(asset_id, &Self::account_id()).is_zero()
+					&& T::Fungibles::balance(asset_id, &Self::rewards_account_id()).is_zero(),
+				Error::<T>::TokenHasDeposits
+			);
+
+			TokenIds::<T>::remove(asset_id);
+			TokenAssetIds::<T>::remove(&token_id);
+
+			Self::deposit_event(Event::TokenDeregistered { asset_id, token_id });
+			Ok(().into())
+		}
+
+		/// Returns the unearned reward of a liquidity mining epoch that ended to the
+		/// `RewardSource`: the reward of blocks without stakes and the reward forfeited by
+		/// orders that left the spread of the epoch.
+		#[pallet::weight(T::WeightInfo::reclaim_rewards())]
+		#[pallet::call_index(32)]
+		pub fn reclaim_rewards(origin: OriginFor<T>, epoch_id: u32) -> DispatchResultWithPostInfo {
+			T::PrivilegedOrigin::ensure_origin(origin)?;
+
+			let now = frame_system::Pallet::<T>::block_number();
+			let (asset_id, amount) = RewardEpochs::<T>::try_mutate(
+				epoch_id,
+				|epoch| -> Result<(u32, BalanceOf<T>), DispatchError> {
+					let epoch = epoch.as_mut().ok_or(Error::<T>::RewardEpochNotFound)?;
+					ensure!(!epoch.is_running(now), Error::<T>::RewardEpochRunning);
+					epoch.update(now);
+					let amount = core::mem::take(&mut epoch.unearned_reward);
+					ensure!(!amount.is_zero(), Error::<T>::NoRewardsToReclaim);
+					Ok((epoch.terms.asset_id, amount))
+				},
+			)?;
+			Self::transfer_asset(
+				asset_id,
+				&Self::rewards_account_id(),
+				&T::RewardSource::get(),
+				amount,
+			)?;
+
+			Self::deposit_event(Event::RewardsReclaimed {
+				epoch_id,
+				asset_id,
+				amount,
+			});
+			Ok(().into())
+		}
+	}
+
+	impl<T: Config> Pallet<T> {
+		pub fn account_id() -> <T as frame_system::Config>::AccountId {
+			<T as Config>::PalletId::get().into_account_truncating()
+		}
+
+		/// Account that holds the funds of the liquidity mining epochs until they are
+		/// claimed.
+		pub fn rewards_account_id() -> <T as frame_system::Config>::AccountId {
+			<T as Config>::PalletId::get().into_sub_account_truncating(b"rewards")
+		}
+
+		pub(crate) fn do_deposit(
+			who: &T::AccountId,
+			asset_id: u32,
+			amount: BalanceOf<T>,
+		) -> DispatchResult {
+			ensure!(
+				TokenIndex::<T>::contains_key(asset_id),
+				Error::<T>::AssetIdNotInTokenIndex
+			);
+
+			<T::Fungibles as Mutate<T::AccountId>>::transfer(
+				asset_id,
+				who,
+				&Self::account_id(),
+				amount,
+				Preservation::Expendable,
+			)?;
+
+			let mut info = TokenInfo::default();
+			if UserTokenInfoes::<T>::contains_key(who, asset_id) {
+				info = UserTokenInfoes::<T>::get(who, asset_id);
+				info.amount = info
+					.amount
+					.checked_add(&amount)
+					.ok_or(Error::<T>::TokenBalanceOverflow)?;
+			} else {
+				info.amount = amount;
+			}
+
+			UserTokenInfoes::<T>::insert(who, asset_id, info);
+
+			Self::deposit_event(Event::Deposited {
+				who: who.clone(),
+				asset_id,
+				amount,
+			});
+			Ok(())
+		}
+
+		/// Handles the memo of a transfer that sent `amount` of `asset_id` from another
+		/// chain to `receiver`, once the tokens are in the account of `receiver`. A DEX
+		/// memo deposits the tokens into the DEX balance of `receiver` and may place an
+		/// order for them, see [`DepositMemo`]. Returns the index of the placed order.
+		///
+		/// Memos that are not addressed to the DEX are ignored. A malformed DEX memo, or a
+		/// deposit or order that fails, changes nothing and leaves the tokens in the
+		/// account of `receiver`, like a transfer without a memo.
+		pub fn deposit_from_transfer(
+			receiver: &T::AccountId,
+			asset_id: u32,
+			amount: BalanceOf<T>,
+			memo: &[u8],
+		) -> Result<Option<u64>, DispatchError> {
+			let memo = match DepositMemo::<BalanceOf<T>, BlockNumberFor<T>>::parse(memo) {
+				Ok(Some(memo)) => memo,
+				Ok(None) => return Ok(None),
+				Err(_) => return Err(Error::<T>::MalformedDepositMemo.into()),
+			};
+
+			with_storage_layer(|| {
+				Self::do_deposit(receiver, asset_id, amount)?;
+				match memo {
+					DepositMemo::Deposit => Ok(None),
+					// the order is normalized to the pair of the assets
+					DepositMemo::Order {
+						asset_id: requested_asset_id,
+						requested_amount,
+						expiration_block,
+					} => Self::do_place_order(
+						receiver.clone(),
+						asset_id,
+						requested_asset_id,
+						amount,
+						requested_amount,
+						OrderType::SELL,
+						OrderKind::Limit,
+						TimeInForce::GoodTillExpiration,
+						expiration_block,
+					)
+					.map(Some),
+				}
+			})
+		}
+
+		pub fn add_assert(
+			account: &T::AccountId,
+			asset_id: u32,
+			amount: BalanceOf<T>,
+		) -> Result<(), DispatchError> {
+			let mut info = TokenInfo::default();
+			if UserTokenInfoes::<T>::contains_key(account.clone(), asset_id) {
+				info = UserTokenInfoes::<T>::get(account.clone(), asset_id);
+				info.amount = info
+					.amount
+					.checked_add(&amount)
+					.ok_or(Error::<T>::TokenBalanceOverflow)?;
+			} else {
+				info.amount = amount;
+			}
+			UserTokenInfoes::<T>::insert(account, asset_id, info);
+
+			Ok(())
+		}
+
+		pub fn sub_assert(
+			account: &T::AccountId,
+			asset_id: u32,
+			amount: BalanceOf<T>,
+		) -> Result<(), DispatchError> {
+			ensure!(
+				UserTokenInfoes::<T>::contains_key(account.clone(), asset_id),
+				Error::<T>::UserAssetNotExist
+			);
+
+			let mut info = UserTokenInfoes::<T>::get(account.clone(), asset_id);
+			info.amount = info
+				.amount
+				.checked_sub(&amount)
+				.ok_or(Error::<T>::NotEnoughBalance)?;
+
+			UserTokenInfoes::<T>::insert(account, asset_id, info);
+
+			Ok(())
+		}
+
+		pub fn sub_reserved_assert(
+			account: &T::AccountId,
+			asset_id: u32,
+			amount: BalanceOf<T>,
+		) -> Result<(), DispatchError> {
+			ensure!(
+				UserTokenInfoes::<T>::contains_key(account.clone(), asset_id),
+				Error::<T>::UserAssetNotExist
+			);
+
+			let mut info = UserTokenInfoes::<T>::get(account.clone(), asset_id);
+			info.reserved = info
+				.reserved
+				.checked_sub(&amount)
+				.ok_or(Error::<T>::NotEnoughBalance)?;
+
+			UserTokenInfoes::<T>::insert(account, asset_id, info);
+
+			Ok(())
+		}
+
+		/// Moves `amount` from the available to the reserved balance of an account.
+		fn reserve_asset(
+			account: &T::AccountId,
+			asset_id: u32,
+			amount: BalanceOf<T>,
+		) -> DispatchResult {
+			let mut info = UserTokenInfoes::<T>::get(account, asset_id);
+			info.amount = info
+				.amount
+				.checked_sub(&amount)
+				.ok_or(Error::<T>::NotEnoughBalance)?;
+			info.reserved = info
+				.reserved
+				.checked_add(&amount)
+				.ok_or(Error::<T>::TokenBalanceOverflow)?;
+			UserTokenInfoes::<T>::insert(account, asset_id, info);
+
+			Ok(())
+		}
+
+		/// Moves `amount` from the reserved back to the available balance of an account.
+		fn unreserve_asset(
+			account: &T::AccountId,
+			asset_id: u32,
+			amount: BalanceOf<T>,
+		) -> DispatchResult {
+			let mut info = UserTokenInfoes::<T>::get(account, asset_id);
+			info.amount = info
+				.amount
+				.checked_add(&amount)
+				.ok_or(Error::<T>::TokenBalanceOverflow)?;
+			info.reserved = info
+				.reserved
+				.checked_sub(&amount)
+				.ok_or(Error::<T>::NotEnoughBalance)?;
+			UserTokenInfoes::<T>::insert(account, asset_id, info);
+
+			Ok(())
+		}
+
+		/// Checks the amounts of an order against the trading rules of its pair.
+		fn ensure_valid_amounts(
+			config: &PairConfig<BalanceOf<T>>,
+			quote_amount: BalanceOf<T>,
+			base_amount: BalanceOf<T>,
+		) -> DispatchResult {
+			ensure!(
+				!base_amount.is_zero() && (base_amount % config.lot_size).is_zero(),
+				Error::<T>::InvalidLotSize
+			);
+			ensure!(
+				quote_amount >= config.min_notional,
+				Error::<T>::BelowMinNotional
+			);
+
+			Ok(())
+		}
+
+		/// Checks that an order can be placed in a pair with the status and minimum order
+		/// size of the pair.
+		fn ensure_order_allowed(
+			pair: (u32, u32),
+			time_in_force: TimeInForce,
+			base_amount: BalanceOf<T>,
+		) -> DispatchResult {
+			let trading_pair = TradingPairs::<T>::get(pair).ok_or(Error::<T>::PairNotRegistered)?;
+			match Self::with_circuit_breaker(pair, trading_pair.status) {
+				PairStatus::Active => {}
+				PairStatus::PostOnly => ensure!(
+					time_in_force == TimeInForce::PostOnly,
+					Error::<T>::PairIsPostOnly
+				),
+				PairStatus::Halted | PairStatus::Delisted => {
+					return Err(Error::<T>::PairNotTradable.into())
+				}
+			}
+			ensure!(
+				base_amount >= trading_pair.min_order_size,
+				Error::<T>::BelowMinOrderSize
+			);
+
+			Ok(())
+		}
+
+		/// Status of a pair, pairs that are not registered don't trade.
+		fn pair_status(pair: (u32, u32)) -> PairStatus {
+			TradingPairs::<T>::get(pair)
+				.map_or(PairStatus::Delisted, |trading_pair| trading_pair.status)
+		}
+
+		/// Status orders of a pair are placed and matched with. A pair whose circuit
+		/// breaker tripped doesn't trade until the halt is over, trades that were matched
+		/// before still settle.
+		fn trading_status(pair: (u32, u32)) -> PairStatus {
+			Self::with_circuit_breaker(pair, Self::pair_status(pair))
+		}
+
+		fn with_circuit_breaker(pair: (u32, u32), status: PairStatus) -> PairStatus {
+			if status.allows_trading() && Self::is_halted_by_circuit_breaker(pair) {
+				PairStatus::Halted
+			} else {
+				status
+			}
+		}
+
+		fn is_halted_by_circuit_breaker(pair: (u32, u32)) -> bool {
+			CircuitBreakerHalts::<T>::get(pair).map_or(false, |halted_until| {
+				frame_system::Pallet::<T>::block_number() < halted_until
+			})
+		}
+
+		/// Price the price band of a pair is centered on, if the pair has a band and the
+		/// price is known.
+		pub fn reference_price(pair: (u32, u32)) -> Option<FixedU128> {
+			let band = PriceBands::<T>::get(pair)?;
+			Self::price_of(pair, band.reference)
+		}
+
+		/// Current price of a pair of the kind of `reference`.
+		fn price_of(pair: (u32, u32), reference: ReferencePrice) -> Option<FixedU128> {
+			match reference {
+				ReferencePrice::LastTrade => LastTradePrice::<T>::get(pair),
+				ReferencePrice::Twap => PriceWindows::<T>::get(pair)
+					.map(|window| window.twap(frame_system::Pallet::<T>::block_number())),
+				ReferencePrice::Oracle => T::PriceOracle::price(pair),
+			}
+		}
+
+		/// Lowest and highest price a pair can trade at within its price band.
+		pub fn price_band_limits(pair: (u32, u32)) -> Option<(FixedU128, FixedU128)> {
+			let max_deviation = PriceBands::<T>::get(pair)?.max_deviation;
+			let reference_price = Self::reference_price(pair)?;
+			let deviation = reference_price.saturating_mul(max_deviation.into());
+			Some((
+				reference_price.saturating_sub(deviation),
+				reference_price.saturating_add(deviation),
+			))
+		}
+
+		fn ensure_within_price_band(pair: (u32, u32), price: FixedU128) -> DispatchResult {
+			if let Some((lowest, highest)) = Self::price_band_limits(pair) {
+				ensure!(
+					lowest <= price && price <= highest,
+					Error::<T>::PriceOutsideBand
+				);
+			}
+			Ok(())
+		}
+
+		/// Moves the window of the circuit breaker of a pair with a trade, and halts the
+		/// pair when the price moved too far within the window.
+		fn update_circuit_breaker(pair: (u32, u32), price: FixedU128) {
+			let band = match PriceBands::<T>::get(pair) {
+				Some(band) => band,
+				None => return,
+			};
+
+			let now = frame_system::Pallet::<T>::block_number();
+			let mut window = match PriceWindows::<T>::get(pair) {
+				Some(window) if now < window.start.saturating_add(band.window) => window,
+				// a new window opens at the price the last one ended with
+				Some(window) => PriceWindow::new(now, window.last_price),
+				None => PriceWindow::new(now, LastTradePrice::<T>::get(pair).unwrap_or(price)),
+			};
+			window.update(now, price);
+
+			let open_price = window.open_price;
+			let price_move = if price > open_price {
+				price - open_price
+			} else {
+				open_price - price
+			};
+			if price_move > open_price.saturating_mul(band.max_move.into())
+				&& !Self::is_halted_by_circuit_breaker(pair)
+			{
+				let halted_until = now.saturating_add(band.halt_duration);
+				CircuitBreakerHalts::<T>::insert(pair, halted_until);
+				// the pair resumes with a new window at the price it halted at
+				window = PriceWindow::new(now, price);
+
+				Self::deposit_event(Event::CircuitBreakerTripped {
+					pair,
+					open_price,
+					price,
+					halted_until,
+				});
+			}
+
+			PriceWindows::<T>::insert(pair, window);
+		}
+
+		/// Accounts for the time the liquidity pool of a pair spent at its current price.
+		/// Called before the reserves of the pool change, the accumulator of a pool starts
+		/// with its first liquidity. The first update of a pair in an observation period
+		/// records its cumulative price.
+		fn accumulate_pool_price(pair: (u32, u32)) {
+			let now = T::UnixTime::now().as_secs();
+			let price = LiquidityPools::<T>::get(pair).and_then(|pool| pool.price());
+			let cumulative_price = PriceAccumulators::<T>::mutate(pair, |accumulator| {
+				let accumulator = accumulator.get_or_insert_with(|| PriceAccumulator::new(now));
+				match price {
+					Some(price) => accumulator.update(now, price),
+					None => accumulator.last_update = now,
+				}
+				accumulator.cumulative_price
+			});
+
+			let period = T::PriceObservationPeriod::get();
+			let max_observations = T::MaxPriceObservations::get() as u64;
+			if period == 0 || max_observations == 0 {
+				return;
+			}
+
+			let slot = ((now / period) % max_observations) as u32;
+			PriceObservations::<T>::mutate(pair, slot, |observation| match observation {
+				Some(observation) if observation.timestamp / period == now / period => {}
+				_ => {
+					*observation = Some(PriceObservation {
+						timestamp: now,
+						cumulative_price,
+					})
+				}
+			});
+		}
+
+		/// Orders two assets and amounts of them by the pair they form.
+		fn pool_amounts(
+			asset_id_1: u32,
+			asset_id_2: u32,
+			amount_1: BalanceOf<T>,
+			amount_2: BalanceOf<T>,
+		) -> Result<((u32, u32), BalanceOf<T>, BalanceOf<T>), DispatchError> {
+			ensure!(
+				asset_id_1 != asset_id_2,
+				Error::<T>::PairAssetIdMustNotEqual
+			);
+			if asset_id_1 < asset_id_2 {
+				Ok(((asset_id_1, asset_id_2), amount_1, amount_2))
+			} else {
+				Ok(((asset_id_2, asset_id_1), amount_2, amount_1))
+			}
+		}
+
+		/// Pair of a swap from `asset_in` to `asset_out` and the side it trades on.
+		pub fn swap_pair(
+			asset_in: u32,
+			asset_out: u32,
+		) -> Result<((u32, u32), OrderType), DispatchError> {
+			ensure!(asset_in != asset_out, Error::<T>::PairAssetIdMustNotEqual);
+			if asset_in < asset_out {
+				Ok(((asset_in, asset_out), OrderType::SELL))
+			} else {
+				Ok(((asset_out, asset_in), OrderType::BUY))
+			}
+		}
+
+		/// Swaps `amount_in` of `asset_in` for `asset_out` with the venue that pays out
+		/// more. Returns the amount spent and the amount received.
+		pub(crate) fn do_swap(
+			who: &T::AccountId,
+			asset_in: u32,
+			asset_out: u32,
+			amount_in: BalanceOf<T>,
+			min_amount_out: BalanceOf<T>,
+		) -> Result<(BalanceOf<T>, BalanceOf<T>), DispatchError> {
+			let (pair, side) = Self::swap_pair(asset_in, asset_out)?;
+			ensure!(!amount_in.is_zero(), Error::<T>::InvalidTradeAmount);
+			Self::ensure_swappable(pair)?;
+
+			let (venue, requested_amount, _) = Self::best_venue(who, pair, &side, amount_in)
+				.ok_or_else(|| {
+					// a pool the trading rules of the pair don't allow to swap with tells why
+					Self::pool_quote(pair, &side, amount_in)
+						.and_then(|amount_out| {
+							Self::ensure_pool_swap_allowed(pair, &side, amount_in, amount_out).err()
+						})
+						.unwrap_or_else(|| Error::<T>::InsufficientLiquidity.into())
+				})?;
+			let (amount_in, amount_out) = match venue {
+				SwapVenue::Pool => {
+					Self::swap_with_pool(who, pair, side, amount_in, requested_amount)?;
+					(amount_in, requested_amount)
+				}
+				SwapVenue::OrderBook => {
+					Self::swap_with_book(who, pair, side, amount_in, requested_amount)?
+				}
+			};
+			ensure!(amount_out >= min_amount_out, Error::<T>::SlippageExceeded);
+
+			Ok((amount_in, amount_out))
+		}
+
+		/// Swaps along a path of assets, each asset for the next one, with the venue that
+		/// pays out more in each pair. Returns the amount spent and the amount received.
+		pub(crate) fn do_swap_exact_in(
+			who: &T::AccountId,
+			path: &[u32],
+			amount_in: BalanceOf<T>,
+			min_amount_out: BalanceOf<T>,
+		) -> Result<(BalanceOf<T>, BalanceOf<T>), DispatchError> {
+			Self::swap_path(path)?;
+
+			let mut spent = None;
+			let mut amount = amount_in;
+			for assets in path.windows(2) {
+				let (hop_amount_in, hop_amount_out) =
+					Self::do_swap(who, assets[0], assets[1], amount, Zero::zero())?;
+				spent.get_or_insert(hop_amount_in);
+				amount = hop_amount_out;
+			}
+			ensure!(amount >= min_amount_out, Error::<T>::SlippageExceeded);
+
+			Ok((spent.unwrap_or(amount_in), amount))
+		}
+
+		/// Swaps along a path of assets through the liquidity pools of its pairs, for
+		/// exactly `amount_out` of the last asset. Returns the amount spent.
+		pub(crate) fn do_swap_exact_out(
+			who: &T::AccountId,
+			path: &[u32],
+			amount_out: BalanceOf<T>,
+			max_amount_in: BalanceOf<T>,
+		) -> Result<BalanceOf<T>, DispatchError> {
+			let hops = Self::swap_path(path)?;
+			let amounts = Self::pool_amounts_in(&hops, amount_out)?;
+			ensure!(amounts[0] <= max_amount_in, Error::<T>::SlippageExceeded);
+
+			for ((pair, side), amounts) in hops.into_iter().zip(amounts.windows(2)) {
+				Self::swap_with_pool(who, pair, side, amounts[0], amounts[1])?;
+			}
+
+			Ok(amounts[0])
+		}
+
+		/// Pairs of the hops of a swap along `path` and the side each hop trades on. A path
+		/// has two to `MaxSwapPathLength` assets and doesn't repeat any of them.
+		fn swap_path(path: &[u32]) -> Result<Vec<((u32, u32), OrderType)>, DispatchError> {
+			ensure!(
+				path.len() >= 2 && path.len() <= T::MaxSwapPathLength::get() as usize,
+				Error::<T>::InvalidSwapPath
+			);
+			for (index, asset_id) in path.iter().enumerate() {
+				ensure!(
+					!path[index + 1..].contains(asset_id),
+					Error::<T>::InvalidSwapPath
+				);
+			}
+
+			path.windows(2)
+				.map(|assets| Self::swap_pair(assets[0], assets[1]))
+				.collect()
+		}
+
+		/// Amounts of each asset of a path that swap through the liquidity pools for
+		/// `amount_out` of the last asset, starting with the amount paid in.
+		fn pool_amounts_in(
+			hops: &[((u32, u32), OrderType)],
+			amount_out: BalanceOf<T>,
+		) -> Result<Vec<BalanceOf<T>>, DispatchError> {
+			ensure!(!amount_out.is_zero(), Error::<T>::InvalidTradeAmount);
+
+			let mut amounts = Vec::with_capacity(hops.len() + 1);
+			amounts.push(amount_out);
+			for (pair, side) in hops.iter().rev() {
+				Self::ensure_swappable(*pair)?;
+				let pool = LiquidityPools::<T>::get(pair).ok_or(Error::<T>::PoolNotFound)?;
+				let amount_in = pool
+					.amount_in(side, amounts[0], T::PoolFee::get())
+					.ok_or(Error::<T>::InsufficientLiquidity)?;
+				amounts.insert(0, amount_in);
+			}
+
+			Ok(amounts)
+		}
+
+		/// Amount of the last asset of `path` that `amount_in` of the first one swaps for,
+		/// with the venue that pays out more in each pair. The order book is quoted for an
+		/// account without orders on it.
+		pub fn quote_swap_exact_in(path: &[u32], amount_in: BalanceOf<T>) -> Option<BalanceOf<T>> {
+			let hops = Self::swap_path(path).ok()?;
+			let who = Self::account_id();
+
+			hops.into_iter()
+				.try_fold(amount_in, |amount, (pair, side)| {
+					Self::ensure_swappable(pair).ok()?;
+					Self::best_venue(&who, pair, &side, amount).map(|(_, _, amount_out)| amount_out)
+				})
+		}
+
+		/// Amount of the first asset of `path` that swaps through the liquidity pools for
+		/// `amount_out` of the last one.
+		pub fn quote_swap_exact_out(
+			path: &[u32],
+			amount_out: BalanceOf<T>,
+		) -> Option<BalanceOf<T>> {
+			let hops = Self::swap_path(path).ok()?;
+			Self::pool_amounts_in(&hops, amount_out)
+				.ok()
+				.map(|amounts| amounts[0])
+		}
+
+		/// Swaps take liquidity like market orders, they need an active pair.
+		fn ensure_swappable(pair: (u32, u32)) -> DispatchResult {
+			match Self::trading_status(pair) {
+				PairStatus::Active => Ok(()),
+				PairStatus::PostOnly => Err(Error::<T>::PairIsPostOnly.into()),
+				PairStatus::Halted | PairStatus::Delisted => {
+					Err(Error::<T>::PairNotTradable.into())
+				}
+			}
+		}
+
+		/// Venue that pays out more for `amount_in` on `side` of a pair, with the amount
+		/// requested from it and the amount it pays out. Ties go to the pool.
+		fn best_venue(
+			who: &T::AccountId,
+			pair: (u32, u32),
+			side: &OrderType,
+			amount_in: BalanceOf<T>,
+		) -> Option<(SwapVenue, BalanceOf<T>, BalanceOf<T>)> {
+			let pool_amount_out = Self::pool_quote(pair, side, amount_in).filter(|amount_out| {
+				Self::ensure_pool_swap_allowed(pair, side, amount_in, *amount_out).is_ok()
+			});
+			match (
+				pool_amount_out,
+				Self::book_quote(who, pair, side, amount_in),
+			) {
+				(_, Some((requested_amount, amount_out)))
+					if pool_amount_out
+						.map_or(true, |pool_amount_out| amount_out > pool_amount_out) =>
+				{
+					Some((SwapVenue::OrderBook, requested_amount, amount_out))
+				}
+				(Some(amount_out), _) => Some((SwapVenue::Pool, amount_out, amount_out)),
+				(None, None) => None,
+			}
+		}
+
+		/// Amount the liquidity pool of a pair pays out for `amount_in` on `side`.
+		pub fn pool_quote(
+			pair: (u32, u32),
+			side: &OrderType,
+			amount_in: BalanceOf<T>,
+		) -> Option<BalanceOf<T>> {
+			LiquidityPools::<T>::get(pair)?
+				.amount_out(side, amount_in, T::PoolFee::get())
+				.filter(|amount_out| !amount_out.is_zero())
+		}
+
+		/// Dry-runs a market order of `who` for `amount_in` on `side` against the open
+		/// orders of a pair. Returns the amount the order has to request to trade the same
+		/// way, and the amount it receives after the taker fee.
+		fn book_quote(
+			who: &T::AccountId,
+			pair: (u32, u32),
+			side: &OrderType,
+			amount_in: BalanceOf<T>,
+		) -> Option<(BalanceOf<T>, BalanceOf<T>)> {
+			let next_order_index = NextOrderIndex::<T>::get();
+			if T::MatchingMode::get().is_on_chain()
+				&& NextMatchOrderIndex::<T>::get() != next_order_index
+			{
+				return None;
+			}
+
+			let maker_book_type = side.get_opposite();
+			let mut maker_book = Self::pair_book(pair, &maker_book_type);
+			let expiration_block = frame_system::Pallet::<T>::block_number() + One::one();
+			let order = Order {
+				counter: next_order_index,
+				pair,
+				expiration_block,
+				order_type: side.clone(),
+				address: who.clone(),
+				amount_offered: amount_in,
+				amout_requested: BalanceOf::<T>::max_value(),
+				price: Zero::zero(),
+				unfilled_offered: amount_in,
+				unfilled_requested: BalanceOf::<T>::max_value(),
+				order_status: OrderStatus::Pending,
+				order_kind: OrderKind::Market,
+				time_in_force: TimeInForce::ImmediateOrCancel,
+			};
+			let dry_run = Self::match_in_orderbook(
+				next_order_index,
+				order,
+				maker_book_type,
+				&mut maker_book,
+				&mut BoundedBTreeMap::new(),
+			)
+			.ok()?;
+
+			let taker_fee = Self::account_fee(pair, who).0.taker_fee;
+			let mut requested_amount = BalanceOf::<T>::zero();
+			let mut amount_out = BalanceOf::<T>::zero();
+			for trade in dry_run.match_details {
+				let received = match side {
+					OrderType::BUY => trade.quantity_base,
+					OrderType::SELL => trade.quantity_quote,
+				};
+				requested_amount = requested_amount.saturating_add(received);
+				amount_out = amount_out.saturating_add(received - taker_fee.mul_floor(received));
+			}
+			if amount_out.is_zero() {
+				return None;
+			}
+
+			// the order must pass the trading rules of the pair like any other order
+			Self::check_order_request(OrderRequest {
+				asset_id_1: pair.0,
+				asset_id_2: pair.1,
+				offered_amount: amount_in,
+				requested_amount,
+				order_type: side.clone(),
+				order_kind: OrderKind::Market,
+				time_in_force: TimeInForce::ImmediateOrCancel,
+				expiration_block,
+			})
+			.ok()?;
+
+			Some((requested_amount, amount_out))
+		}
+
+		/// Open orders of `order_type` in a pair, keyed like the on-chain order book. They
+		/// are read from `PairOrders`, which is kept in every matching mode.
+		fn pair_book(
+			pair: (u32, u32),
+			order_type: &OrderType,
+		) -> BookOrders<OrderOf<T>, FixedU128> {
+			let mut book = BoundedBTreeMap::new();
+			for order_index in PairOrders::<T>::iter_key_prefix(pair) {
+				match Orders::<T>::get(order_index) {
+					Some(order)
+						if order.order_type == *order_type && Self::is_order_open(&order) =>
+					{
+						let key = OrderBookKey {
+							order_id: order_index,
+							price: order.price,
+						};
+						// a pair holds at most `MaxOrdersPerPair` orders, they fit a book
+						let _ = book.try_insert(key, order);
+					}
+					_ => {}
+				}
+			}
+			book
+		}
+
+		/// Weight of reading the open orders of a pair `n` times with `pair_book`. A swap
+		/// reads them to quote the order book and again to trade against it.
+		pub fn pair_book_weight(n: u32) -> Weight {
+			let max_orders = T::MaxOrdersPerPair::get() as u64;
+			T::DbWeight::get()
+				.reads(max_orders.saturating_mul(2).saturating_add(1))
+				.saturating_mul(n.into())
+		}
+
+		/// Checks a pool swap against the trading rules of its pair like a market order of
+		/// the same amounts: the status and the circuit breaker of the pair, its minimum
+		/// order size and its price band.
+		fn ensure_pool_swap_allowed(
+			pair: (u32, u32),
+			side: &OrderType,
+			amount_in: BalanceOf<T>,
+			amount_out: BalanceOf<T>,
+		) -> Result<FixedU128, DispatchError> {
+			let (base_amount, quote_amount) = match side {
+				OrderType::BUY => (amount_out, amount_in),
+				OrderType::SELL => (amount_in, amount_out),
+			};
+			Self::ensure_order_allowed(pair, TimeInForce::ImmediateOrCancel, base_amount)?;
+
+			let base_amount: u128 = base_amount.unique_saturated_into();
+			let quote_amount: u128 = quote_amount.unique_saturated_into();
+			let price = FixedU128::checked_from_rational(quote_amount, base_amount)
+				.ok_or(Error::<T>::InsufficientLiquidity)?;
+			Self::ensure_within_price_band(pair, price)?;
+
+			Ok(price)
+		}
+
+		/// Assets paid in and out by a swap on `side` of a pair.
+		fn swap_assets(pair: (u32, u32), side: &OrderType) -> (u32, u32) {
+			match side {
+				OrderType::BUY => (pair.1, pair.0),
+				OrderType::SELL => (pair.0, pair.1),
+			}
+		}
+
+		fn swap_with_pool(
+			who: &T::AccountId,
+			pair: (u32, u32),
+			side: OrderType,
+			amount_in: BalanceOf<T>,
+			amount_out: BalanceOf<T>,
+		) -> DispatchResult {
+			let price = Self::ensure_pool_swap_allowed(pair, &side, amount_in, amount_out)?;
+			let (asset_in, asset_out) = Self::swap_assets(pair, &side);
+			Self::sub_assert(who, asset_in, amount_in)?;
+			Self::add_assert(who, asset_out, amount_out)?;
+
+			Self::accumulate_pool_price(pair);
+			LiquidityPools::<T>::try_mutate(pair, |pool| -> DispatchResult {
+				let pool = pool.as_mut().ok_or(Error::<T>::PoolNotFound)?;
+				let (reserve_in, reserve_out) = match side {
+					OrderType::BUY => (&mut pool.quote_reserve, &mut pool.base_reserve),
+					OrderType::SELL => (&mut pool.base_reserve, &mut pool.quote_reserve),
+				};
+				*reserve_in = reserve_in
+					.checked_add(&amount_in)
+					.ok_or(Error::<T>::TokenBalanceOverflow)?;
+				*reserve_out = reserve_out
+					.checked_sub(&amount_out)
+					.ok_or(Error::<T>::InsufficientLiquidity)?;
+				Ok(())
+			})?;
+			Self::update_circuit_breaker(pair, price);
+
+			Self::deposit_event(Event::Swapped {
+				who: who.clone(),
+				pair,
+				side,
+				amount_in,
+				amount_out,
+				venue: SwapVenue::Pool,
+			});
+			Ok(())
+		}
+
+		/// Places and matches the market order quoted by `book_quote`. Returns the amount
+		/// it spent and the amount it received.
+		fn swap_with_book(
+			who: &T::AccountId,
+			pair: (u32, u32),
+			side: OrderType,
+			amount_in: BalanceOf<T>,
+			requested_amount: BalanceOf<T>,
+		) -> Result<(BalanceOf<T>, BalanceOf<T>), DispatchError> {
+			let (asset_in, asset_out) = Self::swap_assets(pair, &side);
+			let balance_in = UserTokenInfoes::<T>::get(who, asset_in).amount;
+			let balance_out = UserTokenInfoes::<T>::get(who, asset_out).amount;
+
+			let order_index = Self::do_place_order(
+				who.clone(),
+				pair.0,
+				pair.1,
+				amount_in,
+				requested_amount,
+				side.clone(),
+				OrderKind::Market,
+				TimeInForce::ImmediateOrCancel,
+				frame_system::Pallet::<T>::block_number() + One::one(),
+			)?;
+			if T::MatchingMode::get().is_on_chain() {
+				// orders placed in `OnIdle` mode are matched here as well
+				Self::match_new_order(order_index)?;
+			} else {
+				Self::match_with_pair_orders(order_index)?;
+			}
+
+			let amount_in =
+				balance_in.saturating_sub(UserTokenInfoes::<T>::get(who, asset_in).amount);
+			let amount_out = UserTokenInfoes::<T>::get(who, asset_out)
+				.amount
+				.saturating_sub(balance_out);
+
+			Self::deposit_event(Event::Swapped {
+				who: who.clone(),
+				pair,
+				side,
+				amount_in,
+				amount_out,
+				venue: SwapVenue::OrderBook,
+			});
+			Ok((amount_in, amount_out))
+		}
+
+		#[allow(clippy::too_many_arguments)]
+		fn do_place_order(
+			who: T::AccountId,
+			asset_id_1: u32,
+			asset_id_2: u32,
+			offered_amount: BalanceOf<T>,
+			requested_amount: BalanceOf<T>,
+			order_type: OrderType,
+			order_kind: OrderKind,
+			time_in_force: TimeInForce,
+			expiration_block: BlockNumberFor<T>,
+		) -> Result<u64, DispatchError> {
+			let (
+				OrderRequest {
+					asset_id_1,
+					asset_id_2,
+					order_type,
+					..
+				},
+				price,
+			) = Self::check_order_request(OrderRequest {
+				asset_id_1,
+				asset_id_2,
+				offered_amount,
+				requested_amount,
+				order_type,
+				order_kind,
+				time_in_force,
+				expiration_block,
+			})?;
+			// market orders are kept within the band while matching
+			if order_kind == OrderKind::Limit {
+				Self::ensure_within_price_band((asset_id_1, asset_id_2), price)?;
+			}
+
+			let order_index =
+				NextOrderIndex::<T>::try_mutate(|index| -> Result<u64, DispatchError> {
+					let order_index = *index;
+
+					let order = Order {
+						counter: order_index,
+						pair: (asset_id_1, asset_id_2),
+						expiration_block,
+						order_type,
+						address: who.clone(),
+						amount_offered: offered_amount,
+						amout_requested: requested_amount,
+						price,
+						unfilled_offered: offered_amount,
+						unfilled_requested: requested_amount,
+						order_status: OrderStatus::Pending,
+						order_kind,
+						time_in_force,
+					};
+
+					Self::reserve_asset(&who, order.get_offered_asset_id(), order.amount_offered)?;
+
+					*index = index
+						.checked_add(One::one())
+						.ok_or(Error::<T>::OrderIndexOverflow)?;
+
+					Orders::<T>::insert(order_index, &order);
+					Self::insert_order_indexes(&order)?;
+					Self::stake_order(&order);
+
+					OrderExpiration::<T>::try_mutate(expiration_block, |expiration_orders| {
+						expiration_orders
+							.try_push(order_index)
+							.map_err(|_| Error::<T>::TooManyExpiringOrders)
+					})?;
+
+					Self::deposit_event(Event::OrderCreated { order_index, order });
+					Ok(order_index)
+				})?;
+
+			if T::MatchingMode::get() == MatchingMode::OnOrderCreation {
+				Self::match_new_order(order_index)?;
+			}
+
+			Ok(order_index)
+		}
+
+		/// Matches a new order on chain right away. Orders left over from before are
+		/// matched first in `on_idle`, so the new order is only matched when there is no
+		/// backlog.
+		fn match_new_order(order_index: u64) -> DispatchResult {
+			if NextMatchOrderIndex::<T>::get() != order_index {
+				return Ok(());
+			}
+
+			let trades = Self::match_order_on_chain(order_index)?;
+			NextMatchOrderIndex::<T>::put(order_index + 1);
+
+			// The number of trades is only known after matching, so it is accounted for on
+			// top of the weight of placing the order.
+			frame_system::Pallet::<T>::register_extra_weight_unchecked(
+				T::WeightInfo::match_order(trades),
+				DispatchClass::Normal,
+			);
+
+			Ok(())
+		}
+
+		/// Matches an order against the open orders of its pair and settles the trades,
+		/// for swaps filled by the order book while orders are matched offchain. The
+		/// offchain workers catch up with the filled orders when they refresh their books.
+		fn match_with_pair_orders(order_index: u64) -> DispatchResult {
+			let order = match Orders::<T>::get(order_index) {
+				Some(order) if Self::is_order_open(&order) => order,
+				_ => return Ok(()),
+			};
+
+			let maker_book_type = order.order_type.get_opposite();
+			let mut maker_book = Self::pair_book(order.pair, &maker_book_type);
+			let match_result = Self::match_in_orderbook(
+				order_index,
+				order,
+				maker_book_type,
+				&mut maker_book,
+				&mut BoundedBTreeMap::new(),
+			)?;
+			Self::settle_match_result(&match_result)?;
+			Self::close_taker_order(order_index)?;
+
+			frame_system::Pallet::<T>::register_extra_weight_unchecked(
+				T::WeightInfo::match_order(
+					(match_result.match_details.len() + match_result.self_trades.len()) as u32,
+				),
+				DispatchClass::Normal,
+			);
+			Ok(())
+		}
+
+		/// Checks an order request against the trading rules of its pair. Returns the
+		/// request with the assets of the pair in order, and the price of the order.
+		fn check_order_request(
+			order: OrderRequest<BalanceOf<T>, BlockNumberFor<T>>,
+		) -> Result<(OrderRequest<BalanceOf<T>, BlockNumberFor<T>>, FixedU128), DispatchError> {
+			ensure!(
+				order.order_kind == OrderKind::Limit
+					|| matches!(
+						order.time_in_force,
+						TimeInForce::ImmediateOrCancel | TimeInForce::FillOrKill
+					),
+				Error::<T>::MarketOrderMustNotRest
+			);
+
+			let order = if order.asset_id_1 > order.asset_id_2 {
+				OrderRequest {
+					asset_id_1: order.asset_id_2,
+					asset_id_2: order.asset_id_1,
+					order_type: order.order_type.get_opposite(),
+					..order
+				}
+			} else {
+				order
+			};
+
+			ensure!(
+				order.asset_id_1 != order.asset_id_2,
+				Error::<T>::PairAssetIdMustNotEqual
+			);
+
+			ensure!(
+				order.expiration_block > frame_system::Pallet::<T>::block_number(),
+				Error::<T>::ExpirationMustBeInFuture
+			);
+
+			let (quote_amount, base_amount) = match order.order_type {
+				OrderType::SELL => (order.requested_amount, order.offered_amount),
+				OrderType::BUY => (order.offered_amount, order.requested_amount),
+			};
+
+			let pair = (order.asset_id_1, order.asset_id_2);
+			Self::ensure_order_allowed(pair, order.time_in_force, base_amount)?;
+			let config = PairConfigs::<T>::get(pair);
+			Self::ensure_valid_amounts(&config, quote_amount, base_amount)?;
+
+			let price = Self::order_price(
+				&order.order_type,
+				order.order_kind,
+				quote_amount,
+				base_amount,
+				config.tick_size,
+			)?;
+
+			Ok((order, price))
+		}
+
+		pub(crate) fn do_place_conditional_order(
+			who: T::AccountId,
+			order: OrderRequest<BalanceOf<T>, BlockNumberFor<T>>,
+			trigger: Trigger,
+		) -> Result<u64, DispatchError> {
+			// the trigger price is kept in terms of the pair, like the order
+			let trigger = if order.asset_id_1 > order.asset_id_2 {
+				Trigger {
+					price: FixedU128::one()
+						.checked_div(&trigger.price)
+						.ok_or(Error::<T>::InvalidTriggerPrice)?,
+					..trigger
+				}
+			} else {
+				trigger
+			};
+			ensure!(!trigger.price.is_zero(), Error::<T>::InvalidTriggerPrice);
+			let (order, _) = Self::check_order_request(order)?;
+
+			let conditional_order = ConditionalOrder {
+				address: who,
+				trigger,
+				order,
+			};
+
+			let conditional_order_index = NextConditionalOrderIndex::<T>::try_mutate(
+				|index| -> Result<u64, DispatchError> {
+					let conditional_order_index = *index;
+					*index = index
+						.checked_add(One::one())
+						.ok_or(Error::<T>::OrderIndexOverflow)?;
+					Ok(conditional_order_index)
+				},
+			)?;
+
+			Self::reserve_asset(
+				&conditional_order.address,
+				conditional_order.offered_asset_id(),
+				conditional_order.order.offered_amount,
+			)?;
+
+			PairConditionalOrders::<T>::try_mutate(conditional_order.pair(), |order_indexes| {
+				order_indexes
+					.try_push(conditional_order_index)
+					.map_err(|_| Error::<T>::TooManyConditionalOrders)
+			})?;
+			ConditionalOrders::<T>::insert(conditional_order_index, &conditional_order);
+
+			Self::deposit_event(Event::ConditionalOrderCreated {
+				conditional_order_index,
+				conditional_order,
+			});
+			Ok(conditional_order_index)
+		}
+
+		/// Removes a conditional order and releases its reserved funds.
+		fn remove_conditional_order(
+			conditional_order_index: u64,
+			conditional_order: &ConditionalOrderOf<T>,
+		) -> DispatchResult {
+			ConditionalOrders::<T>::remove(conditional_order_index);
+			PairConditionalOrders::<T>::mutate_exists(conditional_order.pair(), |order_indexes| {
+				if let Some(indexes) = order_indexes {
+					indexes.retain(|order_index| *order_index != conditional_order_index);
+					if indexes.is_empty() {
+						*order_indexes = None;
+					}
+				}
+			});
+
+			Self::unreserve_asset(
+				&conditional_order.address,
+				conditional_order.offered_asset_id(),
+				conditional_order.order.offered_amount,
+			)
+		}
+
+		/// Turns the conditional orders whose trigger fired into live orders, at most
+		/// `MaxTriggeredOrdersPerBlock` of them. Conditional orders that expired are
+		/// canceled. Pairs are checked in turn, starting after the pair checked last.
+		fn trigger_conditional_orders(now: BlockNumberFor<T>, remaining_weight: Weight) -> Weight {
+			let db_weight = T::DbWeight::get();
+			let trigger_weight = T::WeightInfo::trigger_conditional_order()
+				.saturating_add(Self::match_engine_weight(1));
+			let max_triggered = T::MaxTriggeredOrdersPerBlock::get() as usize;
+			let mut consumed_weight = db_weight.reads_writes(1, 1);
+
+			let start = ConditionalOrderCursor::<T>::get();
+			let pairs = match start {
+				Some(pair) => PairConditionalOrders::<T>::iter_from(
+					PairConditionalOrders::<T>::hashed_key_for(pair),
+				),
+				None => PairConditionalOrders::<T>::iter(),
+			};
+
+			// The orders are only triggered once all pairs are checked, triggering changes
+			// the map that is iterated.
+			let mut cursor = start;
+			let mut checked_all_pairs = true;
+			let mut triggered = Vec::new();
+			'pairs: for (pair, order_indexes) in pairs {
+				let check_weight = db_weight.reads(2 + order_indexes.len() as u64);
+				if !remaining_weight.all_gte(consumed_weight.saturating_add(check_weight)) {
+					checked_all_pairs = false;
+					break;
+				}
+				consumed_weight.saturating_accrue(check_weight);
+
+				let last_trade_price = LastTradePrice::<T>::get(pair);
+				let oracle_price = T::PriceOracle::price(pair);
+				let mut pair_triggered = Vec::new();
+				for conditional_order_index in order_indexes {
+					let conditional_order =
+						match ConditionalOrders::<T>::get(conditional_order_index) {
+							Some(conditional_order) => conditional_order,
+							None => continue,
+						};
+
+					let price = match conditional_order.trigger.source {
+						TriggerSource::LastTradePrice => last_trade_price,
+						TriggerSource::Oracle => oracle_price,
+					};
+					let expired = conditional_order.order.expiration_block <= now;
+					let fired = price.map_or(false, |price| {
+						conditional_order
+							.trigger
+							.is_triggered_at(&conditional_order.order.order_type, price)
+					});
+					if !expired && !fired {
+						continue;
+					}
+
+					// The pair is checked again in the next block when it can't be finished.
+					if triggered.len() + pair_triggered.len() >= max_triggered
+						|| !remaining_weight.all_gte(consumed_weight.saturating_add(trigger_weight))
+					{
+						triggered.append(&mut pair_triggered);
+						checked_all_pairs = false;
+						break 'pairs;
+					}
+					consumed_weight.saturating_accrue(trigger_weight);
+					pair_triggered.push((conditional_order_index, conditional_order, expired));
+				}
+
+				triggered.append(&mut pair_triggered);
+				cursor = Some(pair);
+			}
+
+			for (conditional_order_index, conditional_order, expired) in triggered {
+				if expired {
+					match Self::remove_conditional_order(
+						conditional_order_index,
+						&conditional_order,
+					) {
+						Ok(()) => Self::deposit_event(Event::ConditionalOrderCanceled {
+							conditional_order_index,
+						}),
+						Err(e) => log::error!(
+							"Failed to cancel conditional order {:?}: {:?}",
+							conditional_order_index,
+							e
+						),
+					}
+				} else {
+					Self::trigger_conditional_order(conditional_order_index, conditional_order);
+				}
+			}
+
+			if checked_all_pairs {
+				ConditionalOrderCursor::<T>::kill();
+			} else if let Some(pair) = cursor {
+				ConditionalOrderCursor::<T>::put(pair);
+			}
+
+			consumed_weight
+		}
+
+		/// Places the order of a conditional order whose trigger fired. The funds reserved
+		/// for the conditional order are released first and reserved again by the order.
+		pub(crate) fn trigger_conditional_order(
+			conditional_order_index: u64,
+			conditional_order: ConditionalOrderOf<T>,
+		) {
+			let result = with_storage_layer(|| {
+				Self::remove_conditional_order(conditional_order_index, &conditional_order)?;
+
+				let order = conditional_order.order.clone();
+				Self::do_place_order(
+					conditional_order.address.clone(),
+					order.asset_id_1,
+					order.asset_id_2,
+					order.offered_amount,
+					order.requested_amount,
+					order.order_type,
+					order.order_kind,
+					order.time_in_force,
+					order.expiration_block,
+				)
+			});
+
+			match result {
+				Ok(order_index) => Self::deposit_event(Event::ConditionalOrderTriggered {
+					conditional_order_index,
+					order_index,
+				}),
+				Err(error) => {
+					// the conditional order is dropped either way
+					if let Err(e) =
+						Self::remove_conditional_order(conditional_order_index, &conditional_order)
+					{
+						log::error!(
+							"Failed to remove conditional order {:?}: {:?}",
+							conditional_order_index,
+							e
+						);
+					}
+					Self::deposit_event(Event::ConditionalOrderFailed {
+						conditional_order_index,
+						error,
+					});
+				}
+			}
+		}
+
+		fn do_amend_order(
+			who: &T::AccountId,
+			order_index: u64,
+			offered_amount: BalanceOf<T>,
+			requested_amount: BalanceOf<T>,
+		) -> DispatchResult {
+			let mut order = Orders::<T>::get(order_index).ok_or(Error::<T>::InvalidOrderIndex)?;
+			ensure!(order.address == *who, Error::<T>::NotOwner);
+			ensure!(
+				Self::is_order_open(&order) && order.rests_on_book(),
+				Error::<T>::OrderNotAmendable
+			);
+
+			let (quote_amount, base_amount) = match order.order_type {
+				OrderType::SELL => (requested_amount, offered_amount),
+				OrderType::BUY => (offered_amount, requested_amount),
+			};
+			Self::ensure_order_allowed(order.pair, order.time_in_force, base_amount)?;
+			let config = PairConfigs::<T>::get(order.pair);
+			Self::ensure_valid_amounts(&config, quote_amount, base_amount)?;
+			let price = Self::order_price(
+				&order.order_type,
+				order.order_kind,
+				quote_amount,
+				base_amount,
+				config.tick_size,
+			)?;
+
+			let new_order_index = if price == order.price
+				&& offered_amount <= order.unfilled_offered
+				&& requested_amount <= order.unfilled_requested
+			{
+				// a smaller order at the same price keeps its place in the queue
+				let released = order.unfilled_offered - offered_amount;
+				Self::unreserve_asset(who, order.get_offered_asset_id(), released)?;
+
+				order.amount_offered -= released;
+				order.amout_requested -= order.unfilled_requested - requested_amount;
+				order.unfilled_offered = offered_amount;
+				order.unfilled_requested = requested_amount;
+				Orders::<T>::insert(order_index, &order);
+				Self::update_in_match_engine(&order);
+				Self::restake_order(&order);
+
+				order_index
+			} else {
+				Self::cancel_order_impl(order_index)?;
+				Self::do_place_order(
+					who.clone(),
+					order.pair.0,
+					order.pair.1,
+					offered_amount,
+					requested_amount,
+					order.order_type,
+					order.order_kind,
+					order.time_in_force,
+					order.expiration_block,
+				)?
+			};
+
+			Self::deposit_event(Event::OrderAmended {
+				order_index,
+				new_order_index,
+			});
+
+			Ok(())
+		}
+
+		/// Cancels all orders and conditional orders of a pair. Returns how many of each
+		/// were canceled.
+		fn cancel_pair_orders(pair: (u32, u32)) -> Result<(u32, u32), DispatchError> {
+			let order_indexes: Vec<u64> = PairOrders::<T>::iter_key_prefix(pair).collect();
+			for order_index in order_indexes.iter() {
+				Self::cancel_order_impl(*order_index)?;
+			}
+
+			let conditional_order_indexes = PairConditionalOrders::<T>::get(pair);
+			for conditional_order_index in conditional_order_indexes.iter() {
+				if let Some(conditional_order) =
+					ConditionalOrders::<T>::get(conditional_order_index)
+				{
+					Self::remove_conditional_order(*conditional_order_index, &conditional_order)?;
+					Self::deposit_event(Event::ConditionalOrderCanceled {
+						conditional_order_index: *conditional_order_index,
+					});
+				}
+			}
+
+			Ok((
+				order_indexes.len() as u32,
+				conditional_order_indexes.len() as u32,
+			))
+		}
+
+		fn cancel_order_impl(order_index: u64) -> DispatchResult {
+			let who = Self::remove_order(order_index)?;
+
+			Self::deposit_event(Event::OrderCanceled { order_index, who });
+
+			Ok(())
+		}
+
+		/// Closes what is left of a taker order that must not rest on the order book
+		/// once it went through matching, releasing its reserved funds.
+		fn close_taker_order(order_index: u64) -> DispatchResult {
+			let order = match Orders::<T>::get(order_index) {
+				Some(order) if Self::is_order_open(&order) => order,
+				_ => return Ok(()),
+			};
+
+			let order_status = match order.time_in_force {
+				TimeInForce::GoodTillExpiration => return Ok(()),
+				TimeInForce::PostOnly => OrderStatus::Rejected,
+				TimeInForce::FillOrKill => {
+					ensure!(
+						order.order_status == OrderStatus::Pending,
+						Error::<T>::FillOrKillNotFilled
+					);
+					OrderStatus::FullyCancelled
+				}
+				TimeInForce::ImmediateOrCancel => match order.order_status {
+					OrderStatus::Pending => OrderStatus::FullyCancelled,
+					_ => OrderStatus::PartialCancelled,
+				},
+			};
+
+			Self::remove_order(order_index)?;
+
+			Self::deposit_event(Event::OrderClosed {
+				order_index,
+				order_status,
+			});
+
+			Ok(())
+		}
+
+		/// Removes an order and releases the funds reserved for its unfilled part. Returns
+		/// the owner of the order.
+		fn remove_order(order_index: u64) -> Result<T::AccountId, DispatchError> {
+			Orders::<T>::try_mutate_exists(order_index, |order| -> Result<_, DispatchError> {
+				let order = order.take().ok_or(Error::<T>::InvalidOrderIndex)?;
+
+				// only the part of the order that has not been filled yet is still reserved
+				Self::unreserve_asset(
+					&order.address,
+					order.get_offered_asset_id(),
+					order.unfilled_offered,
+				)?;
+
+				Self::remove_order_indexes(&order);
+				Self::remove_from_match_engine(&order);
+
+				Ok(order.address)
+			})
+		}
+
+		/// Checks a trade produced by the offchain matching engine against the orders
+		/// stored on chain and moves the reserved funds between the two parties.
+		pub fn settle_trade(trade: &Trade<BalanceOf<T>, OrderOf<T>>) -> DispatchResult {
+			let mut taker_order =
+				Orders::<T>::get(trade.taker_order.counter).ok_or(Error::<T>::InvalidTradeOrder)?;
+			let mut maker_order =
+				Orders::<T>::get(trade.maker_order.counter).ok_or(Error::<T>::InvalidTradeOrder)?;
+
+			Self::verify_trade(trade, &taker_order, &maker_order)?;
+			ensure!(
+				Self::pair_status(taker_order.pair).allows_trading(),
+				Error::<T>::PairNotTradable
+			);
+			let trade_id = Self::record_trade(
+				taker_order.pair,
+				trade.price,
+				trade.quantity_base,
+				trade.quantity_quote,
+			);
+
+			// update order status
+			Self::fill_order(&mut taker_order, trade.quantity_base, trade.quantity_quote)?;
+			Self::fill_order(&mut maker_order, trade.quantity_base, trade.quantity_quote)?;
+
+			let (fee, maker_rebate) =
+				Self::trade_fees(taker_order.pair, &maker_order.address, &taker_order.address);
+			let (buyer, seller, buyer_fee_rate, seller_fee_rate) = match taker_order.order_type {
+				OrderType::BUY => (
+					&taker_order.address,
+					&maker_order.address,
+					fee.taker_fee,
+					fee.maker_fee,
+				),
+				OrderType::SELL => (
+					&maker_order.address,
+					&taker_order.address,
+					fee.maker_fee,
+					fee.taker_fee,
+				),
+			};
+			let (base_asset_id, quote_asset_id) = taker_order.pair;
+
+			// fees are taken from the asset each side receives
+			let base_fee = buyer_fee_rate.mul_floor(trade.quantity_base);
+			let quote_fee = seller_fee_rate.mul_floor(trade.quantity_quote);
+
+			// exchange asset
+			Self::add_assert(buyer, base_asset_id, trade.quantity_base - base_fee)?;
+			Self::sub_reserved_assert(buyer, quote_asset_id, trade.quantity_quote)?;
+			Self::add_assert(seller, quote_asset_id, trade.quantity_quote - quote_fee)?;
+			Self::sub_reserved_assert(seller, base_asset_id, trade.quantity_base)?;
+
+			Self::record_volume(
+				taker_order.pair,
+				&maker_order.address,
+				&taker_order.address,
+				trade.quantity_quote,
+			);
+
+			let (maker_fee, taker_fee) = match taker_order.order_type {
+				OrderType::BUY => (quote_fee, base_fee),
+				OrderType::SELL => (base_fee, quote_fee),
+			};
+			// the taker fee is taken from the asset the maker gave, its rebate is paid in it
+			let (maker_fee_asset_id, taker_fee_asset_id) = match taker_order.order_type {
+				OrderType::BUY => (quote_asset_id, base_asset_id),
+				OrderType::SELL => (base_asset_id, quote_asset_id),
+			};
+			let taker_fee_paid = Self::pay_maker_rebate(
+				trade_id,
+				&maker_order.address,
+				taker_fee_asset_id,
+				taker_fee,
+				maker_rebate,
+			)?;
+			Self::pay_fee(maker_fee_asset_id, maker_fee)?;
+			Self::pay_fee(taker_fee_asset_id, taker_fee_paid)?;
+
+			// remove fully filled orders
+			Self::remove_order_if_fully_filled(&taker_order)?;
+			Self::remove_order_if_fully_filled(&maker_order)?;
+
+			Self::deposit_event(Event::OrderMatched {
+				trade_id,
+				pair: taker_order.pair,
+				side: taker_order.order_type,
+				price: trade.price,
+				quantity_base: trade.quantity_base,
+				quantity_quote: trade.quantity_quote,
+				maker: maker_order.address,
+				taker: taker_order.address,
+				maker_order_index: maker_order.counter,
+				taker_order_index: taker_order.counter,
+				maker_fee,
+				taker_fee,
+			});
+
+			Ok(())
+		}
+
+		/// Updates the circuit breaker, the last trade price and the candles of a pair with
+		/// a settled trade. Returns the id of the trade.
+		fn record_trade(
+			pair: (u32, u32),
+			price: FixedU128,
+			quantity_base: BalanceOf<T>,
+			quantity_quote: BalanceOf<T>,
+		) -> u64 {
+			let trade_id = NextTradeId::<T>::mutate(|trade_id| {
+				let id = *trade_id;
+				*trade_id = trade_id.saturating_add(1);
+				id
+			});
+
+			Self::update_circuit_breaker(pair, price);
+			LastTradePrice::<T>::insert(pair, price);
+
+			let max_candles = T::MaxCandles::get() as u64;
+			if max_candles == 0 {
+				return trade_id;
+			}
+
+			let now = T::UnixTime::now().as_secs();
+			for interval in T::CandleIntervals::get() {
+				if interval == 0 {
+					continue;
+				}
+
+				let start = now - now % interval;
+				let slot = ((now / interval) % max_candles) as u32;
+				Candles::<T>::mutate((pair, interval), slot, |candle| match candle {
+					Some(candle) if candle.start == start => {
+						candle.add_trade(price, quantity_base, quantity_quote)
+					}
+					_ => *candle = Some(Candle::new(start, price, quantity_base, quantity_quote)),
+				});
+			}
+
+			trade_id
+		}
+
+		/// Sends a trading fee held by the pallet to the fee destination. A fee that can't
+		/// be transferred, e.g. because it is below the minimum balance of the asset, is
+		/// credited to the DEX balance of the fee destination instead.
+		fn pay_fee(asset_id: u32, fee: BalanceOf<T>) -> DispatchResult {
+			if fee.is_zero() {
+				return Ok(());
+			}
+
+			let destination = T::FeeDestination::get();
+			let transferred = with_storage_layer(|| {
+				Self::transfer_asset(asset_id, &Self::account_id(), &destination, fee)
+			});
+
+			if transferred.is_err() {
+				Self::add_assert(&destination, asset_id, fee)?;
+			}
+
+			Ok(())
+		}
+
+		/// Transfers an asset between two accounts outside of the DEX balances.
+		fn transfer_asset(
+			asset_id: u32,
+			from: &T::AccountId,
+			to: &T::AccountId,
+			amount: BalanceOf<T>,
+		) -> DispatchResult {
+			if asset_id == NativeAssetId::<T>::get() {
+				T::Currency::transfer(from, to, amount, AllowDeath)
+			} else {
+				<T::Fungibles as Mutate<T::AccountId>>::transfer(
+					asset_id,
+					from,
+					to,
+					amount,
+					Preservation::Expendable,
+				)
+				.map(|_| ())
+			}
+		}
+
+		/// Fees of `who` in a pair by the fee tier of its trading volume, and the share of
+		/// the taker fee it gets as a maker.
+		fn account_fee(pair: (u32, u32), who: &T::AccountId) -> (PairFee, Permill) {
+			let volume = Self::trading_volume(who, pair);
+			FeeTiers::<T>::get(pair)
+				.into_iter()
+				.rev()
+				.find(|tier| tier.min_volume <= volume)
+				.map(|tier| (tier.fee, tier.maker_rebate))
+				.unwrap_or_else(|| (PairFees::<T>::get(pair), Permill::zero()))
+		}
+
+		/// Fee rates of a trade between a maker and a taker in a pair, and the share of the
+		/// taker fee that is rebated to the maker.
+		fn trade_fees(
+			pair: (u32, u32),
+			maker: &T::AccountId,
+			taker: &T::AccountId,
+		) -> (PairFee, Permill) {
+			let (maker_fee, maker_rebate) = Self::account_fee(pair, maker);
+			let (taker_fee, _) = Self::account_fee(pair, taker);
+			(
+				PairFee {
+					maker_fee: maker_fee.maker_fee,
+					taker_fee: taker_fee.taker_fee,
+				},
+				maker_rebate,
+			)
+		}
+
+		/// Adds a trade to the trading volumes of its maker and taker.
+		fn record_volume(
+			pair: (u32, u32),
+			maker: &T::AccountId,
+			taker: &T::AccountId,
+			quantity_quote: BalanceOf<T>,
+		) {
+			let day = Self::current_day();
+			for who in [maker, taker] {
+				TradingVolumes::<T>::mutate(who, pair, |volume| volume.add(day, quantity_quote));
+			}
+		}
+
+		/// Passes the share `maker_rebate` of the taker fee of a trade on to the maker.
+		/// Returns the rest of the taker fee.
+		fn pay_maker_rebate(
+			trade_id: u64,
+			maker: &T::AccountId,
+			asset_id: u32,
+			taker_fee: BalanceOf<T>,
+			maker_rebate: Permill,
+		) -> Result<BalanceOf<T>, DispatchError> {
+			let amount = maker_rebate.mul_floor(taker_fee);
+			if amount.is_zero() {
+				return Ok(taker_fee);
+			}
+
+			Self::add_assert(maker, asset_id, amount)?;
+			Self::deposit_event(Event::MakerRebatePaid {
+				trade_id,
+				maker: maker.clone(),
+				asset_id,
+				amount,
+			});
+			Ok(taker_fee - amount)
+		}
+
+		/// Stakes a new order in the running liquidity mining epoch of its pair, if it rests
+		/// on the order book within the spread of the epoch.
+		fn stake_order(order: &OrderOf<T>) {
+			if !order.rests_on_book() {
+				return;
+			}
+			let epoch_id = match PairRewardEpochs::<T>::get(order.pair) {
+				Some(epoch_id) => epoch_id,
+				None => return,
+			};
+			let now = frame_system::Pallet::<T>::block_number();
+			let mut epoch = match RewardEpochs::<T>::get(epoch_id) {
+				Some(epoch) if epoch.is_running(now) => epoch,
+				_ => return,
+			};
+
+			if Self::price_of(order.pair, epoch.terms.reference).is_none()
+				|| !Self::within_reward_spread(order, &epoch.terms)
+			{
+				return;
+			}
+
+			let amount = *order.get_unfilled_base_amout();
+			epoch.update(now);
+			epoch.total_stake = epoch.total_stake.saturating_add(amount);
+			RewardStakes::<T>::insert(
+				order.counter,
+				RewardStake {
+					epoch_id,
+					amount,
+					reward_per_stake: epoch.reward_per_stake,
+				},
+			);
+			RewardEpochs::<T>::insert(epoch_id, epoch);
+		}
+
+		/// Whether an order is priced within the spread of a liquidity mining epoch of its
+		/// pair. Orders are within it while the reference price of the epoch isn't known.
+		fn within_reward_spread(order: &OrderOf<T>, terms: &RewardTermsOf<T>) -> bool {
+			Self::price_of(order.pair, terms.reference).map_or(true, |reference_price| {
+				let spread = reference_price.saturating_mul(terms.max_spread.into());
+				reference_price.saturating_sub(spread) <= order.price
+					&& order.price <= reference_price.saturating_add(spread)
+			})
+		}
+
+		/// Sets the stake of an order to its unfilled base amount after it changed, the
+		/// stake ends once the order is closed.
+		fn restake_order(order: &OrderOf<T>) {
+			let amount = if Self::is_order_open(order) {
+				*order.get_unfilled_base_amout()
+			} else {
+				Zero::zero()
+			};
+			Self::update_stake(order, amount);
+		}
+
+		/// Adds the reward the stake of an order earned to the unclaimed rewards of its
+		/// owner and changes the stake to `amount`, a zero amount ends the stake. An order
+		/// that left the spread of the epoch forfeits the reward and its stake ends.
+		fn update_stake(order: &OrderOf<T>, amount: BalanceOf<T>) {
+			let mut stake = match RewardStakes::<T>::get(order.counter) {
+				Some(stake) => stake,
+				None => return,
+			};
+			let mut epoch = match RewardEpochs::<T>::get(stake.epoch_id) {
+				Some(epoch) => epoch,
+				None => return RewardStakes::<T>::remove(order.counter),
+			};
+
+			epoch.update(frame_system::Pallet::<T>::block_number());
+			let reward = epoch.reward(&stake);
+			let amount = if Self::within_reward_spread(order, &epoch.terms) {
+				if !reward.is_zero() {
+					UnclaimedRewards::<T>::mutate(
+						&order.address,
+						epoch.terms.asset_id,
+						|unclaimed| *unclaimed = unclaimed.saturating_add(reward),
+					);
+				}
+				amount
+			} else {
+				epoch.unearned_reward = epoch.unearned_reward.saturating_add(reward);
+				Zero::zero()
+			};
+			epoch.total_stake = epoch
+				.total_stake
+				.saturating_sub(stake.amount)
+				.saturating_add(amount);
+
+			if amount.is_zero() {
+				RewardStakes::<T>::remove(order.counter);
+			} else {
+				stake.amount = amount;
+				stake.reward_per_stake = epoch.reward_per_stake;
+				RewardStakes::<T>::insert(order.counter, &stake);
+			}
+			RewardEpochs::<T>::insert(stake.epoch_id, epoch);
+		}
+
+		/// Weight of reading and writing back the on-chain order book of a pair `n` times. It
+		/// grows with the depth of the book, so a full book is charged. Nothing is charged
+		/// when orders are matched offchain.
+		pub fn match_engine_weight(n: u32) -> Weight {
+			if T::MatchingMode::get().is_on_chain() {
+				T::WeightInfo::update_match_engine(T::MaxOrdersPerPair::get())
+					.saturating_mul(n.into())
+			} else {
+				Weight::zero()
+			}
+		}
+
+		/// A match result only applies to the taker order it was computed for, it is stale
+		/// once the order changed, e.g. because the result was already applied or the
+		/// offchain worker matched the order again after a reorg.
+		fn taker_unchanged(match_result: &MatchResult<BalanceOf<T>, OrderOf<T>>) -> bool {
+			Orders::<T>::get(match_result.taker_order.counter).map_or(false, |order| {
+				Self::is_order_open(&order)
+					&& order.unfilled_offered == match_result.taker_order.unfilled_offered
+					&& order.unfilled_requested == match_result.taker_order.unfilled_requested
+			})
+		}
+
+		/// Makes sure a trade is consistent with the on-chain state of both orders:
+		/// they are open, on opposite sides of the same pair, their prices cross, the
+		/// trade is executed at the maker price and it leaves both orders as the matching
+		/// engine did.
+		fn verify_trade(
+			trade: &Trade<BalanceOf<T>, OrderOf<T>>,
+			taker_order: &OrderOf<T>,
+			maker_order: &OrderOf<T>,
+		) -> DispatchResult {
+			ensure!(
+				taker_order.counter != maker_order.counter
+					&& taker_order.pair == maker_order.pair
+					&& taker_order.order_type == maker_order.order_type.get_opposite(),
+				Error::<T>::InvalidTradeOrder
+			);
+			ensure!(
+				Self::is_order_open(taker_order) && Self::is_order_open(maker_order),
+				Error::<T>::InvalidTradeOrder
+			);
+			// Only orders from the order book can be makers and post-only orders never take.
+			ensure!(
+				maker_order.rests_on_book() && taker_order.time_in_force != TimeInForce::PostOnly,
+				Error::<T>::InvalidTradeOrder
+			);
+			ensure!(
+				taker_order.address != maker_order.address,
+				Error::<T>::SelfTrade
+			);
+
+			ensure!(
+				trade.price == maker_order.price,
+				Error::<T>::InvalidTradePrice
+			);
+
+			// Market orders trade at any price.
+			if taker_order.order_kind == OrderKind::Limit {
+				let (buy_price, sell_price) = match taker_order.order_type {
+					OrderType::BUY => (taker_order.price, maker_order.price),
+					OrderType::SELL => (maker_order.price, taker_order.price),
+				};
+				ensure!(buy_price >= sell_price, Error::<T>::PricesDoNotCross);
+			}
+
+			ensure!(
+				!trade.quantity_base.is_zero()
+					&& trade.quantity_quote
+						== Self::quote_amount(
+							trade.price,
+							trade.quantity_base,
+							&maker_order.order_type
+						)?,
+				Error::<T>::InvalidTradeAmount
+			);
+
+			// The orders of a trade are snapshots taken after the trade, they no longer
+			// apply once the orders changed on chain.
+			for (order, snapshot) in [
+				(taker_order, &trade.taker_order),
+				(maker_order, &trade.maker_order),
+			] {
+				let mut order = order.clone();
+				Self::apply_fill(&mut order, trade.quantity_base, trade.quantity_quote)?;
+				ensure!(
+					order.counter == snapshot.counter
+						&& order.unfilled_offered == snapshot.unfilled_offered
+						&& order.unfilled_requested == snapshot.unfilled_requested,
+					Error::<T>::StaleMatchResult
+				);
+			}
+
+			Ok(())
+		}
+
+		/// Settles the trades and prevented self trades of a match result in the order
+		/// the matching engine produced them.
+		fn settle_match_result(
+			match_result: &MatchResult<BalanceOf<T>, OrderOf<T>>,
+		) -> DispatchResult {
+			let mut self_trades = match_result.self_trades.iter().peekable();
+			for (trade_index, trade) in match_result.match_details.iter().enumerate() {
+				while let Some(self_trade) = self_trades
+					.next_if(|self_trade| self_trade.trades_before as usize <= trade_index)
+				{
+					Self::settle_self_trade(self_trade)?;
+				}
+				Self::settle_trade(trade)?;
+			}
+			for self_trade in self_trades {
+				Self::settle_self_trade(self_trade)?;
+			}
+
+			Ok(())
+		}
+
+		/// Applies the self-trade policy to a taker order and an order of the same account
+		/// it met on the order book.
+		pub fn settle_self_trade(self_trade: &SelfTrade) -> DispatchResult {
+			let taker_order =
+				Orders::<T>::get(self_trade.taker_order).ok_or(Error::<T>::InvalidTradeOrder)?;
+			let maker_order =
+				Orders::<T>::get(self_trade.maker_order).ok_or(Error::<T>::InvalidTradeOrder)?;
+
+			ensure!(
+				taker_order.counter != maker_order.counter
+					&& taker_order.address == maker_order.address
+					&& taker_order.pair == maker_order.pair
+					&& taker_order.order_type == maker_order.order_type.get_opposite(),
+				Error::<T>::InvalidTradeOrder
+			);
+			ensure!(
+				Self::is_order_open(&taker_order)
+					&& Self::is_order_open(&maker_order)
+					&& maker_order.rests_on_book(),
+				Error::<T>::InvalidTradeOrder
+			);
+			ensure!(
+				taker_order.order_kind == OrderKind::Market
+					|| Self::prices_cross(&taker_order, &maker_order),
+				Error::<T>::PricesDoNotCross
+			);
+
+			let policy = T::SelfTradePolicy::get();
+			let quantity_base = match policy {
+				SelfTradePolicy::CancelNewest => {
+					let quantity_base = *taker_order.get_unfilled_base_amout();
+					Self::cancel_order_impl(taker_order.counter)?;
+					quantity_base
+				}
+				SelfTradePolicy::CancelOldest => {
+					let quantity_base = *maker_order.get_unfilled_base_amout();
+					Self::cancel_order_impl(maker_order.counter)?;
+					quantity_base
+				}
+				SelfTradePolicy::DecrementAndCancel => {
+					let quantity_base = (*taker_order.get_unfilled_base_amout())
+						.min(*maker_order.get_unfilled_base_amout());
+					Self::decrement_or_cancel_order(taker_order, quantity_base)?;
+					Self::decrement_or_cancel_order(maker_order, quantity_base)?;
+					quantity_base
+				}
+			};
+
+			Self::deposit_event(Event::SelfTradePrevented {
+				taker_order_index: self_trade.taker_order,
+				maker_order_index: self_trade.maker_order,
+				policy,
+				quantity_base,
+			});
+
+			Ok(())
+		}
+
+		/// Takes `quantity_base` off an order and releases the funds reserved for it, the
+		/// order is canceled when nothing is left of it.
+		fn decrement_or_cancel_order(
+			mut order: OrderOf<T>,
+			quantity_base: BalanceOf<T>,
+		) -> DispatchResult {
+			if quantity_base >= *order.get_unfilled_base_amout() {
+				return Self::cancel_order_impl(order.counter);
+			}
+
+			let released = Self::decrement_order(&mut order, quantity_base)?;
+			Self::unreserve_asset(&order.address, order.get_offered_asset_id(), released)?;
+			Self::update_in_match_engine(&order);
+			Self::restake_order(&order);
+			Orders::<T>::insert(order.counter, order);
+
+			Ok(())
+		}
+
+		/// Reduces the amounts of an order by `quantity_base` without trading it.
+		/// Returns the part of the offered amount that no longer needs to be reserved.
+		fn decrement_order(
+			order: &mut OrderOf<T>,
+			quantity_base: BalanceOf<T>,
+		) -> Result<BalanceOf<T>, DispatchError> {
+			let quantity_quote = Self::quote_amount(order.price, quantity_base, &order.order_type)?;
+			let (offered, requested) = match order.order_type {
+				OrderType::SELL => (quantity_base, quantity_quote),
+				OrderType::BUY => (quantity_quote, quantity_base),
+			};
+			let offered = offered.min(order.unfilled_offered);
+
+			order.unfilled_offered -= offered;
+			order.unfilled_requested = order.unfilled_requested.saturating_sub(requested);
+			order.amount_offered = order.amount_offered.saturating_sub(offered);
+			order.amout_requested = order.amout_requested.saturating_sub(requested);
+
+			Ok(offered)
+		}
+
+		fn is_order_open(order: &OrderOf<T>) -> bool {
+			matches!(
+				order.order_status,
+				OrderStatus::Pending | OrderStatus::PartialFilled
+			)
+		}
+
+		/// Decreases the unfilled amounts of an order by the traded quantities and updates
+		/// its status. Funds left reserved by a fully filled order are released.
+		fn fill_order(
+			order: &mut OrderOf<T>,
+			quantity_base: BalanceOf<T>,
+			quantity_quote: BalanceOf<T>,
+		) -> DispatchResult {
+			Self::apply_fill(order, quantity_base, quantity_quote)?;
+
+			if order.order_status == OrderStatus::FullyFilled {
+				if !order.unfilled_offered.is_zero() {
+					let offered_asset_id = match order.order_type {
+						OrderType::SELL => order.pair.0,
+						OrderType::BUY => order.pair.1,
+					};
+					Self::sub_reserved_assert(
+						&order.address,
+						offered_asset_id,
+						order.unfilled_offered,
+					)?;
+					Self::add_assert(&order.address, offered_asset_id, order.unfilled_offered)?;
+					order.unfilled_offered = Zero::zero();
+				}
+			}
+
+			Orders::<T>::insert(order.counter, &*order);
+			Self::restake_order(order);
+			Ok(())
+		}
+
+		/// Decreases the unfilled amounts of an order by a trade. An order is filled once
+		/// its base amount is, a seller may receive more quote than requested.
+		fn apply_fill(
+			order: &mut OrderOf<T>,
+			quantity_base: BalanceOf<T>,
+			quantity_quote: BalanceOf<T>,
+		) -> DispatchResult {
+			match order.order_type {
+				OrderType::SELL => {
+					order.unfilled_offered = order
+						.unfilled_offered
+						.checked_sub(&quantity_base)
+						.ok_or(Error::<T>::TradeExceedsUnfilledAmount)?;
+					order.unfilled_requested =
+						order.unfilled_requested.saturating_sub(quantity_quote);
+				}
+				OrderType::BUY => {
+					order.unfilled_offered = order
+						.unfilled_offered
+						.checked_sub(&quantity_quote)
+						.ok_or(Error::<T>::TradeExceedsUnfilledAmount)?;
+					order.unfilled_requested = order
+						.unfilled_requested
+						.checked_sub(&quantity_base)
+						.ok_or(Error::<T>::TradeExceedsUnfilledAmount)?;
+				}
+			}
+
+			order.order_status = if order.get_unfilled_base_amout().is_zero() {
+				OrderStatus::FullyFilled
+			} else {
+				OrderStatus::PartialFilled
+			};
+
+			Ok(())
+		}
+
+		/// Price of an order placed for the given amounts. It is rounded to the tick size
+		/// so that the order never trades at a worse price than its amounts imply.
+		pub(crate) fn order_price(
+			order_type: &OrderType,
+			order_kind: OrderKind,
+			quote_amount: BalanceOf<T>,
+			base_amount: BalanceOf<T>,
+			tick_size: FixedU128,
+		) -> Result<FixedU128, DispatchError> {
+			let rounding = match order_type {
+				OrderType::BUY => Rounding::Down,
+				OrderType::SELL => Rounding::Up,
+			};
+			let mut price = multiply_by_rational_with_rounding(
+				quote_amount.unique_saturated_into(),
+				FixedU128::DIV,
+				base_amount.unique_saturated_into(),
+				rounding,
+			)
+			.ok_or(Error::<T>::InvalidPrice)?;
+
+			// market orders don't trade at their own price
+			if order_kind == OrderKind::Limit {
+				let tick = tick_size.into_inner();
+				price = match order_type {
+					OrderType::BUY => price / tick * tick,
+					OrderType::SELL => {
+						price
+							.checked_add(tick - 1)
+							.ok_or(Error::<T>::InvalidPrice)?
+							/ tick * tick
+					}
+				};
+			}
 
-				// remove fully filled UserOrders/PairOrders
-				Self::remove_order_if_fully_filled(&taker_order)?;
-				Self::remove_order_if_fully_filled(&maker_order)?;
+			ensure!(price != 0, Error::<T>::InvalidPrice);
+			Ok(FixedU128::from_inner(price))
+		}
 
-				if taker_order.order_type == OrderType::BUY
-					&& maker_order.order_type == OrderType::SELL
-				{
-					// exchange asset
-					// for maker
-					Self::add_assert(
-						&taker_order.address,
-						taker_order.pair.0,
-						trade.quantity_base,
-					)?;
-					Self::sub_reserved_assert(
-						&taker_order.address,
-						taker_order.pair.1,
-						trade.quantity_quote,
-					)?;
+		/// Amount of the quote asset paid for `quantity_base` at `price`, rounded in favour
+		/// of the maker order.
+		pub fn quote_amount(
+			price: FixedU128,
+			quantity_base: BalanceOf<T>,
+			maker_order_type: &OrderType,
+		) -> Result<BalanceOf<T>, DispatchError> {
+			let rounding = match maker_order_type {
+				OrderType::SELL => Rounding::Up,
+				OrderType::BUY => Rounding::Down,
+			};
+			let quantity_quote = multiply_by_rational_with_rounding(
+				quantity_base.unique_saturated_into(),
+				price.into_inner(),
+				FixedU128::DIV,
+				rounding,
+			)
+			.ok_or(Error::<T>::MulOverflow)?;
+
+			BalanceOf::<T>::try_from(quantity_quote).map_err(|_| Error::<T>::MulOverflow.into())
+		}
 
-					// for taker
-					Self::add_assert(
-						&maker_order.address,
-						taker_order.pair.1,
-						trade.quantity_quote,
-					)?;
-					Self::sub_reserved_assert(
-						&maker_order.address,
-						taker_order.pair.0,
-						trade.quantity_base,
-					)?;
-				} else if taker_order.order_type == OrderType::SELL
-					&& maker_order.order_type == OrderType::BUY
-				{
-					// exchange asset
-					// for maker
-					Self::add_assert(
-						&taker_order.address,
-						taker_order.pair.1,
-						trade.quantity_quote,
-					)?;
-					Self::sub_reserved_assert(
-						&taker_order.address,
-						taker_order.pair.0,
-						trade.quantity_base,
-					)?;
-					// for taker
-					Self::add_assert(
-						&maker_order.address,
-						taker_order.pair.0,
-						trade.quantity_base,
-					)?;
-					Self::sub_reserved_assert(
-						&maker_order.address,
-						taker_order.pair.1,
-						trade.quantity_quote,
-					)?;
-				}
+		/// Largest multiple of the lot size of the base asset that `quantity_quote` pays
+		/// for at `price`.
+		fn affordable_base_amount(
+			price: FixedU128,
+			quantity_quote: BalanceOf<T>,
+			lot_size: BalanceOf<T>,
+		) -> Result<BalanceOf<T>, DispatchError> {
+			let quantity_base = multiply_by_rational_with_rounding(
+				quantity_quote.unique_saturated_into(),
+				FixedU128::DIV,
+				price.into_inner(),
+				Rounding::Down,
+			)
+			.ok_or(Error::<T>::DivOverflow)?;
+			let quantity_base =
+				BalanceOf::<T>::try_from(quantity_base).map_err(|_| Error::<T>::DivOverflow)?;
+
+			Ok(quantity_base - quantity_base % lot_size)
+		}
 
-				Self::deposit_event(Event::OrderMatched {
-					quantity_base: trade.quantity_base,
-					quantity_quote: trade.quantity_quote,
-					taker_order,
-					maker_order,
-				});
+		/// Removes a filled order from storage, its final state is kept in the
+		/// `OrderFilled` event.
+		pub fn remove_order_if_fully_filled(order: &OrderOf<T>) -> Result<(), DispatchError> {
+			if order.order_status != OrderStatus::FullyFilled {
+				return Ok(());
 			}
 
+			Orders::<T>::remove(order.counter);
+			Self::remove_order_indexes(order);
+			Self::deposit_event(Event::OrderFilled {
+				order_index: order.counter,
+				order: order.clone(),
+			});
 			Ok(())
 		}
-	}
 
-	impl<T: Config> Pallet<T> {
-		pub fn account_id() -> <T as frame_system::Config>::AccountId {
-			<T as Config>::PalletId::get().into_account_truncating()
+		/// Adds an order to the open orders of its account and pair, failing when either is
+		/// at its limit.
+		fn insert_order_indexes(order: &OrderOf<T>) -> DispatchResult {
+			UserOrderCount::<T>::try_mutate(&order.address, |count| -> DispatchResult {
+				ensure!(
+					*count < T::MaxOpenOrdersPerAccount::get(),
+					Error::<T>::TooManyOpenOrders
+				);
+				*count += 1;
+				Ok(())
+			})?;
+			PairOrderCount::<T>::try_mutate(order.pair, |count| -> DispatchResult {
+				ensure!(
+					*count < T::MaxOrdersPerPair::get(),
+					Error::<T>::TooManyPairOrders
+				);
+				*count += 1;
+				Ok(())
+			})?;
+
+			UserOrders::<T>::insert(&order.address, order.counter, ());
+			PairOrders::<T>::insert(order.pair, order.counter, ());
+			Ok(())
 		}
 
-		pub fn add_assert(
-			account: &T::AccountId,
-			asset_id: u32,
-			amount: BalanceOf<T>,
-		) -> Result<(), DispatchError> {
-			let mut info = TokenInfo::default();
-			if UserTokenInfoes::<T>::contains_key(account.clone(), asset_id) {
-				info = UserTokenInfoes::<T>::get(account.clone(), asset_id);
-				info.amount = info
-					.amount
-					.checked_add(&amount)
-					.ok_or(Error::<T>::TokenBalanceOverflow)?;
-			} else {
-				info.amount = amount;
+		/// Removes an order from the open orders of its account and pair, frees its slot in
+		/// its expiration block and ends its reward stake.
+		fn remove_order_indexes(order: &OrderOf<T>) {
+			Self::update_stake(order, Zero::zero());
+			if UserOrders::<T>::contains_key(&order.address, order.counter) {
+				UserOrders::<T>::remove(&order.address, order.counter);
+				UserOrderCount::<T>::mutate_exists(&order.address, |count| {
+					*count = count
+						.map(|count| count.saturating_sub(1))
+						.filter(|count| *count > 0);
+				});
 			}
-			UserTokenInfoes::<T>::insert(account, asset_id, info);
+			if PairOrders::<T>::contains_key(order.pair, order.counter) {
+				PairOrders::<T>::remove(order.pair, order.counter);
+				PairOrderCount::<T>::mutate_exists(order.pair, |count| {
+					*count = count
+						.map(|count| count.saturating_sub(1))
+						.filter(|count| *count > 0);
+				});
+			}
+			OrderExpiration::<T>::mutate_exists(order.expiration_block, |expiration_orders| {
+				*expiration_orders = expiration_orders
+					.take()
+					.map(|mut orders| {
+						orders.retain(|order_index| *order_index != order.counter);
+						orders
+					})
+					.filter(|orders| !orders.is_empty());
+			});
+		}
 
-			Ok(())
+		/// Authority keys from the current set that are available in the local keystore,
+		/// together with their index in the set.
+		fn local_authority_keys() -> impl Iterator<Item = (u32, T::AuthorityId)> {
+			let authorities = T::MatchAuthorities::get();
+			let mut local_keys = T::AuthorityId::all();
+			local_keys.sort();
+
+			authorities
+				.into_iter()
+				.enumerate()
+				.filter_map(move |(index, authority)| {
+					local_keys
+						.binary_search(&authority)
+						.ok()
+						.map(|location| (index as u32, local_keys[location].clone()))
+				})
 		}
 
-		pub fn sub_assert(
-			account: &T::AccountId,
-			asset_id: u32,
-			amount: BalanceOf<T>,
-		) -> Result<(), DispatchError> {
-			ensure!(
-				UserTokenInfoes::<T>::contains_key(account.clone(), asset_id),
-				Error::<T>::UserAssetNotExist
-			);
+		/// Whether the offchain worker state saved at block `number` with `hash` belongs to
+		/// the chain the worker runs on now. It doesn't after a reorg, or after a restart
+		/// when the saved block is too old to be checked.
+		fn is_on_current_chain(
+			number: T::BlockNumber,
+			hash: T::Hash,
+			block_number: T::BlockNumber,
+			block_hash: T::Hash,
+		) -> bool {
+			match number.cmp(&block_number) {
+				Ordering::Less => frame_system::Pallet::<T>::block_hash(number) == hash,
+				Ordering::Equal => block_hash == hash,
+				Ordering::Greater => false,
+			}
+		}
 
-			let mut info = UserTokenInfoes::<T>::get(account.clone(), asset_id);
-			info.amount = info
-				.amount
-				.checked_sub(&amount)
-				.ok_or(Error::<T>::NotEnoughBalance)?;
+		/// Matches an order in the offchain order books and submits the result on chain.
+		/// Returns `false` when the order is no longer open, e.g. because it was
+		/// cancelled, so there was nothing to match.
+		fn offchain_match_order(
+			order_index: u64,
+			map_match_engines: &mut MapMatchEnginesOf<T>,
+			authority_index: u32,
+			authority_key: &T::AuthorityId,
+		) -> bool {
+			let order = match Orders::<T>::get(order_index) {
+				Some(order) if Self::is_order_open(&order) => order,
+				_ => return false,
+			};
 
-			UserTokenInfoes::<T>::insert(account, asset_id, info);
+			let mut engine = match map_match_engines.get(&order.pair) {
+				Some(engine) => engine.clone(),
+				None => Self::new_match_engine(order_index),
+			};
 
-			Ok(())
-		}
+			match Self::process_order(order_index, order.clone(), &mut engine) {
+				Ok(match_result) => {
+					engine.last_process_order_id = order_index;
+					let rt = map_match_engines.try_insert(order.pair, engine);
 
-		pub fn sub_reserved_assert(
-			account: &T::AccountId,
-			asset_id: u32,
-			amount: BalanceOf<T>,
-		) -> Result<(), DispatchError> {
-			ensure!(
-				UserTokenInfoes::<T>::contains_key(account.clone(), asset_id),
-				Error::<T>::UserAssetNotExist
-			);
+					if let Err(e) = rt {
+						log::error!("Failed in  map_match_engines.try_insert {:?}", e);
+					}
 
-			let mut info = UserTokenInfoes::<T>::get(account.clone(), asset_id);
-			info.reserved = info
-				.reserved
-				.checked_sub(&amount)
-				.ok_or(Error::<T>::NotEnoughBalance)?;
+					// Orders that don't rest on the book are closed on chain even
+					// when they didn't trade.
+					let close_taker_order = !order.rests_on_book()
+						|| match_result.taker_order.order_status == OrderStatus::Rejected;
+					if !match_result.match_details.is_empty()
+						|| !match_result.self_trades.is_empty()
+						|| close_taker_order
+					{
+						match Self::offchain_unsigned_tx(
+							match_result.clone(),
+							authority_index,
+							authority_key,
+						) {
+							Ok(_) => {}
+							Err(e) => {
+								log::error!(
+									"Failed in  Self::offchain_unsigned_tx {:?} {:?}",
+									e,
+									match_result
+								);
+							}
+						}
+					}
+				}
+				Err(e) => {
+					log::error!("Failed in  Self::process_order {:?}", e);
+				}
+			}
 
-			UserTokenInfoes::<T>::insert(account, asset_id, info);
+			true
+		}
 
-			Ok(())
+		/// Brings the offchain order books in line with the chain: orders that were
+		/// cancelled, taken or expired are dropped and orders amended in place are
+		/// replaced with their amended version.
+		fn refresh_match_engines(map_match_engines: &mut MapMatchEnginesOf<T>) {
+			for (_, engine) in map_match_engines.iter_mut() {
+				for book in [&mut engine.buy_book.book, &mut engine.sell_book.book] {
+					let mut removed = Vec::new();
+					let mut amended = Vec::new();
+					for (key, order) in book.iter() {
+						match Orders::<T>::get(key.order_id) {
+							Some(on_chain_order) if Self::is_order_open(&on_chain_order) => {
+								// amended, or filled on chain by a swap
+								if on_chain_order.amount_offered != order.amount_offered
+									|| on_chain_order.unfilled_offered < order.unfilled_offered
+								{
+									amended.push((key.clone(), on_chain_order));
+								}
+							}
+							_ => removed.push(key.clone()),
+						}
+					}
+
+					for key in removed {
+						book.remove(&key);
+					}
+					for (key, on_chain_order) in amended {
+						if let Some(order) = book.get_mut(&key) {
+							*order = on_chain_order;
+						}
+					}
+				}
+			}
 		}
 
-		fn cancel_order_impl(order_index: u64) -> DispatchResult {
-			Orders::<T>::try_mutate_exists(order_index, |order| -> DispatchResult {
-				let order = order.take().ok_or(Error::<T>::InvalidOrderIndex)?;
+		fn new_match_engine(last_process_order_id: u64) -> MatchEngine<OrderOf<T>, FixedU128> {
+			MatchEngine {
+				buy_book: OrderBook {
+					order_type: OrderType::BUY,
+					book: BoundedBTreeMap::new(),
+				},
+				sell_book: OrderBook {
+					order_type: OrderType::SELL,
+					book: BoundedBTreeMap::new(),
+				},
+				market_price: Default::default(),
+				last_process_order_id,
+			}
+		}
 
-				let update_asset_id = match order.order_type {
-					OrderType::SELL => order.pair.0,
-					OrderType::BUY => order.pair.1,
-				};
+		/// Matches an order against the on-chain order book of its pair and settles
+		/// the resulting trades. Orders that are no longer open are skipped.
+		/// Returns the number of trades, including the prevented self trades.
+		pub(crate) fn match_order_on_chain(order_id: u64) -> Result<u32, DispatchError> {
+			let order = match Orders::<T>::get(order_id) {
+				Some(order) if Self::is_order_open(&order) => order,
+				_ => return Ok(0),
+			};
 
-				let mut info = UserTokenInfoes::<T>::get(order.address.clone(), update_asset_id);
-				info.amount = info
-					.amount
-					.checked_add(&order.amount_offered)
-					.ok_or(Error::<T>::TokenBalanceOverflow)?;
-				info.reserved = info
-					.reserved
-					.checked_sub(&order.amount_offered)
-					.ok_or(Error::<T>::NotEnoughBalance)?;
-				UserTokenInfoes::<T>::insert(order.address.clone(), update_asset_id, info);
-
-				UserOrders::<T>::remove(order.address, order_index);
-
-				PairOrders::<T>::try_mutate_exists(
-					order.pair,
-					|bounded_pair_orders| -> DispatchResult {
-						let pair_orders = bounded_pair_orders
-							.as_mut()
-							.ok_or(Error::<T>::PairOrderNotFound)?;
-						let rt = pair_orders.binary_search(&order_index);
-						if let Ok(rt_index) = rt {
-							pair_orders.remove(rt_index);
-						}
+			let mut engine = MatchEngines::<T>::get(order.pair)
+				.unwrap_or_else(|| Self::new_match_engine(order_id));
+			let match_result = Self::process_order(order_id, order.clone(), &mut engine)?;
+			engine.last_process_order_id = order_id;
+			MatchEngines::<T>::insert(order.pair, engine);
 
-						PairOrders::<T>::insert(order.pair, pair_orders);
-						Ok(())
-					},
-				)?;
+			Self::settle_match_result(&match_result)?;
 
-				Self::deposit_event(Event::OrderCanceled { order_index });
+			if !order.rests_on_book()
+				|| match_result.taker_order.order_status == OrderStatus::Rejected
+			{
+				Self::close_taker_order(order_id)?;
+			}
 
-				Ok(())
-			})
+			Ok((match_result.match_details.len() + match_result.self_trades.len()) as u32)
 		}
 
-		pub fn update_order_from_trade_order(order: &OrderOf<T>) -> Result<(), DispatchError> {
-			Orders::<T>::insert(order.counter, order);
-			Ok(())
-		}
+		/// Replaces an order on the on-chain order book with its amended version.
+		fn update_in_match_engine(order: &OrderOf<T>) {
+			if !T::MatchingMode::get().is_on_chain() {
+				return;
+			}
 
-		pub fn remove_order_if_fully_filled(order: &OrderOf<T>) -> Result<(), DispatchError> {
-			UserOrders::<T>::remove(&order.address, order.counter);
-
-			PairOrders::<T>::try_mutate_exists(
-				order.pair,
-				|bounded_pair_orders| -> DispatchResult {
-					let pair_orders = bounded_pair_orders
-						.as_mut()
-						.ok_or(Error::<T>::PairOrderNotFound)?;
-					let rt = pair_orders.binary_search(&order.counter);
-					if let Ok(rt_index) = rt {
-						pair_orders.remove(rt_index);
+			MatchEngines::<T>::mutate(order.pair, |engine| {
+				if let Some(engine) = engine {
+					let key = OrderBookKey {
+						order_id: order.counter,
+						price: order.price,
+					};
+					let book = match order.order_type {
+						OrderType::BUY => &mut engine.buy_book.book,
+						OrderType::SELL => &mut engine.sell_book.book,
+					};
+					if let Some(book_order) = book.get_mut(&key) {
+						*book_order = order.clone();
 					}
+				}
+			});
+		}
 
-					PairOrders::<T>::insert(order.pair, pair_orders);
-					Ok(())
-				},
-			)?;
-			Ok(())
+		/// Removes a cancelled or taken order from the on-chain order book.
+		fn remove_from_match_engine(order: &OrderOf<T>) {
+			if !T::MatchingMode::get().is_on_chain() {
+				return;
+			}
+
+			MatchEngines::<T>::mutate(order.pair, |engine| {
+				if let Some(engine) = engine {
+					let key = OrderBookKey {
+						order_id: order.counter,
+						price: order.price,
+					};
+					match order.order_type {
+						OrderType::BUY => engine.buy_book.book.remove(&key),
+						OrderType::SELL => engine.sell_book.book.remove(&key),
+					};
+				}
+			});
 		}
 
 		fn process_order(
 			order_id: u64,
 			order: OrderOf<T>,
-			engine: &mut MatchEngine<OrderOf<T>, BalanceOf<T>>,
+			engine: &mut MatchEngine<OrderOf<T>, FixedU128>,
 		) -> Result<MatchResult<BalanceOf<T>, OrderOf<T>>, DispatchError> {
-			match order.order_type {
+			// Orders of a pair that doesn't trade are matched against an empty book, they
+			// rest on the order book or are closed without trading.
+			let mut no_maker_book = BoundedBTreeMap::new();
+			let allows_trading = Self::trading_status(order.pair).allows_trading();
+			let match_result = match order.order_type {
 				OrderType::BUY => Self::match_in_orderbook(
 					order_id,
 					order,
 					engine.sell_book.order_type.clone(),
-					&mut engine.sell_book.book,
+					if allows_trading {
+						&mut engine.sell_book.book
+					} else {
+						&mut no_maker_book
+					},
 					&mut engine.buy_book.book,
 				),
 				OrderType::SELL => Self::match_in_orderbook(
 					order_id,
 					order,
 					engine.buy_book.order_type.clone(),
-					&mut engine.buy_book.book,
+					if allows_trading {
+						&mut engine.buy_book.book
+					} else {
+						&mut no_maker_book
+					},
 					&mut engine.sell_book.book,
 				),
+			}?;
+
+			if let Some(trade) = match_result.match_details.last() {
+				engine.market_price = trade.price;
 			}
+
+			Ok(match_result)
 		}
 
 		fn match_in_orderbook(
 			order_id: u64,
 			mut taker_order: OrderOf<T>,
 			maker_book_type: OrderType,
-			maker_book: &mut BoundedBTreeMap<
-				OrderBookKey<BalanceOf<T>>,
-				OrderOf<T>,
-				ConstU32<{ u32::MAX }>,
-			>,
-			another_book: &mut BoundedBTreeMap<
-				OrderBookKey<BalanceOf<T>>,
-				OrderOf<T>,
-				ConstU32<{ u32::MAX }>,
-			>,
+			maker_book: &mut BookOrders<OrderOf<T>, FixedU128>,
+			another_book: &mut BookOrders<OrderOf<T>, FixedU128>,
 		) -> Result<MatchResult<BalanceOf<T>, OrderOf<T>>, DispatchError> {
 			let mut match_result = MatchResult {
 				taker_order: taker_order.clone(),
 				match_details: vec![],
+				self_trades: vec![],
+			};
+
+			// A post-only order is rejected when it would take liquidity from the book.
+			if taker_order.time_in_force == TimeInForce::PostOnly {
+				let best_maker_order = match maker_book_type {
+					OrderType::BUY => maker_book.last_key_value(),
+					OrderType::SELL => maker_book.first_key_value(),
+				};
+				if let Some((_, maker_order)) = best_maker_order {
+					if Self::prices_cross(&taker_order, maker_order) {
+						match_result.taker_order.order_status = OrderStatus::Rejected;
+						return Ok(match_result);
+					}
+				}
+			}
+
+			// A fill-or-kill order is matched against a copy of the book first, it doesn't
+			// trade at all unless it can be filled completely.
+			if taker_order.time_in_force == TimeInForce::FillOrKill {
+				let mut dry_run_order = taker_order.clone();
+				dry_run_order.time_in_force = TimeInForce::ImmediateOrCancel;
+				let dry_run = Self::match_in_orderbook(
+					order_id,
+					dry_run_order,
+					maker_book_type.clone(),
+					&mut maker_book.clone(),
+					&mut BoundedBTreeMap::new(),
+				)?;
+				let fully_filled = dry_run.match_details.last().map_or(false, |trade| {
+					trade.taker_order.order_status == OrderStatus::FullyFilled
+				});
+				if !fully_filled {
+					return Ok(match_result);
+				}
+			}
+
+			let lot_size = PairConfigs::<T>::get(taker_order.pair).lot_size;
+			// a market order doesn't sweep the book beyond the price band of the pair
+			let price_band_limits = if taker_order.order_kind == OrderKind::Market {
+				Self::price_band_limits(taker_order.pair)
+			} else {
+				None
 			};
-			let mut taker_unfilled_quantity_requested = taker_order.amout_requested;
-			let mut taker_unfilled_quantity_offered = taker_order.amount_offered;
+			let mut taker_cancelled = false;
 			loop {
 				if maker_book.is_empty() {
 					break;
@@ -1188,143 +5792,113 @@ pub mod pallet {
 
 				let maker_order = maker_book.get_mut(&maker_order_key).unwrap();
 
-				if taker_order.order_type == OrderType::BUY && taker_order.price < maker_order.price
+				if taker_order.order_kind == OrderKind::Limit
+					&& !Self::prices_cross(&taker_order, maker_order)
 				{
 					break;
 				}
-
-				if taker_order.order_type == OrderType::SELL
-					&& taker_order.price > maker_order.price
-				{
-					break;
+				if let Some((lowest, highest)) = price_band_limits {
+					if maker_order.price < lowest || maker_order.price > highest {
+						break;
+					}
 				}
 
-				// todo match use maker quantity
-				let (matched_quantity_requested, matched_quantity_offered) =
-					match taker_unfilled_quantity_requested.cmp(&maker_order.unfilled_offered) {
-						Ordering::Greater => {
-							//taker request amout > maker offer amout
-							(maker_order.unfilled_offered, maker_order.unfilled_requested)
+				// Orders of the same account never trade against each other.
+				if maker_order.address == taker_order.address {
+					match_result.self_trades.push(SelfTrade {
+						taker_order: order_id,
+						maker_order: maker_order_key.order_id,
+						trades_before: match_result.match_details.len() as u32,
+					});
+
+					match T::SelfTradePolicy::get() {
+						SelfTradePolicy::CancelNewest => {
+							taker_cancelled = true;
+							break;
 						}
-						Ordering::Equal => {
-							//taker request amout == maker offer amout
+						SelfTradePolicy::CancelOldest => {
+							maker_book.remove(&maker_order_key);
+							continue;
+						}
+						SelfTradePolicy::DecrementAndCancel => {
+							let taker_unfilled_base = *taker_order.get_unfilled_base_amout();
+							let maker_unfilled_base = *maker_order.get_unfilled_base_amout();
+							let quantity_base = taker_unfilled_base.min(maker_unfilled_base);
 
-							if taker_order.amount_offered >= maker_order.unfilled_requested {
-								(maker_order.unfilled_offered, maker_order.unfilled_requested)
+							if quantity_base == maker_unfilled_base {
+								maker_book.remove(&maker_order_key);
 							} else {
-								// taker offer < maker request
-								match taker_order.order_type {
-									OrderType::BUY => {
-										let new_request_amout = taker_order
-											.amount_offered
-											.checked_div(&maker_order.price)
-											.ok_or(Error::<T>::DivOverflow)?;
-
-										(new_request_amout, taker_order.amount_offered)
-									}
-									OrderType::SELL => {
-										let new_request_amout = taker_order
-											.amount_offered
-											.checked_mul(&maker_order.price)
-											.ok_or(Error::<T>::MulOverflow)?;
-
-										(new_request_amout, taker_order.amount_offered)
-									}
-								}
+								Self::decrement_order(maker_order, quantity_base)?;
 							}
-						}
-						Ordering::Less => {
-							//taker request amout < maker offer amout
-							match taker_order.order_type {
-								OrderType::BUY => {
-									let new_offer_amout = taker_unfilled_quantity_requested
-										.checked_mul(&maker_order.price)
-										.ok_or(Error::<T>::MulOverflow)?;
-									(taker_unfilled_quantity_requested, new_offer_amout)
-								}
-								OrderType::SELL => {
-									let new_offer_amout = taker_unfilled_quantity_requested
-										.checked_div(&maker_order.price)
-										.ok_or(Error::<T>::DivOverflow)?;
-									(taker_unfilled_quantity_requested, new_offer_amout)
-								}
+
+							if quantity_base == taker_unfilled_base {
+								taker_cancelled = true;
+								break;
 							}
+							Self::decrement_order(&mut taker_order, quantity_base)?;
+							continue;
 						}
-					};
-
-				taker_unfilled_quantity_requested = taker_unfilled_quantity_requested
-					.checked_sub(&matched_quantity_requested)
-					.ok_or(Error::<T>::NotEnoughBalance)?;
-				taker_unfilled_quantity_offered = taker_unfilled_quantity_offered
-					.checked_sub(&matched_quantity_offered)
-					.ok_or(Error::<T>::NotEnoughBalance)?;
-
-				let maker_unfilled_quantity_offered = maker_order
-					.unfilled_offered
-					.checked_sub(&matched_quantity_requested)
-					.ok_or(Error::<T>::NotEnoughBalance)?;
-				let maker_unfilled_quantity_requested = maker_order
-					.unfilled_requested
-					.checked_sub(&matched_quantity_offered)
-					.ok_or(Error::<T>::NotEnoughBalance)?;
-
-				let (matched_quantity_base, matched_quantity_quote) =
-					if taker_order.order_type == OrderType::BUY {
-						(matched_quantity_requested, matched_quantity_offered)
-					} else {
-						(matched_quantity_offered, matched_quantity_requested)
-					};
+					}
+				}
 
-				// update maker order
-				maker_order.unfilled_offered = maker_unfilled_quantity_offered;
-				maker_order.unfilled_requested = maker_unfilled_quantity_requested;
+				// Trades are executed at the maker price for as much of the base asset as
+				// both orders have left and the buyer can pay for.
+				let mut quantity_base = (*taker_order.get_unfilled_base_amout())
+					.min(*maker_order.get_unfilled_base_amout());
+				let mut quantity_quote =
+					Self::quote_amount(maker_order.price, quantity_base, &maker_order.order_type)?;
 
-				// update taker order
-				taker_order.unfilled_requested = taker_unfilled_quantity_requested;
-				taker_order.unfilled_offered = taker_unfilled_quantity_offered;
+				let buyer_unfilled_quote = match taker_order.order_type {
+					OrderType::BUY => taker_order.unfilled_offered,
+					OrderType::SELL => maker_order.unfilled_offered,
+				};
+				if quantity_quote > buyer_unfilled_quote {
+					quantity_base = Self::affordable_base_amount(
+						maker_order.price,
+						buyer_unfilled_quote,
+						lot_size,
+					)?;
+					quantity_quote = Self::quote_amount(
+						maker_order.price,
+						quantity_base,
+						&maker_order.order_type,
+					)?;
+				}
 
-				if taker_unfilled_quantity_requested == BalanceOf::<T>::default()
-					|| taker_unfilled_quantity_offered == BalanceOf::<T>::default()
-				{
-					taker_order.order_status = OrderStatus::FullyFilled;
-				} else if taker_unfilled_quantity_requested != taker_order.amout_requested {
-					taker_order.order_status = OrderStatus::PartialFilled;
+				if quantity_base.is_zero() {
+					if taker_order.order_type == OrderType::SELL {
+						// A buy order that can't pay for a single lot any more stays on chain
+						// until it is canceled or expires.
+						maker_book.remove(&maker_order_key);
+						continue;
+					}
+					break;
 				}
 
-				if maker_unfilled_quantity_offered == BalanceOf::<T>::default() {
-					maker_order.order_status = OrderStatus::FullyFilled;
+				Self::apply_fill(maker_order, quantity_base, quantity_quote)?;
+				Self::apply_fill(&mut taker_order, quantity_base, quantity_quote)?;
 
-					match_result.match_details.push(Trade {
-						price: maker_order.price,
-						quantity_base: matched_quantity_base,
-						quantity_quote: matched_quantity_quote,
-						taker_order: taker_order.clone(),
-						maker_order: maker_order.clone(),
-					});
+				match_result.match_details.push(Trade {
+					price: maker_order.price,
+					quantity_base,
+					quantity_quote,
+					taker_order: taker_order.clone(),
+					maker_order: maker_order.clone(),
+				});
 
+				if maker_order.order_status == OrderStatus::FullyFilled {
 					// remove order from maker order book
 					maker_book.remove(&maker_order_key);
-				} else {
-					maker_order.order_status = OrderStatus::PartialFilled;
-
-					match_result.match_details.push(Trade {
-						price: maker_order.price,
-						quantity_base: matched_quantity_base,
-						quantity_quote: matched_quantity_quote,
-						taker_order: taker_order.clone(),
-						maker_order: maker_order.clone(),
-					});
 				}
 
-				if taker_unfilled_quantity_requested == BalanceOf::<T>::default()
-					|| taker_unfilled_quantity_offered == BalanceOf::<T>::default()
-				{
+				if taker_order.order_status == OrderStatus::FullyFilled {
 					break;
 				}
 			}
 
-			if taker_unfilled_quantity_requested != BalanceOf::<T>::default()
-				&& taker_unfilled_quantity_offered != BalanceOf::<T>::default()
+			if taker_order.order_status != OrderStatus::FullyFilled
+				&& taker_order.rests_on_book()
+				&& !taker_cancelled
 			{
 				// add to order book
 				let rt = another_book.try_insert(
@@ -1343,10 +5917,28 @@ pub mod pallet {
 			Ok(match_result)
 		}
 
+		/// Whether the taker order accepts the price of the maker order.
+		fn prices_cross(taker_order: &OrderOf<T>, maker_order: &OrderOf<T>) -> bool {
+			match taker_order.order_type {
+				OrderType::BUY => taker_order.price >= maker_order.price,
+				OrderType::SELL => taker_order.price <= maker_order.price,
+			}
+		}
+
 		fn offchain_unsigned_tx(
 			match_result: MatchResult<BalanceOf<T>, OrderOf<T>>,
+			authority_index: u32,
+			authority_key: &T::AuthorityId,
 		) -> Result<(), Error<T>> {
-			let call = Call::update_match_order_unsigned { match_result };
+			let signature = (&match_result, authority_index)
+				.using_encoded(|payload| authority_key.sign(&payload))
+				.ok_or(<Error<T>>::OffchainSigningError)?;
+
+			let call = Call::update_match_order_unsigned {
+				match_result,
+				authority_index,
+				signature,
+			};
 
 			// `submit_unsigned_transaction` returns a type of `Result<(), ()>`
 			//   ref: https://substrate.dev/rustdocs/v2.0.0/frame_system/offchain/struct.SubmitTransaction.html#method.submit_unsigned_transaction
@@ -1358,6 +5950,178 @@ pub mod pallet {
 	}
 }
 
+impl<T: Config> Pallet<T> {
+	/// Order book of `pair` aggregated by price, with at most `levels` prices per side.
+	pub fn order_book_depth(pair: (u32, u32), levels: u32) -> OrderBookDepth<BalanceOf<T>> {
+		let mut bids = BTreeMap::new();
+		let mut asks = BTreeMap::new();
+
+		for order in PairOrders::<T>::iter_key_prefix(pair)
+			.filter_map(Orders::<T>::get)
+			.filter(|order| order.rests_on_book())
+		{
+			let side = match order.order_type {
+				OrderType::BUY => &mut bids,
+				OrderType::SELL => &mut asks,
+			};
+			let level = side.entry(order.price).or_insert_with(|| PriceLevel {
+				price: order.price,
+				..Default::default()
+			});
+			level.base_amount = level
+				.base_amount
+				.saturating_add(*order.get_unfilled_base_amout());
+			level.order_count = level.order_count.saturating_add(1);
+		}
+
+		OrderBookDepth {
+			bids: bids.into_values().rev().take(levels as usize).collect(),
+			asks: asks.into_values().take(levels as usize).collect(),
+		}
+	}
+
+	pub fn best_bid_ask(pair: (u32, u32)) -> BestBidAsk {
+		let depth = Self::order_book_depth(pair, 1);
+
+		BestBidAsk {
+			best_bid: depth.bids.first().map(|level| level.price),
+			best_ask: depth.asks.first().map(|level| level.price),
+		}
+	}
+
+	/// Orders of `who` that are not filled, canceled or expired yet.
+	pub fn user_open_orders(
+		who: &T::AccountId,
+	) -> Vec<Order<T::AccountId, BalanceOf<T>, BlockNumberFor<T>>> {
+		UserOrders::<T>::iter_key_prefix(who)
+			.filter_map(Orders::<T>::get)
+			.collect()
+	}
+
+	pub fn user_balances(who: &T::AccountId) -> Vec<(u32, TokenInfo<BalanceOf<T>>)> {
+		UserTokenInfoes::<T>::iter_prefix(who).collect()
+	}
+
+	/// Up to `limit` orders of `pair` from `offset`, oldest first, with the number of order
+	/// indexes of the pair. All of them are read to sort them.
+	pub fn pair_orders_page(
+		pair: (u32, u32),
+		offset: u32,
+		limit: u32,
+	) -> (
+		u32,
+		Vec<Order<T::AccountId, BalanceOf<T>, BlockNumberFor<T>>>,
+	) {
+		let mut order_index_array: Vec<_> = PairOrders::<T>::iter_key_prefix(pair).collect();
+		order_index_array.sort();
+		let index_count = order_index_array.len() as u32;
+
+		let order_array = order_index_array
+			.into_iter()
+			.skip(offset as usize)
+			.take(limit as usize)
+			.filter_map(Orders::<T>::get)
+			.collect();
+		(index_count, order_array)
+	}
+
+	/// Up to `limit` open orders of `who` from `offset`, with the number of order indexes of
+	/// `who`.
+	pub fn user_orders_page(
+		who: &T::AccountId,
+		offset: u32,
+		limit: u32,
+	) -> (
+		u32,
+		Vec<Order<T::AccountId, BalanceOf<T>, BlockNumberFor<T>>>,
+	) {
+		let order_index_array: Vec<_> = UserOrders::<T>::iter_key_prefix(who).collect();
+		let index_count = order_index_array.len() as u32;
+
+		let order_array = order_index_array
+			.into_iter()
+			.skip(offset as usize)
+			.take(limit as usize)
+			.filter_map(Orders::<T>::get)
+			.collect();
+		(index_count, order_array)
+	}
+
+	/// Latest candles of a pair with at least one trade, at most `limit` of them, oldest
+	/// first. Intervals without trades have no candle.
+	pub fn candles(pair: (u32, u32), interval: u64, limit: u32) -> Vec<Candle<BalanceOf<T>>> {
+		let mut candles: Vec<_> = Candles::<T>::iter_prefix_values((pair, interval)).collect();
+		candles.sort_by_key(|candle| candle.start);
+
+		let skip = candles.len().saturating_sub(limit as usize);
+		candles.split_off(skip)
+	}
+
+	/// Time-weighted average price of the liquidity pool of a pair from the latest
+	/// observation that is at least `period` seconds old until now. `None` if the pair has
+	/// no observation that old.
+	pub fn twap(pair: (u32, u32), period: u64) -> Option<Twap> {
+		let accumulator = PriceAccumulators::<T>::get(pair)?;
+		let price = LiquidityPools::<T>::get(pair)?.price()?;
+		let now = T::UnixTime::now().as_secs();
+		let since = now.checked_sub(period)?;
+
+		let earlier = PriceObservations::<T>::iter_prefix_values(pair)
+			.filter(|observation| observation.timestamp <= since)
+			.max_by_key(|observation| observation.timestamp)?;
+		PriceObservation {
+			timestamp: now,
+			cumulative_price: accumulator.cumulative_price_at(now, price),
+		}
+		.twap_since(&earlier)
+	}
+
+	/// Trading volume of `who` in a pair over the last `VOLUME_WINDOW_DAYS` days, in the
+	/// quote asset of the pair.
+	pub fn trading_volume(who: &T::AccountId, pair: (u32, u32)) -> BalanceOf<T> {
+		TradingVolumes::<T>::get(who, pair).volume(Self::current_day())
+	}
+
+	/// Liquidity mining rewards `who` can claim by asset id, with the rewards its open
+	/// orders within the spread of their epoch earned so far.
+	pub fn pending_rewards(who: &T::AccountId) -> Vec<(u32, BalanceOf<T>)> {
+		let mut rewards: BTreeMap<u32, BalanceOf<T>> =
+			UnclaimedRewards::<T>::iter_prefix(who).collect();
+
+		let now = frame_system::Pallet::<T>::block_number();
+		for order_index in UserOrders::<T>::iter_key_prefix(who) {
+			let (stake, order) = match (
+				RewardStakes::<T>::get(order_index),
+				Orders::<T>::get(order_index),
+			) {
+				(Some(stake), Some(order)) => (stake, order),
+				_ => continue,
+			};
+			if let Some(mut epoch) = RewardEpochs::<T>::get(stake.epoch_id) {
+				// orders that left the spread forfeit their reward
+				if !Self::within_reward_spread(&order, &epoch.terms) {
+					continue;
+				}
+				epoch.update(now);
+				let reward = rewards
+					.entry(epoch.terms.asset_id)
+					.or_insert_with(Zero::zero);
+				*reward = reward.saturating_add(epoch.reward(&stake));
+			}
+		}
+
+		rewards
+			.into_iter()
+			.filter(|(_, reward)| !reward.is_zero())
+			.collect()
+	}
+
+	/// Day since the Unix epoch that trading volumes are recorded for.
+	fn current_day() -> u32 {
+		(T::UnixTime::now().as_secs() / 86_400) as u32
+	}
+}
+
 impl<T: Config> BlockNumberProvider for Pallet<T> {
 	type BlockNumber = T::BlockNumber;
 
@@ -1365,3 +6129,9 @@ impl<T: Config> BlockNumberProvider for Pallet<T> {
 		<frame_system::Pallet<T>>::block_number()
 	}
 }
+
+impl<T: Config> TwapProvider for Pallet<T> {
+	fn twap(pair: (u32, u32), period: u64) -> Option<FixedU128> {
+		Pallet::<T>::twap(pair, period).map(|twap| twap.price)
+	}
+}
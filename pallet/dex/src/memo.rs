@@ -0,0 +1,178 @@
+//! Instructions for the DEX that a transfer from another chain can carry, e.g. in the memo
+//! of an ICS-20 packet, to deposit the received tokens and trade them in the same step.
+
+use frame_support::sp_std::prelude::*;
+
+/// First part of the memos addressed to the DEX.
+pub const DEX_MEMO_PREFIX: &str = "dex";
+
+/// Longest DEX memo that is parsed, longer ones are malformed.
+pub const MAX_DEX_MEMO_LEN: usize = 128;
+
+/// What to do with the tokens of a transfer, parsed from a memo of `:` separated parts.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DepositMemo<Balance, BlockNumber> {
+	/// `dex`: deposits the tokens into the DEX balance of the receiver.
+	Deposit,
+	/// `dex:order:<asset_id>:<requested_amount>:<expiration_block>`: deposits the tokens and
+	/// offers all of them for `requested_amount` of `asset_id` in a limit order.
+	Order {
+		asset_id: u32,
+		requested_amount: Balance,
+		expiration_block: BlockNumber,
+	},
+}
+
+/// A memo addressed to the DEX that is not a valid instruction.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MalformedMemo;
+
+impl<Balance: TryFrom<u128>, BlockNumber: TryFrom<u128>> DepositMemo<Balance, BlockNumber> {
+	/// Parses a memo, memos that are not addressed to the DEX are `None`.
+	pub fn parse(memo: &[u8]) -> Result<Option<Self>, MalformedMemo> {
+		let mut parts = memo.split(|byte| *byte == b':');
+		if parts.next() != Some(DEX_MEMO_PREFIX.as_bytes()) {
+			return Ok(None);
+		}
+		if memo.len() > MAX_DEX_MEMO_LEN {
+			return Err(MalformedMemo);
+		}
+
+		let memo = match parts.next() {
+			None => DepositMemo::Deposit,
+			Some(b"order") => DepositMemo::Order {
+				asset_id: number(parts.next())?,
+				requested_amount: number(parts.next())?,
+				expiration_block: number(parts.next())?,
+			},
+			Some(_) => return Err(MalformedMemo),
+		};
+		match parts.next() {
+			None => Ok(Some(memo)),
+			Some(_) => Err(MalformedMemo),
+		}
+	}
+}
+
+/// Decimal number in a part of a memo.
+fn number<N: TryFrom<u128>>(part: Option<&[u8]>) -> Result<N, MalformedMemo> {
+	let part = part.ok_or(MalformedMemo)?;
+	// `parse` accepts a leading `+`
+	if !part.first().map_or(false, u8::is_ascii_digit) {
+		return Err(MalformedMemo);
+	}
+	let number: u128 = core::str::from_utf8(part)
+		.map_err(|_| MalformedMemo)?
+		.parse()
+		.map_err(|_| MalformedMemo)?;
+	N::try_from(number).map_err(|_| MalformedMemo)
+}
+
+/// Fields of the packet data of an ICS-20 transfer that the DEX needs, parsed from its JSON.
+/// The memo was added to the packet data after the `ibc` crate the runtimes build with, so
+/// the packet data is parsed here instead.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TransferPacketData {
+	/// Denomination of the tokens as traced on the sending chain.
+	pub denom: Vec<u8>,
+	pub amount: u128,
+	pub receiver: Vec<u8>,
+	/// Empty for packets without a memo.
+	pub memo: Vec<u8>,
+}
+
+impl TransferPacketData {
+	/// Parses packet data, a JSON object with string values. Other fields are skipped.
+	pub fn parse(data: &[u8]) -> Option<Self> {
+		let mut json = Json { data, position: 0 };
+		let mut packet_data = Self::default();
+		json.expect(b'{')?;
+		if !json.next_is(b'}') {
+			loop {
+				let key = json.string()?;
+				json.expect(b':')?;
+				let value = json.string()?;
+				match key.as_slice() {
+					b"denom" => packet_data.denom = value,
+					b"amount" => packet_data.amount = number(Some(value.as_slice())).ok()?,
+					b"receiver" => packet_data.receiver = value,
+					b"memo" => packet_data.memo = value,
+					_ => {}
+				}
+				if !json.next_is(b',') {
+					break;
+				}
+			}
+			json.expect(b'}')?;
+		}
+		json.skip_whitespace();
+		(json.position == data.len()).then_some(packet_data)
+	}
+}
+
+/// Reads JSON from the start of `data[position..]`.
+struct Json<'a> {
+	data: &'a [u8],
+	position: usize,
+}
+
+impl Json<'_> {
+	fn next_byte(&mut self) -> Option<u8> {
+		let byte = *self.data.get(self.position)?;
+		self.position += 1;
+		Some(byte)
+	}
+
+	fn skip_whitespace(&mut self) {
+		while self
+			.data
+			.get(self.position)
+			.map_or(false, u8::is_ascii_whitespace)
+		{
+			self.position += 1;
+		}
+	}
+
+	/// Skips `byte` if it comes next.
+	fn next_is(&mut self, byte: u8) -> bool {
+		self.skip_whitespace();
+		let next_is = self.data.get(self.position) == Some(&byte);
+		if next_is {
+			self.position += 1;
+		}
+		next_is
+	}
+
+	fn expect(&mut self, byte: u8) -> Option<()> {
+		self.next_is(byte).then_some(())
+	}
+
+	/// A string without its quotes and with its escape sequences replaced.
+	fn string(&mut self) -> Option<Vec<u8>> {
+		self.expect(b'"')?;
+		let mut string = Vec::new();
+		loop {
+			match self.next_byte()? {
+				b'"' => return Some(string),
+				b'\\' => match self.next_byte()? {
+					escaped @ (b'"' | b'\\' | b'/') => string.push(escaped),
+					b'b' => string.push(0x08),
+					b'f' => string.push(0x0c),
+					b'n' => string.push(b'\n'),
+					b'r' => string.push(b'\r'),
+					b't' => string.push(b'\t'),
+					b'u' => {
+						let hex = self.data.get(self.position..self.position + 4)?;
+						self.position += 4;
+						let code = u32::from_str_radix(core::str::from_utf8(hex).ok()?, 16).ok()?;
+						// surrogate pairs are not decoded, they don't occur in DEX memos
+						let character = char::from_u32(code)?;
+						string.extend_from_slice(character.encode_utf8(&mut [0; 4]).as_bytes());
+					}
+					_ => return None,
+				},
+				byte => string.push(byte),
+			}
+		}
+	}
+}
@@ -0,0 +1,284 @@
+//! Storage migrations for the DEX pallet.
+
+use super::*;
+use frame_support::{
+	pallet_prelude::*,
+	storage_alias,
+	traits::{OnRuntimeUpgrade, StorageVersion},
+};
+
+/// Moves the unversioned storage to the bounded layout of version 1:
+/// - orders get a fixed-point price, an order kind and a time in force,
+/// - filled orders, which used to stay in storage, are removed,
+/// - the orders of a pair are kept in a double map and counted,
+/// - the open orders of an account are counted,
+/// - the orders expiring in a block are capped, the rest expires in the following blocks,
+/// - the allowlisted assets are bounded, the migration doesn't run while there are more
+///   than `MaxAssets` of them.
+pub mod v1 {
+	use super::*;
+
+	/// Order layout before version 1, its price was an integer amount of quote per base.
+	#[derive(Encode, Decode, Clone, PartialEq, RuntimeDebug, TypeInfo)]
+	pub struct OldOrder<AccountId, Balance, BlockNumber> {
+		pub counter: u64,
+		pub address: AccountId,
+		pub pair: (u32, u32),
+		pub expiration_block: BlockNumber,
+		pub order_type: OrderType,
+		pub amount_offered: Balance,
+		pub amout_requested: Balance,
+		pub price: Balance,
+		pub unfilled_offered: Balance,
+		pub unfilled_requested: Balance,
+		pub order_status: OrderStatus,
+	}
+
+	pub type OldOrderOf<T> =
+		OldOrder<<T as frame_system::Config>::AccountId, BalanceOf<T>, BlockNumberFor<T>>;
+
+	#[storage_alias]
+	pub type PairOrders<T: Config> =
+		StorageMap<Pallet<T>, Blake2_128Concat, (u32, u32), Vec<u64>, ValueQuery>;
+
+	#[storage_alias]
+	pub type OrderExpiration<T: Config> =
+		StorageMap<Pallet<T>, Blake2_128Concat, BlockNumberFor<T>, Vec<u64>, ValueQuery>;
+
+	#[storage_alias]
+	pub type TokenInfoes<T: Config> = StorageValue<Pallet<T>, Vec<u32>, ValueQuery>;
+
+	impl<AccountId, Balance, BlockNumber> OldOrder<AccountId, Balance, BlockNumber> {
+		fn quote_and_base(&self) -> (&Balance, &Balance) {
+			match self.order_type {
+				OrderType::BUY => (&self.amount_offered, &self.amout_requested),
+				OrderType::SELL => (&self.amout_requested, &self.amount_offered),
+			}
+		}
+	}
+
+	impl<AccountId, Balance, BlockNumber> From<Order<AccountId, Balance, BlockNumber>>
+		for OldOrder<AccountId, Balance, BlockNumber>
+	where
+		Balance: sp_runtime::traits::UniqueSaturatedFrom<u128>,
+	{
+		/// Converts back to the layout before version 1, the price is rounded down to an integer.
+		/// Readers built against that layout, like contracts, keep decoding it.
+		fn from(order: Order<AccountId, Balance, BlockNumber>) -> Self {
+			OldOrder {
+				counter: order.counter,
+				address: order.address,
+				pair: order.pair,
+				expiration_block: order.expiration_block,
+				order_type: order.order_type,
+				amount_offered: order.amount_offered,
+				amout_requested: order.amout_requested,
+				price: Balance::unique_saturated_from(order.price.saturating_mul_int(1u128)),
+				unfilled_offered: order.unfilled_offered,
+				unfilled_requested: order.unfilled_requested,
+				order_status: order.order_status,
+			}
+		}
+	}
+
+	fn migrate_order<T: Config>(old: OldOrderOf<T>) -> OrderOf<T> {
+		let (quote_amount, base_amount) = old.quote_and_base();
+		let price = Pallet::<T>::order_price(
+			&old.order_type,
+			OrderKind::Limit,
+			*quote_amount,
+			*base_amount,
+			PairConfigs::<T>::get(old.pair).tick_size,
+		)
+		.unwrap_or_else(|_| {
+			let old_price: u128 = old.price.unique_saturated_into();
+			FixedU128::saturating_from_integer(old_price)
+		});
+
+		Order {
+			counter: old.counter,
+			address: old.address,
+			pair: old.pair,
+			expiration_block: old.expiration_block,
+			order_type: old.order_type,
+			amount_offered: old.amount_offered,
+			amout_requested: old.amout_requested,
+			price,
+			unfilled_offered: old.unfilled_offered,
+			unfilled_requested: old.unfilled_requested,
+			order_status: old.order_status,
+			order_kind: OrderKind::Limit,
+			time_in_force: TimeInForce::GoodTillExpiration,
+		}
+	}
+
+	pub struct MigrateToV1<T>(PhantomData<T>);
+
+	impl<T: Config> OnRuntimeUpgrade for MigrateToV1<T> {
+		fn on_runtime_upgrade() -> Weight {
+			if StorageVersion::get::<Pallet<T>>() != 0 {
+				log::info!("pallet-dex storage is already migrated to v1");
+				return T::DbWeight::get().reads(1);
+			}
+
+			// Nothing is migrated unless all allowlisted assets fit, dropping some would
+			// strand the deposits of their holders.
+			let token_infoes = TokenInfoes::<T>::get();
+			let token_infoes = match BoundedVec::<u32, T::MaxAssets>::try_from(token_infoes) {
+				Ok(token_infoes) => token_infoes,
+				Err(token_infoes) => {
+					log::error!(
+						"{} assets are allowlisted, more than MaxAssets, not migrating to v1",
+						token_infoes.len()
+					);
+					return T::DbWeight::get().reads(2);
+				}
+			};
+
+			let mut reads = 2u64;
+			let mut writes = 2u64;
+
+			Orders::<T>::translate::<OldOrderOf<T>, _>(|_, old| {
+				reads += 2;
+				writes += 1;
+				// filled orders were kept before, they are removed once filled now
+				(old.order_status != OrderStatus::FullyFilled).then(|| migrate_order::<T>(old))
+			});
+
+			let old_pair_orders: Vec<_> = PairOrders::<T>::drain().collect();
+			for (pair, order_indexes) in old_pair_orders {
+				reads += 1;
+				writes += 2 + order_indexes.len() as u64;
+				if order_indexes.len() > T::MaxOrdersPerPair::get() as usize {
+					log::warn!(
+						"pair {:?} has {} open orders, more than MaxOrdersPerPair",
+						pair,
+						order_indexes.len()
+					);
+				}
+				PairOrderCount::<T>::insert(pair, order_indexes.len() as u32);
+				for order_index in order_indexes {
+					crate::PairOrders::<T>::insert(pair, order_index, ());
+				}
+			}
+
+			let mut open_orders = BTreeMap::<T::AccountId, u32>::new();
+			for (who, _) in UserOrders::<T>::iter_keys() {
+				reads += 1;
+				*open_orders.entry(who).or_default() += 1;
+			}
+			for (who, count) in open_orders {
+				writes += 1;
+				UserOrderCount::<T>::insert(who, count);
+			}
+
+			// Orders over the cap of a block expire in the first following block with room.
+			let mut old_expirations: Vec<_> = OrderExpiration::<T>::drain().collect();
+			old_expirations.sort_by_key(|(block, _)| *block);
+			for (block, order_indexes) in old_expirations {
+				reads += 1;
+				writes += 1;
+				let mut block = block;
+				for order_index in order_indexes {
+					reads += 1;
+					if !Orders::<T>::contains_key(order_index) {
+						continue;
+					}
+					while crate::OrderExpiration::<T>::try_append(block, order_index).is_err() {
+						reads += 1;
+						writes += 1;
+						block = block.saturating_add(One::one());
+					}
+				}
+			}
+
+			crate::TokenInfoes::<T>::put(token_infoes);
+
+			StorageVersion::new(1).put::<Pallet<T>>();
+			log::info!("pallet-dex storage migrated to v1");
+
+			T::DbWeight::get().reads_writes(reads, writes)
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn pre_upgrade() -> Result<Vec<u8>, &'static str> {
+			ensure!(
+				TokenInfoes::<T>::get().len() <= T::MaxAssets::get() as usize,
+				"more assets are allowlisted than MaxAssets"
+			);
+			let open_order_count = frame_support::storage::migration::storage_key_iter::<
+				u64,
+				OldOrderOf<T>,
+				Blake2_128Concat,
+			>(
+				<Pallet<T> as frame_support::traits::PalletInfoAccess>::name().as_bytes(),
+				b"Orders",
+			)
+			.filter(|(_, order)| order.order_status != OrderStatus::FullyFilled)
+			.count() as u64;
+			let asset_count = TokenInfoes::<T>::get().len() as u32;
+			Ok((open_order_count, asset_count).encode())
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn post_upgrade(state: Vec<u8>) -> Result<(), &'static str> {
+			let (open_order_count, asset_count) = <(u64, u32)>::decode(&mut &state[..])
+				.map_err(|_| "pre_upgrade state doesn't decode")?;
+			ensure!(
+				StorageVersion::get::<Pallet<T>>() >= 1,
+				"storage version is below 1"
+			);
+			// every order that wasn't filled decodes in the new layout
+			ensure!(
+				Orders::<T>::iter_values().count() as u64 == open_order_count,
+				"some orders don't decode after the migration"
+			);
+			ensure!(
+				crate::TokenInfoes::<T>::get().len() as u32 == asset_count,
+				"some allowlisted assets were dropped"
+			);
+			Ok(())
+		}
+	}
+}
+
+/// Registers the pairs that were traded implicitly before version 2 as active trading
+/// pairs. A pair was in use when it has a config, fees, orders or an order book.
+pub mod v2 {
+	use super::*;
+	use frame_support::sp_std::collections::btree_set::BTreeSet;
+
+	pub struct MigrateToV2<T>(PhantomData<T>);
+
+	impl<T: Config> OnRuntimeUpgrade for MigrateToV2<T> {
+		fn on_runtime_upgrade() -> Weight {
+			if StorageVersion::get::<Pallet<T>>() != 1 {
+				log::info!("pallet-dex storage is not at v1, skipping the v2 migration");
+				return T::DbWeight::get().reads(1);
+			}
+
+			let mut reads = 1u64;
+			let mut pairs = BTreeSet::<(u32, u32)>::new();
+			for pair in PairConfigs::<T>::iter_keys()
+				.chain(PairFees::<T>::iter_keys())
+				.chain(PairOrderCount::<T>::iter_keys())
+				.chain(MatchEngines::<T>::iter_keys())
+			{
+				reads += 1;
+				pairs.insert(pair);
+			}
+
+			for pair in pairs.iter() {
+				TradingPairs::<T>::insert(pair, TradingPair::default());
+			}
+
+			StorageVersion::new(2).put::<Pallet<T>>();
+			log::info!(
+				"pallet-dex storage migrated to v2, {} trading pairs registered",
+				pairs.len()
+			);
+
+			T::DbWeight::get().reads_writes(reads, 1 + pairs.len() as u64)
+		}
+	}
+}
@@ -8,7 +8,11 @@ use frame_support::{
 	PalletId,
 };
 use sp_core::{ConstU128, ConstU32, ConstU64, H256};
-use sp_runtime::{testing::Header, traits::IdentityLookup};
+use sp_runtime::{
+	testing::{Header, UintAuthorityId},
+	traits::IdentityLookup,
+	FixedU128, Permill,
+};
 
 pub type AccountId = u128;
 pub type Balance = u128;
@@ -28,6 +32,7 @@ frame_support::construct_runtime!(
 		Balances: pallet_balances,
 		Timestamp: pallet_timestamp,
 	Assets: pallet_assets,
+	Tokens: pallet_assets::<Instance2>,
 	Dex: pallet_dex,
 	}
 );
@@ -82,6 +87,33 @@ impl pallet_assets::Config for Test {
 	type BenchmarkHelper = ();
 }
 
+// Stands in for the `orml_tokens` currencies of the runtimes.
+impl pallet_assets::Config<pallet_assets::Instance2> for Test {
+	type RuntimeEvent = RuntimeEvent;
+	type Balance = Balance;
+	type AssetId = AssetId;
+	type Currency = Balances;
+	type ForceOrigin = frame_system::EnsureRoot<AccountId>;
+	type AssetDeposit = AssetDeposit;
+	type AssetAccountDeposit = AssetAccountDeposit;
+	type MetadataDepositBase = MetadataDepositBase;
+	type MetadataDepositPerByte = MetadataDepositPerByte;
+	type ApprovalDeposit = ApprovalDeposit;
+	type StringLimit = AssetsStringLimit;
+	type Freezer = ();
+	type Extra = ();
+	type CreateOrigin = AsEnsureOriginWithArg<frame_system::EnsureSigned<AccountId>>;
+	type WeightInfo = pallet_assets::weights::SubstrateWeight<Test>;
+	type RemoveItemsLimit = ConstU32<0>;
+	type AssetIdParameter = AssetId;
+	type CallbackHandle = ();
+	#[cfg(feature = "runtime-benchmarks")]
+	type BenchmarkHelper = ();
+}
+
+/// DEX asset id the tests register the token with id 1 under.
+pub const TOKEN_ASSET_ID: AssetId = 5555;
+
 parameter_types! {
 	pub BlockWeights: frame_system::limits::BlockWeights =
 			frame_system::limits::BlockWeights::simple_max(
@@ -135,15 +167,69 @@ impl pallet_timestamp::Config for Test {
 parameter_types! {
 	pub const DexPalletId: PalletId = PalletId(*b"py/sudex");
 	pub const UnsignedPriority: BlockNumber = 1;
+	pub MatchAuthorities: Vec<UintAuthorityId> = vec![UintAuthorityId(0)];
+	pub const DexFeeDestination: AccountId = 99;
+	pub static MaxExpiringOrdersPerBlock: u32 = 100;
+	pub static MaxAssets: u32 = 10;
+	pub static MaxOrdersPerPair: u32 = 1_000;
+	pub static MaxOpenOrdersPerAccount: u32 = 100;
+	pub const MaxOrdersPerBatch: u32 = 20;
+	pub static DexMatchingMode: pallet_dex::MatchingMode = pallet_dex::MatchingMode::Offchain;
+	pub static DexSelfTradePolicy: pallet_dex::SelfTradePolicy = pallet_dex::SelfTradePolicy::CancelNewest;
+	pub static MaxConditionalOrdersPerPair: u32 = 10;
+	pub static MaxTriggeredOrdersPerBlock: u32 = 2;
+	pub static OraclePrice: Option<FixedU128> = None;
+	pub static CandleIntervals: Vec<u64> = vec![60, 3_600];
+	pub static MaxCandles: u32 = 3;
+	pub static DexPoolFee: Permill = Permill::from_parts(3_000);
+	pub static MaxSwapPathLength: u32 = 4;
+	pub static PriceObservationPeriod: u64 = 60;
+	pub static MaxPriceObservations: u32 = 4;
+	pub static MaxFeeTiers: u32 = 3;
+	pub const DexRewardSource: AccountId = 3;
+}
+
+/// Quotes `OraclePrice` for every pair.
+pub struct TestPriceOracle;
+
+impl pallet_dex::PriceOracle for TestPriceOracle {
+	fn price(_pair: (u32, u32)) -> Option<FixedU128> {
+		OraclePrice::get()
+	}
 }
 
 impl pallet_dex::Config for Test {
 	type RuntimeEvent = RuntimeEvent;
 	type PalletId = DexPalletId;
-	type Fungibles = Assets;
+	type Fungibles =
+		pallet_dex::FungiblesAdapter<Assets, Tokens, pallet_dex::RegisteredTokenIds<Test>>;
+	type TokenId = AssetId;
 	type PrivilegedOrigin = frame_system::EnsureRoot<Self::AccountId>;
 	type Currency = Balances;
 	type UnsignedPriority = UnsignedPriority;
+	type AuthorityId = UintAuthorityId;
+	type MatchAuthorities = MatchAuthorities;
+	type MatchingMode = DexMatchingMode;
+	type FeeDestination = DexFeeDestination;
+	type SelfTradePolicy = DexSelfTradePolicy;
+	type MaxExpiringOrdersPerBlock = MaxExpiringOrdersPerBlock;
+	type MaxAssets = MaxAssets;
+	type MaxOrdersPerPair = MaxOrdersPerPair;
+	type MaxOpenOrdersPerAccount = MaxOpenOrdersPerAccount;
+	type MaxOrdersPerBatch = MaxOrdersPerBatch;
+	type PriceOracle = TestPriceOracle;
+	type MaxConditionalOrdersPerPair = MaxConditionalOrdersPerPair;
+	type MaxTriggeredOrdersPerBlock = MaxTriggeredOrdersPerBlock;
+	type UnixTime = Timestamp;
+	type CandleIntervals = CandleIntervals;
+	type MaxCandles = MaxCandles;
+	type PoolFee = DexPoolFee;
+	type MaxSwapPathLength = MaxSwapPathLength;
+	type PriceObservationPeriod = PriceObservationPeriod;
+	type MaxPriceObservations = MaxPriceObservations;
+	type MaxFeeTiers = MaxFeeTiers;
+	type RewardSource = DexRewardSource;
+	type WeightInfo = ();
 }
 
 pub struct ExtBuilder;
@@ -188,15 +274,33 @@ impl ExtBuilder {
 				(999, 2, 1_000_000_000),
 				(888, 2, 1_000_000_000),
 				(777, 2, 1_000_000_000),
+				(999, 3, 1_000_000_000),
+			],
+		}
+		.assimilate_storage(&mut storage)
+		.ok();
+
+		pallet_assets::GenesisConfig::<Test, pallet_assets::Instance2> {
+			assets: vec![(1, 0, true, 1), (2, 0, true, 1)],
+			metadata: vec![
+				(1, "Wrapped BTC".into(), "WBTC".into(), 8),
+				(2, "Polkadot".into(), "DOT".into(), 10),
+			],
+			accounts: vec![
+				(1, 1, 1_000_000_000),
+				(1, 2, 1_000_000_000),
+				(2, 1, 1_000_000_000),
 			],
 		}
 		.assimilate_storage(&mut storage)
 		.ok();
 
-		<pallet_dex::GenesisConfig as frame_support::traits::GenesisBuild<Test>>::assimilate_storage(
+		<pallet_dex::GenesisConfig<Test> as frame_support::traits::GenesisBuild<Test>>::assimilate_storage(
       &pallet_dex::GenesisConfig {
         asset_ids: vec![8888, 999, 888, 777],
         native_asset_id: 8888,
+        trading_pairs: vec![(777, 888), (777, 999), (777, 8888), (888, 999), (888, 8888), (999, 8888)],
+        tokens: vec![],
       },
       &mut storage,
   )
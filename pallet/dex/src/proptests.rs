@@ -0,0 +1,406 @@
+//! Property based tests that drive random sequences of calls through the mock runtime
+//! and check the invariants of the DEX after every call.
+
+use super::*;
+use frame_support::{assert_ok, traits::Hooks, weights::Weight};
+use mock::*;
+use proptest::{collection::vec, prelude::*};
+use scale_info::prelude::collections::BTreeMap;
+use sp_core::offchain::{
+	testing::{TestOffchainExt, TestTransactionPoolExt},
+	OffchainDbExt, OffchainWorkerExt, TransactionPoolExt,
+};
+use sp_runtime::{
+	testing::UintAuthorityId, traits::ValidateUnsigned, transaction_validity::TransactionSource,
+	Permill,
+};
+use test_strategy::{proptest, Arbitrary};
+
+const BASE: u32 = 777;
+const QUOTE: u32 = 888;
+
+fn account() -> impl Strategy<Value = AccountId> {
+	1..=2 as AccountId
+}
+
+fn asset_id() -> impl Strategy<Value = u32> {
+	prop_oneof![Just(BASE), Just(QUOTE)]
+}
+
+fn order_type() -> impl Strategy<Value = OrderType> {
+	prop_oneof![Just(OrderType::BUY), Just(OrderType::SELL)]
+}
+
+fn order_kind() -> impl Strategy<Value = (OrderKind, TimeInForce)> {
+	prop_oneof![
+		Just((OrderKind::Limit, TimeInForce::GoodTillExpiration)),
+		Just((OrderKind::Limit, TimeInForce::ImmediateOrCancel)),
+		Just((OrderKind::Limit, TimeInForce::FillOrKill)),
+		Just((OrderKind::Limit, TimeInForce::PostOnly)),
+		Just((OrderKind::Market, TimeInForce::ImmediateOrCancel)),
+		Just((OrderKind::Market, TimeInForce::FillOrKill)),
+	]
+}
+
+fn matching_mode() -> impl Strategy<Value = MatchingMode> {
+	prop_oneof![
+		Just(MatchingMode::OnOrderCreation),
+		Just(MatchingMode::OnIdle),
+		Just(MatchingMode::Offchain)
+	]
+}
+
+#[derive(Arbitrary, Debug)]
+enum Action {
+	Deposit {
+		#[strategy(account())]
+		who: AccountId,
+		#[strategy(asset_id())]
+		asset_id: u32,
+		#[strategy(1..10_000 as Balance)]
+		amount: Balance,
+	},
+	Withdraw {
+		#[strategy(account())]
+		who: AccountId,
+		#[strategy(asset_id())]
+		asset_id: u32,
+		#[strategy(1..10_000 as Balance)]
+		amount: Balance,
+	},
+	#[weight(3)]
+	PlaceOrder {
+		#[strategy(account())]
+		who: AccountId,
+		#[strategy(order_type())]
+		order_type: OrderType,
+		#[strategy(order_kind())]
+		order_kind: (OrderKind, TimeInForce),
+		#[strategy(1..1_000 as Balance)]
+		offered_amount: Balance,
+		#[strategy(1..1_000 as Balance)]
+		requested_amount: Balance,
+		#[strategy(1..20u64)]
+		expires_in: BlockNumber,
+	},
+	CancelOrder {
+		#[strategy(account())]
+		who: AccountId,
+		#[strategy(0..64u64)]
+		order_index: u64,
+	},
+	TakeOrder {
+		#[strategy(account())]
+		who: AccountId,
+		#[strategy(0..64u64)]
+		order_index: u64,
+	},
+	/// The first liquidity of a pool has to be worth more than `MINIMUM_LIQUIDITY` shares.
+	AddLiquidity {
+		#[strategy(account())]
+		who: AccountId,
+		#[strategy(1..5_000 as Balance)]
+		base_amount: Balance,
+		#[strategy(1..5_000 as Balance)]
+		quote_amount: Balance,
+	},
+	RemoveLiquidity {
+		#[strategy(account())]
+		who: AccountId,
+		#[strategy(1..2_000 as Balance)]
+		shares: Balance,
+	},
+	#[weight(2)]
+	Swap {
+		#[strategy(account())]
+		who: AccountId,
+		#[strategy(order_type())]
+		side: OrderType,
+		#[strategy(1..1_000 as Balance)]
+		amount_in: Balance,
+	},
+	/// Matches the orders on idle, or with the offchain worker when orders are matched
+	/// offchain.
+	Match,
+	NextBlock,
+}
+
+impl Action {
+	/// Applies the action, calls that fail are expected and leave no trace.
+	fn apply(self) {
+		match self {
+			Action::Deposit {
+				who,
+				asset_id,
+				amount,
+			} => {
+				let _ = Dex::deposit(RuntimeOrigin::signed(who), asset_id, amount);
+			}
+			Action::Withdraw {
+				who,
+				asset_id,
+				amount,
+			} => {
+				let _ = Dex::withdraw(RuntimeOrigin::signed(who), asset_id, amount);
+			}
+			Action::PlaceOrder {
+				who,
+				order_type,
+				order_kind: (order_kind, time_in_force),
+				offered_amount,
+				requested_amount,
+				expires_in,
+			} => {
+				let _ = Dex::place_order(
+					RuntimeOrigin::signed(who),
+					BASE,
+					QUOTE,
+					offered_amount,
+					requested_amount,
+					order_type,
+					order_kind,
+					time_in_force,
+					System::block_number() + expires_in,
+				);
+			}
+			Action::CancelOrder { who, order_index } => {
+				let _ = Dex::cancel_order(RuntimeOrigin::signed(who), order_index);
+			}
+			Action::TakeOrder { who, order_index } => {
+				let _ = Dex::take_order(RuntimeOrigin::signed(who), order_index);
+			}
+			Action::AddLiquidity {
+				who,
+				base_amount,
+				quote_amount,
+			} => {
+				let _ = Dex::add_liquidity(
+					RuntimeOrigin::signed(who),
+					BASE,
+					QUOTE,
+					base_amount,
+					quote_amount,
+					0,
+				);
+			}
+			Action::RemoveLiquidity { who, shares } => {
+				let _ =
+					Dex::remove_liquidity(RuntimeOrigin::signed(who), BASE, QUOTE, shares, 0, 0);
+			}
+			Action::Swap {
+				who,
+				side,
+				amount_in,
+			} => {
+				let (asset_in, asset_out) = match side {
+					OrderType::BUY => (QUOTE, BASE),
+					OrderType::SELL => (BASE, QUOTE),
+				};
+				let _ = Dex::swap(
+					RuntimeOrigin::signed(who),
+					asset_in,
+					asset_out,
+					amount_in,
+					0,
+				);
+			}
+			Action::Match => {
+				// the offchain worker runs outside of `apply`, it needs the transaction pool
+				Dex::on_idle(System::block_number(), Weight::MAX);
+			}
+			Action::NextBlock => run_to_block(System::block_number() + 1),
+		}
+	}
+}
+
+/// The pallet account holds exactly the free and reserved DEX balances of all accounts and
+/// the reserves of the liquidity pools.
+fn assert_custody() {
+	let mut custody = BTreeMap::<u32, Balance>::new();
+	for (_, asset_id, token_info) in UserTokenInfoes::<Test>::iter() {
+		*custody.entry(asset_id).or_default() += token_info.amount + token_info.reserved;
+	}
+	for ((base_asset_id, quote_asset_id), pool) in LiquidityPools::<Test>::iter() {
+		*custody.entry(base_asset_id).or_default() += pool.base_reserve;
+		*custody.entry(quote_asset_id).or_default() += pool.quote_reserve;
+	}
+
+	for asset_id in [BASE, QUOTE] {
+		assert_eq!(
+			Assets::balance(asset_id, Dex::account_id()),
+			custody.get(&asset_id).copied().unwrap_or_default(),
+			"custody of asset {} doesn't match the DEX balances",
+			asset_id
+		);
+	}
+}
+
+/// Each account has exactly the unfilled amounts of its orders and the amounts of its
+/// conditional orders reserved.
+fn assert_reserved() {
+	let mut reserved = BTreeMap::<(AccountId, u32), Balance>::new();
+	for (_, order) in Orders::<Test>::iter() {
+		*reserved
+			.entry((order.address, order.get_offered_asset_id()))
+			.or_default() += order.unfilled_offered;
+	}
+	for (_, conditional_order) in ConditionalOrders::<Test>::iter() {
+		*reserved
+			.entry((
+				conditional_order.address,
+				conditional_order.offered_asset_id(),
+			))
+			.or_default() += conditional_order.order.offered_amount;
+	}
+
+	for (who, asset_id, token_info) in UserTokenInfoes::<Test>::iter() {
+		assert_eq!(
+			token_info.reserved,
+			reserved.remove(&(who, asset_id)).unwrap_or_default(),
+			"reserved {} of account {} doesn't match its orders",
+			asset_id,
+			who
+		);
+	}
+	assert!(
+		reserved.values().all(Zero::is_zero),
+		"orders reserve more than their accounts hold: {:?}",
+		reserved
+	);
+}
+
+/// The shares of the liquidity providers add up to the shares of their pool, and pools
+/// with shares have both reserves.
+fn assert_pool_shares() {
+	for (pair, pool) in LiquidityPools::<Test>::iter() {
+		let shares: Balance = PoolShares::<Test>::iter_prefix_values(pair).sum();
+		assert_eq!(
+			shares, pool.total_shares,
+			"shares of the pool of {:?}",
+			pair
+		);
+		assert!(
+			!pool.total_shares.is_zero()
+				&& !pool.base_reserve.is_zero()
+				&& !pool.quote_reserve.is_zero(),
+			"pool of {:?} is empty",
+			pair
+		);
+	}
+	assert!(
+		PoolShares::<Test>::iter_keys().all(|(pair, _)| LiquidityPools::<Test>::contains_key(pair))
+	);
+}
+
+/// Orders never have more left to fill than they were made for.
+fn assert_unfilled_amounts() {
+	for (order_index, order) in Orders::<Test>::iter() {
+		assert!(
+			order.unfilled_offered <= order.amount_offered,
+			"order {} has more unfilled than offered",
+			order_index
+		);
+		assert!(
+			order.unfilled_requested <= order.amout_requested,
+			"order {} has more unfilled than requested",
+			order_index
+		);
+	}
+}
+
+/// The books are sorted by price then order index and hold stored orders of their side.
+fn assert_books_sorted() {
+	for (pair, engine) in MatchEngines::<Test>::iter() {
+		for (book, order_type) in [
+			(&engine.buy_book, OrderType::BUY),
+			(&engine.sell_book, OrderType::SELL),
+		] {
+			let keys: Vec<_> = book.book.keys().collect();
+			assert!(
+				keys.windows(2).all(|keys| keys[0] < keys[1]),
+				"book of {:?} is not sorted",
+				pair
+			);
+
+			for (key, order) in book.book.iter() {
+				assert_eq!(key.order_id, order.counter);
+				assert_eq!(key.price, order.price);
+				assert_eq!(order.order_type, order_type);
+				assert_eq!(order.pair, pair);
+				assert!(
+					Orders::<Test>::contains_key(key.order_id),
+					"order {} is on the book but not stored",
+					key.order_id
+				);
+			}
+		}
+	}
+}
+
+#[proptest(ProptestConfig { cases: 64, ..ProptestConfig::default() })]
+fn dex_invariants_hold(
+	#[strategy(matching_mode())] matching_mode: MatchingMode,
+	#[strategy(vec(any::<Action>(), 1..64))] actions: Vec<Action>,
+) {
+	let mut ext = new_test_ext();
+	let (offchain, _offchain_state) = TestOffchainExt::new();
+	let (pool, pool_state) = TestTransactionPoolExt::new();
+	ext.register_extension(OffchainDbExt::new(offchain.clone()));
+	ext.register_extension(OffchainWorkerExt::new(offchain));
+	ext.register_extension(TransactionPoolExt::new(pool));
+	UintAuthorityId::set_all_keys(vec![0u64]);
+
+	// Runs the offchain worker and settles the match results it signed the way the chain
+	// would, results that went stale in the meantime are rejected.
+	let match_offchain = || {
+		Dex::offchain_worker(System::block_number());
+		for tx in core::mem::take(&mut pool_state.write().transactions) {
+			let call = match Extrinsic::decode(&mut &*tx).unwrap().call {
+				RuntimeCall::Dex(call) => call,
+				call => panic!("unexpected call {:?}", call),
+			};
+			if Dex::validate_unsigned(TransactionSource::Local, &call).is_err() {
+				continue;
+			}
+			if let Call::update_match_order_unsigned {
+				match_result,
+				authority_index,
+				signature,
+			} = call
+			{
+				let _ = Dex::update_match_order_unsigned(
+					RuntimeOrigin::none(),
+					match_result,
+					authority_index,
+					signature,
+				);
+			}
+		}
+	};
+
+	ext.execute_with(|| {
+		DexMatchingMode::set(matching_mode);
+		assert_ok!(Dex::set_pair_fee(
+			RuntimeOrigin::root(),
+			BASE,
+			QUOTE,
+			PairFee {
+				maker_fee: Permill::from_percent(1),
+				taker_fee: Permill::from_percent(2),
+			}
+		));
+
+		for action in actions {
+			match action {
+				Action::Match if matching_mode == MatchingMode::Offchain => match_offchain(),
+				action => action.apply(),
+			}
+
+			assert_custody();
+			assert_reserved();
+			assert_pool_shares();
+			assert_unfilled_amounts();
+			assert_books_sorted();
+		}
+	})
+}
@@ -1,10 +1,20 @@
 use super::{pallet::Error, *};
-use frame_support::{assert_noop, assert_ok};
+use frame_support::{assert_noop, assert_ok, traits::Hooks, weights::Weight, BoundedVec};
 use mock::*;
-use scale_info::prelude::collections::BTreeMap;
-use sp_core::offchain::{
-	testing::{TestOffchainExt, TestTransactionPoolExt},
-	OffchainDbExt, OffchainWorkerExt, TransactionPoolExt,
+use scale_info::prelude::collections::{BTreeMap, BTreeSet};
+use sp_core::{
+	offchain::{
+		testing::{TestOffchainExt, TestTransactionPoolExt},
+		OffchainDbExt, OffchainWorkerExt, TransactionPoolExt,
+	},
+	H256,
+};
+use sp_runtime::{
+	offchain::storage::StorageValueRef,
+	testing::{TestSignature, UintAuthorityId},
+	traits::{Convert, ValidateUnsigned},
+	transaction_validity::{InvalidTransaction, TransactionSource},
+	FixedU128, Permill,
 };
 
 #[test]
@@ -23,9 +33,116 @@ fn test_deposit() {
 				reserved: 0
 			}
 		);
+		System::assert_last_event(
+			Event::<Test>::Deposited {
+				who: 1,
+				asset_id: 777,
+				amount: 10,
+			}
+			.into(),
+		);
+	})
+}
+
+#[test]
+fn test_deposit_from_transfer() {
+	new_test_ext().execute_with(|| {
+		// memos of other applications are left alone
+		let memos: [&[u8]; 4] = [b"", b"swap:777", b"dexter", b"{\"dex\":{}}"];
+		for memo in memos {
+			assert_eq!(Dex::deposit_from_transfer(&1, 777, 100, memo), Ok(None));
+		}
+		assert!(!UserTokenInfoes::<Test>::contains_key(1, 777));
+
+		let memos: [&[u8]; 7] = [
+			b"dex:",
+			b"dex:withdraw",
+			b"dex:order:888:200",
+			b"dex:order:888:+200:1000",
+			b"dex:order:888:-1:1000",
+			b"dex:order:888:200:1000:1",
+			b"dex:order:4294967296:200:1000",
+		];
+		for memo in memos {
+			assert_noop!(
+				Dex::deposit_from_transfer(&1, 777, 100, memo),
+				Error::<Test>::MalformedDepositMemo
+			);
+		}
+		// the deposit is reverted with an order that can't be placed
+		assert_noop!(
+			Dex::deposit_from_transfer(&1, 777, 100, b"dex:order:888:200:0"),
+			Error::<Test>::ExpirationMustBeInFuture
+		);
+
+		assert_eq!(Dex::deposit_from_transfer(&1, 777, 100, b"dex"), Ok(None));
+		assert_eq!(
+			UserTokenInfoes::<Test>::get(1, 777),
+			TokenInfo {
+				amount: 100,
+				reserved: 0
+			}
+		);
+
+		let order_index = NextOrderIndex::<Test>::get();
+		assert_eq!(
+			Dex::deposit_from_transfer(&1, 888, 200, b"dex:order:777:100:1000"),
+			Ok(Some(order_index))
+		);
+		let order = Orders::<Test>::get(order_index).unwrap();
+		assert_eq!(order.pair, (777, 888));
+		assert_eq!(order.order_type, OrderType::BUY);
+		assert_eq!(order.amount_offered, 200);
+		assert_eq!(
+			UserTokenInfoes::<Test>::get(1, 888),
+			TokenInfo {
+				amount: 0,
+				reserved: 200
+			}
+		);
 	})
 }
 
+#[test]
+fn test_transfer_packet_data() {
+	use memo::TransferPacketData;
+
+	assert_eq!(
+		TransferPacketData::parse(
+			br#"{"amount":"100","denom":"transfer/channel-0/uatom","memo":"dex","receiver":"5Grw"}"#
+		),
+		Some(TransferPacketData {
+			denom: b"transfer/channel-0/uatom".to_vec(),
+			amount: 100,
+			receiver: b"5Grw".to_vec(),
+			memo: b"dex".to_vec(),
+		})
+	);
+	// packets without a memo, with other fields, whitespace and escaped characters
+	assert_eq!(
+		TransferPacketData::parse(
+			br#" { "amount" : "7", "denom" : "u\u0061tom\/\"x\"", "sender": "c" } "#
+		),
+		Some(TransferPacketData {
+			denom: b"uatom/\"x\"".to_vec(),
+			amount: 7,
+			..Default::default()
+		})
+	);
+
+	let malformed: [&[u8]; 6] = [
+		b"",
+		br#"{"amount":"100""#,
+		br#"{"amount":100}"#,
+		br#"{"amount":"-1"}"#,
+		br#"{"denom":"uatom",}"#,
+		br#"{"denom":"uatom"} {}"#,
+	];
+	for data in malformed {
+		assert_eq!(TransferPacketData::parse(data), None);
+	}
+}
+
 #[test]
 fn test_withdraw() {
 	new_test_ext().execute_with(|| {
@@ -124,96 +241,196 @@ fn test_withdraw_native() {
 }
 
 #[test]
-fn test_make_order() {
+fn test_trade_tokens_next_to_assets() {
 	new_test_ext().execute_with(|| {
-		assert_ok!(Dex::deposit(RuntimeOrigin::signed(1), 777, 100));
+		DexMatchingMode::set(MatchingMode::OnOrderCreation);
 
-		assert_noop!(
-			Dex::make_order(
-				RuntimeOrigin::signed(1),
-				777,
-				777,
-				200,
-				200,
-				OrderType::SELL,
-				1000
-			),
-			Error::<Test>::PairAssetIdMustNotEqual
+		assert_ok!(Dex::register_token(
+			RuntimeOrigin::root(),
+			TOKEN_ASSET_ID,
+			1
+		));
+		assert_ok!(Dex::allowlist_asset(RuntimeOrigin::root(), TOKEN_ASSET_ID));
+		assert_ok!(Dex::register_trading_pair(
+			RuntimeOrigin::root(),
+			777,
+			TOKEN_ASSET_ID,
+			0
+		));
+
+		// the token is moved by its own pallet
+		assert_ok!(Dex::deposit(RuntimeOrigin::signed(1), TOKEN_ASSET_ID, 100));
+		assert_eq!(Tokens::balance(1, 1), 1_000_000_000 - 100);
+		assert_eq!(Tokens::balance(1, Dex::account_id()), 100);
+		assert_eq!(Assets::balance(TOKEN_ASSET_ID, Dex::account_id()), 0);
+		assert_ok!(Dex::deposit(RuntimeOrigin::signed(2), 777, 5));
+
+		// 5 of 777 for 100 of the token
+		assert_ok!(Dex::make_order(
+			RuntimeOrigin::signed(1),
+			777,
+			TOKEN_ASSET_ID,
+			100,
+			5,
+			OrderType::BUY,
+			1000
+		));
+		assert_ok!(Dex::make_order(
+			RuntimeOrigin::signed(2),
+			777,
+			TOKEN_ASSET_ID,
+			5,
+			100,
+			OrderType::SELL,
+			1000
+		));
+		assert_eq!(
+			UserTokenInfoes::<Test>::get(2, TOKEN_ASSET_ID),
+			TokenInfo {
+				amount: 100,
+				reserved: 0,
+			}
 		);
 
+		assert_ok!(Dex::withdraw(RuntimeOrigin::signed(2), TOKEN_ASSET_ID, 100));
+		assert_eq!(Tokens::balance(1, 2), 1_000_000_000 + 100);
+		assert_eq!(Tokens::balance(1, Dex::account_id()), 0);
+		assert_ok!(Dex::withdraw(RuntimeOrigin::signed(1), 777, 5));
+		assert_eq!(Assets::balance(777, 1), 1_000_000_000 + 5);
+	})
+}
+
+#[test]
+fn test_register_token() {
+	new_test_ext().execute_with(|| {
+		DexMatchingMode::set(MatchingMode::OnOrderCreation);
+		let dot_asset_id = 5556;
+
 		assert_noop!(
-			Dex::make_order(
-				RuntimeOrigin::signed(1),
-				777,
-				888,
-				19, // offered
-				7,  // requested
-				OrderType::BUY,
-				1000
-			),
-			// because in requested * price == offered
-			// `price` cannot be an integer.
-			Error::<Test>::PriceDoNotMatchOfferedRequestedAmount
+			Dex::register_token(RuntimeOrigin::signed(1), dot_asset_id, 2),
+			sp_runtime::DispatchError::BadOrigin
+		);
+		// the id of an asset can't hold a token
+		assert_noop!(
+			Dex::register_token(RuntimeOrigin::root(), 777, 2),
+			Error::<Test>::AssetIdInUse
+		);
+		assert_ok!(Dex::register_token(
+			RuntimeOrigin::root(),
+			TOKEN_ASSET_ID,
+			1
+		));
+		assert_noop!(
+			Dex::register_token(RuntimeOrigin::root(), TOKEN_ASSET_ID, 2),
+			Error::<Test>::AssetIdInUse
+		);
+		assert_noop!(
+			Dex::register_token(RuntimeOrigin::root(), dot_asset_id, 1),
+			Error::<Test>::TokenAlreadyRegistered
+		);
+
+		// a currency added after genesis trades like the others
+		assert_ok!(Dex::register_token(RuntimeOrigin::root(), dot_asset_id, 2));
+		System::assert_last_event(RuntimeEvent::Dex(Event::TokenRegistered {
+			asset_id: dot_asset_id,
+			token_id: 2,
+		}));
+		assert_ok!(Dex::allowlist_asset(RuntimeOrigin::root(), dot_asset_id));
+		assert_ok!(Dex::register_trading_pair(
+			RuntimeOrigin::root(),
+			dot_asset_id,
+			777,
+			0
+		));
+		assert_ok!(Dex::deposit(RuntimeOrigin::signed(1), dot_asset_id, 50));
+		assert_eq!(Tokens::balance(2, Dex::account_id()), 50);
+		assert_ok!(Dex::deposit(RuntimeOrigin::signed(2), 777, 100));
+
+		// 50 DOT for 100 of 777
+		assert_ok!(Dex::make_order(
+			RuntimeOrigin::signed(1),
+			dot_asset_id,
+			777,
+			50,
+			100,
+			OrderType::SELL,
+			1000
+		));
+		assert_ok!(Dex::make_order(
+			RuntimeOrigin::signed(2),
+			dot_asset_id,
+			777,
+			100,
+			50,
+			OrderType::BUY,
+			1000
+		));
+		assert_eq!(
+			UserTokenInfoes::<Test>::get(2, dot_asset_id),
+			TokenInfo {
+				amount: 50,
+				reserved: 0,
+			}
 		);
 
+		// a token can't be removed while the DEX holds some of it
 		assert_noop!(
-			Dex::make_order(
-				RuntimeOrigin::signed(1),
-				777,
-				888,
-				19, // offered
-				7,  // requested
-				OrderType::SELL,
-				1000
-			),
-			// because in requested * price == offered
-			// `price` cannot be an integer.
-			Error::<Test>::PriceDoNotMatchOfferedRequestedAmount
+			Dex::deregister_token(RuntimeOrigin::root(), dot_asset_id),
+			Error::<Test>::TokenHasDeposits
 		);
+		assert_ok!(Dex::withdraw(RuntimeOrigin::signed(2), dot_asset_id, 50));
+		assert_eq!(Tokens::balance(2, 2), 50);
+		assert_eq!(Tokens::balance(2, Dex::account_id()), 0);
 
+		// nor while it pays the rewards of liquidity mining
+		assert_ok!(Tokens::mint_into(2, &Dex::rewards_account_id(), 10));
 		assert_noop!(
-			Dex::make_order(
-				RuntimeOrigin::signed(1),
-				777,
-				888,
-				7,  // offered
-				19, // requested
-				OrderType::BUY,
-				1000
-			),
-			// because in requested * price == offered
-			// `price` cannot be an integer.
-			Error::<Test>::PriceDoNotMatchOfferedRequestedAmount
+			Dex::deregister_token(RuntimeOrigin::root(), dot_asset_id),
+			Error::<Test>::TokenHasDeposits
 		);
+		assert_ok!(Tokens::transfer(
+			RuntimeOrigin::signed(Dex::rewards_account_id()),
+			2,
+			1,
+			10
+		));
 
+		assert_ok!(Dex::deregister_token(RuntimeOrigin::root(), dot_asset_id));
+		System::assert_last_event(RuntimeEvent::Dex(Event::TokenDeregistered {
+			asset_id: dot_asset_id,
+			token_id: 2,
+		}));
+		assert_eq!(RegisteredTokenIds::<Test>::convert(dot_asset_id), None);
 		assert_noop!(
-			Dex::make_order(
-				RuntimeOrigin::signed(1),
-				777,
-				888,
-				7,  // offered
-				19, // requested
-				OrderType::SELL,
-				1000
-			),
-			// because in requested * price == offered
-			// `price` cannot be an integer.
-			Error::<Test>::PriceDoNotMatchOfferedRequestedAmount
+			Dex::deregister_token(RuntimeOrigin::root(), dot_asset_id),
+			Error::<Test>::TokenNotRegistered
 		);
+	})
+}
+
+/// Open orders of a pair, oldest first.
+fn pair_orders(pair: (u32, u32)) -> Vec<u64> {
+	let mut order_indexes: Vec<_> = PairOrders::<Test>::iter_key_prefix(pair).collect();
+	order_indexes.sort();
+	order_indexes
+}
+
+#[test]
+fn test_make_order() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(Dex::deposit(RuntimeOrigin::signed(1), 777, 100));
 
 		assert_noop!(
 			Dex::make_order(
 				RuntimeOrigin::signed(1),
 				777,
-				888,
-				1,   // offered
-				200, // requested
-				OrderType::BUY,
+				777,
+				200,
+				200,
+				OrderType::SELL,
 				1000
 			),
-			// because in requested * price == offered
-			// `price` cannot be an integer.
-			Error::<Test>::PriceDoNotMatchOfferedRequestedAmount
+			Error::<Test>::PairAssetIdMustNotEqual
 		);
 
 		assert_eq!(
@@ -243,17 +460,19 @@ fn test_make_order() {
 				expiration_block: 1000,
 				amount_offered: 1,
 				amout_requested: 200,
-				price: 200,
+				price: FixedU128::from_u32(200),
 				order_type: OrderType::SELL,
 				unfilled_offered: 1,
 				unfilled_requested: 200,
 				order_status: OrderStatus::Pending,
+				order_kind: OrderKind::Limit,
+				time_in_force: TimeInForce::GoodTillExpiration,
 			})
 		);
 
 		assert_eq!(UserOrders::<Test>::get(1, 0), ());
 
-		assert_eq!(PairOrders::<Test>::get((777, 888)), vec![0]);
+		assert_eq!(pair_orders((777, 888)), vec![0]);
 
 		assert_eq!(
 			UserTokenInfoes::<Test>::get(1, 777),
@@ -306,17 +525,19 @@ fn test_make_order_asset_id_1_gt_asset_id_2() {
 				expiration_block: 1000,
 				amount_offered: 200,
 				amout_requested: 1,
-				price: 200,
+				price: FixedU128::from_u32(200),
 				order_type: OrderType::BUY,
 				unfilled_offered: 200,
 				unfilled_requested: 1,
 				order_status: OrderStatus::Pending,
+				order_kind: OrderKind::Limit,
+				time_in_force: TimeInForce::GoodTillExpiration,
 			})
 		);
 
 		assert_eq!(UserOrders::<Test>::get(1, 0), ());
 
-		assert_eq!(PairOrders::<Test>::get((777, 888)), vec![0]);
+		assert_eq!(pair_orders((777, 888)), vec![0]);
 
 		assert_eq!(
 			UserTokenInfoes::<Test>::get(1, 888),
@@ -356,15 +577,17 @@ fn test_cancel_order() {
 				expiration_block: 1000,
 				amount_offered: 1,
 				amout_requested: 200,
-				price: 200,
+				price: FixedU128::from_u32(200),
 				order_type: OrderType::SELL,
 				unfilled_offered: 1,
 				unfilled_requested: 200,
 				order_status: OrderStatus::Pending,
+				order_kind: OrderKind::Limit,
+				time_in_force: TimeInForce::GoodTillExpiration,
 			})
 		);
 		assert_eq!(UserOrders::<Test>::contains_key(1, 0), true);
-		assert_eq!(PairOrders::<Test>::get((777, 888)), vec![0]);
+		assert_eq!(pair_orders((777, 888)), vec![0]);
 
 		assert_noop!(
 			Dex::cancel_order(RuntimeOrigin::signed(2), 0),
@@ -373,9 +596,16 @@ fn test_cancel_order() {
 
 		assert_ok!(Dex::cancel_order(RuntimeOrigin::signed(1), 0));
 
+		System::assert_last_event(
+			Event::<Test>::OrderCanceled {
+				order_index: 0,
+				who: 1,
+			}
+			.into(),
+		);
 		assert_eq!(Orders::<Test>::get(0), None);
 		assert_eq!(UserOrders::<Test>::contains_key(1, 1), false);
-		assert_eq!(PairOrders::<Test>::get((777, 888)), vec![]);
+		assert_eq!(pair_orders((777, 888)), vec![]);
 	})
 }
 
@@ -613,8 +843,8 @@ fn test_make_cancel_take_order_buy() {
 		assert_eq!(UserOrders::<Test>::contains_key(1, 0), true);
 		assert_eq!(UserOrders::<Test>::contains_key(1, 1), true);
 		assert_eq!(UserOrders::<Test>::contains_key(1, 2), true);
-		assert_eq!(PairOrders::<Test>::get((777, 888)), vec![0, 1]);
-		assert_eq!(PairOrders::<Test>::get((888, 999)), vec![2]);
+		assert_eq!(pair_orders((777, 888)), vec![0, 1]);
+		assert_eq!(pair_orders((888, 999)), vec![2]);
 
 		assert_ok!(Dex::cancel_order(RuntimeOrigin::signed(1), 1));
 		assert_ok!(Dex::take_order(RuntimeOrigin::signed(2), 0));
@@ -625,8 +855,98 @@ fn test_make_cancel_take_order_buy() {
 		assert_eq!(UserOrders::<Test>::contains_key(1, 0), false);
 		assert_eq!(UserOrders::<Test>::contains_key(1, 1), false);
 		assert_eq!(UserOrders::<Test>::contains_key(1, 2), true);
-		assert_eq!(PairOrders::<Test>::get((777, 888)), vec![]);
-		assert_eq!(PairOrders::<Test>::get((888, 999)), vec![2]);
+		assert_eq!(pair_orders((777, 888)), vec![]);
+		assert_eq!(pair_orders((888, 999)), vec![2]);
+	})
+}
+
+#[test]
+fn test_take_order_takes_only_unfilled_amounts() {
+	new_test_ext().execute_with(|| {
+		DexMatchingMode::set(MatchingMode::OnOrderCreation);
+
+		assert_ok!(Dex::deposit(RuntimeOrigin::signed(1), 777, 100));
+		assert_ok!(Dex::deposit(RuntimeOrigin::signed(2), 888, 1000));
+
+		// maker sells 10 base at 20 and 4 of them are bought right away
+		assert_ok!(Dex::make_order(
+			RuntimeOrigin::signed(1),
+			777,
+			888,
+			10,
+			200,
+			OrderType::SELL,
+			1000
+		));
+		assert_ok!(Dex::make_order(
+			RuntimeOrigin::signed(2),
+			777,
+			888,
+			80,
+			4,
+			OrderType::BUY,
+			1000
+		));
+		assert_eq!(
+			Orders::<Test>::get(0).unwrap().order_status,
+			OrderStatus::PartialFilled
+		);
+		assert!(!Orders::<Test>::contains_key(1));
+
+		// a filled order is gone
+		assert_noop!(
+			Dex::take_order(RuntimeOrigin::signed(2), 1),
+			Error::<Test>::InvalidOrderIndex
+		);
+
+		// the rest of the partially filled order is taken, not its full size
+		assert_ok!(Dex::take_order(RuntimeOrigin::signed(2), 0));
+		System::assert_last_event(
+			Event::<Test>::OrderTaken {
+				trade_id: 1,
+				order_index: 0,
+				pair: (777, 888),
+				side: OrderType::BUY,
+				price: FixedU128::from_u32(20),
+				quantity_base: 6,
+				quantity_quote: 120,
+				maker: 1,
+				taker: 2,
+				maker_fee: 0,
+				taker_fee: 0,
+			}
+			.into(),
+		);
+		assert!(Orders::<Test>::get(0).is_none());
+
+		assert_eq!(
+			UserTokenInfoes::<Test>::get(1, 777),
+			TokenInfo {
+				amount: 90,
+				reserved: 0,
+			}
+		);
+		assert_eq!(
+			UserTokenInfoes::<Test>::get(1, 888),
+			TokenInfo {
+				amount: 200,
+				reserved: 0,
+			}
+		);
+		assert_eq!(
+			UserTokenInfoes::<Test>::get(2, 777),
+			TokenInfo {
+				amount: 10,
+				reserved: 0,
+			}
+		);
+		assert_eq!(
+			UserTokenInfoes::<Test>::get(2, 888),
+			TokenInfo {
+				amount: 800,
+				reserved: 0,
+			}
+		);
 	})
 }
 
@@ -647,7 +967,7 @@ fn test_expiration_works_as_expected() {
 
 		assert_eq!(Orders::<Test>::contains_key(0), true);
 		assert_eq!(UserOrders::<Test>::contains_key(1, 0), true);
-		assert_eq!(PairOrders::<Test>::get((777, 888)), vec![0]);
+		assert_eq!(pair_orders((777, 888)), vec![0]);
 		assert_eq!(
 			Orders::<Test>::get(0),
 			Some(Order {
@@ -657,11 +977,13 @@ fn test_expiration_works_as_expected() {
 				expiration_block: 10,
 				amount_offered: 100,
 				amout_requested: 1,
-				price: 100,
+				price: FixedU128::from_u32(100),
 				order_type: OrderType::BUY,
 				unfilled_offered: 100,
 				unfilled_requested: 1,
 				order_status: OrderStatus::Pending,
+				order_kind: OrderKind::Limit,
+				time_in_force: TimeInForce::GoodTillExpiration,
 			}),
 		);
 		assert_eq!(OrderExpiration::<Test>::get(10), vec![0]);
@@ -670,51 +992,213 @@ fn test_expiration_works_as_expected() {
 
 		assert!(!Orders::<Test>::contains_key(0));
 		assert!(!UserOrders::<Test>::contains_key(1, 0));
-		assert_eq!(PairOrders::<Test>::get((777, 888)), vec![]);
+		assert_eq!(pair_orders((777, 888)), vec![]);
 		assert_eq!(OrderExpiration::<Test>::get(10), vec![]);
 	});
 }
 
 #[test]
-fn fail_on_invalid_expiry() {
+fn test_order_limits() {
 	new_test_ext().execute_with(|| {
-		assert_ok!(Dex::deposit(RuntimeOrigin::signed(1), 888, 200));
-		run_to_block(5);
-
-		assert_noop!(
-			Dex::make_order(
-				RuntimeOrigin::signed(1),
-				777,
-				888,
-				100,
-				1,
-				OrderType::BUY,
-				3
-			),
-			Error::<Test>::ExpirationMustBeInFuture
-		);
-		assert_noop!(
+		MaxExpiringOrdersPerBlock::set(2);
+		MaxOpenOrdersPerAccount::set(3);
+		MaxOrdersPerPair::set(4);
+		assert_ok!(Dex::deposit(RuntimeOrigin::signed(1), 888, 500));
+		assert_ok!(Dex::deposit(RuntimeOrigin::signed(2), 888, 500));
+		let make_order = |who: AccountId, expiration_block: BlockNumber| {
 			Dex::make_order(
-				RuntimeOrigin::signed(1),
+				RuntimeOrigin::signed(who),
 				777,
 				888,
 				100,
 				1,
 				OrderType::BUY,
-				5
-			),
-			Error::<Test>::ExpirationMustBeInFuture
-		);
+				expiration_block,
+			)
+		};
 
-		assert_ok!(Dex::make_order(
-			RuntimeOrigin::signed(1),
-			777,
-			888,
-			100,
-			1,
-			OrderType::BUY,
-			6
-		));
+		assert_ok!(make_order(1, 10));
+		assert_ok!(make_order(1, 10));
+		assert_noop!(make_order(1, 10), Error::<Test>::TooManyExpiringOrders);
+		assert_ok!(make_order(1, 11));
+		assert_noop!(make_order(1, 12), Error::<Test>::TooManyOpenOrders);
+		assert_ok!(make_order(2, 12));
+		assert_noop!(make_order(2, 12), Error::<Test>::TooManyPairOrders);
+		assert_eq!(UserOrderCount::<Test>::get(1), 3);
+		assert_eq!(PairOrderCount::<Test>::get((777, 888)), 4);
+
+		// canceled and expired orders free their slots
+		assert_ok!(Dex::cancel_order(RuntimeOrigin::signed(1), 0));
+		assert_eq!(UserOrderCount::<Test>::get(1), 2);
+		assert_eq!(PairOrderCount::<Test>::get((777, 888)), 3);
+		assert_ok!(make_order(1, 12));
+
+		run_to_block(10);
+		assert_eq!(pair_orders((777, 888)), vec![2, 3, 4]);
+		assert_eq!(UserOrderCount::<Test>::get(1), 2);
+		assert_eq!(PairOrderCount::<Test>::get((777, 888)), 3);
+
+		run_to_block(12);
+		assert_eq!(pair_orders((777, 888)), vec![]);
+		assert!(!UserOrderCount::<Test>::contains_key(1));
+		assert!(!PairOrderCount::<Test>::contains_key((777, 888)));
+	});
+}
+
+#[test]
+fn test_allowlist_asset_limit() {
+	new_test_ext().execute_with(|| {
+		MaxAssets::set(TokenInfoes::<Test>::get().len() as u32 + 1);
+
+		assert_ok!(Dex::allowlist_asset(RuntimeOrigin::root(), 1));
+		assert_noop!(
+			Dex::allowlist_asset(RuntimeOrigin::root(), 2),
+			Error::<Test>::TooManyAssets
+		);
+	});
+}
+
+#[test]
+fn test_migration_to_v1() {
+	use frame_support::traits::{OnRuntimeUpgrade, StorageVersion};
+	use migration::v1;
+
+	new_test_ext().execute_with(|| {
+		MaxExpiringOrdersPerBlock::set(2);
+		StorageVersion::new(0).put::<Dex>();
+
+		for (order_index, address) in [(0, 1), (1, 1), (2, 2)] {
+			let old_order = v1::OldOrder {
+				counter: order_index,
+				address,
+				pair: (777, 888),
+				expiration_block: 10,
+				order_type: OrderType::SELL,
+				amount_offered: 10,
+				amout_requested: 25,
+				price: 2,
+				unfilled_offered: 10,
+				unfilled_requested: 25,
+				order_status: OrderStatus::Pending,
+			};
+			frame_support::storage::unhashed::put(
+				&Orders::<Test>::hashed_key_for(order_index),
+				&old_order,
+			);
+			UserOrders::<Test>::insert(address, order_index, ());
+			UserTokenInfoes::<Test>::mutate(address, 777, |info| info.reserved += 10);
+		}
+		// filled orders stayed in storage, only their indexes were removed
+		let filled_order = v1::OldOrder {
+			counter: 3,
+			address: 2,
+			pair: (777, 888),
+			expiration_block: 10,
+			order_type: OrderType::SELL,
+			amount_offered: 10,
+			amout_requested: 25,
+			price: 2,
+			unfilled_offered: 0,
+			unfilled_requested: 0,
+			order_status: OrderStatus::FullyFilled,
+		};
+		frame_support::storage::unhashed::put(&Orders::<Test>::hashed_key_for(3), &filled_order);
+		v1::PairOrders::<Test>::insert((777, 888), vec![0, 1, 2]);
+		v1::OrderExpiration::<Test>::insert(10, vec![0, 1, 3, 2]);
+
+		v1::MigrateToV1::<Test>::on_runtime_upgrade();
+
+		assert_eq!(StorageVersion::get::<Dex>(), 1);
+		assert!(!Orders::<Test>::contains_key(3));
+		let order = Orders::<Test>::get(0).unwrap();
+		assert_eq!(order.price, FixedU128::from_rational(5, 2));
+		assert_eq!(order.order_kind, OrderKind::Limit);
+		assert_eq!(order.time_in_force, TimeInForce::GoodTillExpiration);
+		// the old layout is still readable, with the price rounded down
+		assert_eq!(v1::OldOrder::from(order).price, 2);
+		assert_eq!(pair_orders((777, 888)), vec![0, 1, 2]);
+		assert_eq!(PairOrderCount::<Test>::get((777, 888)), 3);
+		assert_eq!(UserOrderCount::<Test>::get(1), 2);
+		assert_eq!(UserOrderCount::<Test>::get(2), 1);
+		// the order over the cap of block 10 expires in block 11
+		assert_eq!(OrderExpiration::<Test>::get(10), vec![0, 1]);
+		assert_eq!(OrderExpiration::<Test>::get(11), vec![2]);
+
+		run_to_block(11);
+		assert_eq!(pair_orders((777, 888)), vec![]);
+		assert_eq!(UserOrderCount::<Test>::get(1), 0);
+		assert_eq!(
+			UserTokenInfoes::<Test>::get(1, 777),
+			TokenInfo {
+				amount: 20,
+				reserved: 0,
+			}
+		);
+	});
+}
+
+#[test]
+fn test_migration_to_v1_keeps_all_assets() {
+	use frame_support::traits::{OnRuntimeUpgrade, StorageVersion};
+	use migration::v1;
+
+	new_test_ext().execute_with(|| {
+		StorageVersion::new(0).put::<Dex>();
+		let assets: Vec<u32> = (1..=MaxAssets::get() + 1).collect();
+		v1::TokenInfoes::<Test>::put(assets.clone());
+
+		// the storage isn't migrated rather than dropping an asset
+		v1::MigrateToV1::<Test>::on_runtime_upgrade();
+		assert_eq!(StorageVersion::get::<Dex>(), 0);
+		assert_eq!(v1::TokenInfoes::<Test>::get(), assets);
+
+		MaxAssets::set(assets.len() as u32);
+		v1::MigrateToV1::<Test>::on_runtime_upgrade();
+		assert_eq!(StorageVersion::get::<Dex>(), 1);
+		assert_eq!(TokenInfoes::<Test>::get().into_inner(), assets);
+	});
+}
+
+#[test]
+fn fail_on_invalid_expiry() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(Dex::deposit(RuntimeOrigin::signed(1), 888, 200));
+		run_to_block(5);
+
+		assert_noop!(
+			Dex::make_order(
+				RuntimeOrigin::signed(1),
+				777,
+				888,
+				100,
+				1,
+				OrderType::BUY,
+				3
+			),
+			Error::<Test>::ExpirationMustBeInFuture
+		);
+		assert_noop!(
+			Dex::make_order(
+				RuntimeOrigin::signed(1),
+				777,
+				888,
+				100,
+				1,
+				OrderType::BUY,
+				5
+			),
+			Error::<Test>::ExpirationMustBeInFuture
+		);
+
+		assert_ok!(Dex::make_order(
+			RuntimeOrigin::signed(1),
+			777,
+			888,
+			100,
+			1,
+			OrderType::BUY,
+			6
+		));
 	});
 }
 
@@ -766,8 +1250,10 @@ fn test_offchain_worker_order_matching() {
 	ext.register_extension(OffchainWorkerExt::new(offchain));
 	ext.register_extension(TransactionPoolExt::new(pool));
 
+	UintAuthorityId::set_all_keys(vec![0u64]);
+
 	ext.execute_with(|| {
-		assert_ok!(Dex::deposit(RuntimeOrigin::signed(1), 777, 1_000_000_000));
+		assert_ok!(Dex::deposit(RuntimeOrigin::signed(2), 777, 1_000_000_000));
 		assert_ok!(Dex::deposit(RuntimeOrigin::signed(1), 888, 1_000_000_000));
 
 		let block = 1;
@@ -784,7 +1270,7 @@ fn test_offchain_worker_order_matching() {
 		));
 
 		assert_ok!(Dex::make_order(
-			RuntimeOrigin::signed(1),
+			RuntimeOrigin::signed(2),
 			777,
 			888,
 			2,
@@ -816,7 +1302,7 @@ fn test_offchain_worker_order_matching() {
 		));
 
 		assert_ok!(Dex::make_order(
-			RuntimeOrigin::signed(1),
+			RuntimeOrigin::signed(2),
 			777,
 			888,
 			3,
@@ -828,7 +1314,7 @@ fn test_offchain_worker_order_matching() {
 		Dex::offchain_worker(block);
 
 		assert_ok!(Dex::make_order(
-			RuntimeOrigin::signed(1),
+			RuntimeOrigin::signed(2),
 			777,
 			888,
 			6,
@@ -869,7 +1355,7 @@ fn test_offchain_worker_order_matching() {
 		));
 
 		assert_ok!(Dex::make_order(
-			RuntimeOrigin::signed(1),
+			RuntimeOrigin::signed(2),
 			777,
 			888,
 			2,
@@ -879,7 +1365,7 @@ fn test_offchain_worker_order_matching() {
 		));
 
 		assert_ok!(Dex::make_order(
-			RuntimeOrigin::signed(1),
+			RuntimeOrigin::signed(2),
 			777,
 			888,
 			5,
@@ -910,8 +1396,23 @@ fn test_offchain_worker_order_matching() {
 
 		for tx in txs {
 			match tx.call {
-				RuntimeCall::Dex(crate::Call::update_match_order_unsigned { match_result: m }) => {
-					let _ = Dex::update_match_order_unsigned(RuntimeOrigin::none(), m);
+				RuntimeCall::Dex(crate::Call::update_match_order_unsigned {
+					match_result,
+					authority_index,
+					signature,
+				}) => {
+					let call = crate::Call::update_match_order_unsigned {
+						match_result: match_result.clone(),
+						authority_index,
+						signature: signature.clone(),
+					};
+					assert_ok!(Dex::validate_unsigned(TransactionSource::Local, &call));
+					assert_ok!(Dex::update_match_order_unsigned(
+						RuntimeOrigin::none(),
+						match_result,
+						authority_index,
+						signature
+					));
 				}
 				_ => {
 					assert_eq!(2, 3);
@@ -957,14 +1458,4060 @@ fn test_offchain_worker_order_matching() {
 		}
 
 		assert_eq!(sell_order_book.len(), 3);
-		assert_eq!(sell_order_book.get(&208802).unwrap(), &(3, 626406));
-		assert_eq!(sell_order_book.get(&208760).unwrap(), &(6, 1252560));
-		assert_eq!(sell_order_book.get(&208655).unwrap(), &(4, 834620));
+		assert_eq!(
+			sell_order_book.get(&FixedU128::from_u32(208802)).unwrap(),
+			&(3, 626406)
+		);
+		assert_eq!(
+			sell_order_book.get(&FixedU128::from_u32(208760)).unwrap(),
+			&(6, 1252560)
+		);
+		assert_eq!(
+			sell_order_book.get(&FixedU128::from_u32(208655)).unwrap(),
+			&(4, 834620)
+		);
 
 		assert_eq!(buy_order_book.len(), 4);
-		assert_eq!(buy_order_book.get(&208600).unwrap(), &(625800, 3));
-		assert_eq!(buy_order_book.get(&208501).unwrap(), &(1042505, 5));
-		assert_eq!(buy_order_book.get(&208234).unwrap(), &(208234, 1));
-		assert_eq!(buy_order_book.get(&208111).unwrap(), &(1456777, 7));
+		assert_eq!(
+			buy_order_book.get(&FixedU128::from_u32(208600)).unwrap(),
+			&(625800, 3)
+		);
+		assert_eq!(
+			buy_order_book.get(&FixedU128::from_u32(208501)).unwrap(),
+			&(1042505, 5)
+		);
+		assert_eq!(
+			buy_order_book.get(&FixedU128::from_u32(208234)).unwrap(),
+			&(208234, 1)
+		);
+		assert_eq!(
+			buy_order_book.get(&FixedU128::from_u32(208111)).unwrap(),
+			&(1456777, 7)
+		);
+	})
+}
+
+/// A stored order as the matching engine leaves it after a trade.
+fn filled_order(
+	order_index: u64,
+	quantity_base: Balance,
+	quantity_quote: Balance,
+) -> Order<AccountId, Balance, BlockNumber> {
+	let mut order = Orders::<Test>::get(order_index).unwrap();
+	let (offered, requested) = match order.order_type {
+		OrderType::SELL => (quantity_base, quantity_quote),
+		OrderType::BUY => (quantity_quote, quantity_base),
+	};
+	order.unfilled_offered = order.unfilled_offered.saturating_sub(offered);
+	order.unfilled_requested = order.unfilled_requested.saturating_sub(requested);
+	order
+}
+
+fn make_trade(
+	taker_index: u64,
+	maker_index: u64,
+	price: u32,
+	quantity_base: Balance,
+	quantity_quote: Balance,
+) -> Trade<Balance, Order<AccountId, Balance, BlockNumber>> {
+	Trade {
+		price: FixedU128::from_u32(price),
+		quantity_base,
+		quantity_quote,
+		taker_order: filled_order(taker_index, quantity_base, quantity_quote),
+		maker_order: filled_order(maker_index, quantity_base, quantity_quote),
+	}
+}
+
+fn signed_match(
+	trades: Vec<Trade<Balance, Order<AccountId, Balance, BlockNumber>>>,
+) -> (
+	MatchResult<Balance, Order<AccountId, Balance, BlockNumber>>,
+	TestSignature,
+) {
+	let match_result = MatchResult {
+		taker_order: Orders::<Test>::get(trades[0].taker_order.counter).unwrap(),
+		match_details: trades,
+		self_trades: vec![],
+	};
+	let signature = (&match_result, 0u32)
+		.using_encoded(|payload| UintAuthorityId(0).sign(&payload))
+		.unwrap();
+	(match_result, signature)
+}
+
+#[test]
+fn test_update_match_order_settles_verified_trade() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(Dex::deposit(RuntimeOrigin::signed(1), 777, 100));
+		assert_ok!(Dex::deposit(RuntimeOrigin::signed(2), 888, 1000));
+
+		// maker sells 10 base at 20
+		assert_ok!(Dex::make_order(
+			RuntimeOrigin::signed(1),
+			777,
+			888,
+			10,
+			200,
+			OrderType::SELL,
+			1000
+		));
+		// taker buys 4 base at 25
+		assert_ok!(Dex::make_order(
+			RuntimeOrigin::signed(2),
+			777,
+			888,
+			100,
+			4,
+			OrderType::BUY,
+			1000
+		));
+
+		let (match_result, signature) = signed_match(vec![make_trade(1, 0, 20, 4, 80)]);
+		assert_ok!(Dex::update_match_order_unsigned(
+			RuntimeOrigin::none(),
+			match_result,
+			0,
+			signature
+		));
+
+		let maker_order = Orders::<Test>::get(0).unwrap();
+		assert_eq!(maker_order.unfilled_offered, 6);
+		assert_eq!(maker_order.unfilled_requested, 120);
+		assert_eq!(maker_order.order_status, OrderStatus::PartialFilled);
+
+		// the filled taker order is removed, its final state is kept in an event
+		assert!(!Orders::<Test>::contains_key(1));
+		let taker_order = System::events()
+			.into_iter()
+			.find_map(|record| match record.event {
+				RuntimeEvent::Dex(Event::OrderFilled {
+					order_index: 1,
+					order,
+				}) => Some(order),
+				_ => None,
+			})
+			.unwrap();
+		assert_eq!(taker_order.unfilled_offered, 0);
+		assert_eq!(taker_order.unfilled_requested, 0);
+		assert_eq!(taker_order.order_status, OrderStatus::FullyFilled);
+
+		assert_eq!(pair_orders((777, 888)), vec![0]);
+		assert!(!UserOrders::<Test>::contains_key(2, 1));
+
+		assert_eq!(
+			UserTokenInfoes::<Test>::get(1, 777),
+			TokenInfo {
+				amount: 90,
+				reserved: 6,
+			}
+		);
+		assert_eq!(
+			UserTokenInfoes::<Test>::get(1, 888),
+			TokenInfo {
+				amount: 80,
+				reserved: 0,
+			}
+		);
+		assert_eq!(
+			UserTokenInfoes::<Test>::get(2, 777),
+			TokenInfo {
+				amount: 4,
+				reserved: 0,
+			}
+		);
+		// 20 of the reserved quote are released because the taker got a better price
+		assert_eq!(
+			UserTokenInfoes::<Test>::get(2, 888),
+			TokenInfo {
+				amount: 920,
+				reserved: 0,
+			}
+		);
+	})
+}
+
+#[test]
+fn test_update_match_order_rejects_fabricated_trades() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(Dex::deposit(RuntimeOrigin::signed(1), 777, 100));
+		assert_ok!(Dex::deposit(RuntimeOrigin::signed(2), 888, 1000));
+
+		assert_ok!(Dex::make_order(
+			RuntimeOrigin::signed(1),
+			777,
+			888,
+			10,
+			200,
+			OrderType::SELL,
+			1000
+		));
+		assert_ok!(Dex::make_order(
+			RuntimeOrigin::signed(2),
+			777,
+			888,
+			60,
+			4,
+			OrderType::BUY,
+			1000
+		));
+		assert_ok!(Dex::make_order(
+			RuntimeOrigin::signed(2),
+			777,
+			888,
+			500,
+			20,
+			OrderType::BUY,
+			1000
+		));
+
+		// buy price 15 is below the sell price 20
+		let (match_result, signature) = signed_match(vec![make_trade(1, 0, 20, 3, 60)]);
+		assert_noop!(
+			Dex::update_match_order_unsigned(RuntimeOrigin::none(), match_result, 0, signature),
+			Error::<Test>::PricesDoNotCross
+		);
+
+		// trade is not executed at the maker price
+		let (match_result, signature) = signed_match(vec![make_trade(2, 0, 1, 10, 10)]);
+		assert_noop!(
+			Dex::update_match_order_unsigned(RuntimeOrigin::none(), match_result, 0, signature),
+			Error::<Test>::InvalidTradePrice
+		);
+
+		// quote amount does not match the price
+		let (match_result, signature) = signed_match(vec![make_trade(2, 0, 20, 10, 20)]);
+		assert_noop!(
+			Dex::update_match_order_unsigned(RuntimeOrigin::none(), match_result, 0, signature),
+			Error::<Test>::InvalidTradeAmount
+		);
+
+		// the maker only has 10 base unfilled
+		let (match_result, signature) = signed_match(vec![make_trade(2, 0, 20, 20, 400)]);
+		assert_noop!(
+			Dex::update_match_order_unsigned(RuntimeOrigin::none(), match_result, 0, signature),
+			Error::<Test>::TradeExceedsUnfilledAmount
+		);
+
+		// two orders on the same side can't trade
+		let (match_result, signature) = signed_match(vec![make_trade(2, 1, 15, 1, 15)]);
+		assert_noop!(
+			Dex::update_match_order_unsigned(RuntimeOrigin::none(), match_result, 0, signature),
+			Error::<Test>::InvalidTradeOrder
+		);
+	})
+}
+
+#[test]
+fn test_validate_unsigned_requires_authority_signature() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(Dex::deposit(RuntimeOrigin::signed(1), 777, 100));
+		assert_ok!(Dex::deposit(RuntimeOrigin::signed(2), 888, 1000));
+		assert_ok!(Dex::make_order(
+			RuntimeOrigin::signed(1),
+			777,
+			888,
+			10,
+			200,
+			OrderType::SELL,
+			1000
+		));
+		assert_ok!(Dex::make_order(
+			RuntimeOrigin::signed(2),
+			777,
+			888,
+			100,
+			4,
+			OrderType::BUY,
+			1000
+		));
+
+		let (match_result, signature) = signed_match(vec![make_trade(1, 0, 20, 4, 80)]);
+
+		let call = crate::Call::update_match_order_unsigned {
+			match_result: match_result.clone(),
+			authority_index: 0,
+			signature: signature.clone(),
+		};
+		assert_ok!(Dex::validate_unsigned(TransactionSource::External, &call));
+
+		// signed by a key that is not in the authority set
+		let forged_signature = (&match_result, 0u32)
+			.using_encoded(|payload| UintAuthorityId(7).sign(&payload))
+			.unwrap();
+		let call = crate::Call::update_match_order_unsigned {
+			match_result: match_result.clone(),
+			authority_index: 0,
+			signature: forged_signature,
+		};
+		assert_eq!(
+			Dex::validate_unsigned(TransactionSource::External, &call),
+			Err(InvalidTransaction::BadProof.into())
+		);
+
+		// unknown authority index
+		let call = crate::Call::update_match_order_unsigned {
+			match_result,
+			authority_index: 1,
+			signature,
+		};
+		assert_eq!(
+			Dex::validate_unsigned(TransactionSource::External, &call),
+			Err(InvalidTransaction::BadProof.into())
+		);
+	})
+}
+
+#[test]
+fn test_match_result_applies_once() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(Dex::deposit(RuntimeOrigin::signed(1), 777, 100));
+		assert_ok!(Dex::deposit(RuntimeOrigin::signed(2), 888, 1000));
+		assert_ok!(Dex::make_order(
+			RuntimeOrigin::signed(1),
+			777,
+			888,
+			10,
+			200,
+			OrderType::SELL,
+			1000
+		));
+		assert_ok!(Dex::make_order(
+			RuntimeOrigin::signed(2),
+			777,
+			888,
+			100,
+			4,
+			OrderType::BUY,
+			1000
+		));
+		assert_ok!(Dex::make_order(
+			RuntimeOrigin::signed(2),
+			777,
+			888,
+			100,
+			4,
+			OrderType::BUY,
+			1000
+		));
+
+		let (match_result, signature) = signed_match(vec![make_trade(1, 0, 20, 4, 80)]);
+		let call = crate::Call::update_match_order_unsigned {
+			match_result: match_result.clone(),
+			authority_index: 0,
+			signature: signature.clone(),
+		};
+		assert_ok!(Dex::validate_unsigned(TransactionSource::External, &call));
+		assert_ok!(Dex::update_match_order_unsigned(
+			RuntimeOrigin::none(),
+			match_result.clone(),
+			0,
+			signature.clone()
+		));
+
+		// the same signed result is stale once it was applied
+		assert_eq!(
+			Dex::validate_unsigned(TransactionSource::External, &call),
+			Err(InvalidTransaction::Stale.into())
+		);
+		assert_noop!(
+			Dex::update_match_order_unsigned(RuntimeOrigin::none(), match_result, 0, signature),
+			Error::<Test>::StaleMatchResult
+		);
+
+		// a result computed before the maker order traded no longer applies to it
+		let mut trade = make_trade(2, 0, 20, 4, 80);
+		trade.maker_order.unfilled_offered += 4;
+		trade.maker_order.unfilled_requested += 80;
+		let (match_result, signature) = signed_match(vec![trade]);
+		assert_noop!(
+			Dex::update_match_order_unsigned(RuntimeOrigin::none(), match_result, 0, signature),
+			Error::<Test>::StaleMatchResult
+		);
+		assert_eq!(Orders::<Test>::get(0).unwrap().unfilled_offered, 6);
+	})
+}
+
+#[test]
+fn test_match_engine_weight() {
+	new_test_ext().execute_with(|| {
+		// the offchain worker keeps the order books off chain
+		assert_eq!(Dex::match_engine_weight(1), Weight::zero());
+
+		// a full order book is charged for every order that changes it on chain
+		DexMatchingMode::set(MatchingMode::OnIdle);
+		let full_book = <() as WeightInfo>::update_match_engine(MaxOrdersPerPair::get());
+		assert_eq!(Dex::match_engine_weight(1), full_book);
+		assert_eq!(Dex::match_engine_weight(3), full_book.saturating_mul(3));
+
+		// the order books of a pair are bounded
+		let mut book = BookOrders::<u64, FixedU128>::new();
+		for order_id in 0..MAX_BOOK_ORDERS as u64 {
+			assert_ok!(book.try_insert(
+				OrderBookKey {
+					order_id,
+					price: FixedU128::from_u32(1),
+				},
+				order_id
+			));
+		}
+		assert!(book
+			.try_insert(
+				OrderBookKey {
+					order_id: MAX_BOOK_ORDERS as u64,
+					price: FixedU128::from_u32(1),
+				},
+				0
+			)
+			.is_err());
+	})
+}
+
+#[test]
+fn test_on_chain_matching_on_order_creation() {
+	new_test_ext().execute_with(|| {
+		DexMatchingMode::set(MatchingMode::OnOrderCreation);
+
+		assert_ok!(Dex::deposit(RuntimeOrigin::signed(1), 777, 100));
+		assert_ok!(Dex::deposit(RuntimeOrigin::signed(2), 888, 1000));
+
+		// maker sells 10 base at 20
+		assert_ok!(Dex::make_order(
+			RuntimeOrigin::signed(1),
+			777,
+			888,
+			10,
+			200,
+			OrderType::SELL,
+			1000
+		));
+		assert_eq!(NextMatchOrderIndex::<Test>::get(), 1);
+
+		// taker buys 4 base at 25 and is filled right away at the maker price
+		assert_ok!(Dex::make_order(
+			RuntimeOrigin::signed(2),
+			777,
+			888,
+			100,
+			4,
+			OrderType::BUY,
+			1000
+		));
+		assert_eq!(NextMatchOrderIndex::<Test>::get(), 2);
+
+		let maker_order = Orders::<Test>::get(0).unwrap();
+		assert_eq!(maker_order.unfilled_offered, 6);
+		assert_eq!(maker_order.order_status, OrderStatus::PartialFilled);
+		assert!(!Orders::<Test>::contains_key(1));
+
+		assert_eq!(
+			UserTokenInfoes::<Test>::get(2, 777),
+			TokenInfo {
+				amount: 4,
+				reserved: 0,
+			}
+		);
+		assert_eq!(
+			UserTokenInfoes::<Test>::get(2, 888),
+			TokenInfo {
+				amount: 920,
+				reserved: 0,
+			}
+		);
+
+		let engine = MatchEngines::<Test>::get((777, 888)).unwrap();
+		assert!(engine.buy_book.book.is_empty());
+		assert_eq!(engine.sell_book.book.len(), 1);
+
+		// unsigned match results are not accepted in on-chain modes
+		let (match_result, signature) = signed_match(vec![make_trade(1, 0, 20, 4, 80)]);
+		let call = crate::Call::update_match_order_unsigned {
+			match_result,
+			authority_index: 0,
+			signature,
+		};
+		assert_eq!(
+			Dex::validate_unsigned(TransactionSource::External, &call),
+			Err(InvalidTransaction::Call.into())
+		);
+	})
+}
+
+#[test]
+fn test_on_chain_matching_on_idle() {
+	new_test_ext().execute_with(|| {
+		DexMatchingMode::set(MatchingMode::OnIdle);
+
+		assert_ok!(Dex::deposit(RuntimeOrigin::signed(1), 777, 100));
+		assert_ok!(Dex::deposit(RuntimeOrigin::signed(2), 888, 1000));
+
+		assert_ok!(Dex::make_order(
+			RuntimeOrigin::signed(1),
+			777,
+			888,
+			10,
+			200,
+			OrderType::SELL,
+			1000
+		));
+		assert_ok!(Dex::make_order(
+			RuntimeOrigin::signed(2),
+			777,
+			888,
+			100,
+			4,
+			OrderType::BUY,
+			1000
+		));
+
+		// nothing is matched until there is idle weight
+		assert_eq!(
+			Orders::<Test>::get(1).unwrap().order_status,
+			OrderStatus::Pending
+		);
+		Dex::on_idle(1, Weight::zero());
+		assert_eq!(NextMatchOrderIndex::<Test>::get(), 0);
+
+		Dex::on_idle(1, Weight::MAX);
+		assert_eq!(NextMatchOrderIndex::<Test>::get(), 2);
+		assert_eq!(
+			Orders::<Test>::get(0).unwrap().order_status,
+			OrderStatus::PartialFilled
+		);
+		assert!(!Orders::<Test>::contains_key(1));
+	})
+}
+
+#[test]
+fn test_on_chain_matching_skips_canceled_orders() {
+	new_test_ext().execute_with(|| {
+		DexMatchingMode::set(MatchingMode::OnOrderCreation);
+
+		assert_ok!(Dex::deposit(RuntimeOrigin::signed(1), 777, 100));
+		assert_ok!(Dex::deposit(RuntimeOrigin::signed(2), 888, 1000));
+
+		assert_ok!(Dex::make_order(
+			RuntimeOrigin::signed(1),
+			777,
+			888,
+			10,
+			200,
+			OrderType::SELL,
+			1000
+		));
+		assert_ok!(Dex::cancel_order(RuntimeOrigin::signed(1), 0));
+
+		let engine = MatchEngines::<Test>::get((777, 888)).unwrap();
+		assert!(engine.sell_book.book.is_empty());
+
+		assert_ok!(Dex::make_order(
+			RuntimeOrigin::signed(2),
+			777,
+			888,
+			100,
+			4,
+			OrderType::BUY,
+			1000
+		));
+
+		assert_eq!(
+			Orders::<Test>::get(1).unwrap().order_status,
+			OrderStatus::Pending
+		);
+		assert_eq!(
+			UserTokenInfoes::<Test>::get(1, 777),
+			TokenInfo {
+				amount: 100,
+				reserved: 0,
+			}
+		);
+	})
+}
+
+#[test]
+fn test_immediate_or_cancel_order_remainder_is_canceled() {
+	new_test_ext().execute_with(|| {
+		DexMatchingMode::set(MatchingMode::OnOrderCreation);
+
+		assert_ok!(Dex::deposit(RuntimeOrigin::signed(1), 777, 100));
+		assert_ok!(Dex::deposit(RuntimeOrigin::signed(2), 888, 1000));
+
+		assert_ok!(Dex::make_order(
+			RuntimeOrigin::signed(1),
+			777,
+			888,
+			10,
+			200,
+			OrderType::SELL,
+			1000
+		));
+		// buys 20 base at 25, only 10 are on the book
+		assert_ok!(Dex::place_order(
+			RuntimeOrigin::signed(2),
+			777,
+			888,
+			500,
+			20,
+			OrderType::BUY,
+			OrderKind::Limit,
+			TimeInForce::ImmediateOrCancel,
+			1000
+		));
+
+		System::assert_last_event(
+			Event::<Test>::OrderClosed {
+				order_index: 1,
+				order_status: OrderStatus::PartialCancelled,
+			}
+			.into(),
+		);
+		assert!(!Orders::<Test>::contains_key(1));
+		assert!(!UserOrders::<Test>::contains_key(2, 1));
+		assert!(MatchEngines::<Test>::get((777, 888))
+			.unwrap()
+			.buy_book
+			.book
+			.is_empty());
+
+		assert_eq!(
+			UserTokenInfoes::<Test>::get(2, 777),
+			TokenInfo {
+				amount: 10,
+				reserved: 0,
+			}
+		);
+		assert_eq!(
+			UserTokenInfoes::<Test>::get(2, 888),
+			TokenInfo {
+				amount: 800,
+				reserved: 0,
+			}
+		);
+	})
+}
+
+#[test]
+fn test_fill_or_kill_order() {
+	new_test_ext().execute_with(|| {
+		DexMatchingMode::set(MatchingMode::OnOrderCreation);
+
+		assert_ok!(Dex::deposit(RuntimeOrigin::signed(1), 777, 100));
+		assert_ok!(Dex::deposit(RuntimeOrigin::signed(2), 888, 1000));
+
+		assert_ok!(Dex::make_order(
+			RuntimeOrigin::signed(1),
+			777,
+			888,
+			10,
+			200,
+			OrderType::SELL,
+			1000
+		));
+
+		// 20 base can't be filled, so nothing trades
+		assert_ok!(Dex::place_order(
+			RuntimeOrigin::signed(2),
+			777,
+			888,
+			500,
+			20,
+			OrderType::BUY,
+			OrderKind::Limit,
+			TimeInForce::FillOrKill,
+			1000
+		));
+		System::assert_last_event(
+			Event::<Test>::OrderClosed {
+				order_index: 1,
+				order_status: OrderStatus::FullyCancelled,
+			}
+			.into(),
+		);
+		assert_eq!(
+			Orders::<Test>::get(0).unwrap().order_status,
+			OrderStatus::Pending
+		);
+		assert_eq!(
+			UserTokenInfoes::<Test>::get(2, 888),
+			TokenInfo {
+				amount: 1000,
+				reserved: 0,
+			}
+		);
+
+		// 10 base are filled completely at the maker price
+		assert_ok!(Dex::place_order(
+			RuntimeOrigin::signed(2),
+			777,
+			888,
+			250,
+			10,
+			OrderType::BUY,
+			OrderKind::Limit,
+			TimeInForce::FillOrKill,
+			1000
+		));
+		assert!(!Orders::<Test>::contains_key(2));
+		assert!(!Orders::<Test>::contains_key(0));
+		assert_eq!(
+			UserTokenInfoes::<Test>::get(2, 777),
+			TokenInfo {
+				amount: 10,
+				reserved: 0,
+			}
+		);
+		assert_eq!(
+			UserTokenInfoes::<Test>::get(2, 888),
+			TokenInfo {
+				amount: 800,
+				reserved: 0,
+			}
+		);
+	})
+}
+
+#[test]
+fn test_post_only_order_is_rejected_when_crossing() {
+	new_test_ext().execute_with(|| {
+		DexMatchingMode::set(MatchingMode::OnOrderCreation);
+
+		assert_ok!(Dex::deposit(RuntimeOrigin::signed(1), 777, 100));
+		assert_ok!(Dex::deposit(RuntimeOrigin::signed(2), 888, 1000));
+
+		assert_ok!(Dex::make_order(
+			RuntimeOrigin::signed(1),
+			777,
+			888,
+			10,
+			200,
+			OrderType::SELL,
+			1000
+		));
+
+		// buying at 20 would take the sell order
+		assert_ok!(Dex::place_order(
+			RuntimeOrigin::signed(2),
+			777,
+			888,
+			80,
+			4,
+			OrderType::BUY,
+			OrderKind::Limit,
+			TimeInForce::PostOnly,
+			1000
+		));
+		System::assert_last_event(
+			Event::<Test>::OrderClosed {
+				order_index: 1,
+				order_status: OrderStatus::Rejected,
+			}
+			.into(),
+		);
+		assert!(!Orders::<Test>::contains_key(1));
+		assert_eq!(
+			Orders::<Test>::get(0).unwrap().order_status,
+			OrderStatus::Pending
+		);
+
+		// buying at 15 rests on the book
+		assert_ok!(Dex::place_order(
+			RuntimeOrigin::signed(2),
+			777,
+			888,
+			60,
+			4,
+			OrderType::BUY,
+			OrderKind::Limit,
+			TimeInForce::PostOnly,
+			1000
+		));
+		assert_eq!(
+			Orders::<Test>::get(2).unwrap().order_status,
+			OrderStatus::Pending
+		);
+		assert_eq!(
+			MatchEngines::<Test>::get((777, 888))
+				.unwrap()
+				.buy_book
+				.book
+				.len(),
+			1
+		);
+		assert_eq!(
+			UserTokenInfoes::<Test>::get(2, 888),
+			TokenInfo {
+				amount: 940,
+				reserved: 60,
+			}
+		);
+	})
+}
+
+#[test]
+fn test_market_order() {
+	new_test_ext().execute_with(|| {
+		DexMatchingMode::set(MatchingMode::OnOrderCreation);
+
+		assert_ok!(Dex::deposit(RuntimeOrigin::signed(1), 777, 100));
+		assert_ok!(Dex::deposit(RuntimeOrigin::signed(2), 888, 1000));
+
+		assert_noop!(
+			Dex::place_order(
+				RuntimeOrigin::signed(2),
+				777,
+				888,
+				120,
+				10,
+				OrderType::BUY,
+				OrderKind::Market,
+				TimeInForce::GoodTillExpiration,
+				1000
+			),
+			Error::<Test>::MarketOrderMustNotRest
+		);
+
+		// 5 base at 20 and 5 base at 30
+		assert_ok!(Dex::make_order(
+			RuntimeOrigin::signed(1),
+			777,
+			888,
+			5,
+			100,
+			OrderType::SELL,
+			1000
+		));
+		assert_ok!(Dex::make_order(
+			RuntimeOrigin::signed(1),
+			777,
+			888,
+			5,
+			150,
+			OrderType::SELL,
+			1000
+		));
+
+		// the budget of 120 only pays for the cheaper 5 base
+		assert_ok!(Dex::place_order(
+			RuntimeOrigin::signed(2),
+			777,
+			888,
+			120,
+			10,
+			OrderType::BUY,
+			OrderKind::Market,
+			TimeInForce::ImmediateOrCancel,
+			1000
+		));
+		System::assert_last_event(
+			Event::<Test>::OrderClosed {
+				order_index: 2,
+				order_status: OrderStatus::PartialCancelled,
+			}
+			.into(),
+		);
+		assert_eq!(
+			UserTokenInfoes::<Test>::get(2, 777),
+			TokenInfo {
+				amount: 5,
+				reserved: 0,
+			}
+		);
+		assert_eq!(
+			UserTokenInfoes::<Test>::get(2, 888),
+			TokenInfo {
+				amount: 900,
+				reserved: 0,
+			}
+		);
+
+		// the price limit of 40 doesn't matter, the rest is bought at 30
+		assert_ok!(Dex::place_order(
+			RuntimeOrigin::signed(2),
+			777,
+			888,
+			200,
+			5,
+			OrderType::BUY,
+			OrderKind::Market,
+			TimeInForce::ImmediateOrCancel,
+			1000
+		));
+		assert!(!Orders::<Test>::contains_key(3));
+		assert!(!Orders::<Test>::contains_key(1));
+		assert_eq!(
+			UserTokenInfoes::<Test>::get(2, 777),
+			TokenInfo {
+				amount: 10,
+				reserved: 0,
+			}
+		);
+		assert_eq!(
+			UserTokenInfoes::<Test>::get(2, 888),
+			TokenInfo {
+				amount: 750,
+				reserved: 0,
+			}
+		);
+	})
+}
+
+#[test]
+fn test_update_match_order_closes_immediate_or_cancel_order() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(Dex::deposit(RuntimeOrigin::signed(2), 888, 1000));
+		assert_ok!(Dex::place_order(
+			RuntimeOrigin::signed(2),
+			777,
+			888,
+			100,
+			4,
+			OrderType::BUY,
+			OrderKind::Limit,
+			TimeInForce::ImmediateOrCancel,
+			1000
+		));
+
+		// nothing to trade with on the book
+		let match_result = MatchResult {
+			taker_order: Orders::<Test>::get(0).unwrap(),
+			match_details: vec![],
+			self_trades: vec![],
+		};
+		let signature = (&match_result, 0u32)
+			.using_encoded(|payload| UintAuthorityId(0).sign(&payload))
+			.unwrap();
+		assert_ok!(Dex::update_match_order_unsigned(
+			RuntimeOrigin::none(),
+			match_result,
+			0,
+			signature
+		));
+
+		System::assert_last_event(
+			Event::<Test>::OrderClosed {
+				order_index: 0,
+				order_status: OrderStatus::FullyCancelled,
+			}
+			.into(),
+		);
+		assert!(!Orders::<Test>::contains_key(0));
+		assert_eq!(
+			UserTokenInfoes::<Test>::get(2, 888),
+			TokenInfo {
+				amount: 1000,
+				reserved: 0,
+			}
+		);
+	})
+}
+
+#[test]
+fn test_set_pair_config() {
+	new_test_ext().execute_with(|| {
+		let config = PairConfig {
+			tick_size: FixedU128::from_rational(1, 2),
+			lot_size: 10,
+			min_notional: 100,
+		};
+
+		assert_noop!(
+			Dex::set_pair_config(RuntimeOrigin::signed(1), 777, 888, config.clone()),
+			sp_runtime::DispatchError::BadOrigin
+		);
+		assert_noop!(
+			Dex::set_pair_config(RuntimeOrigin::root(), 888, 777, config.clone()),
+			Error::<Test>::InvalidPairConfig
+		);
+		assert_noop!(
+			Dex::set_pair_config(
+				RuntimeOrigin::root(),
+				777,
+				888,
+				PairConfig {
+					lot_size: 0,
+					..config.clone()
+				}
+			),
+			Error::<Test>::InvalidPairConfig
+		);
+
+		assert_eq!(PairConfigs::<Test>::get((777, 888)), PairConfig::default());
+		assert_ok!(Dex::set_pair_config(
+			RuntimeOrigin::root(),
+			777,
+			888,
+			config.clone()
+		));
+		assert_eq!(PairConfigs::<Test>::get((777, 888)), config);
+	})
+}
+
+#[test]
+fn test_make_order_with_pair_config() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(Dex::deposit(RuntimeOrigin::signed(1), 777, 100));
+		assert_ok!(Dex::deposit(RuntimeOrigin::signed(2), 888, 1000));
+		assert_ok!(Dex::set_pair_config(
+			RuntimeOrigin::root(),
+			777,
+			888,
+			PairConfig {
+				tick_size: FixedU128::from_rational(1, 2),
+				lot_size: 10,
+				min_notional: 100,
+			}
+		));
+
+		assert_noop!(
+			Dex::make_order(
+				RuntimeOrigin::signed(1),
+				777,
+				888,
+				15,
+				400,
+				OrderType::SELL,
+				1000
+			),
+			Error::<Test>::InvalidLotSize
+		);
+		assert_noop!(
+			Dex::make_order(
+				RuntimeOrigin::signed(1),
+				777,
+				888,
+				10,
+				50,
+				OrderType::SELL,
+				1000
+			),
+			Error::<Test>::BelowMinNotional
+		);
+
+		// 20.3 is rounded up to the tick for a sell order...
+		assert_ok!(Dex::make_order(
+			RuntimeOrigin::signed(1),
+			777,
+			888,
+			10,
+			203,
+			OrderType::SELL,
+			1000
+		));
+		assert_eq!(
+			Orders::<Test>::get(0).unwrap().price,
+			FixedU128::from_rational(41, 2)
+		);
+
+		// ...and down for a buy order
+		assert_ok!(Dex::make_order(
+			RuntimeOrigin::signed(2),
+			777,
+			888,
+			203,
+			10,
+			OrderType::BUY,
+			1000
+		));
+		assert_eq!(
+			Orders::<Test>::get(1).unwrap().price,
+			FixedU128::from_u32(20)
+		);
+	})
+}
+
+#[test]
+fn test_fractional_price_rounds_in_favour_of_maker() {
+	new_test_ext().execute_with(|| {
+		DexMatchingMode::set(MatchingMode::OnOrderCreation);
+
+		assert_ok!(Dex::deposit(RuntimeOrigin::signed(1), 777, 100));
+		assert_ok!(Dex::deposit(RuntimeOrigin::signed(2), 888, 1000));
+
+		// sells 3 base for 10 quote
+		assert_ok!(Dex::make_order(
+			RuntimeOrigin::signed(1),
+			777,
+			888,
+			3,
+			10,
+			OrderType::SELL,
+			1000
+		));
+		assert_eq!(
+			Orders::<Test>::get(0).unwrap().price,
+			FixedU128::from_inner(3_333_333_333_333_333_334)
+		);
+
+		// 2 base cost 6.67 quote, the buyer pays 7
+		assert_ok!(Dex::make_order(
+			RuntimeOrigin::signed(2),
+			777,
+			888,
+			7,
+			2,
+			OrderType::BUY,
+			1000
+		));
+		assert!(!Orders::<Test>::contains_key(1));
+		assert_ok!(Dex::make_order(
+			RuntimeOrigin::signed(2),
+			777,
+			888,
+			4,
+			1,
+			OrderType::BUY,
+			1000
+		));
+		assert!(!Orders::<Test>::contains_key(0));
+		assert_eq!(
+			UserTokenInfoes::<Test>::get(1, 888),
+			TokenInfo {
+				amount: 11,
+				reserved: 0,
+			}
+		);
+
+		// buys 3 base for 10 quote
+		assert_ok!(Dex::make_order(
+			RuntimeOrigin::signed(2),
+			777,
+			888,
+			10,
+			3,
+			OrderType::BUY,
+			1000
+		));
+		assert_eq!(
+			Orders::<Test>::get(3).unwrap().price,
+			FixedU128::from_inner(3_333_333_333_333_333_333)
+		);
+
+		// 2 base are worth 6.67 quote, the seller gets 6
+		assert_ok!(Dex::make_order(
+			RuntimeOrigin::signed(1),
+			777,
+			888,
+			2,
+			6,
+			OrderType::SELL,
+			1000
+		));
+		assert_ok!(Dex::make_order(
+			RuntimeOrigin::signed(1),
+			777,
+			888,
+			1,
+			3,
+			OrderType::SELL,
+			1000
+		));
+		assert!(!Orders::<Test>::contains_key(3));
+
+		assert_eq!(
+			UserTokenInfoes::<Test>::get(1, 777),
+			TokenInfo {
+				amount: 94,
+				reserved: 0,
+			}
+		);
+		assert_eq!(
+			UserTokenInfoes::<Test>::get(1, 888),
+			TokenInfo {
+				amount: 20,
+				reserved: 0,
+			}
+		);
+		assert_eq!(
+			UserTokenInfoes::<Test>::get(2, 777),
+			TokenInfo {
+				amount: 6,
+				reserved: 0,
+			}
+		);
+		// 1 of the reserved quote is released once the buy order is filled
+		assert_eq!(
+			UserTokenInfoes::<Test>::get(2, 888),
+			TokenInfo {
+				amount: 980,
+				reserved: 0,
+			}
+		);
+	})
+}
+
+#[test]
+fn test_trade_fees_go_to_fee_destination() {
+	new_test_ext().execute_with(|| {
+		assert_noop!(
+			Dex::set_pair_fee(RuntimeOrigin::signed(1), 777, 888, PairFee::default()),
+			sp_runtime::DispatchError::BadOrigin
+		);
+		assert_ok!(Dex::set_pair_fee(
+			RuntimeOrigin::root(),
+			777,
+			888,
+			PairFee {
+				maker_fee: Permill::from_percent(10),
+				taker_fee: Permill::from_percent(20),
+			}
+		));
+
+		assert_ok!(Dex::deposit(RuntimeOrigin::signed(1), 777, 100));
+		assert_ok!(Dex::deposit(RuntimeOrigin::signed(2), 888, 1000));
+
+		// maker sells 100 base at 20
+		assert_ok!(Dex::make_order(
+			RuntimeOrigin::signed(1),
+			777,
+			888,
+			100,
+			2000,
+			OrderType::SELL,
+			1000
+		));
+		// taker buys 50 base at 20
+		assert_ok!(Dex::make_order(
+			RuntimeOrigin::signed(2),
+			777,
+			888,
+			1000,
+			50,
+			OrderType::BUY,
+			1000
+		));
+
+		let (match_result, signature) = signed_match(vec![make_trade(1, 0, 20, 50, 1000)]);
+		assert_ok!(Dex::update_match_order_unsigned(
+			RuntimeOrigin::none(),
+			match_result,
+			0,
+			signature
+		));
+
+		System::assert_last_event(
+			Event::<Test>::OrderMatched {
+				trade_id: 0,
+				pair: (777, 888),
+				side: OrderType::BUY,
+				price: FixedU128::from_u32(20),
+				quantity_base: 50,
+				quantity_quote: 1000,
+				maker: 1,
+				taker: 2,
+				maker_order_index: 0,
+				taker_order_index: 1,
+				maker_fee: 100,
+				taker_fee: 10,
+			}
+			.into(),
+		);
+		assert_eq!(Dex::next_trade_id(), 1);
+
+		// the taker pays 20% of the received base, the maker 10% of the received quote
+		assert_eq!(
+			UserTokenInfoes::<Test>::get(2, 777),
+			TokenInfo {
+				amount: 40,
+				reserved: 0,
+			}
+		);
+		assert_eq!(
+			UserTokenInfoes::<Test>::get(1, 888),
+			TokenInfo {
+				amount: 900,
+				reserved: 0,
+			}
+		);
+		assert_eq!(Assets::balance(777, DexFeeDestination::get()), 10);
+		assert_eq!(Assets::balance(888, DexFeeDestination::get()), 100);
+	})
+}
+
+#[test]
+fn test_take_order_fees() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(Dex::set_pair_fee(
+			RuntimeOrigin::root(),
+			777,
+			888,
+			PairFee {
+				maker_fee: Permill::from_percent(10),
+				taker_fee: Permill::from_percent(20),
+			}
+		));
+
+		assert_ok!(Dex::deposit(RuntimeOrigin::signed(1), 777, 100));
+		assert_ok!(Dex::deposit(RuntimeOrigin::signed(2), 888, 1000));
+		assert_ok!(Dex::make_order(
+			RuntimeOrigin::signed(1),
+			777,
+			888,
+			10,
+			200,
+			OrderType::SELL,
+			1000
+		));
+
+		assert_ok!(Dex::take_order(RuntimeOrigin::signed(2), 0));
+
+		System::assert_last_event(
+			Event::<Test>::OrderTaken {
+				trade_id: 0,
+				order_index: 0,
+				pair: (777, 888),
+				side: OrderType::BUY,
+				price: FixedU128::from_u32(20),
+				quantity_base: 10,
+				quantity_quote: 200,
+				maker: 1,
+				taker: 2,
+				maker_fee: 20,
+				taker_fee: 2,
+			}
+			.into(),
+		);
+
+		assert_eq!(
+			UserTokenInfoes::<Test>::get(1, 888),
+			TokenInfo {
+				amount: 180,
+				reserved: 0,
+			}
+		);
+		assert_eq!(
+			UserTokenInfoes::<Test>::get(2, 777),
+			TokenInfo {
+				amount: 8,
+				reserved: 0,
+			}
+		);
+		assert_eq!(Assets::balance(777, DexFeeDestination::get()), 2);
+		assert_eq!(Assets::balance(888, DexFeeDestination::get()), 20);
+	})
+}
+
+#[test]
+fn test_fee_tiers() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(Dex::set_pair_fee(
+			RuntimeOrigin::root(),
+			777,
+			888,
+			PairFee {
+				maker_fee: Permill::from_percent(10),
+				taker_fee: Permill::from_percent(1),
+			}
+		));
+		let tier = |min_volume| FeeTier {
+			min_volume,
+			fee: PairFee {
+				maker_fee: Permill::zero(),
+				taker_fee: Permill::from_percent(10),
+			},
+			maker_rebate: Permill::from_percent(50),
+		};
+
+		let tiers: FeeTiersOf<Test> = vec![tier(2_000), tier(2_000)].try_into().unwrap();
+		assert_noop!(
+			Dex::set_fee_tiers(RuntimeOrigin::root(), 777, 888, tiers),
+			Error::<Test>::InvalidFeeTiers
+		);
+		let tiers: FeeTiersOf<Test> = vec![tier(2_000)].try_into().unwrap();
+		assert_noop!(
+			Dex::set_fee_tiers(RuntimeOrigin::signed(1), 777, 888, tiers.clone()),
+			sp_runtime::DispatchError::BadOrigin
+		);
+		assert_noop!(
+			Dex::set_fee_tiers(RuntimeOrigin::root(), 888, 777, tiers.clone()),
+			Error::<Test>::PairNotRegistered
+		);
+		assert_ok!(Dex::set_fee_tiers(
+			RuntimeOrigin::root(),
+			777,
+			888,
+			tiers.clone()
+		));
+		System::assert_last_event(
+			Event::<Test>::FeeTiersSet {
+				pair: (777, 888),
+				tiers,
+			}
+			.into(),
+		);
+
+		assert_ok!(Dex::deposit(RuntimeOrigin::signed(1), 777, 300));
+		assert_ok!(Dex::deposit(RuntimeOrigin::signed(2), 888, 6_000));
+		let sell = || {
+			assert_ok!(Dex::make_order(
+				RuntimeOrigin::signed(1),
+				777,
+				888,
+				100,
+				2_000,
+				OrderType::SELL,
+				1000
+			));
+			NextOrderIndex::<Test>::get() - 1
+		};
+
+		// below the lowest tier both pay the fees of the pair
+		assert_ok!(Dex::take_order(RuntimeOrigin::signed(2), sell()));
+		assert_eq!(Dex::trading_volume(&1, (777, 888)), 2_000);
+		assert_eq!(Dex::trading_volume(&2, (777, 888)), 2_000);
+		assert_eq!(Assets::balance(777, DexFeeDestination::get()), 1);
+		assert_eq!(Assets::balance(888, DexFeeDestination::get()), 200);
+
+		// in the tier the maker pays nothing and gets half of the taker fee
+		assert_ok!(Dex::take_order(RuntimeOrigin::signed(2), sell()));
+		System::assert_has_event(
+			Event::<Test>::MakerRebatePaid {
+				trade_id: 1,
+				maker: 1,
+				asset_id: 777,
+				amount: 5,
+			}
+			.into(),
+		);
+		assert_eq!(Assets::balance(777, DexFeeDestination::get()), 6);
+		assert_eq!(Assets::balance(888, DexFeeDestination::get()), 200);
+
+		// the same applies to matched trades
+		let maker_order_index = sell();
+		assert_ok!(Dex::make_order(
+			RuntimeOrigin::signed(2),
+			777,
+			888,
+			2_000,
+			100,
+			OrderType::BUY,
+			1000
+		));
+		let (match_result, signature) = signed_match(vec![make_trade(
+			maker_order_index + 1,
+			maker_order_index,
+			20,
+			100,
+			2_000,
+		)]);
+		assert_ok!(Dex::update_match_order_unsigned(
+			RuntimeOrigin::none(),
+			match_result,
+			0,
+			signature
+		));
+		System::assert_has_event(
+			Event::<Test>::MakerRebatePaid {
+				trade_id: 2,
+				maker: 1,
+				asset_id: 777,
+				amount: 5,
+			}
+			.into(),
+		);
+
+		assert_eq!(
+			UserTokenInfoes::<Test>::get(1, 777),
+			TokenInfo {
+				amount: 10,
+				reserved: 0,
+			}
+		);
+		assert_eq!(
+			UserTokenInfoes::<Test>::get(1, 888),
+			TokenInfo {
+				amount: 5_800,
+				reserved: 0,
+			}
+		);
+		assert_eq!(
+			UserTokenInfoes::<Test>::get(2, 777),
+			TokenInfo {
+				amount: 279,
+				reserved: 0,
+			}
+		);
+		assert_eq!(Assets::balance(777, DexFeeDestination::get()), 11);
+		assert_eq!(Assets::balance(888, DexFeeDestination::get()), 200);
+
+		// trades count for the fee tier for 30 days
+		assert_eq!(Dex::trading_volume(&1, (777, 888)), 6_000);
+		Timestamp::set_timestamp(29 * 86_400_000);
+		assert_eq!(Dex::trading_volume(&1, (777, 888)), 6_000);
+		Timestamp::set_timestamp(30 * 86_400_000);
+		assert_eq!(Dex::trading_volume(&1, (777, 888)), 0);
+	})
+}
+
+#[test]
+fn test_liquidity_mining_rewards() {
+	new_test_ext().execute_with(|| {
+		OraclePrice::set(Some(FixedU128::from_u32(20)));
+		let terms = RewardTerms {
+			asset_id: 999,
+			reward_per_block: 100,
+			duration: 10,
+			reference: ReferencePrice::Oracle,
+			max_spread: Permill::from_percent(10),
+		};
+
+		assert_noop!(
+			Dex::fund_reward_epoch(RuntimeOrigin::signed(1), 777, 888, terms.clone()),
+			sp_runtime::DispatchError::BadOrigin
+		);
+		assert_noop!(
+			Dex::fund_reward_epoch(
+				RuntimeOrigin::root(),
+				777,
+				888,
+				RewardTerms {
+					reward_per_block: 0,
+					..terms.clone()
+				}
+			),
+			Error::<Test>::InvalidRewardTerms
+		);
+		assert_noop!(
+			Dex::fund_reward_epoch(
+				RuntimeOrigin::root(),
+				777,
+				888,
+				RewardTerms {
+					asset_id: 5555,
+					..terms.clone()
+				}
+			),
+			Error::<Test>::AssetIdNotInTokenIndex
+		);
+
+		assert_ok!(Dex::fund_reward_epoch(
+			RuntimeOrigin::root(),
+			777,
+			888,
+			terms.clone()
+		));
+		System::assert_last_event(
+			Event::<Test>::RewardEpochFunded {
+				epoch_id: 0,
+				pair: (777, 888),
+				terms: terms.clone(),
+				reward: 1_000,
+			}
+			.into(),
+		);
+		assert_eq!(Assets::balance(999, DexRewardSource::get()), 999_999_000);
+		assert_eq!(Assets::balance(999, Dex::rewards_account_id()), 1_000);
+		assert_noop!(
+			Dex::fund_reward_epoch(RuntimeOrigin::root(), 777, 888, terms.clone()),
+			Error::<Test>::RewardEpochRunning
+		);
+
+		assert_ok!(Dex::deposit(RuntimeOrigin::signed(1), 777, 200));
+		assert_ok!(Dex::deposit(RuntimeOrigin::signed(2), 777, 300));
+		// within 10% of 20
+		assert_ok!(Dex::make_order(
+			RuntimeOrigin::signed(1),
+			777,
+			888,
+			100,
+			2_000,
+			OrderType::SELL,
+			1000
+		));
+		// 25 is too far from 20
+		assert_ok!(Dex::make_order(
+			RuntimeOrigin::signed(1),
+			777,
+			888,
+			100,
+			2_500,
+			OrderType::SELL,
+			1000
+		));
+		assert_ok!(Dex::make_order(
+			RuntimeOrigin::signed(2),
+			777,
+			888,
+			300,
+			6_300,
+			OrderType::SELL,
+			1000
+		));
+		assert!(RewardStakes::<Test>::contains_key(0));
+		assert!(!RewardStakes::<Test>::contains_key(1));
+		assert!(RewardStakes::<Test>::contains_key(2));
+
+		// two blocks of rewards shared by the stakes of 100 and 300
+		run_to_block(3);
+		assert_eq!(Dex::pending_rewards(&1), vec![(999, 50)]);
+		assert_eq!(Dex::pending_rewards(&2), vec![(999, 150)]);
+
+		// canceled orders keep what they earned
+		assert_ok!(Dex::cancel_order(RuntimeOrigin::signed(2), 2));
+		assert_eq!(Dex::unclaimed_rewards(2, 999), 150);
+		assert!(!RewardStakes::<Test>::contains_key(2));
+
+		run_to_block(5);
+		assert_eq!(Dex::pending_rewards(&1), vec![(999, 250)]);
+		assert_ok!(Dex::claim_rewards(RuntimeOrigin::signed(1)));
+		System::assert_last_event(
+			Event::<Test>::RewardsClaimed {
+				who: 1,
+				asset_id: 999,
+				amount: 250,
+			}
+			.into(),
+		);
+		assert_eq!(UserTokenInfoes::<Test>::get(1, 999).amount, 250);
+		assert_eq!(Dex::pending_rewards(&1), vec![]);
+
+		// the epoch ends with block 11
+		run_to_block(20);
+		assert_eq!(Dex::pending_rewards(&1), vec![(999, 600)]);
+		assert_ok!(Dex::claim_rewards(RuntimeOrigin::signed(1)));
+		assert_ok!(Dex::claim_rewards(RuntimeOrigin::signed(2)));
+		assert_eq!(UserTokenInfoes::<Test>::get(1, 999).amount, 850);
+		assert_eq!(UserTokenInfoes::<Test>::get(2, 999).amount, 150);
+		assert_eq!(Assets::balance(999, Dex::rewards_account_id()), 0);
+		assert_eq!(Assets::balance(999, Dex::account_id()), 1_000);
+		assert_noop!(
+			Dex::claim_rewards(RuntimeOrigin::signed(1)),
+			Error::<Test>::NoRewardsToClaim
+		);
+
+		// orders placed in a new epoch earn its rewards
+		assert_ok!(Dex::fund_reward_epoch(
+			RuntimeOrigin::root(),
+			777,
+			888,
+			terms
+		));
+		assert_eq!(Dex::pair_reward_epochs((777, 888)), Some(1));
+	})
+}
+
+#[test]
+fn test_unearned_rewards() {
+	new_test_ext().execute_with(|| {
+		OraclePrice::set(Some(FixedU128::from_u32(20)));
+		assert_ok!(Dex::fund_reward_epoch(
+			RuntimeOrigin::root(),
+			777,
+			888,
+			RewardTerms {
+				asset_id: 999,
+				reward_per_block: 100,
+				duration: 10,
+				reference: ReferencePrice::Oracle,
+				max_spread: Permill::from_percent(10),
+			}
+		));
+
+		// nobody earns the rewards of the first two blocks
+		run_to_block(3);
+		assert_ok!(Dex::deposit(RuntimeOrigin::signed(1), 777, 100));
+		assert_ok!(Dex::make_order(
+			RuntimeOrigin::signed(1),
+			777,
+			888,
+			100,
+			2_000,
+			OrderType::SELL,
+			1000
+		));
+		run_to_block(5);
+		assert_eq!(Dex::pending_rewards(&1), vec![(999, 200)]);
+
+		// 20 is too far from 30, the order forfeits what it earned since the last accrual
+		OraclePrice::set(Some(FixedU128::from_u32(30)));
+		assert_eq!(Dex::pending_rewards(&1), vec![]);
+		assert_ok!(Dex::cancel_order(RuntimeOrigin::signed(1), 0));
+		assert_eq!(Dex::unclaimed_rewards(1, 999), 0);
+		assert_eq!(Dex::reward_epochs(0).unwrap().unearned_reward, 400);
+
+		assert_noop!(
+			Dex::reclaim_rewards(RuntimeOrigin::root(), 0),
+			Error::<Test>::RewardEpochRunning
+		);
+		run_to_block(20);
+		assert_noop!(
+			Dex::reclaim_rewards(RuntimeOrigin::signed(1), 0),
+			sp_runtime::DispatchError::BadOrigin
+		);
+		assert_noop!(
+			Dex::reclaim_rewards(RuntimeOrigin::root(), 1),
+			Error::<Test>::RewardEpochNotFound
+		);
+
+		// the rest of the epoch had no stakes either
+		assert_ok!(Dex::reclaim_rewards(RuntimeOrigin::root(), 0));
+		System::assert_last_event(
+			Event::<Test>::RewardsReclaimed {
+				epoch_id: 0,
+				asset_id: 999,
+				amount: 1_000,
+			}
+			.into(),
+		);
+		assert_eq!(Assets::balance(999, Dex::rewards_account_id()), 0);
+		assert_eq!(Assets::balance(999, DexRewardSource::get()), 1_000_000_000);
+		assert_noop!(
+			Dex::reclaim_rewards(RuntimeOrigin::root(), 0),
+			Error::<Test>::NoRewardsToReclaim
+		);
+	})
+}
+
+#[test]
+fn test_order_book_queries() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(Dex::deposit(RuntimeOrigin::signed(1), 777, 100));
+		assert_ok!(Dex::deposit(RuntimeOrigin::signed(2), 888, 1000));
+
+		assert_eq!(Dex::best_bid_ask((777, 888)), BestBidAsk::default());
+
+		// asks at 20 and 30, bids at 10 and 5
+		for (offered, requested) in [(20, 400), (20, 600), (10, 300)] {
+			assert_ok!(Dex::make_order(
+				RuntimeOrigin::signed(1),
+				777,
+				888,
+				offered,
+				requested,
+				OrderType::SELL,
+				1000
+			));
+		}
+		for (offered, requested) in [(100, 10), (50, 5), (50, 10)] {
+			assert_ok!(Dex::make_order(
+				RuntimeOrigin::signed(2),
+				777,
+				888,
+				offered,
+				requested,
+				OrderType::BUY,
+				1000
+			));
+		}
+
+		assert_eq!(
+			Dex::order_book_depth((777, 888), 1),
+			OrderBookDepth {
+				bids: vec![PriceLevel {
+					price: FixedU128::from_u32(10),
+					base_amount: 15,
+					order_count: 2,
+				}],
+				asks: vec![PriceLevel {
+					price: FixedU128::from_u32(20),
+					base_amount: 20,
+					order_count: 1,
+				}],
+			}
+		);
+		assert_eq!(
+			Dex::order_book_depth((777, 888), 10)
+				.asks
+				.iter()
+				.map(|level| (level.price, level.base_amount))
+				.collect::<Vec<_>>(),
+			vec![(FixedU128::from_u32(20), 20), (FixedU128::from_u32(30), 30),]
+		);
+		assert_eq!(
+			Dex::best_bid_ask((777, 888)),
+			BestBidAsk {
+				best_bid: Some(FixedU128::from_u32(10)),
+				best_ask: Some(FixedU128::from_u32(20)),
+			}
+		);
+
+		assert_ok!(Dex::cancel_order(RuntimeOrigin::signed(1), 0));
+
+		assert_eq!(
+			Dex::best_bid_ask((777, 888)).best_ask,
+			Some(FixedU128::from_u32(30))
+		);
+		let mut open_orders = Dex::user_open_orders(&1)
+			.iter()
+			.map(|order| order.counter)
+			.collect::<Vec<_>>();
+		open_orders.sort();
+		assert_eq!(open_orders, vec![1, 2]);
+
+		// oldest first, all indexes of the pair are counted
+		let (index_count, page) = Dex::pair_orders_page((777, 888), 1, 2);
+		assert_eq!(index_count, 5);
+		assert_eq!(
+			page.iter().map(|order| order.counter).collect::<Vec<_>>(),
+			vec![2, 3]
+		);
+		let (index_count, page) = Dex::user_orders_page(&1, 1, 10);
+		assert_eq!(index_count, 2);
+		assert_eq!(page.len(), 1);
+		assert_eq!(Dex::user_orders_page(&1, 2, 10), (2, vec![]));
+		assert_eq!(
+			Dex::user_balances(&1),
+			vec![(
+				777,
+				TokenInfo {
+					amount: 70,
+					reserved: 30,
+				}
+			)]
+		);
+	})
+}
+
+#[test]
+fn test_amend_order() {
+	new_test_ext().execute_with(|| {
+		DexMatchingMode::set(MatchingMode::OnOrderCreation);
+
+		assert_ok!(Dex::deposit(RuntimeOrigin::signed(1), 777, 100));
+		assert_ok!(Dex::deposit(RuntimeOrigin::signed(2), 777, 100));
+		assert_ok!(Dex::deposit(RuntimeOrigin::signed(3), 888, 1000));
+
+		// two makers sell 10 base at 20
+		for maker in [1, 2] {
+			assert_ok!(Dex::make_order(
+				RuntimeOrigin::signed(maker),
+				777,
+				888,
+				10,
+				200,
+				OrderType::SELL,
+				1000
+			));
+		}
+
+		assert_noop!(
+			Dex::amend_order(RuntimeOrigin::signed(2), 0, 5, 100),
+			Error::<Test>::NotOwner
+		);
+
+		// shrinking an order keeps its place in the queue
+		assert_ok!(Dex::amend_order(RuntimeOrigin::signed(1), 0, 5, 100));
+		System::assert_last_event(
+			Event::<Test>::OrderAmended {
+				order_index: 0,
+				new_order_index: 0,
+			}
+			.into(),
+		);
+		let order = Orders::<Test>::get(0).unwrap();
+		assert_eq!(order.amount_offered, 5);
+		assert_eq!(order.amout_requested, 100);
+		assert_eq!(order.unfilled_offered, 5);
+		assert_eq!(order.unfilled_requested, 100);
+		assert_eq!(
+			UserTokenInfoes::<Test>::get(1, 777),
+			TokenInfo {
+				amount: 95,
+				reserved: 5,
+			}
+		);
+
+		// a new price replaces the order
+		assert_ok!(Dex::amend_order(RuntimeOrigin::signed(2), 1, 10, 250));
+		System::assert_last_event(
+			Event::<Test>::OrderAmended {
+				order_index: 1,
+				new_order_index: 2,
+			}
+			.into(),
+		);
+		assert!(!Orders::<Test>::contains_key(1));
+		assert_eq!(
+			Orders::<Test>::get(2).unwrap().price,
+			FixedU128::from_u32(25)
+		);
+		assert_eq!(pair_orders((777, 888)), vec![0, 2]);
+		assert_eq!(
+			UserTokenInfoes::<Test>::get(2, 777),
+			TokenInfo {
+				amount: 90,
+				reserved: 10,
+			}
+		);
+
+		// the amended order is still first in the queue
+		assert_ok!(Dex::make_order(
+			RuntimeOrigin::signed(3),
+			777,
+			888,
+			100,
+			5,
+			OrderType::BUY,
+			1000
+		));
+		assert!(!Orders::<Test>::contains_key(0));
+		assert_eq!(
+			Orders::<Test>::get(2).unwrap().order_status,
+			OrderStatus::Pending
+		);
+
+		// filled orders can't be amended
+		assert_noop!(
+			Dex::amend_order(RuntimeOrigin::signed(1), 0, 5, 100),
+			Error::<Test>::OrderNotAmendable
+		);
+	})
+}
+
+#[test]
+fn test_batch_place_and_cancel() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(Dex::deposit(RuntimeOrigin::signed(1), 888, 300));
+		assert_ok!(Dex::deposit(RuntimeOrigin::signed(2), 888, 100));
+		let order = |offered_amount| OrderRequest {
+			asset_id_1: 777,
+			asset_id_2: 888,
+			offered_amount,
+			requested_amount: 10,
+			order_type: OrderType::BUY,
+			order_kind: OrderKind::Limit,
+			time_in_force: TimeInForce::GoodTillExpiration,
+			expiration_block: 1000,
+		};
+
+		// either all orders of a batch are placed or none
+		assert_noop!(
+			Dex::batch_place(
+				RuntimeOrigin::signed(1),
+				vec![order(100), order(300)].try_into().unwrap()
+			),
+			Error::<Test>::NotEnoughBalance
+		);
+		assert_ok!(Dex::batch_place(
+			RuntimeOrigin::signed(1),
+			vec![order(100), order(200)].try_into().unwrap()
+		));
+		assert_eq!(pair_orders((777, 888)), vec![0, 1]);
+		assert_eq!(
+			UserTokenInfoes::<Test>::get(1, 888),
+			TokenInfo {
+				amount: 0,
+				reserved: 300,
+			}
+		);
+
+		assert_ok!(Dex::batch_place(
+			RuntimeOrigin::signed(2),
+			vec![order(100)].try_into().unwrap()
+		));
+		assert_noop!(
+			Dex::batch_cancel(RuntimeOrigin::signed(1), vec![0, 2].try_into().unwrap()),
+			Error::<Test>::NotOwner
+		);
+		assert_ok!(Dex::batch_cancel(
+			RuntimeOrigin::signed(1),
+			vec![0, 1].try_into().unwrap()
+		));
+		assert_eq!(pair_orders((777, 888)), vec![2]);
+		assert_eq!(
+			UserTokenInfoes::<Test>::get(1, 888),
+			TokenInfo {
+				amount: 300,
+				reserved: 0,
+			}
+		);
+	})
+}
+
+#[test]
+fn test_cancel_all() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(Dex::deposit(RuntimeOrigin::signed(1), 888, 300));
+
+		// two orders in the 777/888 pair, whichever way round it is given
+		for (asset_id_1, asset_id_2, order_type) in
+			[(777, 888, OrderType::BUY), (888, 777, OrderType::SELL)]
+		{
+			assert_ok!(Dex::make_order(
+				RuntimeOrigin::signed(1),
+				asset_id_1,
+				asset_id_2,
+				100,
+				10,
+				order_type,
+				1000
+			));
+		}
+		assert_ok!(Dex::make_order(
+			RuntimeOrigin::signed(1),
+			888,
+			999,
+			100,
+			10,
+			OrderType::SELL,
+			1000
+		));
+
+		assert_ok!(Dex::cancel_all(RuntimeOrigin::signed(1), 888, 777));
+
+		assert_eq!(pair_orders((777, 888)), vec![]);
+		assert_eq!(pair_orders((888, 999)), vec![2]);
+		assert_eq!(UserOrderCount::<Test>::get(1), 1);
+		assert_eq!(
+			UserTokenInfoes::<Test>::get(1, 888),
+			TokenInfo {
+				amount: 200,
+				reserved: 100,
+			}
+		);
+	})
+}
+
+#[test]
+fn test_self_trade_prevention() {
+	for policy in [
+		SelfTradePolicy::CancelNewest,
+		SelfTradePolicy::CancelOldest,
+		SelfTradePolicy::DecrementAndCancel,
+	] {
+		new_test_ext().execute_with(|| {
+			DexMatchingMode::set(MatchingMode::OnOrderCreation);
+			DexSelfTradePolicy::set(policy);
+
+			assert_ok!(Dex::deposit(RuntimeOrigin::signed(1), 777, 100));
+			assert_ok!(Dex::deposit(RuntimeOrigin::signed(1), 888, 1000));
+			assert_ok!(Dex::deposit(RuntimeOrigin::signed(2), 777, 100));
+
+			// account 1 sells 10 base at 20, account 2 sells 10 base at 22
+			assert_ok!(Dex::make_order(
+				RuntimeOrigin::signed(1),
+				777,
+				888,
+				10,
+				200,
+				OrderType::SELL,
+				1000
+			));
+			assert_ok!(Dex::make_order(
+				RuntimeOrigin::signed(2),
+				777,
+				888,
+				10,
+				220,
+				OrderType::SELL,
+				1000
+			));
+
+			// account 1 buys 4 base at 25 and meets its own sell order first
+			assert_ok!(Dex::make_order(
+				RuntimeOrigin::signed(1),
+				777,
+				888,
+				100,
+				4,
+				OrderType::BUY,
+				1000
+			));
+
+			let quantity_base = match policy {
+				SelfTradePolicy::CancelNewest => {
+					assert!(!Orders::<Test>::contains_key(2));
+					assert_eq!(Orders::<Test>::get(0).unwrap().unfilled_offered, 10);
+					assert_eq!(Orders::<Test>::get(1).unwrap().unfilled_offered, 10);
+					assert_eq!(
+						UserTokenInfoes::<Test>::get(1, 888),
+						TokenInfo {
+							amount: 1000,
+							reserved: 0,
+						}
+					);
+					4
+				}
+				SelfTradePolicy::CancelOldest => {
+					// the buy order trades with account 2 once the own order is gone
+					assert!(!Orders::<Test>::contains_key(0));
+					assert_eq!(Orders::<Test>::get(1).unwrap().unfilled_offered, 6);
+					assert!(!Orders::<Test>::contains_key(2));
+					assert_eq!(
+						UserTokenInfoes::<Test>::get(1, 777),
+						TokenInfo {
+							amount: 104,
+							reserved: 0,
+						}
+					);
+					assert_eq!(
+						UserTokenInfoes::<Test>::get(1, 888),
+						TokenInfo {
+							amount: 912,
+							reserved: 0,
+						}
+					);
+					10
+				}
+				SelfTradePolicy::DecrementAndCancel => {
+					assert!(!Orders::<Test>::contains_key(2));
+					let order = Orders::<Test>::get(0).unwrap();
+					assert_eq!(order.unfilled_offered, 6);
+					assert_eq!(order.unfilled_requested, 120);
+					assert_eq!(order.order_status, OrderStatus::Pending);
+					assert_eq!(
+						MatchEngines::<Test>::get((777, 888))
+							.unwrap()
+							.sell_book
+							.book
+							.values()
+							.find(|order| order.counter == 0),
+						Some(&order)
+					);
+					assert_eq!(
+						UserTokenInfoes::<Test>::get(1, 777),
+						TokenInfo {
+							amount: 94,
+							reserved: 6,
+						}
+					);
+					assert_eq!(
+						UserTokenInfoes::<Test>::get(1, 888),
+						TokenInfo {
+							amount: 1000,
+							reserved: 0,
+						}
+					);
+					4
+				}
+			};
+
+			System::assert_last_event(
+				Event::<Test>::SelfTradePrevented {
+					taker_order_index: 2,
+					maker_order_index: 0,
+					policy,
+					quantity_base,
+				}
+				.into(),
+			);
+		})
+	}
+}
+
+#[test]
+fn test_update_match_order_rejects_self_trade() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(Dex::deposit(RuntimeOrigin::signed(1), 777, 100));
+		assert_ok!(Dex::deposit(RuntimeOrigin::signed(1), 888, 1000));
+
+		assert_ok!(Dex::make_order(
+			RuntimeOrigin::signed(1),
+			777,
+			888,
+			10,
+			200,
+			OrderType::SELL,
+			1000
+		));
+		assert_ok!(Dex::make_order(
+			RuntimeOrigin::signed(1),
+			777,
+			888,
+			100,
+			4,
+			OrderType::BUY,
+			1000
+		));
+
+		let (match_result, signature) = signed_match(vec![make_trade(1, 0, 20, 4, 80)]);
+		assert_noop!(
+			Dex::update_match_order_unsigned(RuntimeOrigin::none(), match_result, 0, signature),
+			Error::<Test>::SelfTrade
+		);
+
+		// the offchain worker reports the orders instead and the policy is applied on chain
+		let match_result = MatchResult {
+			taker_order: Orders::<Test>::get(1).unwrap(),
+			match_details: vec![],
+			self_trades: vec![SelfTrade {
+				taker_order: 1,
+				maker_order: 0,
+				trades_before: 0,
+			}],
+		};
+		let signature = (&match_result, 0u32)
+			.using_encoded(|payload| UintAuthorityId(0).sign(&payload))
+			.unwrap();
+		assert_ok!(Dex::update_match_order_unsigned(
+			RuntimeOrigin::none(),
+			match_result,
+			0,
+			signature
+		));
+
+		assert!(!Orders::<Test>::contains_key(1));
+		assert_eq!(Orders::<Test>::get(0).unwrap().unfilled_offered, 10);
+		System::assert_last_event(
+			Event::<Test>::SelfTradePrevented {
+				taker_order_index: 1,
+				maker_order_index: 0,
+				policy: SelfTradePolicy::CancelNewest,
+				quantity_base: 4,
+			}
+			.into(),
+		);
+	})
+}
+
+#[test]
+fn test_self_trade_prevention_on_take_order() {
+	for policy in [
+		SelfTradePolicy::CancelNewest,
+		SelfTradePolicy::CancelOldest,
+		SelfTradePolicy::DecrementAndCancel,
+	] {
+		new_test_ext().execute_with(|| {
+			DexSelfTradePolicy::set(policy);
+
+			assert_ok!(Dex::deposit(RuntimeOrigin::signed(1), 777, 100));
+			assert_ok!(Dex::deposit(RuntimeOrigin::signed(1), 888, 1000));
+			assert_ok!(Dex::make_order(
+				RuntimeOrigin::signed(1),
+				777,
+				888,
+				10,
+				200,
+				OrderType::SELL,
+				1000
+			));
+
+			// the account takes its own order, nothing is traded
+			assert_ok!(Dex::take_order(RuntimeOrigin::signed(1), 0));
+			System::assert_last_event(
+				Event::<Test>::SelfTakePrevented {
+					order_index: 0,
+					policy,
+					quantity_base: 10,
+				}
+				.into(),
+			);
+			assert_eq!(Dex::trading_volume(&1, (777, 888)), 0);
+			assert_eq!(
+				UserTokenInfoes::<Test>::get(1, 888),
+				TokenInfo {
+					amount: 1000,
+					reserved: 0,
+				}
+			);
+
+			match policy {
+				SelfTradePolicy::CancelNewest => {
+					assert!(Orders::<Test>::contains_key(0));
+					assert_eq!(
+						UserTokenInfoes::<Test>::get(1, 777),
+						TokenInfo {
+							amount: 90,
+							reserved: 10,
+						}
+					);
+				}
+				SelfTradePolicy::CancelOldest | SelfTradePolicy::DecrementAndCancel => {
+					assert!(!Orders::<Test>::contains_key(0));
+					assert_eq!(
+						UserTokenInfoes::<Test>::get(1, 777),
+						TokenInfo {
+							amount: 100,
+							reserved: 0,
+						}
+					);
+				}
+			}
+		})
+	}
+}
+
+fn submitted_match_results(
+	transactions: Vec<Vec<u8>>,
+) -> Vec<(
+	MatchResult<Balance, Order<AccountId, Balance, BlockNumber>>,
+	TestSignature,
+)> {
+	transactions
+		.into_iter()
+		.map(|tx| match Extrinsic::decode(&mut &*tx).unwrap().call {
+			RuntimeCall::Dex(crate::Call::update_match_order_unsigned {
+				match_result,
+				signature,
+				..
+			}) => (match_result, signature),
+			call => panic!("unexpected call {:?}", call),
+		})
+		.collect()
+}
+
+fn matching_health() -> MatchingHealth<BlockNumber> {
+	StorageValueRef::persistent(OCW_HEALTH_KEY)
+		.get()
+		.unwrap()
+		.unwrap()
+}
+
+#[test]
+fn test_offchain_worker_skips_cancelled_orders() {
+	use frame_support::traits::OffchainWorker;
+
+	let mut ext = new_test_ext();
+	let (offchain, _offchain_state) = TestOffchainExt::new();
+	let (pool, pool_state) = TestTransactionPoolExt::new();
+	ext.register_extension(OffchainDbExt::new(offchain.clone()));
+	ext.register_extension(OffchainWorkerExt::new(offchain));
+	ext.register_extension(TransactionPoolExt::new(pool));
+
+	UintAuthorityId::set_all_keys(vec![0u64]);
+
+	ext.execute_with(|| {
+		assert_ok!(Dex::deposit(RuntimeOrigin::signed(1), 777, 100));
+		assert_ok!(Dex::deposit(RuntimeOrigin::signed(2), 888, 1000));
+
+		Dex::offchain_worker(1);
+		assert_eq!(matching_health().last_rebuild_block, 1);
+
+		// order 0 is cancelled before the offchain worker gets to it
+		assert_ok!(Dex::make_order(
+			RuntimeOrigin::signed(1),
+			777,
+			888,
+			10,
+			200,
+			OrderType::SELL,
+			1000
+		));
+		assert_ok!(Dex::cancel_order(RuntimeOrigin::signed(1), 0));
+		assert_ok!(Dex::make_order(
+			RuntimeOrigin::signed(1),
+			777,
+			888,
+			10,
+			200,
+			OrderType::SELL,
+			1000
+		));
+		assert_ok!(Dex::make_order(
+			RuntimeOrigin::signed(2),
+			777,
+			888,
+			100,
+			4,
+			OrderType::BUY,
+			1000
+		));
+
+		Dex::offchain_worker(1);
+
+		let mut results =
+			submitted_match_results(std::mem::take(&mut pool_state.write().transactions));
+		assert_eq!(results.len(), 1);
+		let (match_result, signature) = results.remove(0);
+		assert_eq!(match_result.taker_order.counter, 2);
+		assert_eq!(match_result.match_details.len(), 1);
+		assert_eq!(match_result.match_details[0].maker_order.counter, 1);
+
+		assert_eq!(
+			matching_health(),
+			MatchingHealth {
+				block_number: 1,
+				next_order_index: 3,
+				pending_orders: 0,
+				last_rebuild_block: 1,
+			}
+		);
+
+		// the match result can be applied once
+		let call = crate::Call::update_match_order_unsigned {
+			match_result: match_result.clone(),
+			authority_index: 0,
+			signature: signature.clone(),
+		};
+		assert_ok!(Dex::validate_unsigned(TransactionSource::Local, &call));
+		assert_ok!(Dex::update_match_order_unsigned(
+			RuntimeOrigin::none(),
+			match_result,
+			0,
+			signature
+		));
+		assert_eq!(
+			Dex::validate_unsigned(TransactionSource::Local, &call),
+			Err(InvalidTransaction::Stale.into())
+		);
+	})
+}
+
+#[test]
+fn test_offchain_worker_rebuilds_order_books_after_reorg() {
+	use frame_support::traits::OffchainWorker;
+
+	let mut ext = new_test_ext();
+	let (offchain, _offchain_state) = TestOffchainExt::new();
+	let (pool, pool_state) = TestTransactionPoolExt::new();
+	ext.register_extension(OffchainDbExt::new(offchain.clone()));
+	ext.register_extension(OffchainWorkerExt::new(offchain));
+	ext.register_extension(TransactionPoolExt::new(pool));
+
+	UintAuthorityId::set_all_keys(vec![0u64]);
+
+	ext.execute_with(|| {
+		assert_ok!(Dex::deposit(RuntimeOrigin::signed(1), 777, 100));
+		assert_ok!(Dex::deposit(RuntimeOrigin::signed(2), 888, 1000));
+
+		assert_ok!(Dex::make_order(
+			RuntimeOrigin::signed(1),
+			777,
+			888,
+			10,
+			200,
+			OrderType::SELL,
+			1000
+		));
+		Dex::offchain_worker(1);
+
+		// the order books the worker saved belong to a fork that was abandoned
+		StorageValueRef::persistent(b"dex_ocw::match_engines").clear();
+		frame_system::BlockHash::<Test>::insert(1, H256::repeat_byte(1));
+
+		assert_ok!(Dex::make_order(
+			RuntimeOrigin::signed(2),
+			777,
+			888,
+			100,
+			4,
+			OrderType::BUY,
+			1000
+		));
+		System::set_block_number(2);
+		Dex::offchain_worker(2);
+
+		// order 0 is back on the rebuilt book and order 1 trades with it
+		let results = submitted_match_results(std::mem::take(&mut pool_state.write().transactions));
+		assert_eq!(results.len(), 1);
+		let (match_result, _) = &results[0];
+		assert_eq!(match_result.taker_order.counter, 1);
+		assert_eq!(match_result.match_details[0].maker_order.counter, 0);
+		assert_eq!(matching_health().last_rebuild_block, 2);
+
+		// without a reorg the saved state is used
+		Dex::offchain_worker(2);
+		assert!(pool_state.read().transactions.is_empty());
+		assert_eq!(matching_health().last_rebuild_block, 2);
+	})
+}
+
+fn stop_loss(
+	source: TriggerSource,
+	price: FixedU128,
+	expiration_block: BlockNumber,
+) -> (OrderRequest<Balance, BlockNumber>, Trigger) {
+	let order = OrderRequest {
+		asset_id_1: 777,
+		asset_id_2: 888,
+		offered_amount: 10,
+		requested_amount: 100,
+		order_type: OrderType::SELL,
+		order_kind: OrderKind::Limit,
+		time_in_force: TimeInForce::GoodTillExpiration,
+		expiration_block,
+	};
+	let trigger = Trigger {
+		kind: ConditionalOrderKind::StopLoss,
+		source,
+		price,
+	};
+	(order, trigger)
+}
+
+#[test]
+fn test_place_and_cancel_conditional_order() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(Dex::deposit(RuntimeOrigin::signed(1), 888, 100));
+		let order = OrderRequest {
+			asset_id_1: 888,
+			asset_id_2: 777,
+			offered_amount: 100,
+			requested_amount: 10,
+			order_type: OrderType::BUY,
+			order_kind: OrderKind::Limit,
+			time_in_force: TimeInForce::GoodTillExpiration,
+			expiration_block: 5,
+		};
+		let trigger = |price| Trigger {
+			kind: ConditionalOrderKind::TakeProfit,
+			source: TriggerSource::LastTradePrice,
+			price,
+		};
+
+		assert_noop!(
+			Dex::place_conditional_order(
+				RuntimeOrigin::signed(1),
+				order.clone(),
+				trigger(FixedU128::zero())
+			),
+			Error::<Test>::InvalidTriggerPrice
+		);
+		assert_noop!(
+			Dex::place_conditional_order(
+				RuntimeOrigin::signed(1),
+				OrderRequest {
+					offered_amount: 200,
+					..order.clone()
+				},
+				trigger(FixedU128::from_rational(1, 10))
+			),
+			Error::<Test>::NotEnoughBalance
+		);
+
+		// the order is kept in terms of the 777/888 pair, and so is its trigger price
+		assert_ok!(Dex::place_conditional_order(
+			RuntimeOrigin::signed(1),
+			order.clone(),
+			trigger(FixedU128::from_rational(1, 10))
+		));
+		let conditional_order = ConditionalOrders::<Test>::get(0).unwrap();
+		assert_eq!(conditional_order.pair(), (777, 888));
+		assert_eq!(conditional_order.order.order_type, OrderType::SELL);
+		assert_eq!(
+			conditional_order.trigger.price,
+			FixedU128::saturating_from_integer(10)
+		);
+		assert_eq!(
+			PairConditionalOrders::<Test>::get((777, 888)).to_vec(),
+			vec![0]
+		);
+		assert_eq!(
+			UserTokenInfoes::<Test>::get(1, 888),
+			TokenInfo {
+				amount: 0,
+				reserved: 100,
+			}
+		);
+
+		assert_noop!(
+			Dex::cancel_conditional_order(RuntimeOrigin::signed(2), 0),
+			Error::<Test>::NotOwner
+		);
+		assert_ok!(Dex::cancel_conditional_order(RuntimeOrigin::signed(1), 0));
+		assert!(!ConditionalOrders::<Test>::contains_key(0));
+		assert!(!PairConditionalOrders::<Test>::contains_key((777, 888)));
+		assert_eq!(
+			UserTokenInfoes::<Test>::get(1, 888),
+			TokenInfo {
+				amount: 100,
+				reserved: 0,
+			}
+		);
+		assert_noop!(
+			Dex::cancel_conditional_order(RuntimeOrigin::signed(1), 0),
+			Error::<Test>::InvalidConditionalOrderIndex
+		);
+
+		// a conditional order that expires before its trigger fires is canceled
+		assert_ok!(Dex::place_conditional_order(
+			RuntimeOrigin::signed(1),
+			order,
+			trigger(FixedU128::from_rational(1, 10))
+		));
+		Dex::on_idle(5, Weight::MAX);
+		assert!(!ConditionalOrders::<Test>::contains_key(1));
+		System::assert_last_event(
+			Event::<Test>::ConditionalOrderCanceled {
+				conditional_order_index: 1,
+			}
+			.into(),
+		);
+		assert_eq!(
+			UserTokenInfoes::<Test>::get(1, 888),
+			TokenInfo {
+				amount: 100,
+				reserved: 0,
+			}
+		);
+	})
+}
+
+#[test]
+fn test_stop_loss_triggers_on_last_trade_price() {
+	new_test_ext().execute_with(|| {
+		DexMatchingMode::set(MatchingMode::OnOrderCreation);
+
+		assert_ok!(Dex::deposit(RuntimeOrigin::signed(1), 777, 100));
+		assert_ok!(Dex::deposit(RuntimeOrigin::signed(2), 888, 1000));
+
+		let (order, trigger) = stop_loss(
+			TriggerSource::LastTradePrice,
+			FixedU128::saturating_from_integer(15),
+			1000,
+		);
+		assert_ok!(Dex::place_conditional_order(
+			RuntimeOrigin::signed(1),
+			order,
+			trigger
+		));
+
+		// nothing traded yet
+		Dex::on_idle(1, Weight::MAX);
+		assert!(ConditionalOrders::<Test>::contains_key(0));
+
+		let trade = |quote_amount| {
+			assert_ok!(Dex::make_order(
+				RuntimeOrigin::signed(1),
+				777,
+				888,
+				10,
+				quote_amount,
+				OrderType::SELL,
+				1000
+			));
+			assert_ok!(Dex::make_order(
+				RuntimeOrigin::signed(2),
+				777,
+				888,
+				quote_amount,
+				10,
+				OrderType::BUY,
+				1000
+			));
+		};
+
+		trade(200);
+		assert_eq!(
+			LastTradePrice::<Test>::get((777, 888)),
+			Some(FixedU128::saturating_from_integer(20))
+		);
+		Dex::on_idle(1, Weight::MAX);
+		assert!(ConditionalOrders::<Test>::contains_key(0));
+
+		trade(120);
+		Dex::on_idle(1, Weight::MAX);
+		assert!(!ConditionalOrders::<Test>::contains_key(0));
+		assert!(!PairConditionalOrders::<Test>::contains_key((777, 888)));
+		System::assert_last_event(
+			Event::<Test>::ConditionalOrderTriggered {
+				conditional_order_index: 0,
+				order_index: 4,
+			}
+			.into(),
+		);
+		assert_eq!(pair_orders((777, 888)), vec![4]);
+		let order = Orders::<Test>::get(4).unwrap();
+		assert_eq!(order.address, 1);
+		assert_eq!(order.order_type, OrderType::SELL);
+		assert_eq!(order.amount_offered, 10);
+		assert_eq!(
+			UserTokenInfoes::<Test>::get(1, 777),
+			TokenInfo {
+				amount: 70,
+				reserved: 10,
+			}
+		);
+	})
+}
+
+#[test]
+fn test_take_profit_triggers_on_oracle_price() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(Dex::deposit(RuntimeOrigin::signed(2), 888, 100));
+
+		assert_ok!(Dex::place_conditional_order(
+			RuntimeOrigin::signed(2),
+			OrderRequest {
+				asset_id_1: 777,
+				asset_id_2: 888,
+				offered_amount: 100,
+				requested_amount: 10,
+				order_type: OrderType::BUY,
+				order_kind: OrderKind::Limit,
+				time_in_force: TimeInForce::GoodTillExpiration,
+				expiration_block: 1000,
+			},
+			Trigger {
+				kind: ConditionalOrderKind::TakeProfit,
+				source: TriggerSource::Oracle,
+				price: FixedU128::saturating_from_integer(10),
+			}
+		));
+
+		Dex::on_idle(1, Weight::MAX);
+		assert!(ConditionalOrders::<Test>::contains_key(0));
+
+		OraclePrice::set(Some(FixedU128::saturating_from_integer(11)));
+		Dex::on_idle(1, Weight::MAX);
+		assert!(ConditionalOrders::<Test>::contains_key(0));
+
+		OraclePrice::set(Some(FixedU128::saturating_from_integer(10)));
+		Dex::on_idle(1, Weight::MAX);
+		assert!(!ConditionalOrders::<Test>::contains_key(0));
+		assert_eq!(pair_orders((777, 888)), vec![0]);
+		assert_eq!(
+			Orders::<Test>::get(0).unwrap().order_status,
+			OrderStatus::Pending
+		);
+		assert_eq!(
+			UserTokenInfoes::<Test>::get(2, 888),
+			TokenInfo {
+				amount: 0,
+				reserved: 100,
+			}
+		);
+	})
+}
+
+#[test]
+fn test_conditional_order_limits() {
+	new_test_ext().execute_with(|| {
+		MaxConditionalOrdersPerPair::set(3);
+		assert_ok!(Dex::deposit(RuntimeOrigin::signed(1), 777, 100));
+
+		let (order, trigger) = stop_loss(
+			TriggerSource::Oracle,
+			FixedU128::saturating_from_integer(15),
+			1000,
+		);
+		for _ in 0..3 {
+			assert_ok!(Dex::place_conditional_order(
+				RuntimeOrigin::signed(1),
+				order.clone(),
+				trigger.clone()
+			));
+		}
+		assert_noop!(
+			Dex::place_conditional_order(RuntimeOrigin::signed(1), order, trigger),
+			Error::<Test>::TooManyConditionalOrders
+		);
+
+		// at most two conditional orders are triggered per block, the rest waits
+		OraclePrice::set(Some(FixedU128::saturating_from_integer(14)));
+		Dex::on_idle(1, Weight::MAX);
+		assert_eq!(
+			PairConditionalOrders::<Test>::get((777, 888)).to_vec(),
+			vec![2]
+		);
+		assert_eq!(pair_orders((777, 888)), vec![0, 1]);
+
+		// and the weight left in the block is respected
+		Dex::on_idle(2, Weight::zero());
+		assert!(ConditionalOrders::<Test>::contains_key(2));
+
+		Dex::on_idle(2, Weight::MAX);
+		assert!(!PairConditionalOrders::<Test>::contains_key((777, 888)));
+		assert_eq!(pair_orders((777, 888)), vec![0, 1, 2]);
+	})
+}
+
+#[test]
+fn test_candles() {
+	new_test_ext().execute_with(|| {
+		DexMatchingMode::set(MatchingMode::OnOrderCreation);
+
+		assert_ok!(Dex::deposit(RuntimeOrigin::signed(1), 777, 100));
+		assert_ok!(Dex::deposit(RuntimeOrigin::signed(2), 888, 1000));
+
+		let sell_and_take = |quote_amount| {
+			assert_ok!(Dex::make_order(
+				RuntimeOrigin::signed(1),
+				777,
+				888,
+				10,
+				quote_amount,
+				OrderType::SELL,
+				1000
+			));
+			let order_index = NextOrderIndex::<Test>::get() - 1;
+			assert_ok!(Dex::take_order(RuntimeOrigin::signed(2), order_index));
+		};
+		let price = FixedU128::saturating_from_integer;
+
+		// a matched trade at 20
+		assert_ok!(Dex::make_order(
+			RuntimeOrigin::signed(1),
+			777,
+			888,
+			10,
+			200,
+			OrderType::SELL,
+			1000
+		));
+		assert_ok!(Dex::make_order(
+			RuntimeOrigin::signed(2),
+			777,
+			888,
+			200,
+			10,
+			OrderType::BUY,
+			1000
+		));
+
+		// and taken orders at 12, and at 15 in the next minute
+		Timestamp::set_timestamp(30_000);
+		sell_and_take(120);
+		Timestamp::set_timestamp(70_000);
+		sell_and_take(150);
+
+		assert_eq!(
+			Dex::candles((777, 888), 60, 10),
+			vec![
+				Candle {
+					start: 0,
+					open: price(20),
+					high: price(20),
+					low: price(12),
+					close: price(12),
+					volume_base: 20,
+					volume_quote: 320,
+					trade_count: 2,
+				},
+				Candle {
+					start: 60,
+					open: price(15),
+					high: price(15),
+					low: price(15),
+					close: price(15),
+					volume_base: 10,
+					volume_quote: 150,
+					trade_count: 1,
+				},
+			]
+		);
+		assert_eq!(
+			Dex::candles((777, 888), 3_600, 10),
+			vec![Candle {
+				start: 0,
+				open: price(20),
+				high: price(20),
+				low: price(12),
+				close: price(15),
+				volume_base: 30,
+				volume_quote: 470,
+				trade_count: 3,
+			}]
+		);
+		assert_eq!(Dex::candles((777, 888), 120, 10), vec![]);
+
+		// only the last three minutes are kept, the fifth minute replaces the second
+		Timestamp::set_timestamp(250_000);
+		sell_and_take(160);
+		let starts = |limit| {
+			Dex::candles((777, 888), 60, limit)
+				.into_iter()
+				.map(|candle| candle.start)
+				.collect::<Vec<_>>()
+		};
+		assert_eq!(starts(10), vec![0, 240]);
+		assert_eq!(starts(1), vec![240]);
+	})
+}
+
+#[test]
+fn test_twap() {
+	new_test_ext().execute_with(|| {
+		DexPoolFee::set(Permill::zero());
+		assert_ok!(Dex::deposit(RuntimeOrigin::signed(1), 777, 3_100));
+		assert_ok!(Dex::deposit(RuntimeOrigin::signed(1), 888, 2_000));
+		assert_ok!(Dex::deposit(RuntimeOrigin::signed(2), 888, 2_500));
+		let price = FixedU128::saturating_from_integer;
+
+		assert_eq!(Dex::twap((777, 888), 0), None);
+
+		// the pool is at 1 for 30 seconds, at 4 for 60 seconds and at 1 since then
+		assert_ok!(Dex::add_liquidity(
+			RuntimeOrigin::signed(1),
+			777,
+			888,
+			2_000,
+			2_000,
+			0
+		));
+		Timestamp::set_timestamp(30_000);
+		assert_ok!(Dex::swap(RuntimeOrigin::signed(2), 888, 777, 2_000, 0));
+		assert_eq!(
+			LiquidityPools::<Test>::get((777, 888)).and_then(|pool| pool.price()),
+			Some(price(4))
+		);
+
+		// a trade of a single lot on the order book doesn't price the pair
+		Timestamp::set_timestamp(60_000);
+		assert_ok!(Dex::make_order(
+			RuntimeOrigin::signed(1),
+			777,
+			888,
+			1,
+			100,
+			OrderType::SELL,
+			1000
+		));
+		let order_index = NextOrderIndex::<Test>::get() - 1;
+		assert_ok!(Dex::take_order(RuntimeOrigin::signed(2), order_index));
+		assert_eq!(Dex::last_trade_price((777, 888)), Some(price(100)));
+
+		Timestamp::set_timestamp(90_000);
+		assert_ok!(Dex::swap(RuntimeOrigin::signed(1), 777, 888, 1_000, 0));
+		Timestamp::set_timestamp(150_000);
+
+		// the price before each swap is accumulated
+		assert_eq!(
+			Dex::price_accumulators((777, 888)),
+			Some(PriceAccumulator {
+				cumulative_price: price(270),
+				last_update: 90,
+			})
+		);
+		// the first change of the pool in each minute is observed
+		assert_eq!(
+			PriceObservations::<Test>::iter_prefix_values((777, 888)).count(),
+			2
+		);
+		assert_eq!(
+			Dex::twap((777, 888), 60),
+			Some(Twap {
+				price: price(1),
+				start: 90,
+				end: 150,
+			})
+		);
+		// averaged from the latest observation that is old enough
+		assert_eq!(
+			Dex::twap((777, 888), 100),
+			Some(Twap {
+				price: FixedU128::saturating_from_rational(11, 5),
+				start: 0,
+				end: 150,
+			})
+		);
+		assert_eq!(
+			<Dex as TwapProvider>::twap((777, 888), 100),
+			Some(FixedU128::saturating_from_rational(11, 5))
+		);
+		assert_eq!(Dex::twap((777, 888), 200), None);
+
+		// only four minutes are observed, the sixth minute replaces the second
+		Timestamp::set_timestamp(330_000);
+		assert_ok!(Dex::swap(RuntimeOrigin::signed(2), 888, 777, 100, 0));
+		assert_eq!(
+			PriceObservations::<Test>::get((777, 888), 1),
+			Some(PriceObservation {
+				timestamp: 330,
+				cumulative_price: price(510),
+			})
+		);
+	})
+}
+
+#[test]
+fn test_register_trading_pair() {
+	new_test_ext().execute_with(|| {
+		assert_noop!(
+			Dex::register_trading_pair(RuntimeOrigin::signed(1), 777, 999, 5),
+			sp_runtime::DispatchError::BadOrigin
+		);
+		assert_noop!(
+			Dex::register_trading_pair(RuntimeOrigin::root(), 777, 888, 5),
+			Error::<Test>::PairAlreadyRegistered
+		);
+		assert_noop!(
+			Dex::register_trading_pair(RuntimeOrigin::root(), 999, 777, 5),
+			Error::<Test>::InvalidPairConfig
+		);
+		assert_noop!(
+			Dex::register_trading_pair(RuntimeOrigin::root(), 777, 1234, 5),
+			Error::<Test>::AssetIdNotInTokenIndex
+		);
+
+		assert_ok!(Dex::deposit(RuntimeOrigin::signed(1), 777, 100));
+		TradingPairs::<Test>::remove((777, 999));
+		assert_noop!(
+			Dex::make_order(
+				RuntimeOrigin::signed(1),
+				777,
+				999,
+				10,
+				20,
+				OrderType::SELL,
+				1000
+			),
+			Error::<Test>::PairNotRegistered
+		);
+
+		assert_ok!(Dex::register_trading_pair(
+			RuntimeOrigin::root(),
+			777,
+			999,
+			5
+		));
+		System::assert_last_event(
+			Event::<Test>::TradingPairRegistered {
+				pair: (777, 999),
+				trading_pair: TradingPair {
+					status: PairStatus::Active,
+					min_order_size: 5,
+				},
+			}
+			.into(),
+		);
+		assert_noop!(
+			Dex::make_order(
+				RuntimeOrigin::signed(1),
+				777,
+				999,
+				4,
+				20,
+				OrderType::SELL,
+				1000
+			),
+			Error::<Test>::BelowMinOrderSize
+		);
+		assert_ok!(Dex::make_order(
+			RuntimeOrigin::signed(1),
+			777,
+			999,
+			5,
+			20,
+			OrderType::SELL,
+			1000
+		));
+
+		// resting orders below a new minimum size can't be amended
+		assert_ok!(Dex::set_min_order_size(RuntimeOrigin::root(), 777, 999, 6));
+		assert_noop!(
+			Dex::amend_order(RuntimeOrigin::signed(1), 0, 5, 20),
+			Error::<Test>::BelowMinOrderSize
+		);
+		assert_noop!(
+			Dex::set_min_order_size(RuntimeOrigin::root(), 999, 777, 6),
+			Error::<Test>::PairNotRegistered
+		);
+	})
+}
+
+#[test]
+fn test_trading_pair_status() {
+	new_test_ext().execute_with(|| {
+		DexMatchingMode::set(MatchingMode::OnIdle);
+
+		assert_ok!(Dex::deposit(RuntimeOrigin::signed(1), 777, 100));
+		assert_ok!(Dex::deposit(RuntimeOrigin::signed(2), 888, 1000));
+
+		assert_ok!(Dex::make_order(
+			RuntimeOrigin::signed(1),
+			777,
+			888,
+			10,
+			200,
+			OrderType::SELL,
+			1000
+		));
+		assert_ok!(Dex::make_order(
+			RuntimeOrigin::signed(2),
+			777,
+			888,
+			200,
+			10,
+			OrderType::BUY,
+			1000
+		));
+
+		// only post-only orders are placed and no order is taken
+		assert_ok!(Dex::set_trading_pair_status(
+			RuntimeOrigin::root(),
+			777,
+			888,
+			PairStatus::PostOnly
+		));
+		System::assert_last_event(
+			Event::<Test>::TradingPairStatusChanged {
+				pair: (777, 888),
+				status: PairStatus::PostOnly,
+			}
+			.into(),
+		);
+		assert_noop!(
+			Dex::make_order(
+				RuntimeOrigin::signed(1),
+				777,
+				888,
+				10,
+				300,
+				OrderType::SELL,
+				1000
+			),
+			Error::<Test>::PairIsPostOnly
+		);
+		assert_noop!(
+			Dex::take_order(RuntimeOrigin::signed(2), 0),
+			Error::<Test>::PairIsPostOnly
+		);
+		assert_ok!(Dex::place_order(
+			RuntimeOrigin::signed(1),
+			777,
+			888,
+			10,
+			300,
+			OrderType::SELL,
+			OrderKind::Limit,
+			TimeInForce::PostOnly,
+			1000
+		));
+
+		// nothing is placed or matched while the pair is halted, but orders are canceled
+		assert_ok!(Dex::set_trading_pair_status(
+			RuntimeOrigin::root(),
+			777,
+			888,
+			PairStatus::Halted
+		));
+		assert_noop!(
+			Dex::place_order(
+				RuntimeOrigin::signed(1),
+				777,
+				888,
+				10,
+				300,
+				OrderType::SELL,
+				OrderKind::Limit,
+				TimeInForce::PostOnly,
+				1000
+			),
+			Error::<Test>::PairNotTradable
+		);
+		assert_noop!(
+			Dex::take_order(RuntimeOrigin::signed(2), 0),
+			Error::<Test>::PairNotTradable
+		);
+		assert_ok!(Dex::cancel_order(RuntimeOrigin::signed(1), 2));
+
+		Dex::on_idle(1, Weight::MAX);
+		assert_eq!(NextMatchOrderIndex::<Test>::get(), 3);
+		assert_eq!(pair_orders((777, 888)), vec![0, 1]);
+		assert_eq!(
+			Orders::<Test>::get(1).unwrap().order_status,
+			OrderStatus::Pending
+		);
+
+		// once trading resumes the resting orders trade with new orders
+		assert_ok!(Dex::set_trading_pair_status(
+			RuntimeOrigin::root(),
+			777,
+			888,
+			PairStatus::Active
+		));
+		assert_ok!(Dex::make_order(
+			RuntimeOrigin::signed(2),
+			777,
+			888,
+			200,
+			10,
+			OrderType::BUY,
+			1000
+		));
+		Dex::on_idle(1, Weight::MAX);
+		assert!(!Orders::<Test>::contains_key(0));
+	})
+}
+
+#[test]
+fn test_delisting_cancels_orders() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(Dex::deposit(RuntimeOrigin::signed(1), 777, 100));
+		assert_ok!(Dex::deposit(RuntimeOrigin::signed(1), 888, 100));
+		assert_ok!(Dex::deposit(RuntimeOrigin::signed(2), 888, 1000));
+
+		assert_ok!(Dex::make_order(
+			RuntimeOrigin::signed(1),
+			777,
+			888,
+			10,
+			200,
+			OrderType::SELL,
+			1000
+		));
+		assert_ok!(Dex::make_order(
+			RuntimeOrigin::signed(2),
+			777,
+			888,
+			100,
+			10,
+			OrderType::BUY,
+			1000
+		));
+		assert_ok!(Dex::make_order(
+			RuntimeOrigin::signed(1),
+			888,
+			999,
+			100,
+			10,
+			OrderType::SELL,
+			1000
+		));
+		let (order, trigger) = stop_loss(
+			TriggerSource::LastTradePrice,
+			FixedU128::saturating_from_integer(15),
+			1000,
+		);
+		assert_ok!(Dex::place_conditional_order(
+			RuntimeOrigin::signed(1),
+			order,
+			trigger
+		));
+
+		assert_ok!(Dex::set_trading_pair_status(
+			RuntimeOrigin::root(),
+			777,
+			888,
+			PairStatus::Delisted
+		));
+
+		assert_eq!(pair_orders((777, 888)), vec![]);
+		assert_eq!(pair_orders((888, 999)), vec![2]);
+		assert!(!ConditionalOrders::<Test>::contains_key(0));
+		assert_eq!(
+			UserTokenInfoes::<Test>::get(1, 777),
+			TokenInfo {
+				amount: 100,
+				reserved: 0,
+			}
+		);
+		assert_eq!(
+			UserTokenInfoes::<Test>::get(2, 888),
+			TokenInfo {
+				amount: 1000,
+				reserved: 0,
+			}
+		);
+		assert_eq!(
+			UserTokenInfoes::<Test>::get(1, 888),
+			TokenInfo {
+				amount: 0,
+				reserved: 100,
+			}
+		);
+
+		assert_noop!(
+			Dex::make_order(
+				RuntimeOrigin::signed(1),
+				777,
+				888,
+				10,
+				200,
+				OrderType::SELL,
+				1000
+			),
+			Error::<Test>::PairNotTradable
+		);
+	})
+}
+
+fn trade_at(quantity_base: u128, price: u128) {
+	assert_ok!(Dex::make_order(
+		RuntimeOrigin::signed(1),
+		777,
+		888,
+		quantity_base,
+		quantity_base * price,
+		OrderType::SELL,
+		1000
+	));
+	assert_ok!(Dex::make_order(
+		RuntimeOrigin::signed(2),
+		777,
+		888,
+		quantity_base * price,
+		quantity_base,
+		OrderType::BUY,
+		1000
+	));
+}
+
+#[test]
+fn test_price_band() {
+	new_test_ext().execute_with(|| {
+		DexMatchingMode::set(MatchingMode::OnOrderCreation);
+
+		assert_ok!(Dex::deposit(RuntimeOrigin::signed(1), 777, 100));
+		assert_ok!(Dex::deposit(RuntimeOrigin::signed(2), 888, 1000));
+
+		let price_band = PriceBand {
+			reference: ReferencePrice::Oracle,
+			max_deviation: Permill::from_percent(10),
+			max_move: Permill::from_percent(50),
+			window: 10,
+			halt_duration: 5,
+		};
+		assert_noop!(
+			Dex::set_price_band(RuntimeOrigin::signed(1), 777, 888, Some(price_band.clone())),
+			sp_runtime::DispatchError::BadOrigin
+		);
+		assert_noop!(
+			Dex::set_price_band(RuntimeOrigin::root(), 888, 777, Some(price_band.clone())),
+			Error::<Test>::PairNotRegistered
+		);
+		assert_noop!(
+			Dex::set_price_band(
+				RuntimeOrigin::root(),
+				777,
+				888,
+				Some(PriceBand {
+					window: 0,
+					..price_band.clone()
+				})
+			),
+			Error::<Test>::InvalidPriceBand
+		);
+		assert_ok!(Dex::set_price_band(
+			RuntimeOrigin::root(),
+			777,
+			888,
+			Some(price_band.clone())
+		));
+		System::assert_last_event(
+			Event::<Test>::PriceBandSet {
+				pair: (777, 888),
+				price_band: Some(price_band),
+			}
+			.into(),
+		);
+
+		// without a reference price orders are not limited, 5 base at 30
+		assert_ok!(Dex::make_order(
+			RuntimeOrigin::signed(1),
+			777,
+			888,
+			5,
+			150,
+			OrderType::SELL,
+			1000
+		));
+
+		OraclePrice::set(Some(FixedU128::saturating_from_integer(20)));
+		assert_eq!(
+			Dex::price_band_limits((777, 888)),
+			Some((
+				FixedU128::saturating_from_integer(18),
+				FixedU128::saturating_from_integer(22)
+			))
+		);
+		assert_noop!(
+			Dex::make_order(
+				RuntimeOrigin::signed(1),
+				777,
+				888,
+				5,
+				125,
+				OrderType::SELL,
+				1000
+			),
+			Error::<Test>::PriceOutsideBand
+		);
+		assert_noop!(
+			Dex::make_order(
+				RuntimeOrigin::signed(2),
+				777,
+				888,
+				85,
+				5,
+				OrderType::BUY,
+				1000
+			),
+			Error::<Test>::PriceOutsideBand
+		);
+		// 5 base at 21
+		assert_ok!(Dex::make_order(
+			RuntimeOrigin::signed(1),
+			777,
+			888,
+			5,
+			105,
+			OrderType::SELL,
+			1000
+		));
+
+		// a market order doesn't sweep the book beyond the band
+		assert_ok!(Dex::place_order(
+			RuntimeOrigin::signed(2),
+			777,
+			888,
+			300,
+			10,
+			OrderType::BUY,
+			OrderKind::Market,
+			TimeInForce::ImmediateOrCancel,
+			1000
+		));
+		System::assert_last_event(
+			Event::<Test>::OrderClosed {
+				order_index: 2,
+				order_status: OrderStatus::PartialCancelled,
+			}
+			.into(),
+		);
+		assert_eq!(
+			UserTokenInfoes::<Test>::get(2, 888),
+			TokenInfo {
+				amount: 895,
+				reserved: 0,
+			}
+		);
+		assert_eq!(pair_orders((777, 888)), vec![0]);
+
+		// once the band is removed orders are not limited anymore
+		assert_ok!(Dex::set_price_band(RuntimeOrigin::root(), 777, 888, None));
+		assert_ok!(Dex::make_order(
+			RuntimeOrigin::signed(1),
+			777,
+			888,
+			5,
+			125,
+			OrderType::SELL,
+			1000
+		));
+	})
+}
+
+#[test]
+fn test_twap_price_band() {
+	new_test_ext().execute_with(|| {
+		DexMatchingMode::set(MatchingMode::OnOrderCreation);
+
+		assert_ok!(Dex::deposit(RuntimeOrigin::signed(1), 777, 100));
+		assert_ok!(Dex::deposit(RuntimeOrigin::signed(2), 888, 1000));
+		assert_ok!(Dex::set_price_band(
+			RuntimeOrigin::root(),
+			777,
+			888,
+			Some(PriceBand {
+				reference: ReferencePrice::Twap,
+				max_deviation: Permill::from_percent(10),
+				max_move: Permill::from_percent(50),
+				window: 10,
+				halt_duration: 5,
+			})
+		));
+		assert_eq!(Dex::reference_price((777, 888)), None);
+
+		trade_at(5, 20);
+		System::set_block_number(3);
+		trade_at(5, 22);
+
+		// two blocks at 20 and two blocks at 22
+		System::set_block_number(5);
+		assert_eq!(
+			Dex::reference_price((777, 888)),
+			Some(FixedU128::saturating_from_integer(21))
+		);
+
+		// the next window opens at the price the last one ended with
+		System::set_block_number(11);
+		trade_at(5, 23);
+		let window = Dex::price_windows((777, 888)).unwrap();
+		assert_eq!(window.start, 11);
+		assert_eq!(window.open_price, FixedU128::saturating_from_integer(22));
+		assert_eq!(window.last_price, FixedU128::saturating_from_integer(23));
+	})
+}
+
+#[test]
+fn test_circuit_breaker() {
+	new_test_ext().execute_with(|| {
+		DexMatchingMode::set(MatchingMode::OnOrderCreation);
+
+		assert_ok!(Dex::deposit(RuntimeOrigin::signed(1), 777, 100));
+		assert_ok!(Dex::deposit(RuntimeOrigin::signed(2), 888, 1000));
+		assert_ok!(Dex::set_price_band(
+			RuntimeOrigin::root(),
+			777,
+			888,
+			Some(PriceBand {
+				reference: ReferencePrice::LastTrade,
+				max_deviation: Permill::from_percent(50),
+				max_move: Permill::from_percent(20),
+				window: 10,
+				halt_duration: 5,
+			})
+		));
+
+		trade_at(5, 20);
+		System::set_block_number(3);
+		trade_at(5, 24);
+		assert_eq!(Dex::circuit_breaker_halts((777, 888)), None);
+
+		// a move of 25% within the window halts the pair
+		trade_at(5, 25);
+		System::assert_has_event(
+			Event::<Test>::CircuitBreakerTripped {
+				pair: (777, 888),
+				open_price: FixedU128::saturating_from_integer(20),
+				price: FixedU128::saturating_from_integer(25),
+				halted_until: 8,
+			}
+			.into(),
+		);
+		assert_noop!(
+			Dex::make_order(
+				RuntimeOrigin::signed(1),
+				777,
+				888,
+				5,
+				125,
+				OrderType::SELL,
+				1000
+			),
+			Error::<Test>::PairNotTradable
+		);
+
+		// trading resumes once the halt is over
+		System::set_block_number(8);
+		trade_at(5, 25);
+		assert_eq!(pair_orders((777, 888)), Vec::<u64>::new());
+	})
+}
+
+#[test]
+fn test_migration_to_v2() {
+	use frame_support::traits::{OnRuntimeUpgrade, StorageVersion};
+	use migration::v2;
+
+	new_test_ext().execute_with(|| {
+		let _ = TradingPairs::<Test>::clear(u32::MAX, None);
+		StorageVersion::new(1).put::<Dex>();
+
+		PairOrderCount::<Test>::insert((777, 888), 2);
+		PairFees::<Test>::insert((777, 888), PairFee::default());
+		PairConfigs::<Test>::insert((777, 999), PairConfig::default());
+
+		v2::MigrateToV2::<Test>::on_runtime_upgrade();
+
+		assert_eq!(StorageVersion::get::<Dex>(), 2);
+		assert_eq!(
+			TradingPairs::<Test>::iter_keys().collect::<BTreeSet<_>>(),
+			BTreeSet::from([(777, 888), (777, 999)])
+		);
+		assert_eq!(
+			TradingPairs::<Test>::get((777, 888)),
+			Some(TradingPair {
+				status: PairStatus::Active,
+				min_order_size: 0,
+			})
+		);
+	});
+}
+
+#[test]
+fn test_add_and_remove_liquidity() {
+	new_test_ext().execute_with(|| {
+		for who in [1, 2] {
+			assert_ok!(Dex::deposit(RuntimeOrigin::signed(who), 777, 1_000));
+			assert_ok!(Dex::deposit(RuntimeOrigin::signed(who), 888, 5_000));
+		}
+		assert_noop!(
+			Dex::add_liquidity(RuntimeOrigin::signed(1), 777, 666, 1_000, 1_000, 0),
+			Error::<Test>::PairNotRegistered
+		);
+
+		// the first liquidity locks `MINIMUM_LIQUIDITY` of its shares
+		assert_noop!(
+			Dex::add_liquidity(RuntimeOrigin::signed(1), 777, 888, 100, 1_000, 0),
+			Error::<Test>::InsufficientLiquidity
+		);
+
+		// the first liquidity sets the price, the assets may be given in any order
+		assert_ok!(Dex::add_liquidity(
+			RuntimeOrigin::signed(1),
+			888,
+			777,
+			4_000,
+			1_000,
+			0
+		));
+		System::assert_last_event(
+			Event::<Test>::LiquidityAdded {
+				who: 1,
+				pair: (777, 888),
+				base_amount: 1_000,
+				quote_amount: 4_000,
+				shares: 1_000,
+			}
+			.into(),
+		);
+		assert_eq!(
+			LiquidityPools::<Test>::get((777, 888)),
+			Some(LiquidityPool {
+				base_reserve: 1_000,
+				quote_reserve: 4_000,
+				total_shares: 2_000,
+			})
+		);
+		assert_eq!(PoolShares::<Test>::get((777, 888), 1), 1_000);
+		assert_eq!(
+			PoolShares::<Test>::get((777, 888), Dex::account_id()),
+			MINIMUM_LIQUIDITY as u128
+		);
+		assert_eq!(UserTokenInfoes::<Test>::get(1, 777).amount, 0);
+		assert_eq!(UserTokenInfoes::<Test>::get(1, 888).amount, 1_000);
+
+		// later liquidity is added at the ratio of the reserves
+		assert_noop!(
+			Dex::add_liquidity(RuntimeOrigin::signed(2), 777, 888, 500, 5_000, 1_001),
+			Error::<Test>::SlippageExceeded
+		);
+		assert_ok!(Dex::add_liquidity(
+			RuntimeOrigin::signed(2),
+			777,
+			888,
+			500,
+			5_000,
+			1_000
+		));
+		assert_eq!(PoolShares::<Test>::get((777, 888), 2), 1_000);
+		assert_eq!(UserTokenInfoes::<Test>::get(2, 777).amount, 500);
+		assert_eq!(UserTokenInfoes::<Test>::get(2, 888).amount, 3_000);
+
+		assert_noop!(
+			Dex::remove_liquidity(RuntimeOrigin::signed(2), 777, 888, 1_001, 0, 0),
+			Error::<Test>::InsufficientShares
+		);
+		assert_noop!(
+			Dex::remove_liquidity(RuntimeOrigin::signed(2), 777, 888, 1_000, 501, 0),
+			Error::<Test>::SlippageExceeded
+		);
+		assert_ok!(Dex::remove_liquidity(
+			RuntimeOrigin::signed(2),
+			777,
+			888,
+			1_000,
+			500,
+			2_000
+		));
+		System::assert_last_event(
+			Event::<Test>::LiquidityRemoved {
+				who: 2,
+				pair: (777, 888),
+				base_amount: 500,
+				quote_amount: 2_000,
+				shares: 1_000,
+			}
+			.into(),
+		);
+		assert!(!PoolShares::<Test>::contains_key((777, 888), 2));
+		assert_eq!(UserTokenInfoes::<Test>::get(2, 777).amount, 1_000);
+		assert_eq!(UserTokenInfoes::<Test>::get(2, 888).amount, 5_000);
+
+		// the locked shares keep the pool when every provider left
+		assert_noop!(
+			Dex::remove_liquidity(RuntimeOrigin::signed(1), 777, 888, 2_000, 0, 0),
+			Error::<Test>::InsufficientShares
+		);
+		assert_ok!(Dex::remove_liquidity(
+			RuntimeOrigin::signed(1),
+			777,
+			888,
+			1_000,
+			0,
+			0
+		));
+		assert_eq!(
+			LiquidityPools::<Test>::get((777, 888)),
+			Some(LiquidityPool {
+				base_reserve: 500,
+				quote_reserve: 2_000,
+				total_shares: 1_000,
+			})
+		);
+		assert_eq!(UserTokenInfoes::<Test>::get(1, 777).amount, 500);
+		assert_eq!(UserTokenInfoes::<Test>::get(1, 888).amount, 3_000);
+		assert_noop!(
+			Dex::remove_liquidity(RuntimeOrigin::signed(1), 777, 888, 1, 0, 0),
+			Error::<Test>::InsufficientShares
+		);
+	})
+}
+
+#[test]
+fn test_swap_with_pool() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(Dex::deposit(RuntimeOrigin::signed(1), 777, 1_000));
+		assert_ok!(Dex::deposit(RuntimeOrigin::signed(1), 888, 4_000));
+		assert_ok!(Dex::deposit(RuntimeOrigin::signed(2), 888, 1_000));
+		assert_noop!(
+			Dex::swap(RuntimeOrigin::signed(2), 888, 777, 1_000, 0),
+			Error::<Test>::InsufficientLiquidity
+		);
+		assert_ok!(Dex::add_liquidity(
+			RuntimeOrigin::signed(1),
+			777,
+			888,
+			1_000,
+			4_000,
+			0
+		));
+
+		// 0.3% of the amount paid in stays in the pool as fee
+		assert_noop!(
+			Dex::swap(RuntimeOrigin::signed(2), 888, 777, 1_000, 200),
+			Error::<Test>::SlippageExceeded
+		);
+		assert_ok!(Dex::swap(RuntimeOrigin::signed(2), 888, 777, 1_000, 199));
+		System::assert_last_event(
+			Event::<Test>::Swapped {
+				who: 2,
+				pair: (777, 888),
+				side: OrderType::BUY,
+				amount_in: 1_000,
+				amount_out: 199,
+				venue: SwapVenue::Pool,
+			}
+			.into(),
+		);
+		assert_eq!(
+			LiquidityPools::<Test>::get((777, 888)),
+			Some(LiquidityPool {
+				base_reserve: 801,
+				quote_reserve: 5_000,
+				total_shares: 2_000,
+			})
+		);
+		assert_eq!(UserTokenInfoes::<Test>::get(2, 777).amount, 199);
+		assert_eq!(UserTokenInfoes::<Test>::get(2, 888).amount, 0);
+
+		assert_noop!(
+			Dex::swap(RuntimeOrigin::signed(2), 888, 777, 10, 0),
+			Error::<Test>::NotEnoughBalance
+		);
+
+		// the fees grow the share of the liquidity provider
+		assert_ok!(Dex::remove_liquidity(
+			RuntimeOrigin::signed(1),
+			777,
+			888,
+			1_000,
+			0,
+			0
+		));
+		assert_eq!(UserTokenInfoes::<Test>::get(1, 777).amount, 400);
+		assert_eq!(UserTokenInfoes::<Test>::get(1, 888).amount, 2_500);
+	})
+}
+
+#[test]
+fn test_swap_routes_to_better_venue() {
+	new_test_ext().execute_with(|| {
+		DexMatchingMode::set(MatchingMode::OnOrderCreation);
+		assert_ok!(Dex::deposit(RuntimeOrigin::signed(1), 777, 1_100));
+		assert_ok!(Dex::deposit(RuntimeOrigin::signed(1), 888, 4_000));
+		assert_ok!(Dex::deposit(RuntimeOrigin::signed(2), 888, 600));
+		assert_ok!(Dex::add_liquidity(
+			RuntimeOrigin::signed(1),
+			777,
+			888,
+			1_000,
+			4_000,
+			0
+		));
+		// the order book sells cheaper than the pool
+		assert_ok!(Dex::make_order(
+			RuntimeOrigin::signed(1),
+			777,
+			888,
+			100,
+			300,
+			OrderType::SELL,
+			1000
+		));
+
+		assert_ok!(Dex::swap(RuntimeOrigin::signed(2), 888, 777, 300, 100));
+		System::assert_last_event(
+			Event::<Test>::Swapped {
+				who: 2,
+				pair: (777, 888),
+				side: OrderType::BUY,
+				amount_in: 300,
+				amount_out: 100,
+				venue: SwapVenue::OrderBook,
+			}
+			.into(),
+		);
+		assert_eq!(pair_orders((777, 888)), Vec::<u64>::new());
+		assert_eq!(UserTokenInfoes::<Test>::get(1, 888).amount, 300);
+		assert_eq!(
+			LiquidityPools::<Test>::get((777, 888))
+				.unwrap()
+				.base_reserve,
+			1_000
+		);
+
+		// with the order book empty the pool fills the swap
+		assert_ok!(Dex::swap(RuntimeOrigin::signed(2), 888, 777, 300, 0));
+		System::assert_last_event(
+			Event::<Test>::Swapped {
+				who: 2,
+				pair: (777, 888),
+				side: OrderType::BUY,
+				amount_in: 300,
+				amount_out: 69,
+				venue: SwapVenue::Pool,
+			}
+			.into(),
+		);
+		assert_eq!(UserTokenInfoes::<Test>::get(2, 777).amount, 169);
+		assert_eq!(UserTokenInfoes::<Test>::get(2, 888).amount, 0);
+	})
+}
+
+#[test]
+fn test_swap_with_offchain_order_book() {
+	new_test_ext().execute_with(|| {
+		assert_eq!(DexMatchingMode::get(), MatchingMode::Offchain);
+		assert_ok!(Dex::deposit(RuntimeOrigin::signed(1), 777, 100));
+		assert_ok!(Dex::deposit(RuntimeOrigin::signed(2), 888, 300));
+		assert_ok!(Dex::make_order(
+			RuntimeOrigin::signed(1),
+			777,
+			888,
+			100,
+			300,
+			OrderType::SELL,
+			1000
+		));
+
+		// the open orders of the pair fill the swap before the offchain workers match them
+		assert_ok!(Dex::swap(RuntimeOrigin::signed(2), 888, 777, 300, 100));
+		System::assert_last_event(
+			Event::<Test>::Swapped {
+				who: 2,
+				pair: (777, 888),
+				side: OrderType::BUY,
+				amount_in: 300,
+				amount_out: 100,
+				venue: SwapVenue::OrderBook,
+			}
+			.into(),
+		);
+		assert_eq!(pair_orders((777, 888)), Vec::<u64>::new());
+		assert_eq!(UserTokenInfoes::<Test>::get(1, 888).amount, 300);
+		assert_eq!(UserTokenInfoes::<Test>::get(2, 777).amount, 100);
+	})
+}
+
+#[test]
+fn test_pool_swap_follows_pair_rules() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(Dex::deposit(RuntimeOrigin::signed(1), 777, 1_000));
+		assert_ok!(Dex::deposit(RuntimeOrigin::signed(1), 888, 4_000));
+		assert_ok!(Dex::deposit(RuntimeOrigin::signed(2), 888, 3_000));
+		assert_ok!(Dex::add_liquidity(
+			RuntimeOrigin::signed(1),
+			777,
+			888,
+			1_000,
+			4_000,
+			0
+		));
+		OraclePrice::set(Some(FixedU128::saturating_from_integer(4)));
+		assert_ok!(Dex::set_price_band(
+			RuntimeOrigin::root(),
+			777,
+			888,
+			Some(PriceBand {
+				reference: ReferencePrice::Oracle,
+				max_deviation: Permill::from_percent(30),
+				max_move: Permill::from_percent(20),
+				window: 10,
+				halt_duration: 5,
+			})
+		));
+
+		// 2_000 888 would buy 332 777 at 6.02
+		assert_noop!(
+			Dex::swap(RuntimeOrigin::signed(2), 888, 777, 2_000, 0),
+			Error::<Test>::PriceOutsideBand
+		);
+		// 1_000 888 buy 199 777 at 5.03, which opens the window of the circuit breaker
+		assert_ok!(Dex::swap(RuntimeOrigin::signed(2), 888, 777, 1_000, 0));
+		assert_eq!(Dex::circuit_breaker_halts((777, 888)), None);
+
+		// 100 888 buy 15 777 at 6.67, a move of 33% within the window halts the pair
+		OraclePrice::set(Some(FixedU128::saturating_from_integer(6)));
+		assert_ok!(Dex::swap(RuntimeOrigin::signed(2), 888, 777, 100, 0));
+		System::assert_has_event(
+			Event::<Test>::CircuitBreakerTripped {
+				pair: (777, 888),
+				open_price: FixedU128::checked_from_rational(1_000, 199).unwrap(),
+				price: FixedU128::checked_from_rational(100, 15).unwrap(),
+				halted_until: System::block_number() + 5,
+			}
+			.into(),
+		);
+		assert_noop!(
+			Dex::swap(RuntimeOrigin::signed(2), 888, 777, 100, 0),
+			Error::<Test>::PairNotTradable
+		);
+		assert_noop!(
+			Dex::swap_exact_out(
+				RuntimeOrigin::signed(2),
+				vec![888, 777].try_into().unwrap(),
+				10,
+				1_000
+			),
+			Error::<Test>::PairNotTradable
+		);
+
+		// a delisted pair doesn't swap with its pool either
+		System::set_block_number(System::block_number() + 5);
+		assert_ok!(Dex::set_trading_pair_status(
+			RuntimeOrigin::root(),
+			777,
+			888,
+			PairStatus::Delisted
+		));
+		assert_noop!(
+			Dex::swap_exact_out(
+				RuntimeOrigin::signed(2),
+				vec![888, 777].try_into().unwrap(),
+				10,
+				1_000
+			),
+			Error::<Test>::PairNotTradable
+		);
+	})
+}
+
+/// Pools of 10_000 of both assets for (777, 888) and (888, 999).
+fn add_path_liquidity() {
+	assert_ok!(Dex::deposit(RuntimeOrigin::signed(1), 777, 10_000));
+	assert_ok!(Dex::deposit(RuntimeOrigin::signed(1), 888, 20_000));
+	assert_ok!(Dex::deposit(RuntimeOrigin::signed(1), 999, 10_000));
+	for (asset_id_1, asset_id_2) in [(777, 888), (888, 999)] {
+		assert_ok!(Dex::add_liquidity(
+			RuntimeOrigin::signed(1),
+			asset_id_1,
+			asset_id_2,
+			10_000,
+			10_000,
+			0
+		));
+	}
+}
+
+#[test]
+fn test_swap_exact_in() {
+	new_test_ext().execute_with(|| {
+		add_path_liquidity();
+		assert_ok!(Dex::deposit(RuntimeOrigin::signed(2), 777, 1_000));
+		let path: BoundedVec<u32, MaxSwapPathLength> = vec![777, 888, 999].try_into().unwrap();
+
+		for invalid_path in [vec![777], vec![777, 888, 777]] {
+			assert_noop!(
+				Dex::swap_exact_in(
+					RuntimeOrigin::signed(2),
+					invalid_path.try_into().unwrap(),
+					1_000,
+					0
+				),
+				Error::<Test>::InvalidSwapPath
+			);
+		}
+		assert_noop!(
+			Dex::swap_exact_in(
+				RuntimeOrigin::signed(2),
+				vec![777, 888, 8888].try_into().unwrap(),
+				1_000,
+				0
+			),
+			Error::<Test>::InsufficientLiquidity
+		);
+
+		// 1_000 777 swap for 906 888, which swap for 828 999
+		assert_eq!(Dex::quote_swap_exact_in(&path, 1_000), Some(828));
+		// the first hop is reverted with the second one
+		assert_noop!(
+			Dex::swap_exact_in(RuntimeOrigin::signed(2), path.clone(), 1_000, 829),
+			Error::<Test>::SlippageExceeded
+		);
+		assert_ok!(Dex::swap_exact_in(
+			RuntimeOrigin::signed(2),
+			path.clone(),
+			1_000,
+			828
+		));
+		System::assert_has_event(
+			Event::<Test>::Swapped {
+				who: 2,
+				pair: (777, 888),
+				side: OrderType::SELL,
+				amount_in: 1_000,
+				amount_out: 906,
+				venue: SwapVenue::Pool,
+			}
+			.into(),
+		);
+		System::assert_last_event(
+			Event::<Test>::PathSwapped {
+				who: 2,
+				path,
+				amount_in: 1_000,
+				amount_out: 828,
+			}
+			.into(),
+		);
+		assert_eq!(UserTokenInfoes::<Test>::get(2, 777).amount, 0);
+		assert_eq!(UserTokenInfoes::<Test>::get(2, 888).amount, 0);
+		assert_eq!(UserTokenInfoes::<Test>::get(2, 999).amount, 828);
+		assert_eq!(
+			LiquidityPools::<Test>::get((888, 999)),
+			Some(LiquidityPool {
+				base_reserve: 10_906,
+				quote_reserve: 9_172,
+				total_shares: 10_000,
+			})
+		);
+	})
+}
+
+#[test]
+fn test_swap_exact_out() {
+	new_test_ext().execute_with(|| {
+		add_path_liquidity();
+		assert_ok!(Dex::deposit(RuntimeOrigin::signed(2), 999, 1_000));
+		let path: BoundedVec<u32, MaxSwapPathLength> = vec![999, 888, 777].try_into().unwrap();
+
+		assert_noop!(
+			Dex::swap_exact_out(RuntimeOrigin::signed(2), path.clone(), 10_000, 1_000),
+			Error::<Test>::InsufficientLiquidity
+		);
+
+		// 500 777 are bought for 529 888, which are bought for 561 999
+		assert_eq!(Dex::quote_swap_exact_out(&path, 500), Some(561));
+		assert_noop!(
+			Dex::swap_exact_out(RuntimeOrigin::signed(2), path.clone(), 500, 560),
+			Error::<Test>::SlippageExceeded
+		);
+		assert_ok!(Dex::swap_exact_out(
+			RuntimeOrigin::signed(2),
+			path.clone(),
+			500,
+			561
+		));
+		System::assert_last_event(
+			Event::<Test>::PathSwapped {
+				who: 2,
+				path,
+				amount_in: 561,
+				amount_out: 500,
+			}
+			.into(),
+		);
+		assert_eq!(UserTokenInfoes::<Test>::get(2, 999).amount, 439);
+		assert_eq!(UserTokenInfoes::<Test>::get(2, 888).amount, 0);
+		assert_eq!(UserTokenInfoes::<Test>::get(2, 777).amount, 500);
+		assert_eq!(
+			LiquidityPools::<Test>::get((777, 888)),
+			Some(LiquidityPool {
+				base_reserve: 9_500,
+				quote_reserve: 10_529,
+				total_shares: 10_000,
+			})
+		);
+		assert_eq!(
+			LiquidityPools::<Test>::get((888, 999)),
+			Some(LiquidityPool {
+				base_reserve: 9_471,
+				quote_reserve: 10_561,
+				total_shares: 10_000,
+			})
+		);
 	})
 }
@@ -0,0 +1,736 @@
+//! Weights for pallet_dex
+//!
+//! The runtimes use the weights in `runtime_common::weights::pallet_dex`, these are used by
+//! the tests. The weights are estimates that were not produced by the benchmarks: the storage
+//! lists are the worst case of each call as read from the code, the execution times are
+//! guesses. Replace both files with the output of:
+//!
+//! ./target/release/ggxchain-node benchmark pallet --chain dev --pallet pallet_dex --extrinsic '*'
+//! --steps 50 --repeat 20 --output runtime/runtime-common/src/weights/pallet_dex.rs
+
+#![cfg_attr(rustfmt, rustfmt_skip)]
+#![allow(unused_parens)]
+#![allow(unused_imports)]
+
+use frame_support::{traits::Get, weights::{Weight, constants::RocksDbWeight}};
+
+/// Weight functions needed for pallet_dex.
+pub trait WeightInfo {
+	fn deposit() -> Weight;
+	fn withdraw() -> Weight;
+	fn make_order() -> Weight;
+	fn cancel_order() -> Weight;
+	fn take_order() -> Weight;
+	fn deposit_native() -> Weight;
+	fn withdraw_native() -> Weight;
+	fn allowlist_asset() -> Weight;
+	fn update_match_order_unsigned(t: u32, ) -> Weight;
+	fn place_order() -> Weight;
+	fn set_pair_config() -> Weight;
+	fn set_pair_fee() -> Weight;
+	fn on_initialize(e: u32, ) -> Weight;
+	fn match_order(t: u32, ) -> Weight;
+	fn amend_order() -> Weight;
+	fn batch_place(n: u32, ) -> Weight;
+	fn batch_cancel(n: u32, ) -> Weight;
+	fn cancel_all(n: u32, ) -> Weight;
+	fn place_conditional_order() -> Weight;
+	fn cancel_conditional_order() -> Weight;
+	fn trigger_conditional_order() -> Weight;
+	fn register_trading_pair() -> Weight;
+	fn set_trading_pair_status(o: u32, c: u32, ) -> Weight;
+	fn set_min_order_size() -> Weight;
+	fn set_price_band() -> Weight;
+	fn add_liquidity() -> Weight;
+	fn remove_liquidity() -> Weight;
+	fn swap() -> Weight;
+	fn swap_exact_in(n: u32, ) -> Weight;
+	fn swap_exact_out(n: u32, ) -> Weight;
+	fn set_fee_tiers() -> Weight;
+	fn fund_reward_epoch() -> Weight;
+	fn claim_rewards(o: u32, ) -> Weight;
+	fn update_match_engine(b: u32, ) -> Weight;
+	fn register_token() -> Weight;
+	fn deregister_token() -> Weight;
+	fn reclaim_rewards() -> Weight;
+	fn query_tokens() -> Weight;
+	fn query_balance() -> Weight;
+	fn query_owner_tokens(t: u32, ) -> Weight;
+	fn query_order() -> Weight;
+	fn query_pair_orders(n: u32, l: u32, ) -> Weight;
+	fn query_user_orders(n: u32, l: u32, ) -> Weight;
+	fn query_liquidity_pool() -> Weight;
+	fn query_pool_shares() -> Weight;
+}
+
+// For backwards compatibility and tests
+impl WeightInfo for () {
+	/// Storage: Dex TokenIndex (r:1 w:0)
+	/// Storage: Dex TokenIds (r:1 w:0)
+	/// Storage: Assets Asset (r:1 w:1)
+	/// Storage: Assets Account (r:2 w:2)
+	/// Storage: Dex UserTokenInfoes (r:1 w:1)
+	fn deposit() -> Weight {
+		Weight::from_parts(48_000_000, 6208)
+			.saturating_add(RocksDbWeight::get().reads(6_u64))
+			.saturating_add(RocksDbWeight::get().writes(4_u64))
+	}
+	/// Storage: Dex TokenIndex (r:1 w:0)
+	/// Storage: Dex UserTokenInfoes (r:1 w:1)
+	/// Storage: Dex TokenIds (r:1 w:0)
+	/// Storage: Assets Asset (r:1 w:1)
+	/// Storage: Assets Account (r:2 w:2)
+	fn withdraw() -> Weight {
+		Weight::from_parts(50_000_000, 6208)
+			.saturating_add(RocksDbWeight::get().reads(6_u64))
+			.saturating_add(RocksDbWeight::get().writes(4_u64))
+	}
+	/// Storage: Dex PairConfigs (r:1 w:0)
+	/// Storage: Dex TradingPairs (r:1 w:0)
+	/// Storage: Dex CircuitBreakerHalts (r:1 w:0)
+	/// Storage: Dex PriceBands (r:1 w:0)
+	/// Storage: Dex LastTradePrice (r:1 w:0)
+	/// Storage: Dex NextOrderIndex (r:1 w:1)
+	/// Storage: Dex UserTokenInfoes (r:1 w:1)
+	/// Storage: Dex UserOrderCount (r:1 w:1)
+	/// Storage: Dex PairOrderCount (r:1 w:1)
+	/// Storage: Dex PairRewardEpochs (r:1 w:0)
+	/// Storage: Dex RewardEpochs (r:1 w:1)
+	/// Storage: Dex OrderExpiration (r:1 w:1)
+	/// Storage: Dex NextMatchOrderIndex (r:1 w:0)
+	/// Storage: Dex Orders (r:0 w:1)
+	/// Storage: Dex UserOrders (r:0 w:1)
+	/// Storage: Dex PairOrders (r:0 w:1)
+	/// Storage: Dex RewardStakes (r:0 w:1)
+	fn make_order() -> Weight {
+		Weight::from_parts(42_000_000, 4070)
+			.saturating_add(RocksDbWeight::get().reads(13_u64))
+			.saturating_add(RocksDbWeight::get().writes(10_u64))
+	}
+	/// Storage: Dex Orders (r:1 w:1)
+	/// Storage: Dex UserTokenInfoes (r:1 w:1)
+	/// Storage: Dex RewardStakes (r:1 w:1)
+	/// Storage: Dex RewardEpochs (r:1 w:1)
+	/// Storage: Dex LastTradePrice (r:1 w:0)
+	/// Storage: Dex UnclaimedRewards (r:1 w:1)
+	/// Storage: Dex UserOrders (r:1 w:1)
+	/// Storage: Dex UserOrderCount (r:1 w:1)
+	/// Storage: Dex PairOrders (r:1 w:1)
+	/// Storage: Dex PairOrderCount (r:1 w:1)
+	/// Storage: Dex OrderExpiration (r:1 w:1)
+	/// Storage: Dex MatchEngines (r:1 w:1)
+	fn cancel_order() -> Weight {
+		Weight::from_parts(36_000_000, 4070)
+			.saturating_add(RocksDbWeight::get().reads(12_u64))
+			.saturating_add(RocksDbWeight::get().writes(11_u64))
+	}
+	/// Storage: Dex Orders (r:1 w:1)
+	/// Storage: Dex TradingPairs (r:1 w:0)
+	/// Storage: Dex CircuitBreakerHalts (r:1 w:1)
+	/// Storage: Dex RewardStakes (r:1 w:1)
+	/// Storage: Dex RewardEpochs (r:1 w:1)
+	/// Storage: Dex LastTradePrice (r:1 w:1)
+	/// Storage: Dex UnclaimedRewards (r:1 w:1)
+	/// Storage: Dex UserOrders (r:1 w:1)
+	/// Storage: Dex UserOrderCount (r:1 w:1)
+	/// Storage: Dex PairOrders (r:1 w:1)
+	/// Storage: Dex PairOrderCount (r:1 w:1)
+	/// Storage: Dex OrderExpiration (r:1 w:1)
+	/// Storage: Dex MatchEngines (r:1 w:1)
+	/// Storage: Dex NextTradeId (r:1 w:1)
+	/// Storage: Dex PriceBands (r:1 w:0)
+	/// Storage: Dex PriceWindows (r:1 w:1)
+	/// Storage: Timestamp Now (r:1 w:0)
+	/// Storage: Dex Candles (r:3 w:3)
+	/// Storage: Dex FeeTiers (r:1 w:0)
+	/// Storage: Dex PairFees (r:1 w:0)
+	/// Storage: Dex TradingVolumes (r:2 w:2)
+	/// Storage: Dex UserTokenInfoes (r:4 w:4)
+	/// Storage: Dex NativeAssetId (r:1 w:0)
+	/// Storage: Dex TokenIds (r:2 w:0)
+	/// Storage: Assets Asset (r:2 w:2)
+	/// Storage: Assets Account (r:4 w:4)
+	fn take_order() -> Weight {
+		Weight::from_parts(95_000_000, 11_350)
+			.saturating_add(RocksDbWeight::get().reads(37_u64))
+			.saturating_add(RocksDbWeight::get().writes(29_u64))
+	}
+	/// Storage: Dex NativeAssetId (r:1 w:0)
+	/// Storage: Dex TokenIndex (r:1 w:0)
+	/// Storage: System Account (r:2 w:2)
+	/// Storage: Dex UserTokenInfoes (r:1 w:1)
+	fn deposit_native() -> Weight {
+		Weight::from_parts(44_000_000, 6196)
+			.saturating_add(RocksDbWeight::get().reads(5_u64))
+			.saturating_add(RocksDbWeight::get().writes(3_u64))
+	}
+	/// Storage: Dex NativeAssetId (r:1 w:0)
+	/// Storage: Dex TokenIndex (r:1 w:0)
+	/// Storage: Dex UserTokenInfoes (r:1 w:1)
+	/// Storage: System Account (r:2 w:2)
+	fn withdraw_native() -> Weight {
+		Weight::from_parts(46_000_000, 6196)
+			.saturating_add(RocksDbWeight::get().reads(5_u64))
+			.saturating_add(RocksDbWeight::get().writes(3_u64))
+	}
+	/// Storage: Dex TokenInfoes (r:1 w:1)
+	/// Storage: Dex TokenIndex (r:0 w:1)
+	fn allowlist_asset() -> Weight {
+		Weight::from_parts(14_000_000, 1501)
+			.saturating_add(RocksDbWeight::get().reads(1_u64))
+			.saturating_add(RocksDbWeight::get().writes(2_u64))
+	}
+	/// Storage: Dex Orders (r:2 w:2)
+	/// Storage: Dex TradingPairs (r:1 w:0)
+	/// Storage: Dex NextTradeId (r:1 w:1)
+	/// Storage: Dex PriceBands (r:1 w:0)
+	/// Storage: Dex PriceWindows (r:1 w:1)
+	/// Storage: Dex LastTradePrice (r:1 w:1)
+	/// Storage: Dex CircuitBreakerHalts (r:1 w:1)
+	/// Storage: Timestamp Now (r:1 w:0)
+	/// Storage: Dex Candles (r:3 w:3)
+	/// Storage: Dex RewardStakes (r:2 w:2)
+	/// Storage: Dex RewardEpochs (r:1 w:1)
+	/// Storage: Dex UnclaimedRewards (r:2 w:2)
+	/// Storage: Dex FeeTiers (r:1 w:0)
+	/// Storage: Dex PairFees (r:1 w:0)
+	/// Storage: Dex TradingVolumes (r:2 w:2)
+	/// Storage: Dex UserTokenInfoes (r:4 w:4)
+	/// Storage: Dex NativeAssetId (r:1 w:0)
+	/// Storage: Dex TokenIds (r:2 w:0)
+	/// Storage: Assets Asset (r:2 w:2)
+	/// Storage: Assets Account (r:4 w:4)
+	/// Storage: Dex UserOrders (r:2 w:2)
+	/// Storage: Dex UserOrderCount (r:2 w:2)
+	/// Storage: Dex PairOrders (r:2 w:2)
+	/// Storage: Dex PairOrderCount (r:1 w:1)
+	/// Storage: Dex OrderExpiration (r:2 w:2)
+	/// Storage: Dex MatchEngines (r:1 w:1)
+	/// The range of component `t` is `[1, 100]`.
+	fn update_match_order_unsigned(t: u32, ) -> Weight {
+		Weight::from_parts(38_000_000, 4070)
+			.saturating_add(Weight::from_parts(86_000_000, 0).saturating_mul(t.into()))
+			.saturating_add(RocksDbWeight::get().reads(12_u64))
+			.saturating_add(RocksDbWeight::get().reads((43_u64).saturating_mul(t.into())))
+			.saturating_add(RocksDbWeight::get().writes(11_u64))
+			.saturating_add(RocksDbWeight::get().writes((35_u64).saturating_mul(t.into())))
+			.saturating_add(Weight::from_parts(0, 7_800).saturating_mul(t.into()))
+	}
+	/// Storage: Dex PairConfigs (r:1 w:0)
+	/// Storage: Dex TradingPairs (r:1 w:0)
+	/// Storage: Dex CircuitBreakerHalts (r:1 w:0)
+	/// Storage: Dex PriceBands (r:1 w:0)
+	/// Storage: Dex LastTradePrice (r:1 w:0)
+	/// Storage: Dex NextOrderIndex (r:1 w:1)
+	/// Storage: Dex UserTokenInfoes (r:1 w:1)
+	/// Storage: Dex UserOrderCount (r:1 w:1)
+	/// Storage: Dex PairOrderCount (r:1 w:1)
+	/// Storage: Dex PairRewardEpochs (r:1 w:0)
+	/// Storage: Dex RewardEpochs (r:1 w:1)
+	/// Storage: Dex OrderExpiration (r:1 w:1)
+	/// Storage: Dex NextMatchOrderIndex (r:1 w:0)
+	/// Storage: Dex Orders (r:0 w:1)
+	/// Storage: Dex UserOrders (r:0 w:1)
+	/// Storage: Dex PairOrders (r:0 w:1)
+	/// Storage: Dex RewardStakes (r:0 w:1)
+	fn place_order() -> Weight {
+		Weight::from_parts(43_000_000, 4070)
+			.saturating_add(RocksDbWeight::get().reads(13_u64))
+			.saturating_add(RocksDbWeight::get().writes(10_u64))
+	}
+	/// Storage: Dex PairConfigs (r:0 w:1)
+	fn set_pair_config() -> Weight {
+		Weight::from_parts(12_000_000, 0)
+			.saturating_add(RocksDbWeight::get().writes(1_u64))
+	}
+	/// Storage: Dex PairFees (r:0 w:1)
+	fn set_pair_fee() -> Weight {
+		Weight::from_parts(12_000_000, 0)
+			.saturating_add(RocksDbWeight::get().writes(1_u64))
+	}
+	/// Storage: Dex OrderExpiration (r:1 w:1)
+	/// Storage: Dex Orders (r:1 w:1)
+	/// Storage: Dex UserTokenInfoes (r:1 w:1)
+	/// Storage: Dex RewardStakes (r:1 w:1)
+	/// Storage: Dex RewardEpochs (r:1 w:1)
+	/// Storage: Dex LastTradePrice (r:1 w:0)
+	/// Storage: Dex UnclaimedRewards (r:1 w:1)
+	/// Storage: Dex UserOrders (r:1 w:1)
+	/// Storage: Dex UserOrderCount (r:1 w:1)
+	/// Storage: Dex PairOrders (r:1 w:1)
+	/// Storage: Dex PairOrderCount (r:1 w:1)
+	/// Storage: Dex MatchEngines (r:1 w:1)
+	/// The range of component `e` is `[0, 100]`.
+	fn on_initialize(e: u32, ) -> Weight {
+		Weight::from_parts(6_000_000, 3_530)
+			.saturating_add(Weight::from_parts(34_000_000, 0).saturating_mul(e.into()))
+			.saturating_add(RocksDbWeight::get().reads(1_u64))
+			.saturating_add(RocksDbWeight::get().reads((11_u64).saturating_mul(e.into())))
+			.saturating_add(RocksDbWeight::get().writes(1_u64))
+			.saturating_add(RocksDbWeight::get().writes((10_u64).saturating_mul(e.into())))
+			.saturating_add(Weight::from_parts(0, 4_070).saturating_mul(e.into()))
+	}
+	/// Storage: Dex Orders (r:2 w:2)
+	/// Storage: Dex TradingPairs (r:1 w:0)
+	/// Storage: Dex NextTradeId (r:1 w:1)
+	/// Storage: Dex PriceBands (r:1 w:0)
+	/// Storage: Dex PriceWindows (r:1 w:1)
+	/// Storage: Dex LastTradePrice (r:1 w:1)
+	/// Storage: Dex CircuitBreakerHalts (r:1 w:1)
+	/// Storage: Timestamp Now (r:1 w:0)
+	/// Storage: Dex Candles (r:3 w:3)
+	/// Storage: Dex RewardStakes (r:2 w:2)
+	/// Storage: Dex RewardEpochs (r:1 w:1)
+	/// Storage: Dex UnclaimedRewards (r:2 w:2)
+	/// Storage: Dex FeeTiers (r:1 w:0)
+	/// Storage: Dex PairFees (r:1 w:0)
+	/// Storage: Dex TradingVolumes (r:2 w:2)
+	/// Storage: Dex UserTokenInfoes (r:4 w:4)
+	/// Storage: Dex NativeAssetId (r:1 w:0)
+	/// Storage: Dex TokenIds (r:2 w:0)
+	/// Storage: Assets Asset (r:2 w:2)
+	/// Storage: Assets Account (r:4 w:4)
+	/// Storage: Dex UserOrders (r:2 w:2)
+	/// Storage: Dex UserOrderCount (r:2 w:2)
+	/// Storage: Dex PairOrders (r:2 w:2)
+	/// Storage: Dex PairOrderCount (r:1 w:1)
+	/// Storage: Dex OrderExpiration (r:2 w:2)
+	/// Storage: Dex MatchEngines (r:1 w:1)
+	/// The range of component `t` is `[0, 100]`.
+	fn match_order(t: u32, ) -> Weight {
+		Weight::from_parts(30_000_000, 4070)
+			.saturating_add(Weight::from_parts(92_000_000, 0).saturating_mul(t.into()))
+			.saturating_add(RocksDbWeight::get().reads(12_u64))
+			.saturating_add(RocksDbWeight::get().reads((43_u64).saturating_mul(t.into())))
+			.saturating_add(RocksDbWeight::get().writes(11_u64))
+			.saturating_add(RocksDbWeight::get().writes((35_u64).saturating_mul(t.into())))
+			.saturating_add(Weight::from_parts(0, 7_800).saturating_mul(t.into()))
+	}
+	/// Storage: Dex Orders (r:1 w:2)
+	/// Storage: Dex PairConfigs (r:1 w:0)
+	/// Storage: Dex TradingPairs (r:1 w:0)
+	/// Storage: Dex CircuitBreakerHalts (r:1 w:0)
+	/// Storage: Dex PriceBands (r:1 w:0)
+	/// Storage: Dex LastTradePrice (r:1 w:0)
+	/// Storage: Dex UserTokenInfoes (r:1 w:1)
+	/// Storage: Dex RewardStakes (r:1 w:2)
+	/// Storage: Dex RewardEpochs (r:1 w:1)
+	/// Storage: Dex UnclaimedRewards (r:1 w:1)
+	/// Storage: Dex UserOrders (r:1 w:2)
+	/// Storage: Dex UserOrderCount (r:1 w:1)
+	/// Storage: Dex PairOrders (r:1 w:2)
+	/// Storage: Dex PairOrderCount (r:1 w:1)
+	/// Storage: Dex OrderExpiration (r:1 w:1)
+	/// Storage: Dex MatchEngines (r:1 w:1)
+	/// Storage: Dex NextOrderIndex (r:1 w:1)
+	/// Storage: Dex PairRewardEpochs (r:1 w:0)
+	/// Storage: Dex NextMatchOrderIndex (r:1 w:0)
+	fn amend_order() -> Weight {
+		Weight::from_parts(82_000_000, 4070)
+			.saturating_add(RocksDbWeight::get().reads(19_u64))
+			.saturating_add(RocksDbWeight::get().writes(16_u64))
+	}
+	/// Storage: Dex PairConfigs (r:1 w:0)
+	/// Storage: Dex TradingPairs (r:1 w:0)
+	/// Storage: Dex CircuitBreakerHalts (r:1 w:0)
+	/// Storage: Dex PriceBands (r:1 w:0)
+	/// Storage: Dex LastTradePrice (r:1 w:0)
+	/// Storage: Dex NextOrderIndex (r:1 w:1)
+	/// Storage: Dex UserTokenInfoes (r:1 w:1)
+	/// Storage: Dex UserOrderCount (r:1 w:1)
+	/// Storage: Dex PairOrderCount (r:1 w:1)
+	/// Storage: Dex PairRewardEpochs (r:1 w:0)
+	/// Storage: Dex RewardEpochs (r:1 w:1)
+	/// Storage: Dex OrderExpiration (r:1 w:1)
+	/// Storage: Dex NextMatchOrderIndex (r:1 w:0)
+	/// Storage: Dex Orders (r:0 w:1)
+	/// Storage: Dex UserOrders (r:0 w:1)
+	/// Storage: Dex PairOrders (r:0 w:1)
+	/// Storage: Dex RewardStakes (r:0 w:1)
+	/// The range of component `n` is `[1, 20]`.
+	fn batch_place(n: u32, ) -> Weight {
+		Weight::from_parts(6_000_000, 0)
+			.saturating_add(Weight::from_parts(41_000_000, 0).saturating_mul(n.into()))
+			.saturating_add(RocksDbWeight::get().reads((13_u64).saturating_mul(n.into())))
+			.saturating_add(RocksDbWeight::get().writes((10_u64).saturating_mul(n.into())))
+			.saturating_add(Weight::from_parts(0, 4070).saturating_mul(n.into()))
+	}
+	/// Storage: Dex Orders (r:1 w:1)
+	/// Storage: Dex UserTokenInfoes (r:1 w:1)
+	/// Storage: Dex RewardStakes (r:1 w:1)
+	/// Storage: Dex RewardEpochs (r:1 w:1)
+	/// Storage: Dex LastTradePrice (r:1 w:0)
+	/// Storage: Dex UnclaimedRewards (r:1 w:1)
+	/// Storage: Dex UserOrders (r:1 w:1)
+	/// Storage: Dex UserOrderCount (r:1 w:1)
+	/// Storage: Dex PairOrders (r:1 w:1)
+	/// Storage: Dex PairOrderCount (r:1 w:1)
+	/// Storage: Dex OrderExpiration (r:1 w:1)
+	/// Storage: Dex MatchEngines (r:1 w:1)
+	/// The range of component `n` is `[1, 20]`.
+	fn batch_cancel(n: u32, ) -> Weight {
+		Weight::from_parts(6_000_000, 0)
+			.saturating_add(Weight::from_parts(37_000_000, 0).saturating_mul(n.into()))
+			.saturating_add(RocksDbWeight::get().reads((12_u64).saturating_mul(n.into())))
+			.saturating_add(RocksDbWeight::get().writes((11_u64).saturating_mul(n.into())))
+			.saturating_add(Weight::from_parts(0, 4070).saturating_mul(n.into()))
+	}
+	/// Storage: Dex Orders (r:1 w:1)
+	/// Storage: Dex UserTokenInfoes (r:1 w:1)
+	/// Storage: Dex RewardStakes (r:1 w:1)
+	/// Storage: Dex RewardEpochs (r:1 w:1)
+	/// Storage: Dex LastTradePrice (r:1 w:0)
+	/// Storage: Dex UnclaimedRewards (r:1 w:1)
+	/// Storage: Dex UserOrders (r:1 w:1)
+	/// Storage: Dex UserOrderCount (r:1 w:1)
+	/// Storage: Dex PairOrders (r:1 w:1)
+	/// Storage: Dex PairOrderCount (r:1 w:1)
+	/// Storage: Dex OrderExpiration (r:1 w:1)
+	/// Storage: Dex MatchEngines (r:1 w:1)
+	/// The range of component `n` is `[0, 100]`.
+	fn cancel_all(n: u32, ) -> Weight {
+		Weight::from_parts(8_000_000, 3_530)
+			.saturating_add(Weight::from_parts(38_000_000, 0).saturating_mul(n.into()))
+			.saturating_add(RocksDbWeight::get().reads(1_u64))
+			.saturating_add(RocksDbWeight::get().reads((13_u64).saturating_mul(n.into())))
+			.saturating_add(RocksDbWeight::get().writes((11_u64).saturating_mul(n.into())))
+			.saturating_add(Weight::from_parts(0, 4070).saturating_mul(n.into()))
+	}
+	/// Storage: Dex PairConfigs (r:1 w:0)
+	/// Storage: Dex TradingPairs (r:1 w:0)
+	/// Storage: Dex CircuitBreakerHalts (r:1 w:0)
+	/// Storage: Dex NextConditionalOrderIndex (r:1 w:1)
+	/// Storage: Dex UserTokenInfoes (r:1 w:1)
+	/// Storage: Dex PairConditionalOrders (r:1 w:1)
+	/// Storage: Dex ConditionalOrders (r:0 w:1)
+	fn place_conditional_order() -> Weight {
+		Weight::from_parts(32_000_000, 3_530)
+			.saturating_add(RocksDbWeight::get().reads(6_u64))
+			.saturating_add(RocksDbWeight::get().writes(4_u64))
+	}
+	/// Storage: Dex ConditionalOrders (r:1 w:1)
+	/// Storage: Dex PairConditionalOrders (r:1 w:1)
+	/// Storage: Dex UserTokenInfoes (r:1 w:1)
+	fn cancel_conditional_order() -> Weight {
+		Weight::from_parts(27_000_000, 3_530)
+			.saturating_add(RocksDbWeight::get().reads(3_u64))
+			.saturating_add(RocksDbWeight::get().writes(3_u64))
+	}
+	/// Storage: Dex ConditionalOrders (r:1 w:1)
+	/// Storage: Dex PairConditionalOrders (r:1 w:1)
+	/// Storage: Dex PairConfigs (r:1 w:0)
+	/// Storage: Dex TradingPairs (r:1 w:0)
+	/// Storage: Dex CircuitBreakerHalts (r:1 w:0)
+	/// Storage: Dex PriceBands (r:1 w:0)
+	/// Storage: Dex LastTradePrice (r:1 w:0)
+	/// Storage: Dex NextOrderIndex (r:1 w:1)
+	/// Storage: Dex UserTokenInfoes (r:1 w:1)
+	/// Storage: Dex UserOrderCount (r:1 w:1)
+	/// Storage: Dex PairOrderCount (r:1 w:1)
+	/// Storage: Dex PairRewardEpochs (r:1 w:0)
+	/// Storage: Dex RewardEpochs (r:1 w:1)
+	/// Storage: Dex OrderExpiration (r:1 w:1)
+	/// Storage: Dex NextMatchOrderIndex (r:1 w:0)
+	/// Storage: Dex Orders (r:0 w:1)
+	/// Storage: Dex UserOrders (r:0 w:1)
+	/// Storage: Dex PairOrders (r:0 w:1)
+	/// Storage: Dex RewardStakes (r:0 w:1)
+	fn trigger_conditional_order() -> Weight {
+		Weight::from_parts(68_000_000, 4070)
+			.saturating_add(RocksDbWeight::get().reads(15_u64))
+			.saturating_add(RocksDbWeight::get().writes(12_u64))
+	}
+	/// Storage: Dex TokenIndex (r:2 w:0)
+	/// Storage: Dex TradingPairs (r:1 w:1)
+	fn register_trading_pair() -> Weight {
+		Weight::from_parts(15_000_000, 3_541)
+			.saturating_add(RocksDbWeight::get().reads(3_u64))
+			.saturating_add(RocksDbWeight::get().writes(1_u64))
+	}
+	/// Storage: Dex TradingPairs (r:1 w:1)
+	/// Storage: Dex Orders (r:1 w:1)
+	/// Storage: Dex UserTokenInfoes (r:1 w:1)
+	/// Storage: Dex RewardStakes (r:1 w:1)
+	/// Storage: Dex RewardEpochs (r:1 w:1)
+	/// Storage: Dex LastTradePrice (r:1 w:0)
+	/// Storage: Dex UnclaimedRewards (r:1 w:1)
+	/// Storage: Dex UserOrders (r:1 w:1)
+	/// Storage: Dex UserOrderCount (r:1 w:1)
+	/// Storage: Dex PairOrders (r:1 w:1)
+	/// Storage: Dex PairOrderCount (r:1 w:1)
+	/// Storage: Dex OrderExpiration (r:1 w:1)
+	/// Storage: Dex MatchEngines (r:1 w:1)
+	/// Storage: Dex PairConditionalOrders (r:1 w:1)
+	/// Storage: Dex ConditionalOrders (r:1 w:1)
+	/// The range of component `o` is `[0, 100]`.
+	/// The range of component `c` is `[0, 100]`.
+	fn set_trading_pair_status(o: u32, c: u32, ) -> Weight {
+		Weight::from_parts(17_000_000, 3_541)
+			.saturating_add(Weight::from_parts(37_000_000, 0).saturating_mul(o.into()))
+			.saturating_add(Weight::from_parts(24_000_000, 0).saturating_mul(c.into()))
+			.saturating_add(RocksDbWeight::get().reads(3_u64))
+			.saturating_add(RocksDbWeight::get().reads((12_u64).saturating_mul(o.into())))
+			.saturating_add(RocksDbWeight::get().reads((3_u64).saturating_mul(c.into())))
+			.saturating_add(RocksDbWeight::get().writes(3_u64))
+			.saturating_add(RocksDbWeight::get().writes((11_u64).saturating_mul(o.into())))
+			.saturating_add(RocksDbWeight::get().writes((3_u64).saturating_mul(c.into())))
+			.saturating_add(Weight::from_parts(0, 4070).saturating_mul(o.into()))
+			.saturating_add(Weight::from_parts(0, 3_530).saturating_mul(c.into()))
+	}
+	/// Storage: Dex TradingPairs (r:1 w:1)
+	fn set_min_order_size() -> Weight {
+		Weight::from_parts(13_000_000, 3_541)
+			.saturating_add(RocksDbWeight::get().reads(1_u64))
+			.saturating_add(RocksDbWeight::get().writes(1_u64))
+	}
+	/// Storage: Dex TradingPairs (r:1 w:0)
+	/// Storage: Dex PriceBands (r:0 w:1)
+	/// Storage: Dex PriceWindows (r:0 w:1)
+	/// Storage: Dex CircuitBreakerHalts (r:0 w:1)
+	fn set_price_band() -> Weight {
+		Weight::from_parts(14_000_000, 3_541)
+			.saturating_add(RocksDbWeight::get().reads(1_u64))
+			.saturating_add(RocksDbWeight::get().writes(3_u64))
+	}
+	/// Storage: Dex TradingPairs (r:1 w:0)
+	/// Storage: Dex LiquidityPools (r:1 w:1)
+	/// Storage: Dex UserTokenInfoes (r:2 w:2)
+	/// Storage: Timestamp Now (r:1 w:0)
+	/// Storage: Dex PriceAccumulators (r:1 w:1)
+	/// Storage: Dex PriceObservations (r:1 w:1)
+	/// Storage: Dex PoolShares (r:2 w:2)
+	fn add_liquidity() -> Weight {
+		Weight::from_parts(38_000_000, 6_196)
+			.saturating_add(RocksDbWeight::get().reads(9_u64))
+			.saturating_add(RocksDbWeight::get().writes(7_u64))
+	}
+	/// Storage: Dex LiquidityPools (r:1 w:1)
+	/// Storage: Dex PoolShares (r:1 w:1)
+	/// Storage: Timestamp Now (r:1 w:0)
+	/// Storage: Dex PriceAccumulators (r:1 w:1)
+	/// Storage: Dex PriceObservations (r:1 w:1)
+	/// Storage: Dex UserTokenInfoes (r:2 w:2)
+	fn remove_liquidity() -> Weight {
+		Weight::from_parts(36_000_000, 6_196)
+			.saturating_add(RocksDbWeight::get().reads(7_u64))
+			.saturating_add(RocksDbWeight::get().writes(6_u64))
+	}
+	/// Storage: Dex TradingPairs (r:1 w:0)
+	/// Storage: Dex CircuitBreakerHalts (r:1 w:1)
+	/// Storage: Dex LiquidityPools (r:1 w:1)
+	/// Storage: Dex PriceBands (r:1 w:0)
+	/// Storage: Dex LastTradePrice (r:1 w:0)
+	/// Storage: Dex NextOrderIndex (r:1 w:1)
+	/// Storage: Dex NextMatchOrderIndex (r:1 w:1)
+	/// Storage: Dex FeeTiers (r:1 w:0)
+	/// Storage: Dex TradingVolumes (r:1 w:0)
+	/// Storage: Dex PairFees (r:1 w:0)
+	/// Storage: Dex PairConfigs (r:1 w:0)
+	/// Storage: Dex UserTokenInfoes (r:2 w:2)
+	/// Storage: Dex Orders (r:1 w:1)
+	/// Storage: Dex UserOrders (r:1 w:1)
+	/// Storage: Dex PairOrders (r:1 w:1)
+	/// Storage: Dex UserOrderCount (r:1 w:1)
+	/// Storage: Dex PairOrderCount (r:1 w:1)
+	/// Storage: Dex OrderExpiration (r:1 w:1)
+	/// Storage: Dex RewardStakes (r:1 w:0)
+	/// Storage: Dex MatchEngines (r:1 w:1)
+	/// Storage: Timestamp Now (r:1 w:0)
+	/// Storage: Dex PriceAccumulators (r:1 w:1)
+	/// Storage: Dex PriceObservations (r:1 w:1)
+	/// Storage: Dex PriceWindows (r:1 w:1)
+	fn swap() -> Weight {
+		Weight::from_parts(86_000_000, 11_350)
+			.saturating_add(RocksDbWeight::get().reads(25_u64))
+			.saturating_add(RocksDbWeight::get().writes(16_u64))
+	}
+	/// Storage: Dex TradingPairs (r:1 w:0)
+	/// Storage: Dex CircuitBreakerHalts (r:1 w:1)
+	/// Storage: Dex LiquidityPools (r:1 w:1)
+	/// Storage: Dex PriceBands (r:1 w:0)
+	/// Storage: Dex LastTradePrice (r:1 w:0)
+	/// Storage: Dex NextOrderIndex (r:1 w:1)
+	/// Storage: Dex NextMatchOrderIndex (r:1 w:1)
+	/// Storage: Dex FeeTiers (r:1 w:0)
+	/// Storage: Dex TradingVolumes (r:1 w:0)
+	/// Storage: Dex PairFees (r:1 w:0)
+	/// Storage: Dex PairConfigs (r:1 w:0)
+	/// Storage: Dex UserTokenInfoes (r:2 w:2)
+	/// Storage: Dex Orders (r:1 w:1)
+	/// Storage: Dex UserOrders (r:1 w:1)
+	/// Storage: Dex PairOrders (r:1 w:1)
+	/// Storage: Dex UserOrderCount (r:1 w:1)
+	/// Storage: Dex PairOrderCount (r:1 w:1)
+	/// Storage: Dex OrderExpiration (r:1 w:1)
+	/// Storage: Dex RewardStakes (r:1 w:0)
+	/// Storage: Dex MatchEngines (r:1 w:1)
+	/// Storage: Timestamp Now (r:1 w:0)
+	/// Storage: Dex PriceAccumulators (r:1 w:1)
+	/// Storage: Dex PriceObservations (r:1 w:1)
+	/// Storage: Dex PriceWindows (r:1 w:1)
+	/// The range of component `n` is `[2, 4]`.
+	fn swap_exact_in(n: u32, ) -> Weight {
+		Weight::from_parts(4_000_000, 0)
+			.saturating_add(Weight::from_parts(86_000_000, 0).saturating_mul(n.into()))
+			.saturating_add(RocksDbWeight::get().reads((25_u64).saturating_mul(n.into())))
+			.saturating_add(RocksDbWeight::get().writes((16_u64).saturating_mul(n.into())))
+			.saturating_add(Weight::from_parts(0, 11350).saturating_mul(n.into()))
+	}
+	/// Storage: Dex LiquidityPools (r:1 w:1)
+	/// Storage: Dex TradingPairs (r:1 w:0)
+	/// Storage: Dex CircuitBreakerHalts (r:1 w:1)
+	/// Storage: Dex PriceBands (r:1 w:0)
+	/// Storage: Dex LastTradePrice (r:1 w:0)
+	/// Storage: Dex UserTokenInfoes (r:2 w:2)
+	/// Storage: Timestamp Now (r:1 w:0)
+	/// Storage: Dex PriceAccumulators (r:1 w:1)
+	/// Storage: Dex PriceObservations (r:1 w:1)
+	/// Storage: Dex PriceWindows (r:1 w:1)
+	/// The range of component `n` is `[2, 4]`.
+	fn swap_exact_out(n: u32, ) -> Weight {
+		Weight::from_parts(5_000_000, 0)
+			.saturating_add(Weight::from_parts(34_000_000, 0).saturating_mul(n.into()))
+			.saturating_add(RocksDbWeight::get().reads((11_u64).saturating_mul(n.into())))
+			.saturating_add(RocksDbWeight::get().writes((7_u64).saturating_mul(n.into())))
+			.saturating_add(Weight::from_parts(0, 6196).saturating_mul(n.into()))
+	}
+	/// Storage: Dex TradingPairs (r:1 w:0)
+	/// Storage: Dex FeeTiers (r:0 w:1)
+	fn set_fee_tiers() -> Weight {
+		Weight::from_parts(14_000_000, 3_541)
+			.saturating_add(RocksDbWeight::get().reads(1_u64))
+			.saturating_add(RocksDbWeight::get().writes(1_u64))
+	}
+	/// Storage: Dex TradingPairs (r:1 w:0)
+	/// Storage: Dex TokenIndex (r:1 w:0)
+	/// Storage: Dex PairRewardEpochs (r:1 w:1)
+	/// Storage: Dex RewardEpochs (r:1 w:1)
+	/// Storage: Dex NativeAssetId (r:1 w:0)
+	/// Storage: Dex TokenIds (r:1 w:0)
+	/// Storage: Assets Asset (r:1 w:1)
+	/// Storage: Assets Account (r:2 w:2)
+	/// Storage: Dex NextRewardEpochId (r:1 w:1)
+	fn fund_reward_epoch() -> Weight {
+		Weight::from_parts(54_000_000, 6_208)
+			.saturating_add(RocksDbWeight::get().reads(10_u64))
+			.saturating_add(RocksDbWeight::get().writes(6_u64))
+	}
+	/// Storage: Dex UserOrders (r:1 w:0)
+	/// Storage: Dex Orders (r:1 w:0)
+	/// Storage: Dex RewardStakes (r:1 w:1)
+	/// Storage: Dex RewardEpochs (r:1 w:1)
+	/// Storage: Dex LastTradePrice (r:1 w:0)
+	/// Storage: Dex UnclaimedRewards (r:1 w:1)
+	/// Storage: Dex NativeAssetId (r:1 w:0)
+	/// Storage: Dex TokenIds (r:1 w:0)
+	/// Storage: Assets Asset (r:1 w:1)
+	/// Storage: Assets Account (r:2 w:2)
+	/// Storage: Dex UserTokenInfoes (r:1 w:1)
+	/// The range of component `o` is `[0, 100]`.
+	fn claim_rewards(o: u32, ) -> Weight {
+		Weight::from_parts(52_000_000, 6_208)
+			.saturating_add(Weight::from_parts(16_000_000, 0).saturating_mul(o.into()))
+			.saturating_add(RocksDbWeight::get().reads(9_u64))
+			.saturating_add(RocksDbWeight::get().reads((4_u64).saturating_mul(o.into())))
+			.saturating_add(RocksDbWeight::get().writes(5_u64))
+			.saturating_add(RocksDbWeight::get().writes((2_u64).saturating_mul(o.into())))
+			.saturating_add(Weight::from_parts(0, 3_530).saturating_mul(o.into()))
+	}
+	/// Storage: Dex MatchEngines (r:1 w:1)
+	/// The range of component `b` is `[0, 1000]`.
+	fn update_match_engine(b: u32, ) -> Weight {
+		Weight::from_parts(8_000_000, 3_530)
+			.saturating_add(Weight::from_parts(610_000, 0).saturating_mul(b.into()))
+			.saturating_add(RocksDbWeight::get().reads(1_u64))
+			.saturating_add(RocksDbWeight::get().writes(1_u64))
+			.saturating_add(Weight::from_parts(0, 162).saturating_mul(b.into()))
+	}
+	/// Storage: Dex TokenIds (r:1 w:1)
+	/// Storage: Assets Asset (r:1 w:0)
+	/// Storage: Dex TokenAssetIds (r:1 w:1)
+	fn register_token() -> Weight {
+		Weight::from_parts(14_000_000, 3_675)
+			.saturating_add(RocksDbWeight::get().reads(3_u64))
+			.saturating_add(RocksDbWeight::get().writes(2_u64))
+	}
+	/// Storage: Dex TokenIds (r:3 w:1)
+	/// Storage: Tokens Accounts (r:2 w:0)
+	/// Storage: Dex TokenAssetIds (r:0 w:1)
+	fn deregister_token() -> Weight {
+		Weight::from_parts(18_000_000, 6_186)
+			.saturating_add(RocksDbWeight::get().reads(5_u64))
+			.saturating_add(RocksDbWeight::get().writes(2_u64))
+	}
+	/// Storage: Dex RewardEpochs (r:1 w:1)
+	/// Storage: Dex NativeAssetId (r:1 w:0)
+	/// Storage: Dex TokenIds (r:1 w:0)
+	/// Storage: Assets Asset (r:1 w:1)
+	/// Storage: Assets Account (r:2 w:2)
+	fn reclaim_rewards() -> Weight {
+		Weight::from_parts(40_000_000, 6_208)
+			.saturating_add(RocksDbWeight::get().reads(6_u64))
+			.saturating_add(RocksDbWeight::get().writes(4_u64))
+	}
+	/// Storage: Dex TokenInfoes (r:1 w:0)
+	fn query_tokens() -> Weight {
+		Weight::from_parts(4_000_000, 1_517)
+			.saturating_add(RocksDbWeight::get().reads(1_u64))
+	}
+	/// Storage: Dex UserTokenInfoes (r:1 w:0)
+	fn query_balance() -> Weight {
+		Weight::from_parts(5_000_000, 3_530)
+			.saturating_add(RocksDbWeight::get().reads(1_u64))
+	}
+	/// Storage: Dex UserTokenInfoes (r:257 w:0)
+	/// The range of component `t` is `[0, 256]`.
+	fn query_owner_tokens(t: u32, ) -> Weight {
+		Weight::from_parts(4_000_000, 3_530)
+			.saturating_add(Weight::from_parts(1_500_000, 0).saturating_mul(t.into()))
+			.saturating_add(RocksDbWeight::get().reads(1_u64))
+			.saturating_add(RocksDbWeight::get().reads((1_u64).saturating_mul(t.into())))
+			.saturating_add(Weight::from_parts(0, 2_551).saturating_mul(t.into()))
+	}
+	/// Storage: Dex Orders (r:1 w:0)
+	fn query_order() -> Weight {
+		Weight::from_parts(6_000_000, 3_530)
+			.saturating_add(RocksDbWeight::get().reads(1_u64))
+	}
+	/// Storage: Dex PairOrders (r:1001 w:0)
+	/// Storage: Dex Orders (r:100 w:0)
+	/// The range of component `n` is `[100, 1000]`.
+	/// The range of component `l` is `[0, 100]`.
+	fn query_pair_orders(n: u32, l: u32, ) -> Weight {
+		Weight::from_parts(5_000_000, 3_530)
+			.saturating_add(Weight::from_parts(1_600_000, 0).saturating_mul(n.into()))
+			.saturating_add(Weight::from_parts(5_000_000, 0).saturating_mul(l.into()))
+			.saturating_add(RocksDbWeight::get().reads(1_u64))
+			.saturating_add(RocksDbWeight::get().reads((1_u64).saturating_mul(n.into())))
+			.saturating_add(RocksDbWeight::get().reads((1_u64).saturating_mul(l.into())))
+			.saturating_add(Weight::from_parts(0, 2_531).saturating_mul(n.into()))
+			.saturating_add(Weight::from_parts(0, 3_530).saturating_mul(l.into()))
+	}
+	/// Storage: Dex UserOrders (r:1001 w:0)
+	/// Storage: Dex Orders (r:100 w:0)
+	/// The range of component `n` is `[100, 1000]`.
+	/// The range of component `l` is `[0, 100]`.
+	fn query_user_orders(n: u32, l: u32, ) -> Weight {
+		Weight::from_parts(5_000_000, 3_530)
+			.saturating_add(Weight::from_parts(1_500_000, 0).saturating_mul(n.into()))
+			.saturating_add(Weight::from_parts(5_000_000, 0).saturating_mul(l.into()))
+			.saturating_add(RocksDbWeight::get().reads(1_u64))
+			.saturating_add(RocksDbWeight::get().reads((1_u64).saturating_mul(n.into())))
+			.saturating_add(RocksDbWeight::get().reads((1_u64).saturating_mul(l.into())))
+			.saturating_add(Weight::from_parts(0, 2_563).saturating_mul(n.into()))
+			.saturating_add(Weight::from_parts(0, 3_530).saturating_mul(l.into()))
+	}
+	/// Storage: Dex LiquidityPools (r:1 w:0)
+	fn query_liquidity_pool() -> Weight {
+		Weight::from_parts(5_000_000, 3_530)
+			.saturating_add(RocksDbWeight::get().reads(1_u64))
+	}
+	/// Storage: Dex PoolShares (r:1 w:0)
+	fn query_pool_shares() -> Weight {
+		Weight::from_parts(5_000_000, 3_530)
+			.saturating_add(RocksDbWeight::get().reads(1_u64))
+	}
+}
@@ -0,0 +1,191 @@
+// SPDX-License-Identifier: GPL-3.0-only
+pragma solidity >=0.8.0;
+
+/**
+ * @title Pallet Dex Interface
+ *
+ * The interface through which solidity contracts will interact with pallet-dex
+ * Address :    0x0000000000000000000000000000000000002053
+ *
+ * Order types: 0 - buy, 1 - sell.
+ * Order kinds: 0 - limit, 1 - market.
+ * Time in force: 0 - good till expiration, 1 - immediate or cancel, 2 - fill or kill, 3 - post only.
+ * Order statuses: 0 - pending, 1 - fully filled, 2 - partial filled, 3 - partial cancelled,
+ * 4 - fully cancelled, 5 - rejected.
+ */
+
+interface Dex {
+    /**
+     * Emitted when an order is created by the caller
+     *
+     * @param orderIndex The index of the new order
+     * @param caller The address that created the order
+     */
+    event OrderCreated(uint64 indexed orderIndex, address indexed caller);
+
+    /**
+     * Emitted when an order is canceled by its owner
+     *
+     * @param orderIndex The index of the canceled order
+     * @param caller The address that canceled the order
+     */
+    event OrderCanceled(uint64 indexed orderIndex, address indexed caller);
+
+    /**
+     * Emitted when an order is taken by the caller
+     *
+     * @param orderIndex The index of the taken order
+     * @param caller The address that took the order
+     */
+    event OrderTaken(uint64 indexed orderIndex, address indexed caller);
+
+    /**
+     * Deposit an asset to the dex
+     * Selector: 0xaaf10398
+     *
+     * @param assetId The asset to deposit
+     * @param amount The amount to deposit
+     */
+    function deposit(uint32 assetId, uint256 amount) external returns (bool);
+
+    /**
+     * Withdraw an asset from the dex
+     * Selector: 0x0e19dc3a
+     *
+     * @param assetId The asset to withdraw
+     * @param amount The amount to withdraw
+     */
+    function withdraw(uint32 assetId, uint256 amount) external returns (bool);
+
+    /**
+     * Deposit the native currency to the dex
+     * Selector: 0x608fc37a
+     *
+     * @param amount The amount to deposit
+     */
+    function depositNative(uint256 amount) external returns (bool);
+
+    /**
+     * Withdraw the native currency from the dex
+     * Selector: 0x84276d81
+     *
+     * @param amount The amount to withdraw
+     */
+    function withdrawNative(uint256 amount) external returns (bool);
+
+    /**
+     * Make a limit order
+     * Selector: 0x2221eaa6
+     *
+     * @param assetId1 One asset of the pair
+     * @param assetId2 The other asset of the pair
+     * @param offeredAmount The amount offered
+     * @param requestedAmount The amount requested
+     * @param orderType The order type
+     * @param expirationBlock The block the order expires in
+     * @return orderIndex The index of the new order
+     */
+    function makeOrder(
+        uint32 assetId1,
+        uint32 assetId2,
+        uint256 offeredAmount,
+        uint256 requestedAmount,
+        uint8 orderType,
+        uint64 expirationBlock
+    ) external returns (uint64 orderIndex);
+
+    /**
+     * Place an order of any kind and time in force
+     * Selector: 0x2935ab81
+     *
+     * @param assetId1 One asset of the pair
+     * @param assetId2 The other asset of the pair
+     * @param offeredAmount The amount offered
+     * @param requestedAmount The amount requested
+     * @param orderType The order type
+     * @param orderKind The order kind
+     * @param timeInForce The time in force
+     * @param expirationBlock The block the order expires in
+     * @return orderIndex The index of the new order
+     */
+    function placeOrder(
+        uint32 assetId1,
+        uint32 assetId2,
+        uint256 offeredAmount,
+        uint256 requestedAmount,
+        uint8 orderType,
+        uint8 orderKind,
+        uint8 timeInForce,
+        uint64 expirationBlock
+    ) external returns (uint64 orderIndex);
+
+    /**
+     * Cancel an order of the caller
+     * Selector: 0x523eb537
+     *
+     * @param orderIndex The order to cancel
+     */
+    function cancelOrder(uint64 orderIndex) external returns (bool);
+
+    /**
+     * Take an order
+     * Selector: 0x6b64c072
+     *
+     * @param orderIndex The order to take
+     */
+    function takeOrder(uint64 orderIndex) external returns (bool);
+
+    /**
+     * Get the dex balance of an address
+     * Selector: 0x5be035a9
+     *
+     * @param owner The address to query
+     * @param assetId The asset to query
+     * @return amount The free amount
+     * @return reserved The amount reserved by open orders
+     */
+    function balanceOf(address owner, uint32 assetId)
+        external
+        view
+        returns (uint256 amount, uint256 reserved);
+
+    /**
+     * Get an order
+     * Selector: 0xc4a70859
+     *
+     * @param orderIndex The order to query
+     */
+    function orderOf(uint64 orderIndex)
+        external
+        view
+        returns (
+            bytes32 owner,
+            uint32 baseAssetId,
+            uint32 quoteAssetId,
+            uint8 orderType,
+            uint256 price,
+            uint256 baseAmount,
+            uint256 quoteAmount,
+            uint256 unfilledBaseAmount,
+            uint256 unfilledQuoteAmount,
+            uint8 orderStatus,
+            uint64 expirationBlock
+        );
+
+    /**
+     * Get the open orders of an address
+     * Selector: 0x053695ba
+     *
+     * @param owner The address to query
+     */
+    function userOrders(address owner) external view returns (uint64[] memory);
+
+    /**
+     * Get the open orders of a pair
+     * Selector: 0x9a180927
+     *
+     * @param assetId1 One asset of the pair
+     * @param assetId2 The other asset of the pair
+     */
+    function pairOrders(uint32 assetId1, uint32 assetId2) external view returns (uint64[] memory);
+}
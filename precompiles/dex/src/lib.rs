@@ -0,0 +1,408 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use fp_evm::PrecompileOutput;
+use frame_support::dispatch::{Dispatchable, GetDispatchInfo, PostDispatchInfo};
+use frame_system::pallet_prelude::BlockNumberFor;
+use pallet_dex::{
+	BalanceOf, Call as DexCall, OrderKind, OrderStatus, OrderType, Orders, PairOrderCount,
+	PairOrders, TimeInForce, UserOrderCount, UserOrders, UserTokenInfoes,
+};
+use pallet_evm::{AddressMapping, Precompile, PrecompileHandle};
+use precompile_utils::{
+	keccak256, log3, revert, succeed, Address, EvmData, EvmDataWriter, EvmResult, FunctionModifier,
+	LogExt, PrecompileHandleExt, RuntimeHelper,
+};
+use sp_core::{H160, H256, U256};
+use sp_std::{fmt::Debug, marker::PhantomData, vec::Vec};
+
+pub const SELECTOR_LOG_ORDER_CREATED: [u8; 32] = keccak256!("OrderCreated(uint64,address)");
+pub const SELECTOR_LOG_ORDER_CANCELED: [u8; 32] = keccak256!("OrderCanceled(uint64,address)");
+pub const SELECTOR_LOG_ORDER_TAKEN: [u8; 32] = keccak256!("OrderTaken(uint64,address)");
+
+#[precompile_utils::generate_function_selector]
+#[derive(Debug, PartialEq)]
+pub enum Action {
+	Deposit = "deposit(uint32,uint256)",
+	Withdraw = "withdraw(uint32,uint256)",
+	DepositNative = "depositNative(uint256)",
+	WithdrawNative = "withdrawNative(uint256)",
+	MakeOrder = "makeOrder(uint32,uint32,uint256,uint256,uint8,uint64)",
+	PlaceOrder = "placeOrder(uint32,uint32,uint256,uint256,uint8,uint8,uint8,uint64)",
+	CancelOrder = "cancelOrder(uint64)",
+	TakeOrder = "takeOrder(uint64)",
+	BalanceOf = "balanceOf(address,uint32)",
+	OrderOf = "orderOf(uint64)",
+	UserOrders = "userOrders(address)",
+	PairOrders = "pairOrders(uint32,uint32)",
+}
+
+/// A precompile to trade on pallet-dex from EVM contracts, on behalf of the account
+/// `AddressMapping` maps the caller to.
+pub struct DexPrecompile<Runtime>(PhantomData<Runtime>);
+
+impl<Runtime> Precompile for DexPrecompile<Runtime>
+where
+	Runtime: pallet_dex::Config + pallet_evm::Config + frame_system::Config,
+	<Runtime as frame_system::Config>::RuntimeCall:
+		Dispatchable<PostInfo = PostDispatchInfo> + GetDispatchInfo,
+	<<Runtime as frame_system::Config>::RuntimeCall as Dispatchable>::RuntimeOrigin:
+		From<Option<Runtime::AccountId>>,
+	<Runtime as frame_system::Config>::RuntimeCall: From<DexCall<Runtime>>,
+	Runtime::AccountId: AsRef<[u8; 32]>,
+	BalanceOf<Runtime>: EvmData,
+	BlockNumberFor<Runtime>: TryFrom<u64> + Into<u64>,
+{
+	fn execute(handle: &mut impl PrecompileHandle) -> EvmResult<PrecompileOutput> {
+		log::trace!(target: "dex-precompile", "In dex precompile");
+
+		let selector = handle.read_selector()?;
+
+		handle.check_function_modifier(match selector {
+			Action::BalanceOf | Action::OrderOf | Action::UserOrders | Action::PairOrders => {
+				FunctionModifier::View
+			}
+			_ => FunctionModifier::NonPayable,
+		})?;
+
+		match selector {
+			// Dispatchables
+			Action::Deposit => Self::deposit(handle),
+			Action::Withdraw => Self::withdraw(handle),
+			Action::DepositNative => Self::deposit_native(handle),
+			Action::WithdrawNative => Self::withdraw_native(handle),
+			Action::MakeOrder => Self::make_order(handle),
+			Action::PlaceOrder => Self::place_order(handle),
+			Action::CancelOrder => Self::cancel_order(handle),
+			Action::TakeOrder => Self::take_order(handle),
+			// Storage getters
+			Action::BalanceOf => Self::balance_of(handle),
+			Action::OrderOf => Self::order_of(handle),
+			Action::UserOrders => Self::user_orders(handle),
+			Action::PairOrders => Self::pair_orders(handle),
+		}
+	}
+}
+
+impl<Runtime> DexPrecompile<Runtime>
+where
+	Runtime: pallet_dex::Config + pallet_evm::Config + frame_system::Config,
+	<Runtime as frame_system::Config>::RuntimeCall:
+		Dispatchable<PostInfo = PostDispatchInfo> + GetDispatchInfo,
+	<<Runtime as frame_system::Config>::RuntimeCall as Dispatchable>::RuntimeOrigin:
+		From<Option<Runtime::AccountId>>,
+	<Runtime as frame_system::Config>::RuntimeCall: From<DexCall<Runtime>>,
+	Runtime::AccountId: AsRef<[u8; 32]>,
+	BalanceOf<Runtime>: EvmData,
+	BlockNumberFor<Runtime>: TryFrom<u64> + Into<u64>,
+{
+	// The dispatchable wrappers are next. They dispatch a Substrate inner Call.
+	fn deposit(handle: &mut impl PrecompileHandle) -> EvmResult<PrecompileOutput> {
+		let mut input = handle.read_input()?;
+		input.expect_arguments(2)?;
+
+		let asset_id = input.read::<u32>()?;
+		let amount = input.read::<BalanceOf<Runtime>>()?;
+
+		let origin = Runtime::AddressMapping::into_account_id(handle.context().caller);
+		let call = DexCall::<Runtime>::deposit { asset_id, amount };
+
+		RuntimeHelper::<Runtime>::try_dispatch(handle, Some(origin).into(), call)?;
+
+		Ok(succeed(EvmDataWriter::new().write(true).build()))
+	}
+
+	fn withdraw(handle: &mut impl PrecompileHandle) -> EvmResult<PrecompileOutput> {
+		let mut input = handle.read_input()?;
+		input.expect_arguments(2)?;
+
+		let asset_id = input.read::<u32>()?;
+		let amount = input.read::<BalanceOf<Runtime>>()?;
+
+		let origin = Runtime::AddressMapping::into_account_id(handle.context().caller);
+		let call = DexCall::<Runtime>::withdraw { asset_id, amount };
+
+		RuntimeHelper::<Runtime>::try_dispatch(handle, Some(origin).into(), call)?;
+
+		Ok(succeed(EvmDataWriter::new().write(true).build()))
+	}
+
+	fn deposit_native(handle: &mut impl PrecompileHandle) -> EvmResult<PrecompileOutput> {
+		let mut input = handle.read_input()?;
+		input.expect_arguments(1)?;
+
+		let amount = input.read::<BalanceOf<Runtime>>()?;
+
+		let origin = Runtime::AddressMapping::into_account_id(handle.context().caller);
+		let call = DexCall::<Runtime>::deposit_native { amount };
+
+		RuntimeHelper::<Runtime>::try_dispatch(handle, Some(origin).into(), call)?;
+
+		Ok(succeed(EvmDataWriter::new().write(true).build()))
+	}
+
+	fn withdraw_native(handle: &mut impl PrecompileHandle) -> EvmResult<PrecompileOutput> {
+		let mut input = handle.read_input()?;
+		input.expect_arguments(1)?;
+
+		let amount = input.read::<BalanceOf<Runtime>>()?;
+
+		let origin = Runtime::AddressMapping::into_account_id(handle.context().caller);
+		let call = DexCall::<Runtime>::withdraw_native { amount };
+
+		RuntimeHelper::<Runtime>::try_dispatch(handle, Some(origin).into(), call)?;
+
+		Ok(succeed(EvmDataWriter::new().write(true).build()))
+	}
+
+	fn make_order(handle: &mut impl PrecompileHandle) -> EvmResult<PrecompileOutput> {
+		let mut input = handle.read_input()?;
+		input.expect_arguments(6)?;
+
+		let asset_id_1 = input.read::<u32>()?;
+		let asset_id_2 = input.read::<u32>()?;
+		let offered_amount = input.read::<BalanceOf<Runtime>>()?;
+		let requested_amount = input.read::<BalanceOf<Runtime>>()?;
+		let order_type = Self::order_type(input.read::<u8>()?)?;
+		let expiration_block = Self::block_number(input.read::<u64>()?)?;
+
+		let call = DexCall::<Runtime>::make_order {
+			asset_id_1,
+			asset_id_2,
+			offered_amount,
+			requested_amount,
+			order_type,
+			expiration_block,
+		};
+
+		Self::dispatch_order(handle, call)
+	}
+
+	fn place_order(handle: &mut impl PrecompileHandle) -> EvmResult<PrecompileOutput> {
+		let mut input = handle.read_input()?;
+		input.expect_arguments(8)?;
+
+		let asset_id_1 = input.read::<u32>()?;
+		let asset_id_2 = input.read::<u32>()?;
+		let offered_amount = input.read::<BalanceOf<Runtime>>()?;
+		let requested_amount = input.read::<BalanceOf<Runtime>>()?;
+		let order_type = Self::order_type(input.read::<u8>()?)?;
+		let order_kind = match input.read::<u8>()? {
+			0 => OrderKind::Limit,
+			1 => OrderKind::Market,
+			_ => return Err(revert("invalid order kind")),
+		};
+		let time_in_force = match input.read::<u8>()? {
+			0 => TimeInForce::GoodTillExpiration,
+			1 => TimeInForce::ImmediateOrCancel,
+			2 => TimeInForce::FillOrKill,
+			3 => TimeInForce::PostOnly,
+			_ => return Err(revert("invalid time in force")),
+		};
+		let expiration_block = Self::block_number(input.read::<u64>()?)?;
+
+		let call = DexCall::<Runtime>::place_order {
+			asset_id_1,
+			asset_id_2,
+			offered_amount,
+			requested_amount,
+			order_type,
+			order_kind,
+			time_in_force,
+			expiration_block,
+		};
+
+		Self::dispatch_order(handle, call)
+	}
+
+	fn cancel_order(handle: &mut impl PrecompileHandle) -> EvmResult<PrecompileOutput> {
+		let mut input = handle.read_input()?;
+		input.expect_arguments(1)?;
+
+		let order_index = input.read::<u64>()?;
+
+		handle.record_log_costs_manual(3, 0)?;
+
+		let origin = Runtime::AddressMapping::into_account_id(handle.context().caller);
+		let call = DexCall::<Runtime>::cancel_order { order_index };
+
+		RuntimeHelper::<Runtime>::try_dispatch(handle, Some(origin).into(), call)?;
+
+		Self::order_log(handle, SELECTOR_LOG_ORDER_CANCELED, order_index)?;
+
+		Ok(succeed(EvmDataWriter::new().write(true).build()))
+	}
+
+	fn take_order(handle: &mut impl PrecompileHandle) -> EvmResult<PrecompileOutput> {
+		let mut input = handle.read_input()?;
+		input.expect_arguments(1)?;
+
+		let order_index = input.read::<u64>()?;
+
+		handle.record_log_costs_manual(3, 0)?;
+
+		let origin = Runtime::AddressMapping::into_account_id(handle.context().caller);
+		let call = DexCall::<Runtime>::take_order { order_index };
+
+		RuntimeHelper::<Runtime>::try_dispatch(handle, Some(origin).into(), call)?;
+
+		Self::order_log(handle, SELECTOR_LOG_ORDER_TAKEN, order_index)?;
+
+		Ok(succeed(EvmDataWriter::new().write(true).build()))
+	}
+
+	// The storage getters are next.
+	fn balance_of(handle: &mut impl PrecompileHandle) -> EvmResult<PrecompileOutput> {
+		let mut input = handle.read_input()?;
+		input.expect_arguments(2)?;
+
+		let owner: H160 = input.read::<Address>()?.into();
+		let asset_id = input.read::<u32>()?;
+
+		handle.record_cost(RuntimeHelper::<Runtime>::db_read_gas_cost())?;
+
+		let who = Runtime::AddressMapping::into_account_id(owner);
+		let token_info = UserTokenInfoes::<Runtime>::get(who, asset_id);
+
+		Ok(succeed(
+			EvmDataWriter::new()
+				.write(token_info.amount)
+				.write(token_info.reserved)
+				.build(),
+		))
+	}
+
+	fn order_of(handle: &mut impl PrecompileHandle) -> EvmResult<PrecompileOutput> {
+		let mut input = handle.read_input()?;
+		input.expect_arguments(1)?;
+
+		let order_index = input.read::<u64>()?;
+
+		handle.record_cost(RuntimeHelper::<Runtime>::db_read_gas_cost())?;
+
+		let order = Orders::<Runtime>::get(order_index).ok_or_else(|| revert("order not found"))?;
+		let (base, quote) = order.pair();
+		let order_type: u8 = match order.order_type() {
+			OrderType::BUY => 0,
+			OrderType::SELL => 1,
+		};
+		let order_status: u8 = match order.order_status() {
+			OrderStatus::Pending => 0,
+			OrderStatus::FullyFilled => 1,
+			OrderStatus::PartialFilled => 2,
+			OrderStatus::PartialCancelled => 3,
+			OrderStatus::FullyCancelled => 4,
+			OrderStatus::Rejected => 5,
+		};
+		let expiration_block: u64 = (*order.expiration_block()).into();
+
+		Ok(succeed(
+			EvmDataWriter::new()
+				.write(H256::from(*order.address().as_ref()))
+				.write(base)
+				.write(quote)
+				.write(order_type)
+				.write(U256::from(order.price().into_inner()))
+				.write(order.get_base_amount())
+				.write(order.get_quote_amout())
+				.write(order.get_unfilled_base_amout())
+				.write(order.get_unfilled_quote_amout())
+				.write(order_status)
+				.write(expiration_block)
+				.build(),
+		))
+	}
+
+	fn user_orders(handle: &mut impl PrecompileHandle) -> EvmResult<PrecompileOutput> {
+		let mut input = handle.read_input()?;
+		input.expect_arguments(1)?;
+
+		let owner: H160 = input.read::<Address>()?.into();
+
+		let who = Runtime::AddressMapping::into_account_id(owner);
+		// the open orders of an account are counted, so the whole prefix is paid upfront
+		handle.record_cost(RuntimeHelper::<Runtime>::db_read_gas_cost())?;
+		let order_count = UserOrderCount::<Runtime>::get(&who);
+		handle.record_cost(
+			RuntimeHelper::<Runtime>::db_read_gas_cost().saturating_mul(order_count.into()),
+		)?;
+
+		let order_indexes: Vec<u64> = UserOrders::<Runtime>::iter_key_prefix(who).collect();
+
+		Ok(succeed(EvmDataWriter::new().write(order_indexes).build()))
+	}
+
+	fn pair_orders(handle: &mut impl PrecompileHandle) -> EvmResult<PrecompileOutput> {
+		let mut input = handle.read_input()?;
+		input.expect_arguments(2)?;
+
+		let asset_id_1 = input.read::<u32>()?;
+		let asset_id_2 = input.read::<u32>()?;
+		// pairs are keyed with the lower asset id as base
+		let pair = if asset_id_1 > asset_id_2 {
+			(asset_id_2, asset_id_1)
+		} else {
+			(asset_id_1, asset_id_2)
+		};
+
+		handle.record_cost(RuntimeHelper::<Runtime>::db_read_gas_cost())?;
+		let order_count = PairOrderCount::<Runtime>::get(pair);
+		handle.record_cost(
+			RuntimeHelper::<Runtime>::db_read_gas_cost().saturating_mul(order_count.into()),
+		)?;
+
+		let order_indexes: Vec<u64> = PairOrders::<Runtime>::iter_key_prefix(pair).collect();
+
+		Ok(succeed(EvmDataWriter::new().write(order_indexes).build()))
+	}
+
+	/// Dispatches a call that places an order and returns the index of the new order.
+	fn dispatch_order(
+		handle: &mut impl PrecompileHandle,
+		call: DexCall<Runtime>,
+	) -> EvmResult<PrecompileOutput> {
+		// the new order gets the next order index
+		handle.record_cost(RuntimeHelper::<Runtime>::db_read_gas_cost())?;
+		handle.record_log_costs_manual(3, 0)?;
+		let order_index = pallet_dex::Pallet::<Runtime>::next_order_index();
+
+		let origin = Runtime::AddressMapping::into_account_id(handle.context().caller);
+
+		RuntimeHelper::<Runtime>::try_dispatch(handle, Some(origin).into(), call)?;
+
+		Self::order_log(handle, SELECTOR_LOG_ORDER_CREATED, order_index)?;
+
+		Ok(succeed(EvmDataWriter::new().write(order_index).build()))
+	}
+
+	fn order_log(
+		handle: &mut impl PrecompileHandle,
+		selector: [u8; 32],
+		order_index: u64,
+	) -> EvmResult {
+		let address = handle.context().address;
+		let caller = handle.context().caller;
+
+		log3(
+			address,
+			selector,
+			H256::from_low_u64_be(order_index),
+			H256::from(caller),
+			Vec::new(),
+		)
+		.record(handle)
+	}
+
+	fn order_type(order_type: u8) -> EvmResult<OrderType> {
+		match order_type {
+			0 => Ok(OrderType::BUY),
+			1 => Ok(OrderType::SELL),
+			_ => Err(revert("invalid order type")),
+		}
+	}
+
+	fn block_number(block_number: u64) -> EvmResult<BlockNumberFor<Runtime>> {
+		block_number
+			.try_into()
+			.map_err(|_| revert("expiration block out of bounds"))
+	}
+}
@@ -1,23 +1,37 @@
 #![cfg_attr(not(feature = "std"), no_std)]
 use sp_runtime::{DispatchError, ModuleError};
 
-use frame_support::traits::Currency;
+use frame_support::{
+	dispatch::DispatchResultWithPostInfo,
+	pallet_prelude::Weight,
+	traits::{Currency, Get},
+};
 use frame_system::{pallet_prelude::BlockNumberFor, RawOrigin};
 use pallet_contracts::chain_extension::{
-	ChainExtension, Environment, Ext, InitState, RetVal, SysConfig,
+	BufInBufOutState, ChainExtension, Environment, Ext, InitState, RetVal, SysConfig,
 };
+use pallet_dex::{WeightInfo, MAX_ORDERS_PER_PAGE};
 
 use scale_codec::{Decode, Encode, MaxEncodedLen};
 use sp_core::crypto::UncheckedFrom;
 
 use crate::chain_extensions::get_address_from_caller;
 
-use sp_std::{vec, vec::Vec};
+use sp_std::vec::Vec;
 
 type BalanceOf<Runtime> = <<Runtime as pallet_dex::Config>::Currency as Currency<
 	<Runtime as frame_system::Config>::AccountId,
 >>::Balance;
 
+type OrderOf<Runtime> = pallet_dex::Order<
+	<Runtime as frame_system::Config>::AccountId,
+	BalanceOf<Runtime>,
+	BlockNumberFor<Runtime>,
+>;
+
+/// Order layout of the func ids that predate fixed-point prices.
+type LegacyOrderOf<Runtime> = pallet_dex::migration::v1::OldOrderOf<Runtime>;
+
 #[derive(Debug, PartialEq, Encode, Decode, MaxEncodedLen)]
 struct DexDepositInput<AssetId, Balance> {
 	asset_id: AssetId,
@@ -62,11 +76,26 @@ struct DexPairOrdersInput<AssetId> {
 	asset_id_2: AssetId,
 }
 
+#[derive(Debug, PartialEq, Encode, Decode, MaxEncodedLen)]
+struct DexPairOrdersPageInput<AssetId> {
+	asset_id_1: AssetId,
+	asset_id_2: AssetId,
+	offset: u32,
+	limit: u32,
+}
+
 #[derive(Debug, PartialEq, Encode, Decode, MaxEncodedLen)]
 struct DexUserOrdersInput<AccountId> {
 	owner: AccountId,
 }
 
+#[derive(Debug, PartialEq, Encode, Decode, MaxEncodedLen)]
+struct DexUserOrdersPageInput<AccountId> {
+	owner: AccountId,
+	offset: u32,
+	limit: u32,
+}
+
 #[derive(Debug, PartialEq, Encode, Decode, MaxEncodedLen)]
 struct DexMakeOrderInput<AssetId, Balance, OrderType, BlockNumber> {
 	asset_id_1: AssetId,
@@ -78,6 +107,18 @@ struct DexMakeOrderInput<AssetId, Balance, OrderType, BlockNumber> {
 	expires: BlockNumber,
 }
 
+#[derive(Debug, PartialEq, Encode, Decode, MaxEncodedLen)]
+struct DexPlaceOrderInput<AssetId, Balance, OrderType, OrderKind, TimeInForce, BlockNumber> {
+	asset_id_1: AssetId,
+	asset_id_2: AssetId,
+	offered_amount: Balance,
+	requested_amount: Balance,
+	order_type: OrderType,
+	expires: BlockNumber,
+	order_kind: OrderKind,
+	time_in_force: TimeInForce,
+}
+
 #[derive(Debug, PartialEq, Encode, Decode, MaxEncodedLen)]
 struct DexCancelOrderInput {
 	index: u64,
@@ -107,6 +148,45 @@ struct DexUserOrderByIndexInput<AccountId> {
 	index: u64,
 }
 
+#[derive(Debug, PartialEq, Encode, Decode, MaxEncodedLen)]
+struct DexLiquidityPoolInput<AssetId> {
+	asset_id_1: AssetId,
+	asset_id_2: AssetId,
+}
+
+#[derive(Debug, PartialEq, Encode, Decode, MaxEncodedLen)]
+struct DexPoolSharesInput<AssetId, AccountId> {
+	owner: AccountId,
+	asset_id_1: AssetId,
+	asset_id_2: AssetId,
+}
+
+#[derive(Debug, PartialEq, Encode, Decode, MaxEncodedLen)]
+struct DexAddLiquidityInput<AssetId, Balance> {
+	asset_id_1: AssetId,
+	asset_id_2: AssetId,
+	amount_1: Balance,
+	amount_2: Balance,
+	min_shares: Balance,
+}
+
+#[derive(Debug, PartialEq, Encode, Decode, MaxEncodedLen)]
+struct DexRemoveLiquidityInput<AssetId, Balance> {
+	asset_id_1: AssetId,
+	asset_id_2: AssetId,
+	shares: Balance,
+	min_amount_1: Balance,
+	min_amount_2: Balance,
+}
+
+#[derive(Debug, PartialEq, Encode, Decode, MaxEncodedLen)]
+struct DexSwapInput<AssetId, Balance> {
+	asset_in: AssetId,
+	asset_out: AssetId,
+	amount_in: Balance,
+	min_amount_out: Balance,
+}
+
 #[derive(PartialEq, Eq, Copy, Clone, Encode, Decode, Debug)]
 #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
 pub enum Outcome {
@@ -114,6 +194,14 @@ pub enum Outcome {
 	Success = 0_isize,
 	/// Account balance must be greater than or equal to the transfer amount.
 	BalanceLow = 1_isize,
+	/// The order doesn't exist, it was filled, canceled or never made.
+	OrderNotFound = 2_isize,
+	/// The index is past the end of the list.
+	IndexOutOfRange = 3_isize,
+	/// The pair has no liquidity pool.
+	PoolNotFound = 4_isize,
+	/// The swap or the liquidity change would get less than the given minimum.
+	SlippageExceeded = 5_isize,
 
 	/// Unknown error
 	RuntimeError = 99,
@@ -127,6 +215,9 @@ impl From<DispatchError> for Outcome {
 		};
 		match error_text {
 			Some("BalanceLow") => Outcome::BalanceLow,
+			Some("InvalidOrderIndex") => Outcome::OrderNotFound,
+			Some("PoolNotFound") => Outcome::PoolNotFound,
+			Some("SlippageExceeded") => Outcome::SlippageExceeded,
 			_ => Outcome::RuntimeError,
 		}
 	}
@@ -150,6 +241,15 @@ enum DexFunc {
 	UserOrderByIndex,
 	DepositNative,
 	WithdrawNative,
+	LiquidityPool,
+	PoolShares,
+	AddLiquidity,
+	RemoveLiquidity,
+	Swap,
+	PlaceOrder,
+	PairOrdersPage,
+	UserOrdersPage,
+	OrderDetails,
 }
 
 impl TryFrom<u16> for DexFunc {
@@ -173,6 +273,15 @@ impl TryFrom<u16> for DexFunc {
 			14 => Ok(DexFunc::UserOrderByIndex),
 			15 => Ok(DexFunc::DepositNative),
 			16 => Ok(DexFunc::WithdrawNative),
+			17 => Ok(DexFunc::LiquidityPool),
+			18 => Ok(DexFunc::PoolShares),
+			19 => Ok(DexFunc::AddLiquidity),
+			20 => Ok(DexFunc::RemoveLiquidity),
+			21 => Ok(DexFunc::Swap),
+			22 => Ok(DexFunc::PlaceOrder),
+			23 => Ok(DexFunc::PairOrdersPage),
+			24 => Ok(DexFunc::UserOrdersPage),
+			25 => Ok(DexFunc::OrderDetails),
 			_ => Err(DispatchError::Other("DexExtension: Unimplemented func_id")),
 		}
 	}
@@ -182,6 +291,115 @@ impl TryFrom<u16> for DexFunc {
 #[derive(Default)]
 pub struct DexExtension;
 
+/// Weight of going from the contract layer to the substrate layer, `debug_message` weight is
+/// a good approximation of it.
+fn overhead<T: pallet_contracts::Config>() -> Weight {
+	<T as pallet_contracts::Config>::Schedule::get()
+		.host_fn_weights
+		.debug_message
+}
+
+/// Weight of reading `reads` items from storage.
+fn reads<T: frame_system::Config>(reads: u64) -> Weight {
+	<T as frame_system::Config>::DbWeight::get().reads(reads)
+}
+
+/// Dispatches a call of pallet-dex signed by the caller of the contract, charges `weight` for
+/// it and refunds what the call didn't use.
+fn dispatch<T, E>(
+	env: &mut Environment<'_, '_, E, BufInBufOutState>,
+	weight: Weight,
+	call: impl FnOnce(<T as frame_system::Config>::RuntimeOrigin) -> DispatchResultWithPostInfo,
+) -> Result<RetVal, DispatchError>
+where
+	T: pallet_contracts::Config + pallet_dex::Config,
+	E: Ext<T = T>,
+{
+	let charged = env.charge_weight(weight.saturating_add(overhead::<T>()))?;
+
+	let sender = get_address_from_caller(env.ext().caller().clone())?;
+	let (post_info, outcome) = match call(RawOrigin::Signed(sender).into()) {
+		Err(e) => (e.post_info, Outcome::from(e.error)),
+		Ok(post_info) => (post_info, Outcome::Success),
+	};
+	let actual_weight = post_info.actual_weight.unwrap_or(weight);
+	env.adjust_weight(charged, actual_weight.saturating_add(overhead::<T>()));
+
+	Ok(RetVal::Converging(outcome as u32))
+}
+
+/// Reads up to `limit` orders of `pair` from `offset`, oldest first, with the number of order
+/// indexes of the pair. The order count of the pair is read first to charge for the page.
+fn pair_orders<T, E>(
+	env: &mut Environment<'_, '_, E, BufInBufOutState>,
+	pair: (u32, u32),
+	offset: u32,
+	limit: u32,
+) -> Result<(u32, Vec<OrderOf<T>>), DispatchError>
+where
+	T: pallet_contracts::Config + pallet_dex::Config,
+	E: Ext<T = T>,
+{
+	let limit = limit.min(MAX_ORDERS_PER_PAGE);
+
+	env.charge_weight(reads::<T>(1).saturating_add(overhead::<T>()))?;
+	let order_count = pallet_dex::PairOrderCount::<T>::get(pair);
+	let charged = env.charge_weight(<T as pallet_dex::Config>::WeightInfo::query_pair_orders(
+		order_count,
+		limit,
+	))?;
+
+	let (index_count, order_array) = pallet_dex::Pallet::<T>::pair_orders_page(pair, offset, limit);
+	env.adjust_weight(
+		charged,
+		<T as pallet_dex::Config>::WeightInfo::query_pair_orders(
+			index_count,
+			order_array.len() as u32,
+		),
+	);
+	Ok((index_count, order_array))
+}
+
+/// Reads up to `limit` open orders of `owner` from `offset`, with the number of order indexes
+/// of `owner`. The order count of `owner` is read first to charge for the page.
+fn user_orders<T, E>(
+	env: &mut Environment<'_, '_, E, BufInBufOutState>,
+	owner: T::AccountId,
+	offset: u32,
+	limit: u32,
+) -> Result<(u32, Vec<OrderOf<T>>), DispatchError>
+where
+	T: pallet_contracts::Config + pallet_dex::Config,
+	E: Ext<T = T>,
+{
+	let limit = limit.min(MAX_ORDERS_PER_PAGE);
+
+	env.charge_weight(reads::<T>(1).saturating_add(overhead::<T>()))?;
+	let order_count = pallet_dex::UserOrderCount::<T>::get(&owner);
+	let charged = env.charge_weight(<T as pallet_dex::Config>::WeightInfo::query_user_orders(
+		order_count,
+		limit,
+	))?;
+
+	let (index_count, order_array) =
+		pallet_dex::Pallet::<T>::user_orders_page(&owner, offset, limit);
+	env.adjust_weight(
+		charged,
+		<T as pallet_dex::Config>::WeightInfo::query_user_orders(
+			index_count,
+			order_array.len() as u32,
+		),
+	);
+	Ok((index_count, order_array))
+}
+
+fn legacy_orders<T>(orders: Vec<OrderOf<T>>) -> Vec<LegacyOrderOf<T>>
+where
+	T: pallet_dex::Config,
+{
+	orders.into_iter().map(Into::into).collect()
+}
+
 impl<T> ChainExtension<T> for DexExtension
 where
 	T: frame_system::Config + pallet_contracts::Config + pallet_dex::Config,
@@ -201,23 +419,18 @@ where
 			DexFunc::Deposit => {
 				let input: DexDepositInput<u32, BalanceOf<T>> = env.read_as()?;
 
-				let sender = get_address_from_caller(env.ext().caller().clone())?;
-				let call_result = pallet_dex::Pallet::<T>::deposit(
-					RawOrigin::Signed(sender).into(),
-					input.asset_id,
-					input.amount,
+				return dispatch(
+					&mut env,
+					<T as pallet_dex::Config>::WeightInfo::deposit(),
+					|origin| pallet_dex::Pallet::<T>::deposit(origin, input.asset_id, input.amount),
 				);
-
-				return match call_result {
-					Err(e) => {
-						let mapped_error = Outcome::from(e.error);
-						Ok(RetVal::Converging(mapped_error as u32))
-					}
-					Ok(_) => Ok(RetVal::Converging(Outcome::Success as u32)),
-				};
 			}
 			DexFunc::BalanceOf => {
 				let input: DexBalanceOfInput<u32, T::AccountId> = env.read_as()?;
+				env.charge_weight(
+					<T as pallet_dex::Config>::WeightInfo::query_balance()
+						.saturating_add(overhead::<T>()),
+				)?;
 
 				let token_info =
 					pallet_dex::UserTokenInfoes::<T>::get(&input.owner, input.asset_id);
@@ -226,95 +439,117 @@ where
 			DexFunc::Withdraw => {
 				let input: DexWithdrawInput<u32, BalanceOf<T>> = env.read_as()?;
 
-				let sender = get_address_from_caller(env.ext().caller().clone())?;
-				let call_result = pallet_dex::Pallet::<T>::withdraw(
-					RawOrigin::Signed(sender).into(),
-					input.asset_id,
-					input.amount,
+				return dispatch(
+					&mut env,
+					<T as pallet_dex::Config>::WeightInfo::withdraw(),
+					|origin| {
+						pallet_dex::Pallet::<T>::withdraw(origin, input.asset_id, input.amount)
+					},
 				);
-
-				return match call_result {
-					Err(e) => {
-						let mapped_error = Outcome::from(e.error);
-						Ok(RetVal::Converging(mapped_error as u32))
-					}
-					Ok(_) => Ok(RetVal::Converging(Outcome::Success as u32)),
-				};
 			}
 			DexFunc::DepositNative => {
 				let input: DexDepositNativeInput<BalanceOf<T>> = env.read_as()?;
 
-				let sender = get_address_from_caller(env.ext().caller().clone())?;
-				let call_result = pallet_dex::Pallet::<T>::deposit_native(
-					RawOrigin::Signed(sender).into(),
-					input.amount,
+				return dispatch(
+					&mut env,
+					<T as pallet_dex::Config>::WeightInfo::deposit_native(),
+					|origin| pallet_dex::Pallet::<T>::deposit_native(origin, input.amount),
 				);
-
-				return match call_result {
-					Err(e) => {
-						let mapped_error = Outcome::from(e.error);
-						Ok(RetVal::Converging(mapped_error as u32))
-					}
-					Ok(_) => Ok(RetVal::Converging(Outcome::Success as u32)),
-				};
 			}
 			DexFunc::WithdrawNative => {
 				let input: DexWithdrawNativeInput<BalanceOf<T>> = env.read_as()?;
 
-				let sender = get_address_from_caller(env.ext().caller().clone())?;
-				let call_result = pallet_dex::Pallet::<T>::withdraw_native(
-					RawOrigin::Signed(sender).into(),
-					input.amount,
+				return dispatch(
+					&mut env,
+					<T as pallet_dex::Config>::WeightInfo::withdraw_native(),
+					|origin| pallet_dex::Pallet::<T>::withdraw_native(origin, input.amount),
 				);
-
-				return match call_result {
-					Err(e) => {
-						let mapped_error = Outcome::from(e.error);
-						Ok(RetVal::Converging(mapped_error as u32))
-					}
-					Ok(_) => Ok(RetVal::Converging(Outcome::Success as u32)),
-				};
 			}
 			DexFunc::Tokens => {
+				env.charge_weight(
+					<T as pallet_dex::Config>::WeightInfo::query_tokens()
+						.saturating_add(overhead::<T>()),
+				)?;
+
 				let tokens = pallet_dex::TokenInfoes::<T>::get();
 				env.write(&tokens.encode(), false, None)?;
 			}
 			DexFunc::OwnersTokens => {
 				let input: DexOwnersTokensInput<T::AccountId> = env.read_as()?;
+				// an account holds at most the allowlisted assets
+				let max_tokens = <T as pallet_dex::Config>::MaxAssets::get();
+				let charged = env.charge_weight(
+					<T as pallet_dex::Config>::WeightInfo::query_owner_tokens(max_tokens)
+						.saturating_add(overhead::<T>()),
+				)?;
 
 				let owner_tokens: Vec<_> =
 					pallet_dex::UserTokenInfoes::<T>::iter_key_prefix(input.owner).collect();
+				env.adjust_weight(
+					charged,
+					<T as pallet_dex::Config>::WeightInfo::query_owner_tokens(
+						owner_tokens.len() as u32
+					)
+					.saturating_add(overhead::<T>()),
+				);
 				env.write(&owner_tokens.encode(), false, None)?;
 			}
 			DexFunc::OrderFor => {
 				let input: DexOrderForInput = env.read_as()?;
-
-				let order = pallet_dex::Orders::<T>::get(input.index);
-				env.write(&order.unwrap().encode(), false, None)?;
+				env.charge_weight(
+					<T as pallet_dex::Config>::WeightInfo::query_order()
+						.saturating_add(overhead::<T>()),
+				)?;
+
+				let order = match pallet_dex::Orders::<T>::get(input.index) {
+					Some(order) => order,
+					None => return Ok(RetVal::Converging(Outcome::OrderNotFound as u32)),
+				};
+				env.write(&LegacyOrderOf::<T>::from(order).encode(), false, None)?;
+			}
+			DexFunc::OrderDetails => {
+				let input: DexOrderForInput = env.read_as()?;
+				env.charge_weight(
+					<T as pallet_dex::Config>::WeightInfo::query_order()
+						.saturating_add(overhead::<T>()),
+				)?;
+
+				let order = match pallet_dex::Orders::<T>::get(input.index) {
+					Some(order) => order,
+					None => return Ok(RetVal::Converging(Outcome::OrderNotFound as u32)),
+				};
+				env.write(&order.encode(), false, None)?;
 			}
 			DexFunc::PairOrders => {
+				// the first page only, the pair's orders used to be unbounded
 				let input: DexPairOrdersInput<u32> = env.read_as()?;
-
-				let order_index_array =
-					pallet_dex::PairOrders::<T>::get((input.asset_id_1, input.asset_id_2));
-
-				let mut order_array = vec![];
-				for i in order_index_array {
-					let order = pallet_dex::Orders::<T>::get(i).unwrap();
-					order_array.push(order);
-				}
+				let (_, order_array) = pair_orders(
+					&mut env,
+					(input.asset_id_1, input.asset_id_2),
+					0,
+					MAX_ORDERS_PER_PAGE,
+				)?;
+				env.write(&legacy_orders::<T>(order_array).encode(), false, None)?;
+			}
+			DexFunc::PairOrdersPage => {
+				let input: DexPairOrdersPageInput<u32> = env.read_as()?;
+				let (_, order_array) = pair_orders(
+					&mut env,
+					(input.asset_id_1, input.asset_id_2),
+					input.offset,
+					input.limit,
+				)?;
 				env.write(&order_array.encode(), false, None)?;
 			}
 			DexFunc::UserOrders => {
 				let input: DexUserOrdersInput<T::AccountId> = env.read_as()?;
-
-				let order_index_array: Vec<_> =
-					pallet_dex::UserOrders::<T>::iter_key_prefix(input.owner).collect();
-				let mut order_array = vec![];
-				for i in order_index_array {
-					let order = pallet_dex::Orders::<T>::get(i).unwrap();
-					order_array.push(order);
-				}
+				let (_, order_array) = user_orders(&mut env, input.owner, 0, MAX_ORDERS_PER_PAGE)?;
+				env.write(&legacy_orders::<T>(order_array).encode(), false, None)?;
+			}
+			DexFunc::UserOrdersPage => {
+				let input: DexUserOrdersPageInput<T::AccountId> = env.read_as()?;
+				let (_, order_array) =
+					user_orders(&mut env, input.owner, input.offset, input.limit)?;
 				env.write(&order_array.encode(), false, None)?;
 			}
 			DexFunc::MakeOrder => {
@@ -325,87 +560,202 @@ where
 					BlockNumberFor<T>,
 				> = env.read_as()?;
 
-				let sender = get_address_from_caller(env.ext().caller().clone())?;
-				let call_result = pallet_dex::Pallet::<T>::make_order(
-					RawOrigin::Signed(sender).into(),
-					input.asset_id_1,
-					input.asset_id_2,
-					input.offered_amount,
-					input.requested_amount,
-					input.order_type,
-					input.expires,
+				return dispatch(
+					&mut env,
+					<T as pallet_dex::Config>::WeightInfo::make_order()
+						.saturating_add(pallet_dex::Pallet::<T>::match_engine_weight(1)),
+					|origin| {
+						pallet_dex::Pallet::<T>::make_order(
+							origin,
+							input.asset_id_1,
+							input.asset_id_2,
+							input.offered_amount,
+							input.requested_amount,
+							input.order_type,
+							input.expires,
+						)
+					},
 				);
+			}
+			DexFunc::PlaceOrder => {
+				let input: DexPlaceOrderInput<
+					u32,
+					BalanceOf<T>,
+					pallet_dex::OrderType,
+					pallet_dex::OrderKind,
+					pallet_dex::TimeInForce,
+					BlockNumberFor<T>,
+				> = env.read_as()?;
 
-				return match call_result {
-					Err(e) => {
-						let mapped_error = Outcome::from(e.error);
-						Ok(RetVal::Converging(mapped_error as u32))
-					}
-					Ok(_) => Ok(RetVal::Converging(Outcome::Success as u32)),
-				};
+				return dispatch(
+					&mut env,
+					<T as pallet_dex::Config>::WeightInfo::place_order()
+						.saturating_add(pallet_dex::Pallet::<T>::match_engine_weight(1)),
+					|origin| {
+						pallet_dex::Pallet::<T>::place_order(
+							origin,
+							input.asset_id_1,
+							input.asset_id_2,
+							input.offered_amount,
+							input.requested_amount,
+							input.order_type,
+							input.order_kind,
+							input.time_in_force,
+							input.expires,
+						)
+					},
+				);
 			}
 			DexFunc::CancelOrder => {
 				let input: DexCancelOrderInput = env.read_as()?;
 
-				let sender = get_address_from_caller(env.ext().caller().clone())?;
-				let call_result = pallet_dex::Pallet::<T>::cancel_order(
-					RawOrigin::Signed(sender).into(),
-					input.index,
+				return dispatch(
+					&mut env,
+					<T as pallet_dex::Config>::WeightInfo::cancel_order()
+						.saturating_add(pallet_dex::Pallet::<T>::match_engine_weight(1)),
+					|origin| pallet_dex::Pallet::<T>::cancel_order(origin, input.index),
 				);
-
-				return match call_result {
-					Err(e) => {
-						let mapped_error = Outcome::from(e.error);
-						Ok(RetVal::Converging(mapped_error as u32))
-					}
-					Ok(_) => Ok(RetVal::Converging(Outcome::Success as u32)),
-				};
 			}
 			DexFunc::TakeOrder => {
 				let input: DexTakeOrderInput = env.read_as()?;
 
-				let sender = get_address_from_caller(env.ext().caller().clone())?;
-				let call_result = pallet_dex::Pallet::<T>::take_order(
-					RawOrigin::Signed(sender).into(),
-					input.index,
+				return dispatch(
+					&mut env,
+					<T as pallet_dex::Config>::WeightInfo::take_order()
+						.saturating_add(pallet_dex::Pallet::<T>::match_engine_weight(1)),
+					|origin| pallet_dex::Pallet::<T>::take_order(origin, input.index),
 				);
-
-				return match call_result {
-					Err(e) => {
-						let mapped_error = Outcome::from(e.error);
-						Ok(RetVal::Converging(mapped_error as u32))
-					}
-					Ok(_) => Ok(RetVal::Converging(Outcome::Success as u32)),
-				};
 			}
 			DexFunc::OwnerTokenByIndex => {
 				let input: DexOwnerTokenByIndexInput<T::AccountId> = env.read_as()?;
+				// an account holds at most the allowlisted assets
+				let max_tokens = <T as pallet_dex::Config>::MaxAssets::get();
+				env.charge_weight(
+					<T as pallet_dex::Config>::WeightInfo::query_owner_tokens(max_tokens)
+						.saturating_add(overhead::<T>()),
+				)?;
+
+				let token_id = match usize::try_from(input.index).ok().and_then(|index| {
+					pallet_dex::UserTokenInfoes::<T>::iter_key_prefix(input.owner).nth(index)
+				}) {
+					Some(token_id) => token_id,
+					None => return Ok(RetVal::Converging(Outcome::IndexOutOfRange as u32)),
+				};
 
-				let token_id_array: Vec<_> =
-					pallet_dex::UserTokenInfoes::<T>::iter_key_prefix(input.owner).collect();
-
-				env.write(&token_id_array[input.index as usize].encode(), false, None)?;
+				env.write(&token_id.encode(), false, None)?;
 			}
 			DexFunc::PairOrderByIndex => {
 				let input: DexPairOrderByIndexInput<u32> = env.read_as()?;
+				// an index past the last order of the pair is out of range
+				let offset = u32::try_from(input.index).unwrap_or(u32::MAX);
+				let (index_count, order_array) =
+					pair_orders(&mut env, (input.asset_id_1, input.asset_id_2), offset, 1)?;
+				if offset >= index_count {
+					return Ok(RetVal::Converging(Outcome::IndexOutOfRange as u32));
+				}
+				let order = match order_array.into_iter().next() {
+					Some(order) => order,
+					None => return Ok(RetVal::Converging(Outcome::OrderNotFound as u32)),
+				};
 
-				let order_index_array =
-					pallet_dex::PairOrders::<T>::get((input.asset_id_1, input.asset_id_2));
-				let order_index = order_index_array[input.index as usize];
-				let order = pallet_dex::Orders::<T>::get(order_index);
-
-				env.write(&order.unwrap().encode(), false, None)?;
+				env.write(&LegacyOrderOf::<T>::from(order).encode(), false, None)?;
 			}
 			DexFunc::UserOrderByIndex => {
 				let input: DexUserOrderByIndexInput<T::AccountId> = env.read_as()?;
+				// an index past the last order of the owner is out of range
+				let offset = u32::try_from(input.index).unwrap_or(u32::MAX);
+				let (index_count, order_array) = user_orders(&mut env, input.owner, offset, 1)?;
+				if offset >= index_count {
+					return Ok(RetVal::Converging(Outcome::IndexOutOfRange as u32));
+				}
+				let order = match order_array.into_iter().next() {
+					Some(order) => order,
+					None => return Ok(RetVal::Converging(Outcome::OrderNotFound as u32)),
+				};
 
-				let order_index_array: Vec<_> =
-					pallet_dex::UserOrders::<T>::iter_key_prefix(input.owner).collect();
-
-				let order =
-					pallet_dex::Orders::<T>::get(order_index_array[input.index as usize]).unwrap();
-
-				env.write(&order.encode(), false, None)?;
+				env.write(&LegacyOrderOf::<T>::from(order).encode(), false, None)?;
+			}
+			DexFunc::LiquidityPool => {
+				let input: DexLiquidityPoolInput<u32> = env.read_as()?;
+				env.charge_weight(
+					<T as pallet_dex::Config>::WeightInfo::query_liquidity_pool()
+						.saturating_add(overhead::<T>()),
+				)?;
+
+				let pool = match pallet_dex::LiquidityPools::<T>::get((
+					input.asset_id_1,
+					input.asset_id_2,
+				)) {
+					Some(pool) => pool,
+					None => return Ok(RetVal::Converging(Outcome::PoolNotFound as u32)),
+				};
+				env.write(&pool.encode(), false, None)?;
+			}
+			DexFunc::PoolShares => {
+				let input: DexPoolSharesInput<u32, T::AccountId> = env.read_as()?;
+				env.charge_weight(
+					<T as pallet_dex::Config>::WeightInfo::query_pool_shares()
+						.saturating_add(overhead::<T>()),
+				)?;
+
+				let shares = pallet_dex::PoolShares::<T>::get(
+					(input.asset_id_1, input.asset_id_2),
+					&input.owner,
+				);
+				env.write(&shares.encode(), false, None)?;
+			}
+			DexFunc::AddLiquidity => {
+				let input: DexAddLiquidityInput<u32, BalanceOf<T>> = env.read_as()?;
+
+				return dispatch(
+					&mut env,
+					<T as pallet_dex::Config>::WeightInfo::add_liquidity(),
+					|origin| {
+						pallet_dex::Pallet::<T>::add_liquidity(
+							origin,
+							input.asset_id_1,
+							input.asset_id_2,
+							input.amount_1,
+							input.amount_2,
+							input.min_shares,
+						)
+					},
+				);
+			}
+			DexFunc::RemoveLiquidity => {
+				let input: DexRemoveLiquidityInput<u32, BalanceOf<T>> = env.read_as()?;
+
+				return dispatch(
+					&mut env,
+					<T as pallet_dex::Config>::WeightInfo::remove_liquidity(),
+					|origin| {
+						pallet_dex::Pallet::<T>::remove_liquidity(
+							origin,
+							input.asset_id_1,
+							input.asset_id_2,
+							input.shares,
+							input.min_amount_1,
+							input.min_amount_2,
+						)
+					},
+				);
+			}
+			DexFunc::Swap => {
+				let input: DexSwapInput<u32, BalanceOf<T>> = env.read_as()?;
+
+				return dispatch(
+					&mut env,
+					<T as pallet_dex::Config>::WeightInfo::swap(),
+					|origin| {
+						pallet_dex::Pallet::<T>::swap(
+							origin,
+							input.asset_in,
+							input.asset_out,
+							input.amount_in,
+							input.min_amount_out,
+						)
+					},
+				);
 			}
 		}
 		Ok(RetVal::Converging(Outcome::Success as u32))
@@ -3,15 +3,15 @@ use crate::{prelude::*, Balance, Balances, Runtime, RuntimeEvent, RuntimeSpecifi
 use super::{Assets, Ics20Transfer};
 
 use pallet_ibc_utils::module::Router;
+use runtime_common::ibc::DexTransferModule;
+
 pub struct IbcModule;
 
 impl pallet_ibc_utils::module::AddModule for IbcModule {
 	fn add_module(router: Router) -> Router {
 		match router.add_route(
 			"transfer".parse().expect("never failed"),
-			pallet_ics20_transfer::callback::IbcTransferModule::<Runtime>(
-				sp_std::marker::PhantomData::<Runtime>,
-			),
+			DexTransferModule::<Runtime>::default(),
 		) {
 			Ok(ret) => ret,
 			Err(e) => panic!("add module failed by {e}"),
@@ -203,7 +203,10 @@ pub type SignedExtra = (
 	OptionalSignedExtension,
 );
 
-pub type Migrations = ();
+pub type Migrations = (
+	pallet_dex::migration::v1::MigrateToV1<Runtime>,
+	pallet_dex::migration::v2::MigrateToV2<Runtime>,
+);
 
 /// Unchecked extrinsic type as expected by this runtime.
 pub type UncheckedExtrinsic =
@@ -747,7 +750,10 @@ extern crate frame_benchmarking;
 
 #[cfg(feature = "runtime-benchmarks")]
 mod benches {
-	define_benchmarks!([pallet_evm, EVM]);
+	define_benchmarks!(
+		[pallet_evm, EVM]
+		[pallet_dex, Dex]
+	);
 }
 
 use fp_rpc::TransactionStatus;
@@ -1305,6 +1311,7 @@ impl_runtime_apis! {
 			let params = (&config, &whitelist);
 
 			add_benchmark!(params, batches, pallet_evm, PalletEvmBench::<Runtime>);
+			add_benchmark!(params, batches, pallet_dex, Dex);
 			add_benchmark!(params, batches, pallet_hotfix_sufficients, PalletHotfixSufficients::<Runtime>);
 
 			if batches.is_empty() { return Err("Benchmark not found for this pallet.".into()) }
@@ -1509,6 +1516,65 @@ impl_runtime_apis! {
 				Replace::get_replace_requests_for_new_vault(vault_id)
 		}
 	}
+
+	impl pallet_dex_rpc_runtime_api::DexApi<
+		Block,
+		AccountId,
+		Balance,
+		BlockNumber,
+	> for Runtime {
+		fn order_book_depth(pair: (u32, u32), levels: u32) -> pallet_dex::OrderBookDepth<Balance> {
+				Dex::order_book_depth(pair, levels)
+		}
+
+		fn best_bid_ask(pair: (u32, u32)) -> pallet_dex::BestBidAsk {
+				Dex::best_bid_ask(pair)
+		}
+
+		fn user_open_orders(account_id: AccountId) -> Vec<pallet_dex::Order<AccountId, Balance, BlockNumber>> {
+				Dex::user_open_orders(&account_id)
+		}
+
+		fn user_balances(account_id: AccountId) -> Vec<(u32, pallet_dex::TokenInfo<Balance>)> {
+				Dex::user_balances(&account_id)
+		}
+
+		fn candles(pair: (u32, u32), interval: u64, limit: u32) -> Vec<pallet_dex::Candle<Balance>> {
+				Dex::candles(pair, interval, limit)
+		}
+
+		fn liquidity_pool(pair: (u32, u32)) -> Option<pallet_dex::LiquidityPool<Balance>> {
+				Dex::liquidity_pools(pair)
+		}
+
+		fn pool_shares(pair: (u32, u32), account_id: AccountId) -> Balance {
+				Dex::pool_shares(pair, account_id)
+		}
+
+		fn quote_swap_exact_in(path: Vec<u32>, amount_in: Balance) -> Option<Balance> {
+				Dex::quote_swap_exact_in(&path, amount_in)
+		}
+
+		fn quote_swap_exact_out(path: Vec<u32>, amount_out: Balance) -> Option<Balance> {
+				Dex::quote_swap_exact_out(&path, amount_out)
+		}
+
+		fn price_accumulator(pair: (u32, u32)) -> Option<pallet_dex::PriceAccumulator> {
+				Dex::price_accumulators(pair)
+		}
+
+		fn twap(pair: (u32, u32), period: u64) -> Option<pallet_dex::Twap> {
+				Dex::twap(pair, period)
+		}
+
+		fn trading_volume(account_id: AccountId, pair: (u32, u32)) -> Balance {
+				Dex::trading_volume(&account_id, pair)
+		}
+
+		fn pending_rewards(account_id: AccountId) -> Vec<(u32, Balance)> {
+				Dex::pending_rewards(&account_id)
+		}
+	}
 }
 
 #[cfg(test)]
@@ -1542,4 +1608,139 @@ mod tests {
 			 If the limit is too strong, maybe consider increase the limit.",
 		);
 	}
+
+	#[test]
+	fn dex_trades_registered_tokens() {
+		use crate::{btcbridge::GetWrappedCurrencyId, AccountId, Dex, RuntimeOrigin, Tokens};
+		use frame_support::{
+			assert_ok,
+			traits::{GenesisBuild, Get},
+		};
+		use orml_traits::MultiCurrency;
+		use primitives::{CurrencyId::Token, TokenSymbol::DOT};
+		use sp_runtime::{traits::Convert, BuildStorage};
+
+		let wrapped_asset_id = 1_000;
+		let mut storage = frame_system::GenesisConfig::default()
+			.build_storage()
+			.unwrap();
+		<pallet_dex::GenesisConfig<Runtime> as GenesisBuild<Runtime>>::assimilate_storage(
+			&pallet_dex::GenesisConfig {
+				tokens: vec![(wrapped_asset_id, GetWrappedCurrencyId::get())],
+				..Default::default()
+			},
+			&mut storage,
+		)
+		.unwrap();
+		let mut ext: sp_io::TestExternalities = storage.into();
+		ext.execute_with(|| {
+			assert_eq!(
+				pallet_dex::RegisteredTokenIds::<Runtime>::convert(wrapped_asset_id),
+				Some(GetWrappedCurrencyId::get())
+			);
+
+			// a currency registered later is moved by orml_tokens as well
+			let dot_asset_id = 1_001;
+			let who = AccountId::from([1; 32]);
+			assert_ok!(Dex::register_token(
+				RuntimeOrigin::root(),
+				dot_asset_id,
+				Token(DOT)
+			));
+			assert_ok!(Dex::allowlist_asset(RuntimeOrigin::root(), dot_asset_id));
+			assert_ok!(Tokens::deposit(Token(DOT), &who, 1_000));
+
+			assert_ok!(Dex::deposit(
+				RuntimeOrigin::signed(who.clone()),
+				dot_asset_id,
+				400
+			));
+			assert_eq!(Tokens::free_balance(Token(DOT), &who), 600);
+			assert_eq!(Tokens::free_balance(Token(DOT), &Dex::account_id()), 400);
+			assert_eq!(Dex::user_token_infoes(&who, dot_asset_id).amount, 400);
+
+			assert_ok!(Dex::withdraw(
+				RuntimeOrigin::signed(who.clone()),
+				dot_asset_id,
+				400
+			));
+			assert_eq!(Tokens::free_balance(Token(DOT), &who), 1_000);
+		});
+	}
+
+	#[test]
+	fn ics20_transfer_deposits_into_dex() {
+		use crate::{AccountId, Assets, Dex, RuntimeOrigin};
+		use frame_support::{assert_ok, traits::fungibles::Mutate};
+		use runtime_common::ibc::deposit_transfer_into_dex;
+		use sp_core::crypto::Ss58Codec;
+		use sp_runtime::BuildStorage;
+
+		let mut ext: sp_io::TestExternalities = frame_system::GenesisConfig::default()
+			.build_storage()
+			.unwrap()
+			.into();
+		ext.execute_with(|| {
+			// a voucher received over channel-0 and a token of this chain that returns
+			let (voucher, token) = (1_000, 1_001);
+			let who = AccountId::from([1; 32]);
+			for (asset_id, denom) in [
+				(voucher, &b"transfer/channel-0/uatom"[..]),
+				(token, &b"utoken"[..]),
+			] {
+				assert_ok!(Assets::force_create(
+					RuntimeOrigin::root(),
+					asset_id.into(),
+					who.clone().into(),
+					true,
+					1
+				));
+				pallet_ics20_transfer::AssetIdByName::<Runtime>::insert(denom.to_vec(), asset_id);
+				assert_ok!(Dex::allowlist_asset(RuntimeOrigin::root(), asset_id));
+			}
+			assert_ok!(Dex::register_trading_pair(
+				RuntimeOrigin::root(),
+				voucher,
+				token,
+				0
+			));
+
+			let packet_data = |denom: &str, memo: &str| {
+				format!(
+					r#"{{"denom":"{denom}","amount":"100","sender":"cosmos1","receiver":"{}","memo":"{memo}"}}"#,
+					who.to_ss58check()
+				)
+			};
+			// the transfer module has minted or unescrowed the tokens before
+			let receive = |asset_id, denom, memo| {
+				assert_ok!(Assets::mint_into(asset_id, &who, 100));
+				deposit_transfer_into_dex::<Runtime>(
+					"transfer/channel-7/",
+					"transfer/channel-0/",
+					packet_data(denom, memo).as_bytes(),
+				)
+			};
+
+			// tokens of transfers without a DEX memo stay with the receiver
+			assert_eq!(receive(voucher, "uatom", ""), Ok(None));
+			assert_eq!(receive(voucher, "uatom", "swap"), Ok(None));
+			assert_eq!(Dex::user_token_infoes(&who, voucher).amount, 0);
+			assert_eq!(Assets::balance(voucher, &who), 200);
+
+			assert_eq!(receive(voucher, "uatom", "dex"), Ok(None));
+			assert_eq!(Dex::user_token_infoes(&who, voucher).amount, 100);
+			assert_eq!(Assets::balance(voucher, &who), 200);
+
+			let order_index = receive(
+				token,
+				"transfer/channel-7/utoken",
+				&format!("dex:order:{voucher}:50:1000"),
+			)
+			.unwrap()
+			.unwrap();
+			let order = Dex::orders(order_index).unwrap();
+			assert_eq!(order.pair, (voucher, token));
+			assert_eq!(Dex::user_token_infoes(&who, token).reserved, 100);
+		});
+	}
 }
@@ -0,0 +1,426 @@
+//! ICS-20 transfers into the DEX.
+
+use core::fmt;
+
+use ibc::{
+	applications::transfer::acknowledgement::TokenTransferAcknowledgement,
+	core::{
+		ics04_channel::{
+			channel::{Counterparty, Order},
+			error::{ChannelError, PacketError},
+			msgs::acknowledgement::Acknowledgement,
+			packet::Packet,
+			Version,
+		},
+		ics24_host::identifier::{ChannelId, ConnectionId, PortId},
+		ics26_routing::context::{Module, ModuleExtras, ModuleOutputBuilder},
+	},
+	signer::Signer,
+};
+use pallet_dex::{memo::TransferPacketData, BalanceOf};
+use pallet_ics20_transfer::{callback::IbcTransferModule, AssetIdAndNameProvider};
+use sp_runtime::{traits::IdentifyAccount, DispatchError};
+use sp_std::{marker::PhantomData, prelude::*};
+
+/// The ICS-20 transfer module, which also deposits the received tokens into the DEX when the
+/// memo of the transfer asks for it.
+///
+/// The tokens are deposited after the transfer module has minted or unescrowed them, and a
+/// deposit that fails leaves them with the receiver instead of failing the packet: the
+/// acknowledgement can't turn into an error after the tokens were received.
+pub struct DexTransferModule<T: pallet_ics20_transfer::Config>(pub IbcTransferModule<T>);
+
+impl<T: pallet_ics20_transfer::Config> Default for DexTransferModule<T> {
+	fn default() -> Self {
+		Self(IbcTransferModule(PhantomData))
+	}
+}
+
+impl<T: pallet_ics20_transfer::Config> fmt::Debug for DexTransferModule<T>
+where
+	IbcTransferModule<T>: fmt::Debug,
+{
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_tuple("DexTransferModule").field(&self.0).finish()
+	}
+}
+
+impl<T> DexTransferModule<T>
+where
+	T: pallet_ics20_transfer::Config<AssetId = u32> + pallet_dex::Config,
+	T::AccountIdConversion: TryFrom<Signer> + IdentifyAccount<AccountId = T::AccountId>,
+{
+	fn deposit_into_dex(packet: &Packet, acknowledgement: &Acknowledgement) {
+		// the transfer module acknowledges every received transfer with the same success
+		if *acknowledgement != Acknowledgement::from(TokenTransferAcknowledgement::success()) {
+			return;
+		}
+		let source_prefix = [
+			packet.port_on_a.as_str(),
+			"/",
+			packet.chan_on_a.as_str(),
+			"/",
+		]
+		.concat();
+		let destination_prefix = [
+			packet.port_on_b.as_str(),
+			"/",
+			packet.chan_on_b.as_str(),
+			"/",
+		]
+		.concat();
+		if let Err(e) =
+			deposit_transfer_into_dex::<T>(&source_prefix, &destination_prefix, &packet.data)
+		{
+			log::warn!(
+				target: "runtime::ibc",
+				"received tokens were not deposited into the DEX: {e:?}"
+			);
+		}
+	}
+}
+
+/// Deposits the tokens of a received ICS-20 transfer into the DEX of the receiver when the
+/// memo of the transfer is addressed to it, see [`pallet_dex::Pallet::deposit_from_transfer`].
+///
+/// `source_prefix` and `destination_prefix` are `"{port}/{channel}/"` of the sending and of the
+/// receiving end of the channel, they trace the denomination of the tokens on this chain.
+pub fn deposit_transfer_into_dex<T>(
+	source_prefix: &str,
+	destination_prefix: &str,
+	data: &[u8],
+) -> Result<Option<u64>, DispatchError>
+where
+	T: pallet_ics20_transfer::Config<AssetId = u32> + pallet_dex::Config,
+	T::AccountIdConversion: TryFrom<Signer> + IdentifyAccount<AccountId = T::AccountId>,
+{
+	let packet_data = match TransferPacketData::parse(data) {
+		Some(packet_data) if !packet_data.memo.is_empty() => packet_data,
+		_ => return Ok(None),
+	};
+
+	// tokens that return to this chain lose the prefix of the channel, the others are
+	// vouchers prefixed with the receiving end
+	let denom = match packet_data.denom.strip_prefix(source_prefix.as_bytes()) {
+		Some(denom) => denom.to_vec(),
+		None => [destination_prefix.as_bytes(), &packet_data.denom].concat(),
+	};
+	// the native token isn't an asset the DEX can hold
+	if denom == <T as pallet_ics20_transfer::Config>::NATIVE_TOKEN_NAME {
+		return Ok(None);
+	}
+	let asset_id = <T as pallet_ics20_transfer::Config>::AssetIdByName::try_get_asset_id(&denom)
+		.map_err(|_| DispatchError::Other("unknown ICS-20 denomination"))?;
+	let amount = BalanceOf::<T>::try_from(packet_data.amount)
+		.map_err(|_| DispatchError::Other("ICS-20 amount overflows the balance"))?;
+
+	let receiver = core::str::from_utf8(&packet_data.receiver)
+		.ok()
+		.and_then(|receiver| receiver.parse::<Signer>().ok())
+		.and_then(|receiver| T::AccountIdConversion::try_from(receiver).ok())
+		.ok_or(DispatchError::Other("invalid ICS-20 receiver"))?
+		.into_account();
+
+	pallet_dex::Pallet::<T>::deposit_from_transfer(&receiver, asset_id, amount, &packet_data.memo)
+}
+
+#[allow(deprecated)]
+impl<T> Module for DexTransferModule<T>
+where
+	T: pallet_ics20_transfer::Config<AssetId = u32> + pallet_dex::Config,
+	T::AccountIdConversion: TryFrom<Signer> + IdentifyAccount<AccountId = T::AccountId>,
+	IbcTransferModule<T>: Module,
+{
+	fn on_chan_open_init_validate(
+		&self,
+		order: Order,
+		connection_hops: &[ConnectionId],
+		port_id: &PortId,
+		channel_id: &ChannelId,
+		counterparty: &Counterparty,
+		version: &Version,
+	) -> Result<Version, ChannelError> {
+		self.0.on_chan_open_init_validate(
+			order,
+			connection_hops,
+			port_id,
+			channel_id,
+			counterparty,
+			version,
+		)
+	}
+
+	fn on_chan_open_init_execute(
+		&mut self,
+		order: Order,
+		connection_hops: &[ConnectionId],
+		port_id: &PortId,
+		channel_id: &ChannelId,
+		counterparty: &Counterparty,
+		version: &Version,
+	) -> Result<(ModuleExtras, Version), ChannelError> {
+		self.0.on_chan_open_init_execute(
+			order,
+			connection_hops,
+			port_id,
+			channel_id,
+			counterparty,
+			version,
+		)
+	}
+
+	fn on_chan_open_init(
+		&mut self,
+		order: Order,
+		connection_hops: &[ConnectionId],
+		port_id: &PortId,
+		channel_id: &ChannelId,
+		counterparty: &Counterparty,
+		version: &Version,
+	) -> Result<(ModuleExtras, Version), ChannelError> {
+		self.0.on_chan_open_init(
+			order,
+			connection_hops,
+			port_id,
+			channel_id,
+			counterparty,
+			version,
+		)
+	}
+
+	fn on_chan_open_try_validate(
+		&self,
+		order: Order,
+		connection_hops: &[ConnectionId],
+		port_id: &PortId,
+		channel_id: &ChannelId,
+		counterparty: &Counterparty,
+		counterparty_version: &Version,
+	) -> Result<Version, ChannelError> {
+		self.0.on_chan_open_try_validate(
+			order,
+			connection_hops,
+			port_id,
+			channel_id,
+			counterparty,
+			counterparty_version,
+		)
+	}
+
+	fn on_chan_open_try_execute(
+		&mut self,
+		order: Order,
+		connection_hops: &[ConnectionId],
+		port_id: &PortId,
+		channel_id: &ChannelId,
+		counterparty: &Counterparty,
+		counterparty_version: &Version,
+	) -> Result<(ModuleExtras, Version), ChannelError> {
+		self.0.on_chan_open_try_execute(
+			order,
+			connection_hops,
+			port_id,
+			channel_id,
+			counterparty,
+			counterparty_version,
+		)
+	}
+
+	fn on_chan_open_try(
+		&mut self,
+		order: Order,
+		connection_hops: &[ConnectionId],
+		port_id: &PortId,
+		channel_id: &ChannelId,
+		counterparty: &Counterparty,
+		counterparty_version: &Version,
+	) -> Result<(ModuleExtras, Version), ChannelError> {
+		self.0.on_chan_open_try(
+			order,
+			connection_hops,
+			port_id,
+			channel_id,
+			counterparty,
+			counterparty_version,
+		)
+	}
+
+	fn on_chan_open_ack_validate(
+		&self,
+		port_id: &PortId,
+		channel_id: &ChannelId,
+		counterparty_version: &Version,
+	) -> Result<(), ChannelError> {
+		self.0
+			.on_chan_open_ack_validate(port_id, channel_id, counterparty_version)
+	}
+
+	fn on_chan_open_ack_execute(
+		&mut self,
+		port_id: &PortId,
+		channel_id: &ChannelId,
+		counterparty_version: &Version,
+	) -> Result<ModuleExtras, ChannelError> {
+		self.0
+			.on_chan_open_ack_execute(port_id, channel_id, counterparty_version)
+	}
+
+	fn on_chan_open_ack(
+		&mut self,
+		port_id: &PortId,
+		channel_id: &ChannelId,
+		counterparty_version: &Version,
+	) -> Result<ModuleExtras, ChannelError> {
+		self.0
+			.on_chan_open_ack(port_id, channel_id, counterparty_version)
+	}
+
+	fn on_chan_open_confirm_validate(
+		&self,
+		port_id: &PortId,
+		channel_id: &ChannelId,
+	) -> Result<(), ChannelError> {
+		self.0.on_chan_open_confirm_validate(port_id, channel_id)
+	}
+
+	fn on_chan_open_confirm_execute(
+		&mut self,
+		port_id: &PortId,
+		channel_id: &ChannelId,
+	) -> Result<ModuleExtras, ChannelError> {
+		self.0.on_chan_open_confirm_execute(port_id, channel_id)
+	}
+
+	fn on_chan_open_confirm(
+		&mut self,
+		port_id: &PortId,
+		channel_id: &ChannelId,
+	) -> Result<ModuleExtras, ChannelError> {
+		self.0.on_chan_open_confirm(port_id, channel_id)
+	}
+
+	fn on_chan_close_init_validate(
+		&self,
+		port_id: &PortId,
+		channel_id: &ChannelId,
+	) -> Result<(), ChannelError> {
+		self.0.on_chan_close_init_validate(port_id, channel_id)
+	}
+
+	fn on_chan_close_init_execute(
+		&mut self,
+		port_id: &PortId,
+		channel_id: &ChannelId,
+	) -> Result<ModuleExtras, ChannelError> {
+		self.0.on_chan_close_init_execute(port_id, channel_id)
+	}
+
+	fn on_chan_close_init(
+		&mut self,
+		port_id: &PortId,
+		channel_id: &ChannelId,
+	) -> Result<ModuleExtras, ChannelError> {
+		self.0.on_chan_close_init(port_id, channel_id)
+	}
+
+	fn on_chan_close_confirm_validate(
+		&self,
+		port_id: &PortId,
+		channel_id: &ChannelId,
+	) -> Result<(), ChannelError> {
+		self.0.on_chan_close_confirm_validate(port_id, channel_id)
+	}
+
+	fn on_chan_close_confirm_execute(
+		&mut self,
+		port_id: &PortId,
+		channel_id: &ChannelId,
+	) -> Result<ModuleExtras, ChannelError> {
+		self.0.on_chan_close_confirm_execute(port_id, channel_id)
+	}
+
+	fn on_chan_close_confirm(
+		&mut self,
+		port_id: &PortId,
+		channel_id: &ChannelId,
+	) -> Result<ModuleExtras, ChannelError> {
+		self.0.on_chan_close_confirm(port_id, channel_id)
+	}
+
+	fn on_recv_packet_execute(
+		&mut self,
+		packet: &Packet,
+		relayer: &Signer,
+	) -> (ModuleExtras, Acknowledgement) {
+		let (extras, acknowledgement) = self.0.on_recv_packet_execute(packet, relayer);
+		Self::deposit_into_dex(packet, &acknowledgement);
+		(extras, acknowledgement)
+	}
+
+	fn on_recv_packet(
+		&mut self,
+		output: &mut ModuleOutputBuilder,
+		packet: &Packet,
+		relayer: &Signer,
+	) -> Acknowledgement {
+		let acknowledgement = self.0.on_recv_packet(output, packet, relayer);
+		Self::deposit_into_dex(packet, &acknowledgement);
+		acknowledgement
+	}
+
+	fn on_acknowledgement_packet_validate(
+		&self,
+		packet: &Packet,
+		acknowledgement: &Acknowledgement,
+		relayer: &Signer,
+	) -> Result<(), PacketError> {
+		self.0
+			.on_acknowledgement_packet_validate(packet, acknowledgement, relayer)
+	}
+
+	fn on_acknowledgement_packet_execute(
+		&mut self,
+		packet: &Packet,
+		acknowledgement: &Acknowledgement,
+		relayer: &Signer,
+	) -> (ModuleExtras, Result<(), PacketError>) {
+		self.0
+			.on_acknowledgement_packet_execute(packet, acknowledgement, relayer)
+	}
+
+	fn on_acknowledgement_packet(
+		&mut self,
+		output: &mut ModuleOutputBuilder,
+		packet: &Packet,
+		acknowledgement: &Acknowledgement,
+		relayer: &Signer,
+	) -> Result<(), PacketError> {
+		self.0
+			.on_acknowledgement_packet(output, packet, acknowledgement, relayer)
+	}
+
+	fn on_timeout_packet_validate(
+		&self,
+		packet: &Packet,
+		relayer: &Signer,
+	) -> Result<(), PacketError> {
+		self.0.on_timeout_packet_validate(packet, relayer)
+	}
+
+	fn on_timeout_packet_execute(
+		&mut self,
+		packet: &Packet,
+		relayer: &Signer,
+	) -> (ModuleExtras, Result<(), PacketError>) {
+		self.0.on_timeout_packet_execute(packet, relayer)
+	}
+
+	fn on_timeout_packet(
+		&mut self,
+		output: &mut ModuleOutputBuilder,
+		packet: &Packet,
+		relayer: &Signer,
+	) -> Result<(), PacketError> {
+		self.0.on_timeout_packet(output, packet, relayer)
+	}
+}
@@ -7,6 +7,7 @@ pub mod precompiles;
 
 pub mod chain_spec;
 pub mod constants;
+pub mod ibc;
 #[cfg(feature = "pos")]
 pub mod pos;
 pub mod validator_manager;
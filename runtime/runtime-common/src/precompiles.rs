@@ -7,6 +7,7 @@ use sp_std::marker::PhantomData;
 use pallet_evm_eth_receipt_provider::EthReceiptPrecompile;
 use pallet_evm_precompile_blake2::Blake2F;
 use pallet_evm_precompile_bn128::{Bn128Add, Bn128Mul, Bn128Pairing};
+use pallet_evm_precompile_dex::DexPrecompile;
 use pallet_evm_precompile_ed25519::Ed25519Verify;
 use pallet_evm_precompile_modexp::Modexp;
 use pallet_evm_precompile_session::SessionWrapper;
@@ -48,12 +49,13 @@ pub mod consts {
 	pub const XVM: H160 = hash(0x5005);
 
 	pub const SESSION_WRAPPER: H160 = hash(0x2052);
+	pub const DEX: H160 = hash(0x2053);
 
 	pub const ZK_GROTH16_VERIFY: H160 = hash(0x8888);
 
 	pub const ETH_RECEIPT_PROVIDER: H160 = hash(0x9999);
 
-	const ARRAY_SIZE: usize = 19;
+	const ARRAY_SIZE: usize = 20;
 
 	pub const SUPPORTED_PRECOMPILES: [H160; ARRAY_SIZE] = [
 		EC_RECOVER,
@@ -73,6 +75,7 @@ pub mod consts {
 		ECDSA_VERIFY,
 		XVM,
 		SESSION_WRAPPER,
+		DEX,
 		ZK_GROTH16_VERIFY,
 		ETH_RECEIPT_PROVIDER,
 	];
@@ -132,6 +135,10 @@ impl<R, XS> GoldenGatePrecompiles<R, XS> {
 	/// * 0x5002 - is Sr25519 verify
 	/// * 0x5003 - is Ecdsa verify
 	/// * 0x5005 - is cross virtual machine (XVM)
+	///
+	/// The next list contains GGX specific precompiles:
+	/// * 0x2052 - is pallet-session wrapper
+	/// * 0x2053 - is pallet-dex
 	pub fn used_addresses() -> impl Iterator<Item = H160> {
 		consts::SUPPORTED_PRECOMPILES.into_iter()
 	}
@@ -141,6 +148,7 @@ impl<R, XS> PrecompileSet for GoldenGatePrecompiles<R, XS>
 where
 	XvmPrecompile<R, XS>: Precompile,
 	SessionWrapper<R>: Precompile,
+	DexPrecompile<R>: Precompile,
 	R: pallet_evm::Config + pallet_xvm::Config + pallet_receipt_registry::Config,
 {
 	fn execute(&self, handle: &mut impl PrecompileHandle) -> Option<PrecompileResult> {
@@ -167,6 +175,7 @@ where
 			// 0x5005 - is cross virtual machine (XVM)
 			a if a == consts::XVM => Some(XvmPrecompile::<R, XS>::execute(handle)),
 			a if a == consts::SESSION_WRAPPER => Some(SessionWrapper::<R>::execute(handle)),
+			a if a == consts::DEX => Some(DexPrecompile::<R>::execute(handle)),
 
 			// 0x8888 - is zk-groth16 verify
 			a if a == consts::ZK_GROTH16_VERIFY => Some(ZKGroth16Verify::execute(handle)),
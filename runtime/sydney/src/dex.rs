@@ -1,17 +1,91 @@
-use frame_support::PalletId;
+use frame_support::{traits::Get, PalletId};
+use primitives::{oracle::Key as OracleKey, CurrencyId};
+use sp_runtime::{traits::CheckedDiv, FixedU128, Permill};
+use sp_std::prelude::*;
 
-use crate::{prelude::*, Assets, BlockNumber};
+use crate::{prelude::*, Assets, Aura, AuraId, BlockNumber, Oracle, Timestamp, Tokens, Treasury};
 
 parameter_types! {
 	pub const UnsignedPriority: BlockNumber = 1;
 	pub const DexPalletId: PalletId = PalletId(*b"py/sudex");
+	pub const DexMatchingMode: pallet_dex::MatchingMode = pallet_dex::MatchingMode::Offchain;
+	pub DexFeeDestination: AccountId = Treasury::account_id();
+	pub const DexSelfTradePolicy: pallet_dex::SelfTradePolicy = pallet_dex::SelfTradePolicy::CancelNewest;
+	pub const DexMaxExpiringOrdersPerBlock: u32 = 100;
+	pub const DexMaxAssets: u32 = 256;
+	pub const DexMaxOrdersPerPair: u32 = 1_000;
+	pub const DexMaxOpenOrdersPerAccount: u32 = 100;
+	pub const DexMaxOrdersPerBatch: u32 = 20;
+	pub const DexMaxConditionalOrdersPerPair: u32 = 1_000;
+	pub const DexMaxTriggeredOrdersPerBlock: u32 = 20;
+	// a minute, an hour and a day
+	pub DexCandleIntervals: Vec<u64> = vec![60, 3_600, 86_400];
+	pub const DexMaxCandles: u32 = 1_440;
+	// 0.3%
+	pub const DexPoolFee: Permill = Permill::from_parts(3_000);
+	pub const DexMaxSwapPathLength: u32 = 4;
+	// hourly observations of the last day
+	pub const DexPriceObservationPeriod: u64 = 3_600;
+	pub const DexMaxPriceObservations: u32 = 24;
+	pub const DexMaxFeeTiers: u32 = 8;
+	pub DexRewardSource: AccountId = Treasury::account_id();
 }
 
 impl pallet_dex::Config for Runtime {
 	type RuntimeEvent = RuntimeEvent;
 	type PalletId = DexPalletId;
-	type Fungibles = Assets;
+	type Fungibles =
+		pallet_dex::FungiblesAdapter<Assets, Tokens, pallet_dex::RegisteredTokenIds<Runtime>>;
+	type TokenId = CurrencyId;
 	type PrivilegedOrigin = frame_system::EnsureRoot<Self::AccountId>;
 	type UnsignedPriority = UnsignedPriority;
 	type Currency = Balances;
+	type AuthorityId = AuraId;
+	type MatchAuthorities = DexMatchAuthorities;
+	type MatchingMode = DexMatchingMode;
+	type FeeDestination = DexFeeDestination;
+	type SelfTradePolicy = DexSelfTradePolicy;
+	type MaxExpiringOrdersPerBlock = DexMaxExpiringOrdersPerBlock;
+	type MaxAssets = DexMaxAssets;
+	type MaxOrdersPerPair = DexMaxOrdersPerPair;
+	type MaxOpenOrdersPerAccount = DexMaxOpenOrdersPerAccount;
+	type MaxOrdersPerBatch = DexMaxOrdersPerBatch;
+	type PriceOracle = DexPriceOracle;
+	type MaxConditionalOrdersPerPair = DexMaxConditionalOrdersPerPair;
+	type MaxTriggeredOrdersPerBlock = DexMaxTriggeredOrdersPerBlock;
+	type UnixTime = Timestamp;
+	type CandleIntervals = DexCandleIntervals;
+	type MaxCandles = DexMaxCandles;
+	type PoolFee = DexPoolFee;
+	type MaxSwapPathLength = DexMaxSwapPathLength;
+	type PriceObservationPeriod = DexPriceObservationPeriod;
+	type MaxPriceObservations = DexMaxPriceObservations;
+	type MaxFeeTiers = DexMaxFeeTiers;
+	type RewardSource = DexRewardSource;
+	type WeightInfo = runtime_common::weights::pallet_dex::WeightInfo<Runtime>;
+}
+
+/// Block producers sign the order matches computed by their offchain workers.
+pub struct DexMatchAuthorities;
+
+impl Get<Vec<AuraId>> for DexMatchAuthorities {
+	fn get() -> Vec<AuraId> {
+		Aura::authorities().into_inner()
+	}
+}
+
+/// Prices a pair from the exchange rates the oracle reports for its assets against the
+/// wrapped currency. DEX assets are quoted by the oracle as foreign assets with the same id,
+/// `orml_tokens` currencies by their currency id.
+pub struct DexPriceOracle;
+
+impl pallet_dex::PriceOracle for DexPriceOracle {
+	fn price((base_asset_id, quote_asset_id): (u32, u32)) -> Option<FixedU128> {
+		let exchange_rate = |asset_id| {
+			let currency_id = pallet_dex::TokenIds::<Runtime>::get(asset_id)
+				.unwrap_or(CurrencyId::ForeignAsset(asset_id));
+			Oracle::get_price(OracleKey::ExchangeRate(currency_id)).ok()
+		};
+		exchange_rate(quote_asset_id)?.checked_div(&exchange_rate(base_asset_id)?)
+	}
 }